@@ -10,6 +10,9 @@ use custody_engine::logging::init_logging;
 use custody::custody_management_service_client::CustodyManagementServiceClient;
 use custody::GetDIDDocumentRequest;
 use std::path::Path;
+use std::time::Duration;
+use custody_engine::membership::MembershipRegistry;
+use custody_engine::tls::{self, CertifiedKey};
 use custody_engine::{
    // init_logging,
     crypto::{keys, signing},
@@ -23,7 +26,7 @@ use crate::proto::custodyvc::{
 use custodydkg::custody_dkg_client::CustodyDkgClient;
 use custodydkg::{
     StartDkgSessionRequest, StartDkgSessionResponse,
-    BroadcastRound2Request, FinalizeDkgRequest, FinalizeDkgResponse,
+    GetDkgStatusRequest, GetDkgStatusResponse, AbortDkgSessionRequest,
 };
 
 #[derive(Parser)]
@@ -39,6 +42,9 @@ struct Cli {
 pub enum Commands {
     #[command(subcommand)]
     Dkg(DkgCommand),
+    /// Cluster CA and node certificates for mutual TLS
+    #[command(subcommand)]
+    Ca(CaCommand),
     /// Generate a new MPC key set
     GenerateKeys {
         #[arg(short, long)]
@@ -188,14 +194,159 @@ pub enum DkgCommand {
         #[arg(long)]
         peers: Vec<String>,
     },
-    Round2 {
+    Status {
         #[arg(long)]
         group_id: String,
+        #[arg(long)]
+        wait: bool,
     },
-    Finalize {
+    Abort {
         #[arg(long)]
         group_id: String,
+        #[arg(long)]
+        reason: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CaCommand {
+    /// Create the cluster CA in `dir`
+    Init {
+        #[arg(long)]
+        dir: String,
+        #[arg(long, default_value = "custody-cluster-ca")]
+        name: String,
+        #[arg(long, default_value_t = 3650)]
+        days: u64,
+    },
+    /// Issue a node certificate bound to the node's identity key, into `out`
+    Issue {
+        #[arg(long)]
+        ca_dir: String,
+        #[arg(long)]
+        node: String,
+        #[arg(long, help = "Hex Ed25519 identity key; read from --membership when omitted")]
+        identity_key: Option<String>,
+        #[arg(long, default_value = "/var/lib/custody/membership.json")]
+        membership: String,
+        #[arg(long)]
+        out: String,
+        #[arg(long, default_value_t = 365)]
+        days: u64,
+    },
+    /// Replace the certificate and TLS key in `out`, keeping the node and identity key it names
+    Rotate {
+        #[arg(long)]
+        ca_dir: String,
+        #[arg(long)]
+        out: String,
+        #[arg(long, default_value_t = 365)]
+        days: u64,
     },
+    /// Issue an operator a client certificate, into `out`
+    IssueOperator {
+        #[arg(long)]
+        ca_dir: String,
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        out: String,
+        #[arg(long, default_value_t = 90)]
+        days: u64,
+    },
+}
+
+const CA_KEY_FILE: &str = "ca.key";
+
+fn run_ca(cmd: &CaCommand) -> Result<(), String> {
+    match cmd {
+        CaCommand::Init { dir, name, days } => {
+            let dir = Path::new(dir);
+            if dir.join(tls::CA_CERT_FILE).exists() {
+                return Err(format!("{} already holds a CA", dir.display()));
+            }
+            let ca = tls::create_ca(name, Duration::from_secs(days * 86_400))?;
+            write_pair(dir, tls::CA_CERT_FILE, CA_KEY_FILE, &ca)?;
+            println!("🔏 Cluster CA {name} created in {}", dir.display());
+        }
+
+        CaCommand::Issue { ca_dir, node, identity_key, membership, out, days } => {
+            let identity_key = match identity_key {
+                Some(key) => hex::decode(key).map_err(|_| "identity key must be hex")?,
+                None => {
+                    let registry = MembershipRegistry::new();
+                    registry.load(Path::new(membership))?;
+                    registry.get(node).ok_or_else(|| format!("{node} is not enrolled in {membership}"))?.identity.signing_key
+                }
+            };
+            let ca = read_ca(Path::new(ca_dir))?;
+            let cert = tls::issue_node_certificate(&ca, node, &identity_key, Duration::from_secs(days * 86_400))?;
+            install_node_certificate(Path::new(out), &ca, &cert)?;
+            println!("📜 Certificate for {node} issued into {out}");
+        }
+
+        CaCommand::Rotate { ca_dir, out, days } => {
+            let out = Path::new(out);
+            let current = std::fs::read(out.join(tls::NODE_CERT_FILE))
+                .map_err(|e| format!("read current certificate failed: {e}"))?;
+            let holder = tls::certificate_identity(&current)?;
+            if holder.role != tls::PeerRole::Node {
+                return Err(format!("{} is not a node certificate", out.join(tls::NODE_CERT_FILE).display()));
+            }
+
+            let ca = read_ca(Path::new(ca_dir))?;
+            let cert = tls::issue_node_certificate(&ca, &holder.name, &holder.identity_key, Duration::from_secs(days * 86_400))?;
+            install_node_certificate(out, &ca, &cert)?;
+            println!("🔄 Certificate for {} rotated; restart the node to serve it", holder.name);
+        }
+
+        CaCommand::IssueOperator { ca_dir, name, out, days } => {
+            let ca = read_ca(Path::new(ca_dir))?;
+            let cert = tls::issue_operator_certificate(&ca, name, Duration::from_secs(days * 86_400))?;
+            let out = Path::new(out);
+            write_file(&out.join(tls::CA_CERT_FILE), &ca.cert_pem, false)?;
+            write_pair(out, "operator.pem", "operator.key", &cert)?;
+            println!("📜 Operator certificate for {name} issued into {}", out.display());
+        }
+    }
+    Ok(())
+}
+
+fn read_ca(dir: &Path) -> Result<CertifiedKey, String> {
+    let read = |file: &str| std::fs::read_to_string(dir.join(file))
+        .map_err(|e| format!("read {} failed: {e}", dir.join(file).display()));
+    Ok(CertifiedKey { cert_pem: read(tls::CA_CERT_FILE)?, key_pem: read(CA_KEY_FILE)? })
+}
+
+/// Lays out a node's TLS directory the way the server loads it
+fn install_node_certificate(out: &Path, ca: &CertifiedKey, cert: &CertifiedKey) -> Result<(), String> {
+    write_file(&out.join(tls::CA_CERT_FILE), &ca.cert_pem, false)?;
+    write_pair(out, tls::NODE_CERT_FILE, tls::NODE_KEY_FILE, cert)
+}
+
+fn write_pair(dir: &Path, cert_file: &str, key_file: &str, pair: &CertifiedKey) -> Result<(), String> {
+    // Key first: a certificate is never left next to the key it replaced
+    write_file(&dir.join(key_file), &pair.key_pem, true)?;
+    write_file(&dir.join(cert_file), &pair.cert_pem, false)
+}
+
+/// Writes aside and renames, so a node never loads a half-written file. Keys are
+/// readable by their owner only.
+fn write_file(path: &Path, contents: &str, secret: bool) -> Result<(), String> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("create {} failed: {e}", dir.display()))?;
+    }
+    let staged = path.with_extension("tmp");
+    let mut file = std::fs::OpenOptions::new()
+        .write(true).create(true).truncate(true)
+        .mode(if secret { 0o600 } else { 0o644 })
+        .open(&staged)
+        .map_err(|e| format!("write {} failed: {e}", path.display()))?;
+    file.write_all(contents.as_bytes()).map_err(|e| format!("write {} failed: {e}", path.display()))?;
+    std::fs::rename(&staged, path).map_err(|e| format!("write {} failed: {e}", path.display()))
 }
 
 fn main() {
@@ -385,12 +536,24 @@ fn main() {
                 timestamp: now_rfc3339(),
             });            
         }
+
+        // Subcommand: Ca
+        // Creates the cluster CA and issues or rotates certificates for mutual TLS
+        Commands::Ca(cmd) => {
+            if let Err(e) = run_ca(&cmd) {
+                eprintln!("CA command failed: {}", e);
+                std::process::exit(1);
+            }
+        }
     }
 }
 
 // cargo run -p cli -- generate-keys --threshold 2 --participants 3 --vault tee-sim
 // --vault memory
 // cargo run -p cli -- aggregate-signature --shares abcd --shares efgh --msg "hello" --vault tee-sim
+// cargo run -p cli -- ca init --dir ./cluster-ca
+// cargo run -p cli -- ca issue --ca-dir ./cluster-ca --node node-a --out ./tls/node-a
+// cargo run -p cli -- ca rotate --ca-dir ./cluster-ca --out /etc/custody/tls
 
 /*
     need to circle back to this part. 
@@ -667,24 +830,35 @@ if let Some(sub) = matches.subcommand_matches("get-mpc-group") {
                 operational_did: op_did.clone(),
                 threshold: *threshold as u32,
                 participant_nodes: peers.clone(),
+                group_id: uuid::Uuid::new_v4().to_string(),
+                ..Default::default()
             }).await?.into_inner();
             println!("🟢 DKG Session started.\nGroup ID: {}", response.group_id);
         }
 
-        DkgCommand::Round2 { group_id } => {
+        DkgCommand::Status { group_id, wait } => {
             let mut client = CustodyDkgClient::connect("http://[::1]:50051").await?;
-            client.broadcast_round2(BroadcastRound2Request {
+            let resp = client.get_dkg_status(GetDkgStatusRequest {
                 group_id: group_id.clone(),
-            }).await?;
-            println!("📤 Round 2 broadcasted.");
+                wait: *wait,
+            }).await?.into_inner();
+            println!("📊 Phase: {}\nRound1: {}/{}\nRound2: {}/{}\nError: {}",
+                resp.phase, resp.round1_received, resp.expected, resp.round2_received, resp.expected, resp.error);
+            for c in &resp.complaints {
+                println!("🚨 {} accuses {}: {} (confirmed: {})", c.accuser, c.accused, c.reason, c.confirmed);
+            }
+            if !resp.misbehaving.is_empty() {
+                println!("Misbehaving: {}", resp.misbehaving.join(", "));
+            }
         }
 
-        DkgCommand::Finalize { group_id } => {
+        DkgCommand::Abort { group_id, reason } => {
             let mut client = CustodyDkgClient::connect("http://[::1]:50051").await?;
-            let resp = client.finalize_dkg_session(FinalizeDkgRequest {
+            client.abort_dkg_session(AbortDkgSessionRequest {
                 group_id: group_id.clone(),
-            }).await?.into_inner();
-            println!("✅ Finalized. Stored shard (base64):\n{}", resp.shard_base64);
+                reason: reason.clone(),
+            }).await?;
+            println!("🛑 DKG session {group_id} aborted.");
         }
     }
     }
@@ -11,6 +11,7 @@ use trust_dns_resolver::{TokioAsyncResolver, config::*};
 pub struct NodeBootstrap {
     pub local_node_id: String,
     pub relay_bind: SocketAddr,
+    pub device_bind: SocketAddr,        // Devices connect here, without a client certificate
     pub peer_nodes: Vec<String>,
 }
 
@@ -21,6 +22,7 @@ pub async fn init_bootstrap(service_dns_name: &str) -> Result<NodeBootstrap, Box
 
     // Relay gRPC bind address
     let relay_bind = "0.0.0.0:50051".parse::<SocketAddr>()?;
+    let device_bind = "0.0.0.0:50052".parse::<SocketAddr>()?;
 
     // Use DNS SRV or A-record lookup for the headless service
     let resolver = TokioAsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default())?;
@@ -36,6 +38,7 @@ pub async fn init_bootstrap(service_dns_name: &str) -> Result<NodeBootstrap, Box
     Ok(NodeBootstrap {
         local_node_id,
         relay_bind,
+        device_bind,
         peer_nodes: peers,
     })
 }
\ No newline at end of file
@@ -0,0 +1,917 @@
+//! FROST ciphersuite selection and suite-generic threshold operations.
+//! The suite is chosen per operational DID at provisioning time and recorded in
+//! `MPCGroupDescriptor.dkg_protocol`. Everything that touches key material goes
+//! through the generic helpers below so DKG, nonces, signing and aggregation
+//! never hardcode a curve.
+
+use std::collections::{BTreeMap, HashMap};
+
+use frost_core::{Ciphersuite, Field, Group, Identifier, Signature, SigningPackage, VerifyingKey};
+use frost_core::keys::{KeyPackage, PublicKeyPackage, SigningShare, VerifyingShare};
+use frost_core::keys::dkg::{self, round1, round2};
+use frost_core::keys::refresh;
+use frost_core::round1::{SigningCommitments, SigningNonces};
+use frost_core::round2::SignatureShare;
+use rand_core::OsRng;
+use sha2::{Digest, Sha512};
+use zeroize::Zeroizing;
+
+use crate::membership::MEMBERSHIP;
+use crate::mpc::device;
+
+/// Supported FROST ciphersuites
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum SuiteId {
+    Ed25519,
+    /// secp256k1 Schnorr, BIP-340 / Taproot compatible
+    Secp256k1Tr,
+    P256,
+    Ristretto255,
+}
+
+impl Default for SuiteId {
+    fn default() -> Self {
+        SuiteId::Ed25519
+    }
+}
+
+impl SuiteId {
+    /// Value recorded in `MPCGroupDescriptor.dkg_protocol`
+    pub fn dkg_protocol(&self) -> &'static str {
+        match self {
+            SuiteId::Ed25519 => "frost-ed25519-dkg-v1",
+            SuiteId::Secp256k1Tr => "frost-secp256k1-tr-dkg-v1",
+            SuiteId::P256 => "frost-p256-dkg-v1",
+            SuiteId::Ristretto255 => "frost-ristretto255-dkg-v1",
+        }
+    }
+
+    /// Short name used in requests and the CLI (e.g. "secp256k1-tr")
+    pub fn name(&self) -> &'static str {
+        match self {
+            SuiteId::Ed25519 => "ed25519",
+            SuiteId::Secp256k1Tr => "secp256k1-tr",
+            SuiteId::P256 => "p256",
+            SuiteId::Ristretto255 => "ristretto255",
+        }
+    }
+
+    /// Parses a suite name from a request. Empty selects the default (Ed25519).
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "" | "ed25519" => Ok(SuiteId::Ed25519),
+            "secp256k1-tr" | "secp256k1" | "bip340" => Ok(SuiteId::Secp256k1Tr),
+            "p256" | "p-256" => Ok(SuiteId::P256),
+            "ristretto255" => Ok(SuiteId::Ristretto255),
+            other => Err(format!("Unsupported ciphersuite: {other}")),
+        }
+    }
+
+    /// Resolves the suite of an existing group. Groups created before suites were
+    /// recorded ("frost-dkg-v1" or no value) are Ed25519.
+    pub fn from_dkg_protocol(protocol: Option<&str>) -> Result<Self, String> {
+        match protocol {
+            None | Some("frost-dkg-v1") | Some("frost-ed25519-dkg-v1") => Ok(SuiteId::Ed25519),
+            Some("frost-secp256k1-tr-dkg-v1") => Ok(SuiteId::Secp256k1Tr),
+            Some("frost-p256-dkg-v1") => Ok(SuiteId::P256),
+            Some("frost-ristretto255-dkg-v1") => Ok(SuiteId::Ristretto255),
+            Some(other) => Err(format!("Unknown DKG protocol: {other}")),
+        }
+    }
+}
+
+/// Runs a generic expression with `$c` bound to the concrete ciphersuite type.
+///
+/// ```ignore
+/// let vk = with_ciphersuite!(suite, |C| ciphersuite::group_verifying_key::<C>(&pkg))?;
+/// ```
+#[macro_export]
+macro_rules! with_ciphersuite {
+    ($suite:expr, |$c:ident| $body:expr) => {
+        match $suite {
+            $crate::ciphersuite::SuiteId::Ed25519 => { type $c = frost_ed25519::Ed25519Sha512; $body }
+            $crate::ciphersuite::SuiteId::Secp256k1Tr => { type $c = frost_secp256k1_tr::Secp256K1Sha256TR; $body }
+            $crate::ciphersuite::SuiteId::P256 => { type $c = frost_p256::P256Sha256; $body }
+            $crate::ciphersuite::SuiteId::Ristretto255 => { type $c = frost_ristretto255::Ristretto255Sha512; $body }
+        }
+    };
+}
+
+/// Result of a completed DKG, serialized for the vault and registry
+pub struct DkgOutput {
+    pub key_package: Vec<u8>,                     // Sealed into the vault as this node's shard
+    pub public_key_package: Vec<u8>,              // Stored on the MPCGroupDescriptor
+    pub group_public_key: Vec<u8>,                // Group verifying key bytes
+    pub verifying_shares: Vec<(String, Vec<u8>)>, // node_id → verifying share
+}
+
+/// A DKG step that failed, naming the participant whose package caused it when FROST can tell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DkgFault {
+    pub culprit: Option<String>, // node_id of the misbehaving participant
+    pub reason: String,
+}
+
+impl DkgFault {
+    fn local(reason: String) -> Self {
+        DkgFault { culprit: None, reason }
+    }
+
+    fn from_frost<C: Ciphersuite>(step: &str, e: frost_core::Error<C>, node_ids: &HashMap<Identifier<C>, String>) -> Self {
+        DkgFault {
+            culprit: e.culprit().and_then(|id| node_ids.get(&id).cloned()),
+            reason: format!("dkg {step} failed: {e:?}"),
+        }
+    }
+}
+
+impl std::fmt::Display for DkgFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.culprit {
+            Some(culprit) => write!(f, "{} (caused by {culprit})", self.reason),
+            None => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+impl From<DkgFault> for String {
+    fn from(fault: DkgFault) -> String {
+        fault.to_string()
+    }
+}
+
+/// Maps a participant to its FROST identifier. Enrolled custody nodes use the identifier
+/// membership gave them; devices, whose participant ID names their enrolled device,
+/// derive one from that ID. Any other participant has no identifier.
+pub fn participant_identifier<C: Ciphersuite>(node_id: &str) -> Result<Identifier<C>, String> {
+    match MEMBERSHIP.identifier_of(node_id) {
+        Some(identifier) => Identifier::try_from(identifier),
+        None if device::is_device_participant(node_id) => Identifier::derive(node_id.as_bytes()),
+        None => return Err(format!("{node_id} is not an enrolled custody node")),
+    }.map_err(|e| format!("bad identifier for {node_id}: {e:?}"))
+}
+
+/// Serialized FROST identifier of a participant, as recorded in its member descriptor
+pub fn participant_identifier_bytes<C: Ciphersuite>(node_id: &str) -> Result<Vec<u8>, String> {
+    Ok(participant_identifier::<C>(node_id)?.serialize())
+}
+
+/// DKG part 1: returns (serialized round1 secret, serialized round1 broadcast package)
+pub fn dkg_part1<C: Ciphersuite>(node_id: &str, max_signers: u16, min_signers: u16) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let id = participant_identifier::<C>(node_id)?;
+    let (secret, package) = dkg::part1(id, max_signers, min_signers, OsRng)
+        .map_err(|e| format!("dkg part1 failed: {e:?}"))?;
+
+    Ok((
+        secret.serialize().map_err(|e| format!("serialize failed: {e:?}"))?,
+        package.serialize().map_err(|e| format!("serialize failed: {e:?}"))?,
+    ))
+}
+
+/// Checks a peer's round1 package on arrival: it must decode and commit to the
+/// expected number of coefficients (`min_signers` for a DKG, one fewer for a
+/// refresh, whose constant term is zero). The proof of knowledge is checked in part 2.
+pub fn check_round1_package<C: Ciphersuite>(package: &[u8], coefficients: u16) -> Result<(), String> {
+    let package = round1::Package::<C>::deserialize(package).map_err(|e| format!("undecodable round1 package: {e:?}"))?;
+    let degree = package.commitment().coefficients().len();
+    if degree != coefficients as usize {
+        return Err(format!("round1 package commits to {degree} coefficients, expected {coefficients}"));
+    }
+    Ok(())
+}
+
+/// DKG part 2: consumes the round1 secret and every peer's round1 package.
+/// Returns the round2 secret and one round2 package per peer (node_id → package).
+pub fn dkg_part2<C: Ciphersuite>(
+    round1_secret: &[u8],
+    round1_received: &HashMap<String, Vec<u8>>,
+) -> Result<(Vec<u8>, HashMap<String, Vec<u8>>), DkgFault> {
+    let secret = round1::SecretPackage::<C>::deserialize(round1_secret)
+        .map_err(|e| DkgFault::local(format!("bad round1 secret: {e:?}")))?;
+    let (round1_packages, node_ids) = decode_round1::<C>(round1_received)?;
+
+    let (secret2, packages) = dkg::part2(secret, &round1_packages)
+        .map_err(|e| DkgFault::from_frost("part2", e, &node_ids))?;
+
+    let mut outgoing = HashMap::new();
+    for (id, package) in packages {
+        let node_id = node_ids.get(&id).ok_or_else(|| DkgFault::local("round2 package for unknown participant".into()))?;
+        outgoing.insert(node_id.clone(), package.serialize().map_err(|e| DkgFault::local(format!("serialize failed: {e:?}")))?);
+    }
+
+    Ok((secret2.serialize().map_err(|e| DkgFault::local(format!("serialize failed: {e:?}")))?, outgoing))
+}
+
+/// DKG part 3: produces this node's key package and the group public key package
+pub fn dkg_part3<C: Ciphersuite>(
+    round2_secret: &[u8],
+    round1_received: &HashMap<String, Vec<u8>>,
+    round2_received: &HashMap<String, Vec<u8>>,
+    participant_ids: &[String],
+) -> Result<DkgOutput, DkgFault> {
+    let secret = round2::SecretPackage::<C>::deserialize(round2_secret)
+        .map_err(|e| DkgFault::local(format!("bad round2 secret: {e:?}")))?;
+    let (round1_packages, node_ids) = decode_round1::<C>(round1_received)?;
+
+    let mut round2_packages = BTreeMap::new();
+    for (node_id, raw) in round2_received {
+        let package = round2::Package::<C>::deserialize(raw).map_err(|e| DkgFault {
+            culprit: Some(node_id.clone()),
+            reason: format!("bad round2 package: {e:?}"),
+        })?;
+        round2_packages.insert(participant_identifier::<C>(node_id).map_err(DkgFault::local)?, package);
+    }
+
+    let (key_package, public_key_package) = dkg::part3(&secret, &round1_packages, &round2_packages)
+        .map_err(|e| DkgFault::from_frost("part3", e, &node_ids))?;
+
+    let mut verifying_shares = Vec::new();
+    for node_id in participant_ids {
+        let id = participant_identifier::<C>(node_id).map_err(DkgFault::local)?;
+        let share = public_key_package.verifying_shares().get(&id)
+            .ok_or_else(|| DkgFault::local(format!("missing verifying share for {node_id}")))?;
+        verifying_shares.push((node_id.clone(), share.serialize().map_err(|e| DkgFault::local(format!("serialize failed: {e:?}")))?));
+    }
+
+    let serialize_failed = |e: frost_core::Error<C>| DkgFault::local(format!("serialize failed: {e:?}"));
+    Ok(DkgOutput {
+        key_package: key_package.serialize().map_err(serialize_failed)?,
+        public_key_package: public_key_package.serialize().map_err(serialize_failed)?,
+        group_public_key: public_key_package.verifying_key().serialize().map_err(serialize_failed)?,
+        verifying_shares,
+    })
+}
+
+// ==============================
+// Proactive share refresh
+// ==============================
+
+/// Refresh part 1: like `dkg_part1`, but every node deals a sharing of zero, so
+/// adding the results to the old shares changes them without changing the group key
+pub fn refresh_part1<C: Ciphersuite>(node_id: &str, max_signers: u16, min_signers: u16) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let id = participant_identifier::<C>(node_id)?;
+    let (secret, package) = refresh::refresh_dkg_part_1(id, max_signers, min_signers, OsRng)
+        .map_err(|e| format!("refresh part1 failed: {e:?}"))?;
+
+    Ok((
+        secret.serialize().map_err(|e| format!("serialize failed: {e:?}"))?,
+        package.serialize().map_err(|e| format!("serialize failed: {e:?}"))?,
+    ))
+}
+
+/// Refresh part 2: one round2 package per peer (node_id → package)
+pub fn refresh_part2<C: Ciphersuite>(
+    round1_secret: &[u8],
+    round1_received: &HashMap<String, Vec<u8>>,
+) -> Result<(Vec<u8>, HashMap<String, Vec<u8>>), DkgFault> {
+    let secret = round1::SecretPackage::<C>::deserialize(round1_secret)
+        .map_err(|e| DkgFault::local(format!("bad round1 secret: {e:?}")))?;
+    let (round1_packages, node_ids) = decode_round1::<C>(round1_received)?;
+
+    let (secret2, packages) = refresh::refresh_dkg_part2(secret, &round1_packages)
+        .map_err(|e| DkgFault::from_frost("refresh part2", e, &node_ids))?;
+
+    let mut outgoing = HashMap::new();
+    for (id, package) in packages {
+        let node_id = node_ids.get(&id).ok_or_else(|| DkgFault::local("round2 package for unknown participant".into()))?;
+        outgoing.insert(node_id.clone(), package.serialize().map_err(|e| DkgFault::local(format!("serialize failed: {e:?}")))?);
+    }
+
+    Ok((secret2.serialize().map_err(|e| DkgFault::local(format!("serialize failed: {e:?}")))?, outgoing))
+}
+
+/// Refresh part 3: adds the zero sharing to this node's old key package. The
+/// group verifying key of the output is the old one; every verifying share changes.
+pub fn refresh_part3<C: Ciphersuite>(
+    round2_secret: &[u8],
+    round1_received: &HashMap<String, Vec<u8>>,
+    round2_received: &HashMap<String, Vec<u8>>,
+    participant_ids: &[String],
+    old_key_package: &[u8],
+    old_public_key_package: &[u8],
+) -> Result<DkgOutput, DkgFault> {
+    let secret = round2::SecretPackage::<C>::deserialize(round2_secret)
+        .map_err(|e| DkgFault::local(format!("bad round2 secret: {e:?}")))?;
+    let old_key_package = KeyPackage::<C>::deserialize(old_key_package)
+        .map_err(|_| DkgFault::local("bad shard".into()))?;
+    let old_pubkeys = PublicKeyPackage::<C>::deserialize(old_public_key_package)
+        .map_err(|e| DkgFault::local(format!("bad group pubkey: {e:?}")))?;
+    let (round1_packages, node_ids) = decode_round1::<C>(round1_received)?;
+
+    let mut round2_packages = BTreeMap::new();
+    for (node_id, raw) in round2_received {
+        let package = round2::Package::<C>::deserialize(raw).map_err(|e| DkgFault {
+            culprit: Some(node_id.clone()),
+            reason: format!("bad round2 package: {e:?}"),
+        })?;
+        round2_packages.insert(participant_identifier::<C>(node_id).map_err(DkgFault::local)?, package);
+    }
+
+    let (key_package, public_key_package) = refresh::refresh_dkg_shares(&secret, &round1_packages, &round2_packages, old_pubkeys.clone(), old_key_package)
+        .map_err(|e| DkgFault::from_frost("refresh part3", e, &node_ids))?;
+    if public_key_package.verifying_key() != old_pubkeys.verifying_key() {
+        return Err(DkgFault::local("refresh changed the group key".into()));
+    }
+
+    let mut verifying_shares = Vec::new();
+    for node_id in participant_ids {
+        let id = participant_identifier::<C>(node_id).map_err(DkgFault::local)?;
+        let share = public_key_package.verifying_shares().get(&id)
+            .ok_or_else(|| DkgFault::local(format!("missing verifying share for {node_id}")))?;
+        verifying_shares.push((node_id.clone(), share.serialize().map_err(|e| DkgFault::local(format!("serialize failed: {e:?}")))?));
+    }
+
+    let serialize_failed = |e: frost_core::Error<C>| DkgFault::local(format!("serialize failed: {e:?}"));
+    Ok(DkgOutput {
+        key_package: key_package.serialize().map_err(serialize_failed)?,
+        public_key_package: public_key_package.serialize().map_err(serialize_failed)?,
+        group_public_key: public_key_package.verifying_key().serialize().map_err(serialize_failed)?,
+        verifying_shares,
+    })
+}
+
+// ==============================
+// Dealing existing key material
+// ==============================
+
+/// Result of a completed reshare or import. A dealer outside the new committee gets no key package.
+pub struct DealtOutput {
+    pub key_package: Option<Vec<u8>>,
+    pub public_key_package: Vec<u8>,
+    pub group_public_key: Vec<u8>,
+    pub verifying_shares: Vec<(String, Vec<u8>)>, // node_id → verifying share, new committee only
+}
+
+/// Reshare deal: this old member weights its share by its Lagrange coefficient among
+/// `dealers` and splits the result t'-of-n' over `members`. Returns the Feldman
+/// commitment to the polynomial (broadcast) and one share per member (node_id → share,
+/// sealed to its recipient). Any threshold of old members can deal; the weighted
+/// shares add up to the group secret, so the members' sums are shares of it.
+pub fn reshare_deal<C: Ciphersuite>(
+    key_package: &[u8],
+    dealers: &[String],
+    members: &[String],
+    threshold: u16,
+) -> Result<(Vec<u8>, HashMap<String, Vec<u8>>), String> {
+    let key_package = KeyPackage::<C>::deserialize(key_package).map_err(|_| "bad shard")?;
+    let dealer_ids = dealers.iter().map(|d| participant_identifier::<C>(d)).collect::<Result<Vec<_>, _>>()?;
+    if !dealer_ids.contains(key_package.identifier()) {
+        return Err("this node is not one of the dealers".into());
+    }
+
+    let lambda = lagrange_coefficient::<C>(&dealer_ids, key_package.identifier())?;
+    let secret = decode_scalar::<C>(&key_package.signing_share().serialize())? * lambda;
+    deal_polynomial::<C>(secret, members, threshold)
+}
+
+/// Reshare finish: checks every dealer's commitment against its old verifying share
+/// and, for a member of the new committee, every share it was dealt against the
+/// commitment. Builds the new committee's public key package, whose group key must be
+/// the old one, and this node's key package if it is a member.
+pub fn reshare_finish<C: Ciphersuite>(
+    node_id: &str,
+    commitments: &HashMap<String, Vec<u8>>,
+    shares: &HashMap<String, Vec<u8>>,
+    dealers: &[String],
+    members: &[String],
+    threshold: u16,
+    old_public_key_package: &[u8],
+) -> Result<DealtOutput, DkgFault> {
+    let old_pubkeys = PublicKeyPackage::<C>::deserialize(old_public_key_package)
+        .map_err(|e| DkgFault::local(format!("bad group pubkey: {e:?}")))?;
+    let dealer_ids = dealers.iter()
+        .map(|d| participant_identifier::<C>(d))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(DkgFault::local)?;
+
+    // Each dealer must deal its own weighted share, or the group key would move
+    let mut constant_terms = Vec::new();
+    for (dealer, id) in dealers.iter().zip(&dealer_ids) {
+        let old_share = old_pubkeys.verifying_shares().get(id).ok_or_else(|| DkgFault {
+            culprit: Some(dealer.clone()),
+            reason: "dealer holds no share of the group".into(),
+        })?;
+        let old_share = decode_element::<C>(&old_share.serialize().map_err(|e| DkgFault::local(format!("serialize failed: {e:?}")))?)
+            .map_err(DkgFault::local)?;
+        constant_terms.push(old_share * lagrange_coefficient::<C>(&dealer_ids, id).map_err(DkgFault::local)?);
+    }
+
+    combine_dealings::<C>(node_id, commitments, shares, dealers, &constant_terms, members, threshold, old_pubkeys.verifying_key())
+}
+
+/// Expands an RFC 8032 Ed25519 private key (the 32-byte seed, or seed followed by
+/// the public key) into the signing scalar FROST shares. Fails if an appended public
+/// key does not belong to the seed.
+pub fn ed25519_signing_key(secret: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    type Ed = frost_ed25519::Ed25519Sha512;
+    let seed = match secret.len() {
+        32 | 64 => &secret[..32],
+        other => return Err(format!("an Ed25519 private key is 32 or 64 bytes, got {other}")),
+    };
+
+    let mut expanded = Zeroizing::new([0u8; 32]);
+    expanded.copy_from_slice(&Sha512::digest(seed)[..32]);
+    expanded[0] &= 248;
+    expanded[31] &= 127;
+    expanded[31] |= 64;
+    let scalar = <<<Ed as Ciphersuite>::Group as Group>::Field as Field>::Scalar::from_bytes_mod_order(*expanded);
+
+    if secret.len() == 64 {
+        let public = encode_element::<Ed>(&(<Ed as Ciphersuite>::Group::generator() * scalar))?;
+        if AsRef::<[u8]>::as_ref(&public) != &secret[32..] {
+            return Err("the public key does not belong to the private key".into());
+        }
+    }
+    let serialized = <<<Ed as Ciphersuite>::Group as Group>::Field as Field>::serialize(&scalar);
+    Ok(Zeroizing::new(AsRef::<[u8]>::as_ref(&serialized).to_vec()))
+}
+
+/// Import deal: splits an existing signing key t-of-n over `members` (trusted-dealer
+/// Shamir sharing with a Feldman commitment). The key must be the one behind
+/// `group_public_key`, which every member checks its share against.
+pub fn import_deal<C: Ciphersuite>(
+    signing_key: &[u8],
+    group_public_key: &[u8],
+    members: &[String],
+    threshold: u16,
+) -> Result<(Vec<u8>, HashMap<String, Vec<u8>>), String> {
+    let secret = decode_scalar::<C>(signing_key)?;
+    if encode_element::<C>(&(C::Group::generator() * secret))?.as_ref() != group_public_key {
+        return Err("the key does not match the public key being imported".into());
+    }
+    deal_polynomial::<C>(secret, members, threshold)
+}
+
+/// Import finish: like `reshare_finish` with one dealer, whose commitment must open
+/// to the public key being imported
+pub fn import_finish<C: Ciphersuite>(
+    node_id: &str,
+    commitments: &HashMap<String, Vec<u8>>,
+    shares: &HashMap<String, Vec<u8>>,
+    dealer: &str,
+    members: &[String],
+    threshold: u16,
+    group_public_key: &[u8],
+) -> Result<DealtOutput, DkgFault> {
+    let verifying_key = VerifyingKey::<C>::deserialize(group_public_key)
+        .map_err(|e| DkgFault::local(format!("bad group public key: {e:?}")))?;
+    let constant = decode_element::<C>(group_public_key).map_err(DkgFault::local)?;
+
+    combine_dealings::<C>(node_id, commitments, shares, &[dealer.to_string()], &[constant], members, threshold, &verifying_key)
+}
+
+/// Checks a dealer's commitment on arrival: it must decode into `coefficients` group
+/// elements. Whether it commits to the right secret is checked when finishing.
+pub fn check_dealing_commitment<C: Ciphersuite>(commitment: &[u8], coefficients: u16) -> Result<(), String> {
+    decode_commitment::<C>(commitment, coefficients).map(|_| ())
+}
+
+/// Splits `secret` t-of-n over `members`: returns the Feldman commitment and one share per member
+fn deal_polynomial<C: Ciphersuite>(
+    secret: ScalarOf<C>,
+    members: &[String],
+    threshold: u16,
+) -> Result<(Vec<u8>, HashMap<String, Vec<u8>>), String> {
+    if threshold == 0 || threshold as usize > members.len() {
+        return Err(format!("threshold {threshold} does not fit {} members", members.len()));
+    }
+
+    let mut coefficients = vec![secret];
+    for _ in 1..threshold {
+        coefficients.push(<<C::Group as Group>::Field as Field>::random(&mut OsRng));
+    }
+
+    let commitment = coefficients.iter()
+        .map(|a| encode_element::<C>(&(C::Group::generator() * *a)).map(|e| e.as_ref().to_vec()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let commitment = bincode::serialize(&commitment).map_err(|e| format!("serialize failed: {e}"))?;
+
+    let mut shares = HashMap::new();
+    for member in members {
+        let x = identifier_scalar::<C>(&participant_identifier::<C>(member)?)?;
+        let share = coefficients.iter().rev()
+            .fold(<<C::Group as Group>::Field as Field>::zero(), |acc, a| acc * x + *a);
+        shares.insert(member.clone(), <<C::Group as Group>::Field as Field>::serialize(&share).as_ref().to_vec());
+    }
+
+    Ok((commitment, shares))
+}
+
+/// Adds up every dealer's dealing. Each commitment must open with the dealer's entry in
+/// `constant_terms`, and a member's shares must match the commitments. The summed
+/// commitment gives the new committee's verifying shares; its constant term must be
+/// `verifying_key`.
+#[allow(clippy::too_many_arguments)]
+fn combine_dealings<C: Ciphersuite>(
+    node_id: &str,
+    commitments: &HashMap<String, Vec<u8>>,
+    shares: &HashMap<String, Vec<u8>>,
+    dealers: &[String],
+    constant_terms: &[ElementOf<C>],
+    members: &[String],
+    threshold: u16,
+    verifying_key: &VerifyingKey<C>,
+) -> Result<DealtOutput, DkgFault> {
+    let is_member = members.iter().any(|m| m == node_id);
+    let x_self = identifier_scalar::<C>(&participant_identifier::<C>(node_id).map_err(DkgFault::local)?).map_err(DkgFault::local)?;
+
+    let mut group_commitment = vec![C::Group::identity(); threshold as usize];
+    let mut signing_share = <<C::Group as Group>::Field as Field>::zero();
+    for (dealer, constant_term) in dealers.iter().zip(constant_terms) {
+        let blame = |reason: String| DkgFault { culprit: Some(dealer.clone()), reason };
+        let raw = commitments.get(dealer).ok_or_else(|| DkgFault::local(format!("no commitment from {dealer}")))?;
+        let commitment = decode_commitment::<C>(raw, threshold).map_err(blame)?;
+        if commitment[0] != *constant_term {
+            return Err(blame("dealt a secret other than the one it holds".into()));
+        }
+
+        if is_member {
+            let raw = shares.get(dealer).ok_or_else(|| DkgFault::local(format!("no share from {dealer}")))?;
+            let share = decode_scalar::<C>(raw).map_err(blame)?;
+            if C::Group::generator() * share != evaluate_commitment::<C>(&commitment, x_self) {
+                return Err(blame("dealt a share that does not match its commitment".into()));
+            }
+            signing_share = signing_share + share;
+        }
+
+        for (sum, term) in group_commitment.iter_mut().zip(&commitment) {
+            *sum = *sum + *term;
+        }
+    }
+
+    let serialize_failed = |e: frost_core::Error<C>| DkgFault::local(format!("serialize failed: {e:?}"));
+    if group_commitment[0] != decode_element::<C>(&verifying_key.serialize().map_err(serialize_failed)?).map_err(DkgFault::local)? {
+        return Err(DkgFault::local("dealt shares do not add up to the group key; too few dealers?".into()));
+    }
+
+    let mut new_shares = BTreeMap::new();
+    let mut verifying_shares = Vec::new();
+    for member in members {
+        let id = participant_identifier::<C>(member).map_err(DkgFault::local)?;
+        let element = evaluate_commitment::<C>(&group_commitment, identifier_scalar::<C>(&id).map_err(DkgFault::local)?);
+        let share = VerifyingShare::<C>::deserialize(encode_element::<C>(&element).map_err(DkgFault::local)?.as_ref())
+            .map_err(|e| DkgFault::local(format!("bad verifying share: {e:?}")))?;
+        verifying_shares.push((member.clone(), share.serialize().map_err(serialize_failed)?));
+        new_shares.insert(id, share);
+    }
+    let public_key_package = PublicKeyPackage::new(new_shares, *verifying_key);
+
+    let key_package = if is_member {
+        let id = participant_identifier::<C>(node_id).map_err(DkgFault::local)?;
+        let key_package = KeyPackage::new(
+            id,
+            SigningShare::<C>::deserialize(<<C::Group as Group>::Field as Field>::serialize(&signing_share).as_ref())
+                .map_err(|e| DkgFault::local(format!("bad signing share: {e:?}")))?,
+            public_key_package.verifying_shares()[&id],
+            *public_key_package.verifying_key(),
+            threshold,
+        );
+        Some(key_package.serialize().map_err(serialize_failed)?)
+    } else {
+        None
+    };
+
+    Ok(DealtOutput {
+        key_package,
+        public_key_package: public_key_package.serialize().map_err(serialize_failed)?,
+        group_public_key: public_key_package.verifying_key().serialize().map_err(serialize_failed)?,
+        verifying_shares,
+    })
+}
+
+/// λ_i = Π x_j / (x_j - x_i) over the other identifiers in `ids`
+fn lagrange_coefficient<C: Ciphersuite>(ids: &[Identifier<C>], id: &Identifier<C>) -> Result<ScalarOf<C>, String> {
+    lagrange_coefficient_at::<C>(ids, id, <<C::Group as Group>::Field as Field>::zero())
+}
+
+/// λ_i(x) = Π (x - x_j) / (x_i - x_j) over the other identifiers in `ids`
+fn lagrange_coefficient_at<C: Ciphersuite>(ids: &[Identifier<C>], id: &Identifier<C>, x: ScalarOf<C>) -> Result<ScalarOf<C>, String> {
+    let x_i = identifier_scalar::<C>(id)?;
+    let mut numerator = <<C::Group as Group>::Field as Field>::one();
+    let mut denominator = <<C::Group as Group>::Field as Field>::one();
+    for other in ids.iter().filter(|other| *other != id) {
+        let x_j = identifier_scalar::<C>(other)?;
+        numerator = numerator * (x - x_j);
+        denominator = denominator * (x_i - x_j);
+    }
+    let inverse = <<C::Group as Group>::Field as Field>::invert(&denominator).map_err(|_| "duplicate participant")?;
+    Ok(numerator * inverse)
+}
+
+fn identifier_scalar<C: Ciphersuite>(id: &Identifier<C>) -> Result<ScalarOf<C>, String> {
+    decode_scalar::<C>(&id.serialize())
+}
+
+fn decode_commitment<C: Ciphersuite>(commitment: &[u8], coefficients: u16) -> Result<Vec<ElementOf<C>>, String> {
+    let elements: Vec<Vec<u8>> = bincode::deserialize(commitment).map_err(|_| "undecodable commitment")?;
+    if elements.len() != coefficients as usize {
+        return Err(format!("commitment has {} coefficients, expected {coefficients}", elements.len()));
+    }
+    elements.iter().map(|e| decode_element::<C>(e)).collect()
+}
+
+/// Σ C_k · x^k
+fn evaluate_commitment<C: Ciphersuite>(commitment: &[ElementOf<C>], x: ScalarOf<C>) -> ElementOf<C> {
+    commitment.iter().rev().fold(C::Group::identity(), |acc, c| acc * x + *c)
+}
+
+/// Signing round 1: returns (serialized nonces for the vault, serialized public commitments)
+pub fn commit<C: Ciphersuite>(key_package: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let key_package = KeyPackage::<C>::deserialize(key_package).map_err(|_| "bad shard")?;
+    let (nonces, commitments) = frost_core::round1::commit(key_package.signing_share(), &mut OsRng);
+
+    Ok((
+        nonces.serialize().map_err(|e| format!("serialize failed: {e:?}"))?,
+        commitments.serialize().map_err(|e| format!("serialize failed: {e:?}"))?,
+    ))
+}
+
+/// Signing round 2: computes this node's signature share
+pub fn sign<C: Ciphersuite>(
+    key_package: &[u8],
+    nonces: &[u8],
+    message: &[u8],
+    commitments: &[(String, Vec<u8>)],
+) -> Result<Vec<u8>, String> {
+    let key_package = KeyPackage::<C>::deserialize(key_package).map_err(|_| "bad shard")?;
+    let nonces = SigningNonces::<C>::deserialize(nonces).map_err(|_| "bad nonce format")?;
+    let signing_package = signing_package::<C>(message, commitments)?;
+
+    let share = frost_core::round2::sign(&signing_package, &nonces, &key_package)
+        .map_err(|e| format!("signing failed: {e:?}"))?;
+
+    Ok(share.serialize())
+}
+
+/// Aggregates signature shares into a group signature
+pub fn aggregate<C: Ciphersuite>(
+    message: &[u8],
+    commitments: &[(String, Vec<u8>)],
+    shares: &[(String, Vec<u8>)],
+    public_key_package: &[u8],
+) -> Result<Vec<u8>, String> {
+    let signing_package = signing_package::<C>(message, commitments)?;
+    let pubkeys = PublicKeyPackage::<C>::deserialize(public_key_package)
+        .map_err(|e| format!("bad group pubkey: {e:?}"))?;
+
+    let mut signature_shares = BTreeMap::new();
+    for (node_id, raw) in shares {
+        let share = SignatureShare::<C>::deserialize(raw).map_err(|_| "Invalid sig")?;
+        signature_shares.insert(participant_identifier::<C>(node_id)?, share);
+    }
+
+    let signature = frost_core::aggregate(&signing_package, &signature_shares, &pubkeys)
+        .map_err(|e| format!("Aggregation failed: {e:?}"))?;
+
+    signature.serialize().map_err(|e| format!("serialize failed: {e:?}"))
+}
+
+/// Returns the group verifying key bytes from a serialized public key package
+pub fn group_verifying_key<C: Ciphersuite>(public_key_package: &[u8]) -> Result<Vec<u8>, String> {
+    let pubkeys = PublicKeyPackage::<C>::deserialize(public_key_package)
+        .map_err(|e| format!("bad group pubkey: {e:?}"))?;
+    pubkeys.verifying_key().serialize().map_err(|e| format!("serialize failed: {e:?}"))
+}
+
+/// Verifies a group signature against a verifying key
+pub fn verify<C: Ciphersuite>(verifying_key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), String> {
+    let key = VerifyingKey::<C>::deserialize(verifying_key).map_err(|e| format!("bad verifying key: {e:?}"))?;
+    let sig = Signature::<C>::deserialize(signature).map_err(|e| format!("bad signature: {e:?}"))?;
+    key.verify(message, &sig).map_err(|e| format!("verification failed: {e:?}"))
+}
+
+// ==============================
+// Share repair
+// ==============================
+
+/// Repair step 1, run by each of the t helpers: weights this helper's share by its
+/// Lagrange coefficient for the lost member's identifier and splits the result into
+/// random parts, one per helper (node_id → part, sealed to its recipient). No part
+/// reveals anything about the share on its own.
+pub fn repair_step1<C: Ciphersuite>(key_package: &[u8], helpers: &[String], lost: &str) -> Result<HashMap<String, Vec<u8>>, String> {
+    let key_package = KeyPackage::<C>::deserialize(key_package).map_err(|_| "bad shard")?;
+    let helper_ids = helpers.iter().map(|h| participant_identifier::<C>(h)).collect::<Result<Vec<_>, _>>()?;
+    if !helper_ids.contains(key_package.identifier()) {
+        return Err("this node is not one of the helpers".into());
+    }
+    if helpers.iter().any(|h| h == lost) {
+        return Err("the lost member cannot help repair its own share".into());
+    }
+    let x_lost = identifier_scalar::<C>(&participant_identifier::<C>(lost)?)?;
+
+    let share = decode_scalar::<C>(&key_package.signing_share().serialize())?;
+    let delta = share * lagrange_coefficient_at::<C>(&helper_ids, key_package.identifier(), x_lost)?;
+
+    // Random parts for all but the last helper, which gets what makes them add up to delta
+    let mut parts = HashMap::new();
+    let mut remaining = delta;
+    for helper in &helpers[..helpers.len() - 1] {
+        let part = <<C::Group as Group>::Field as Field>::random(&mut OsRng);
+        remaining = remaining - part;
+        parts.insert(helper.clone(), <<C::Group as Group>::Field as Field>::serialize(&part).as_ref().to_vec());
+    }
+    parts.insert(helpers[helpers.len() - 1].clone(), <<C::Group as Group>::Field as Field>::serialize(&remaining).as_ref().to_vec());
+    Ok(parts)
+}
+
+/// Repair step 2, run by each helper: adds up the parts it was sent (sender → part),
+/// its own included. The sum goes to the lost member.
+pub fn repair_step2<C: Ciphersuite>(parts: &HashMap<String, Vec<u8>>) -> Result<Vec<u8>, DkgFault> {
+    let mut sum = <<C::Group as Group>::Field as Field>::zero();
+    for (helper, part) in parts {
+        sum = sum + decode_scalar::<C>(part).map_err(|reason| DkgFault { culprit: Some(helper.clone()), reason })?;
+    }
+    Ok(<<C::Group as Group>::Field as Field>::serialize(&sum).as_ref().to_vec())
+}
+
+/// Repair step 3, run by the lost member: adds up the helpers' sums (helper → sum) into
+/// its signing share and checks it against the verifying share the group has on record
+/// for it. Returns the serialized key package to seal.
+pub fn repair_finish<C: Ciphersuite>(
+    node_id: &str,
+    sums: &HashMap<String, Vec<u8>>,
+    public_key_package: &[u8],
+    threshold: u16,
+) -> Result<Vec<u8>, DkgFault> {
+    let pubkeys = PublicKeyPackage::<C>::deserialize(public_key_package)
+        .map_err(|e| DkgFault::local(format!("bad public key package: {e:?}")))?;
+    let id = participant_identifier::<C>(node_id).map_err(DkgFault::local)?;
+    let verifying_share = *pubkeys.verifying_shares().get(&id)
+        .ok_or_else(|| DkgFault::local(format!("{node_id} is not a member of the group")))?;
+
+    let mut share = <<C::Group as Group>::Field as Field>::zero();
+    for (helper, sum) in sums {
+        share = share + decode_scalar::<C>(sum).map_err(|reason| DkgFault { culprit: Some(helper.clone()), reason })?;
+    }
+
+    // A wrong sum cannot be pinned on one helper; the repair just fails
+    let serialize_failed = |e: frost_core::Error<C>| DkgFault::local(format!("serialize failed: {e:?}"));
+    let expected = decode_element::<C>(&verifying_share.serialize().map_err(serialize_failed)?).map_err(DkgFault::local)?;
+    if C::Group::generator() * share != expected {
+        return Err(DkgFault::local("repaired share does not match the verifying share on record".into()));
+    }
+
+    let key_package = KeyPackage::new(
+        id,
+        SigningShare::<C>::deserialize(<<C::Group as Group>::Field as Field>::serialize(&share).as_ref())
+            .map_err(|e| DkgFault::local(format!("bad signing share: {e:?}")))?,
+        verifying_share,
+        *pubkeys.verifying_key(),
+        threshold,
+    );
+    key_package.serialize().map_err(serialize_failed)
+}
+
+// ==============================
+// Child key derivation
+// ==============================
+
+/// Domain separator for derivation tweaks
+const DERIVATION_DOMAIN: &[u8] = b"custody-child-key-v1";
+
+type ScalarOf<C> = <<<C as Ciphersuite>::Group as Group>::Field as Field>::Scalar;
+type ElementOf<C> = <<C as Ciphersuite>::Group as Group>::Element;
+
+/// Group key material of a derived child key
+pub struct DerivedGroup {
+    pub public_key_package: Vec<u8>,
+    pub group_public_key: Vec<u8>,
+    pub verifying_shares: Vec<(String, Vec<u8>)>, // node_id → tweaked verifying share
+}
+
+/// Splits a derivation path such as `m/dids/42` into its segments
+pub fn parse_derivation_path(path: &str) -> Result<Vec<&str>, String> {
+    let mut segments = path.split('/');
+    if segments.next() != Some("m") {
+        return Err(format!("derivation path must start with 'm/': {path}"));
+    }
+
+    let segments = segments.collect::<Vec<_>>();
+    if segments.is_empty() || segments.iter().any(|s| s.is_empty()) {
+        return Err(format!("derivation path has an empty segment: {path}"));
+    }
+    Ok(segments)
+}
+
+/// Tweaks this node's key package so it signs for the child key at `path`.
+/// Every share moves by the same public scalar, so any threshold of tweaked
+/// shares produces a signature valid under the tweaked group key.
+pub fn derive_key_package<C: Ciphersuite>(key_package: &[u8], path: &str) -> Result<Vec<u8>, String> {
+    let key_package = KeyPackage::<C>::deserialize(key_package).map_err(|_| "bad shard")?;
+    let parent_key = key_package.verifying_key().serialize().map_err(|e| format!("serialize failed: {e:?}"))?;
+    let tweak = derivation_tweak::<C>(&parent_key, path)?;
+    let offset = C::Group::generator() * tweak;
+
+    let signing_share = decode_scalar::<C>(&key_package.signing_share().serialize())? + tweak;
+    let verifying_share = decode_element::<C>(&key_package.verifying_share().serialize().map_err(|e| format!("serialize failed: {e:?}"))?)? + offset;
+    let verifying_key = decode_element::<C>(&parent_key)? + offset;
+
+    let derived = KeyPackage::new(
+        *key_package.identifier(),
+        SigningShare::<C>::deserialize(<C::Group as Group>::Field::serialize(&signing_share).as_ref())
+            .map_err(|e| format!("bad derived share: {e:?}"))?,
+        VerifyingShare::<C>::deserialize(encode_element::<C>(&verifying_share)?.as_ref())
+            .map_err(|e| format!("bad derived verifying share: {e:?}"))?,
+        VerifyingKey::<C>::deserialize(encode_element::<C>(&verifying_key)?.as_ref())
+            .map_err(|e| format!("bad derived key: {e:?}"))?,
+        *key_package.min_signers(),
+    );
+
+    derived.serialize().map_err(|e| format!("serialize failed: {e:?}"))
+}
+
+/// Tweaks a group public key package to the child key at `path`
+pub fn derive_public_key_package<C: Ciphersuite>(
+    public_key_package: &[u8],
+    path: &str,
+    participant_ids: &[String],
+) -> Result<DerivedGroup, String> {
+    let pubkeys = PublicKeyPackage::<C>::deserialize(public_key_package)
+        .map_err(|e| format!("bad group pubkey: {e:?}"))?;
+    let parent_key = pubkeys.verifying_key().serialize().map_err(|e| format!("serialize failed: {e:?}"))?;
+    let offset = C::Group::generator() * derivation_tweak::<C>(&parent_key, path)?;
+
+    let mut shares = BTreeMap::new();
+    for (id, share) in pubkeys.verifying_shares() {
+        let tweaked = decode_element::<C>(&share.serialize().map_err(|e| format!("serialize failed: {e:?}"))?)? + offset;
+        let tweaked = VerifyingShare::<C>::deserialize(encode_element::<C>(&tweaked)?.as_ref())
+            .map_err(|e| format!("bad derived verifying share: {e:?}"))?;
+        shares.insert(*id, tweaked);
+    }
+    let verifying_key = VerifyingKey::<C>::deserialize(encode_element::<C>(&(decode_element::<C>(&parent_key)? + offset))?.as_ref())
+        .map_err(|e| format!("bad derived key: {e:?}"))?;
+    let derived = PublicKeyPackage::new(shares, verifying_key);
+
+    let mut verifying_shares = Vec::new();
+    for node_id in participant_ids {
+        let id = participant_identifier::<C>(node_id)?;
+        let share = derived.verifying_shares().get(&id)
+            .ok_or_else(|| format!("missing verifying share for {node_id}"))?;
+        verifying_shares.push((node_id.clone(), share.serialize().map_err(|e| format!("serialize failed: {e:?}"))?));
+    }
+
+    Ok(DerivedGroup {
+        public_key_package: derived.serialize().map_err(|e| format!("serialize failed: {e:?}"))?,
+        group_public_key: derived.verifying_key().serialize().map_err(|e| format!("serialize failed: {e:?}"))?,
+        verifying_shares,
+    })
+}
+
+/// Sum of the per-segment tweaks. Each segment is hashed with the key derived so far,
+/// so deriving `m/a` then `m/b` gives the same key as deriving `m/a/b`.
+fn derivation_tweak<C: Ciphersuite>(parent_key: &[u8], path: &str) -> Result<ScalarOf<C>, String> {
+    let mut key = decode_element::<C>(parent_key)?;
+    let mut tweak = <<C::Group as Group>::Field as Field>::zero();
+
+    for segment in parse_derivation_path(path)? {
+        let mut input = DERIVATION_DOMAIN.to_vec();
+        input.extend_from_slice(encode_element::<C>(&key)?.as_ref());
+        input.extend_from_slice(segment.as_bytes());
+
+        let step = C::HID(&input).ok_or("ciphersuite has no hash-to-scalar")?;
+        key = key + C::Group::generator() * step;
+        tweak = tweak + step;
+    }
+
+    Ok(tweak)
+}
+
+fn decode_scalar<C: Ciphersuite>(bytes: &[u8]) -> Result<ScalarOf<C>, String> {
+    let bytes = bytes.try_into().map_err(|_| "bad scalar length")?;
+    <<C::Group as Group>::Field as Field>::deserialize(&bytes).map_err(|e| format!("bad scalar: {e:?}"))
+}
+
+fn decode_element<C: Ciphersuite>(bytes: &[u8]) -> Result<ElementOf<C>, String> {
+    let bytes = bytes.try_into().map_err(|_| "bad element length")?;
+    <C::Group as Group>::deserialize(&bytes).map_err(|e| format!("bad element: {e:?}"))
+}
+
+fn encode_element<C: Ciphersuite>(element: &ElementOf<C>) -> Result<<C::Group as Group>::Serialization, String> {
+    <C::Group as Group>::serialize(element).map_err(|e| format!("bad element: {e:?}"))
+}
+
+fn signing_package<C: Ciphersuite>(message: &[u8], commitments: &[(String, Vec<u8>)]) -> Result<SigningPackage<C>, String> {
+    let mut map = BTreeMap::new();
+    for (node_id, raw) in commitments {
+        let c = SigningCommitments::<C>::deserialize(raw).map_err(|_| "bad commitment")?;
+        map.insert(participant_identifier::<C>(node_id)?, c);
+    }
+    Ok(SigningPackage::new(map, message))
+}
+
+fn decode_round1<C: Ciphersuite>(
+    received: &HashMap<String, Vec<u8>>,
+) -> Result<(BTreeMap<Identifier<C>, round1::Package<C>>, HashMap<Identifier<C>, String>), DkgFault> {
+    let mut packages = BTreeMap::new();
+    let mut node_ids = HashMap::new();
+    for (node_id, raw) in received {
+        let id = participant_identifier::<C>(node_id).map_err(DkgFault::local)?;
+        let package = round1::Package::<C>::deserialize(raw).map_err(|e| DkgFault {
+            culprit: Some(node_id.clone()),
+            reason: format!("bad round1 package: {e:?}"),
+        })?;
+        packages.insert(id, package);
+        node_ids.insert(id, node_id.clone());
+    }
+    Ok((packages, node_ids))
+}
@@ -2,6 +2,11 @@
 use trust_dns_resolver::{TokioAsyncResolver, config::*};
 
 pub async fn discover_peer_nodes(service_dns_name: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    // A simulated node's peers are the hosts on its network
+    if let Some(network) = crate::sim::network::local_node().and_then(|node| crate::sim::network::network_of(&node)) {
+        return Ok(network.hosts());
+    }
+
     let resolver = TokioAsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default())?;
     let response = resolver.lookup_ip(service_dns_name).await?;
 
@@ -1,154 +1,1299 @@
 // File: src/dkg/engine.rs
 
-use std::collections::HashMap;
-use std::sync::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, SystemTime};
 
-use frost_ed25519::keys::{KeyPackage, PublicKeyPackage};
-use frost_ed25519::dkg::{self, Round1Package, Round2Package, KeyGenMachine};
-use frost_core::ciphersuite::Ciphersuite;
-use frost_ed25519::Ed25519;
-use frost_core::group::Group;
-use frost_core::Curve;
-
-use rand_core::OsRng;
 use serde_json;
+use zeroize::Zeroizing;
 
+use crate::ciphersuite::{self, SuiteId};
 use crate::dkg::types::*;
-use crate::relay::RelayClient;
-use crate::registry::{OperationalDIDRegistry, MPCGroupDescriptor, MPCMemberDescriptor};
+use crate::identity::{IdentityDirectory, IdentityPublicKeys, NodeIdentity, SealedBox};
+use crate::membership;
+use crate::mpc::device;
+use crate::relay::{self, MessageType, RelayClient, RelayHandlers};
+use crate::registry::{OperationalDID, OperationalDIDRegistry, GroupStatus, MPCGroupDescriptor};
+use crate::types::VaultRecord;
 use crate::vault;
+use crate::with_ciphersuite;
+
+/// How long each DKG phase may take before the session fails
+pub const DKG_PHASE_TIMEOUT: Duration = Duration::from_secs(60);
 
-/// Node-local distributed key generation engine
+/// Node-local distributed key generation engine. The orchestrator starts the same
+/// group ID on every node; each node then moves to round 2 once every round-1
+/// package has arrived and finalizes once every round-2 package has arrived.
+/// A node that catches a peer sending a bad package broadcasts a signed complaint
+/// naming it, and every node aborts the session and wipes what it had so far.
+/// Session state is sealed into the DID's vault record at every step; see
+/// `recover_sessions` for what happens after a restart.
 pub struct DKGEngine {
     pub sessions: Mutex<HashMap<String, DKGSession>>,
-    pub did_registry: OperationalDIDRegistry,
-    pub relay: RelayClient,
+    pub pending: Mutex<HashMap<String, Vec<(String, Vec<u8>)>>>, // Messages that arrived before the local session started
+    pub did_registry: Arc<OperationalDIDRegistry>,
+    pub relay: Arc<RelayClient>,
+    pub identity: Arc<NodeIdentity>,          // Signs our packages and opens round-2 packages sent to us
+    pub directory: Arc<IdentityDirectory>,    // Pinned identity keys of peer nodes
     pub node_id: String,
 }
 
 impl DKGEngine {
-    /// Start a new session and return the session ID
-    pub fn start_session(&self, op_did: String, threshold: u8, participant_ids: Vec<String>) -> Result<String, DKGError> {
+    pub fn new(
+        did_registry: Arc<OperationalDIDRegistry>,
+        relay: Arc<RelayClient>,
+        identity: Arc<NodeIdentity>,
+        directory: Arc<IdentityDirectory>,
+        node_id: String,
+    ) -> Self {
+        DKGEngine {
+            sessions: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+            did_registry,
+            relay,
+            identity,
+            directory,
+            node_id,
+        }
+    }
+
+    /// Identity keys of a participant: pinned for custody nodes, registered for devices
+    pub fn participant_keys(&self, participant_id: &str) -> Option<IdentityPublicKeys> {
+        if device::is_device_participant(participant_id) {
+            let record = self.did_registry.find_device(participant_id)?;
+            return Some(IdentityPublicKeys {
+                signing_key: record.identity_public_key,
+                encryption_key: record.encryption_public_key,
+            });
+        }
+        self.directory.get(participant_id)
+    }
+
+    /// Takes the relay channels of DKG sessions. A message must come on the channel of
+    /// its session's kind, so resharing and repair traffic cannot pass for one another.
+    pub fn register_relay_handlers(self: &Arc<Self>, handlers: &RelayHandlers) -> Result<(), String> {
+        for channel in [MessageType::Dkg, MessageType::Reshare, MessageType::ShareRepair] {
+            let engine = self.clone();
+            handlers.register(channel, move |group_id, from, payload| {
+                let kind = engine.sessions.lock().unwrap().get(group_id).map(|s| s.local.kind);
+                if let Some(kind) = kind.filter(|kind| relay_channel(*kind) != channel) {
+                    return Err(format!("{kind:?} session {group_id} takes no {channel:?} messages"));
+                }
+                engine.handle_message(group_id, from, payload)
+                    .map_err(|e| format!("DKG handling failed: {:?}", e))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Fails a live session when the relay gives up on one of its messages: the peer
+    /// would otherwise wait for it until the phase deadline
+    pub fn watch_relay(self: &Arc<Self>) {
+        let engine: Weak<DKGEngine> = Arc::downgrade(self);
+        self.relay.on_undeliverable(move |undelivered| {
+            if !matches!(undelivered.channel, MessageType::Dkg | MessageType::Reshare | MessageType::ShareRepair) {
+                return;
+            }
+            let Some(engine) = engine.upgrade() else { return };
+            let mut sessions = engine.sessions.lock().unwrap();
+            let Some(session) = sessions.get_mut(&undelivered.group_id) else { return };
+            if session.local.phase.is_terminal() {
+                println!("⚠️ DKG {}: could not deliver to {}: {}", undelivered.group_id, undelivered.to_node, undelivered.error);
+                return;
+            }
+            engine.set_phase(session, DKGPhase::Failed(format!("send to {}: {}", undelivered.to_node, undelivered.error)));
+        });
+    }
+
+    /// Fetches and pins the identity of every custody node we have no key for yet
+    pub async fn learn_participants(&self, participant_ids: &[String]) -> Result<(), DKGError> {
+        let unknown = participant_ids.iter()
+            .filter(|id| **id != self.node_id && !device::is_device_participant(id) && self.directory.get(id).is_none());
+
+        for node in unknown {
+            let keys = relay::fetch_identity(node).await?;
+            self.directory.pin(node, keys).map_err(DKGError::Unauthenticated)?;
+        }
+        Ok(())
+    }
+
+    /// Start this node's side of the session `group_id` and broadcast its round-1 package
+    pub fn start_session(&self, group_id: &str, op_did: String, threshold: u8, participant_ids: Vec<String>, suite: SuiteId) -> Result<(), DKGError> {
+        self.launch(group_id, DKGKind::Generate, op_did, threshold, participant_ids, suite, None, None, None)
+    }
+
+    /// Start refreshing the shares of `op_did`'s current group. The committee, threshold
+    /// and suite are the group's own; every member must take part, since a member left
+    /// out would keep a share that no longer fits the others.
+    pub fn start_refresh(&self, group_id: &str, op_did: String) -> Result<(), DKGError> {
+        let group = self.did_registry
+            .get_mpc_group(&OperationalDID(op_did.clone()))
+            .ok_or(DKGError::SessionFailed("DID has no group to refresh".into()))?;
+        let suite = SuiteId::from_dkg_protocol(group.dkg_protocol.as_deref())
+            .map_err(|_| DKGError::SessionFailed("only FROST groups can be refreshed".into()))?;
+
+        let participant_ids = group.members.iter().map(|m| m.node_id.clone()).collect::<Vec<_>>();
+        if let Some(device) = participant_ids.iter().find(|id| device::is_device_participant(id)) {
+            return Err(DKGError::SessionFailed(format!("{device} cannot refresh its share; rotate the group instead")));
+        }
+        if !participant_ids.contains(&self.node_id) {
+            return Err(DKGError::SessionFailed("this node holds no share of the group".into()));
+        }
+
+        self.launch(group_id, DKGKind::Refresh, op_did, group.threshold as u8, participant_ids, suite, None, None, None)
+    }
+
+    /// Start moving `op_did`'s group key to the committee `members` with a new `threshold`.
+    /// `dealers` are old members handing over their shares; at least the old threshold
+    /// of them must take part. A node new to the DID has no group on record and takes
+    /// `old_public_key_package` from the orchestrator; a node that has one checks they agree.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start_reshare(
+        &self,
+        group_id: &str,
+        op_did: String,
+        dealers: Vec<String>,
+        members: Vec<String>,
+        threshold: u8,
+        suite: SuiteId,
+        old_public_key_package: Vec<u8>,
+    ) -> Result<(), DKGError> {
+        let (suite, old_public_key_package) = match self.did_registry.get_mpc_group(&OperationalDID(op_did.clone())) {
+            Some(group) => {
+                let recorded = SuiteId::from_dkg_protocol(group.dkg_protocol.as_deref())
+                    .map_err(|_| DKGError::SessionFailed("only FROST groups can be reshared".into()))?;
+                let package = group.public_key_package
+                    .ok_or(DKGError::SessionFailed("group has no public key package".into()))?;
+                if recorded != suite || (!old_public_key_package.is_empty() && old_public_key_package != package) {
+                    return Err(DKGError::SessionFailed("the orchestrator's view of the group differs from ours".into()));
+                }
+                (recorded, package)
+            }
+            None if !old_public_key_package.is_empty() => (suite, old_public_key_package),
+            None => return Err(DKGError::SessionFailed("DID has no group to reshare".into())),
+        };
+
+        if dealers.is_empty() || threshold == 0 || threshold as usize > members.len() {
+            return Err(DKGError::SessionFailed(format!("cannot reshare from {} dealers to {threshold}-of-{}", dealers.len(), members.len())));
+        }
+        if let Some(device) = dealers.iter().chain(&members).find(|id| device::is_device_participant(id)) {
+            return Err(DKGError::SessionFailed(format!("{device} cannot take part in a resharing; rotate the group instead")));
+        }
+
+        let committees = DealingCommittees { dealers, members, old_public_key_package, group_public_key: Vec::new() };
+        let participant_ids = committees.participants();
+        if !participant_ids.contains(&self.node_id) {
+            return Err(DKGError::SessionFailed("this node is in neither committee".into()));
+        }
+
+        self.launch(group_id, DKGKind::Reshare, op_did, threshold, participant_ids, suite, Some(committees), None, None)
+    }
+
+    /// Start splitting an existing Ed25519 key behind `group_public_key` over `members`.
+    /// Only `dealer` ever sees the key: it arrives sealed to the dealer's identity key
+    /// and bound to the DID and public key, and the dealer deals it like a one-node
+    /// reshare. Every member checks its share against the public key being imported.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start_import(
+        &self,
+        group_id: &str,
+        op_did: String,
+        dealer: String,
+        members: Vec<String>,
+        threshold: u8,
+        group_public_key: Vec<u8>,
+        sealed_key: Option<SealedBox>,
+    ) -> Result<(), DKGError> {
+        if self.did_registry.get_mpc_group(&OperationalDID(op_did.clone())).is_some() {
+            return Err(DKGError::SessionFailed("DID already has a group; rotate it instead of importing".into()));
+        }
+        if threshold == 0 || threshold as usize > members.len() {
+            return Err(DKGError::SessionFailed(format!("cannot import into {threshold}-of-{}", members.len())));
+        }
+        if let Some(device) = members.iter().chain([&dealer]).find(|id| device::is_device_participant(id)) {
+            return Err(DKGError::SessionFailed(format!("{device} cannot take part in a key import")));
+        }
+
+        // The dealer opens the key now, so a bad box fails the start rather than the session
+        let signing_key = if dealer == self.node_id {
+            let sealed = sealed_key.ok_or(DKGError::SessionFailed("the dealer needs the sealed key".into()))?;
+            let secret = self.identity
+                .open(&sealed, &key_import_aad(&op_did, &group_public_key))
+                .map_err(|e| DKGError::CryptoFailure(format!("cannot open the imported key: {e}")))?;
+            let signing_key = ciphersuite::ed25519_signing_key(&secret).map_err(DKGError::CryptoFailure)?;
+            Some(signing_key)
+        } else {
+            None
+        };
+
+        let committees = DealingCommittees { dealers: vec![dealer], members, old_public_key_package: Vec::new(), group_public_key };
+        let participant_ids = committees.participants();
+        if !participant_ids.contains(&self.node_id) {
+            return Err(DKGError::SessionFailed("this node neither deals nor receives the key".into()));
+        }
+
+        self.launch(group_id, DKGKind::Import, op_did, threshold, participant_ids, SuiteId::Ed25519, Some(committees), signing_key, None)
+    }
+
+    /// Start rebuilding `lost`'s share of `op_did`'s group from `helpers`, at least the
+    /// group's threshold of its other members. Neither the share nor the group secret
+    /// is revealed to anyone; the lost member checks what it rebuilt against the
+    /// verifying share on record and seals it. The lost member needs the DID and its
+    /// group on record; a vault record that is gone altogether is recreated empty.
+    pub fn start_repair(&self, group_id: &str, op_did: String, mut helpers: Vec<String>, lost: String) -> Result<(), DKGError> {
+        let did = OperationalDID(op_did.clone());
+        let group = self.did_registry
+            .get_mpc_group(&did)
+            .ok_or(DKGError::SessionFailed("DID has no group to repair".into()))?;
+        let suite = SuiteId::from_dkg_protocol(group.dkg_protocol.as_deref())
+            .map_err(|_| DKGError::SessionFailed("only FROST groups can be repaired".into()))?;
+
+        let members = group.members.iter().map(|m| m.node_id.clone()).collect::<Vec<_>>();
+        if !members.contains(&lost) {
+            return Err(DKGError::SessionFailed(format!("{lost} holds no share of the group")));
+        }
+        // A helper named twice would count twice towards the threshold and skew the coefficients
+        let mut seen = HashSet::new();
+        helpers.retain(|helper| seen.insert(helper.clone()));
+        if helpers.len() < group.threshold as usize || helpers.contains(&lost) || helpers.iter().any(|h| !members.contains(h)) {
+            return Err(DKGError::SessionFailed(format!("repair needs {} other members of the group as helpers", group.threshold)));
+        }
+        if let Some(device) = helpers.iter().chain([&lost]).find(|id| device::is_device_participant(id)) {
+            return Err(DKGError::SessionFailed(format!("{device} cannot take part in a share repair")));
+        }
+
+        let plan = RepairPlan { helpers, lost };
+        let participant_ids = plan.participants();
+        if !participant_ids.contains(&self.node_id) {
+            return Err(DKGError::SessionFailed("this node neither helps nor is being repaired".into()));
+        }
+        if plan.lost == self.node_id {
+            self.prepare_vault_for_repair(&did)?;
+        }
+
+        self.launch(group_id, DKGKind::Repair, op_did, group.threshold, participant_ids, suite, None, None, Some(plan))
+    }
+
+    /// Makes sure the lost member has a vault record to seal its repaired share into,
+    /// and refuses a repair of a share that is still there
+    fn prepare_vault_for_repair(&self, op_did: &OperationalDID) -> Result<(), DKGError> {
+        let vault_id = self.did_registry
+            .get_vault_id_for_operational_did(op_did)
+            .ok_or(DKGError::VaultNotFound)?;
+        match vault::load_record(&vault_id) {
+            Ok(record) if record.mpc_shard.is_some() => {
+                Err(DKGError::SessionFailed("this node still holds its share".into()))
+            }
+            Ok(_) => Ok(()),
+            Err(_) => {
+                let root_did = self.did_registry.get_root_for_operational_did(op_did).ok_or(DKGError::VaultNotFound)?;
+                println!("🗄️ Recreating lost vault {vault_id} for {}", op_did.0);
+                vault::store_record(&vault_id, &VaultRecord {
+                    root_did: root_did.0,
+                    op_dids: vec![op_did.0.clone()],
+                    mpc_shard: None,
+                    group_metadata: None,
+                    public_keys: vec![],
+                    vcs: vec![],
+                    bbs_private_key: None,
+                    bbs_public_key: None,
+                    active_nonce: None,
+                    batch_nonces: vec![],
+                    ecdsa_share: None,
+                    ecdsa_presignatures: Default::default(),
+                    shard_epoch: 0,
+                    dkg_sessions: Default::default(),
+                    consumed_approvals: Default::default(),
+                }).map_err(|_| DKGError::VaultStorageFailed)
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn launch(
+        &self,
+        group_id: &str,
+        kind: DKGKind,
+        op_did: String,
+        threshold: u8,
+        participant_ids: Vec<String>,
+        suite: SuiteId,
+        dealing: Option<DealingCommittees>,
+        import_key: Option<Zeroizing<Vec<u8>>>,
+        repair: Option<RepairPlan>,
+    ) -> Result<(), DKGError> {
         let mut sessions = self.sessions.lock().unwrap();
+        if sessions.contains_key(group_id) {
+            return Err(DKGError::SessionAlreadyExists);
+        }
 
-        let id = frost_core::Identifier::try_from(self.node_id.as_bytes()).unwrap();
-        let mut machine = KeyGenMachine::<Ed25519>::new(&id, threshold, &participant_ids).map_err(|e| DKGError::CryptoFailure(format!("KeyGen init: {e:?}")))?;
+        // State is sealed in the DID's vault as the session runs, so the vault must exist
+        self.did_registry
+            .get_vault_id_for_operational_did(&OperationalDID(op_did.clone()))
+            .ok_or(DKGError::VaultNotFound)?;
 
-        let (round1_pkg, _machine) = machine.round1().map_err(|e| DKGError::CryptoFailure(format!("Round1 failed: {e:?}")))?;
-        let group_id = uuid::Uuid::new_v4().to_string();
+        // Every peer must be known before anything is sent or accepted
+        if let Some(unknown) = participant_ids.iter().find(|id| **id != self.node_id && self.participant_keys(id).is_none()) {
+            return Err(DKGError::Unauthenticated(format!("no identity key for {unknown}")));
+        }
 
+        // A dealer sends everything at once, so a reshare or import starts in round 2,
+        // as does the member being repaired, which only waits for the helpers' sums
+        let receives_only = dealing.is_some() || repair.as_ref().is_some_and(|plan| plan.lost == self.node_id);
+        let phase = if receives_only { DKGPhase::Round2 } else { DKGPhase::Round1 };
         let local_state = DKGLocalState {
             operational_did: op_did.clone(),
+            kind,
             threshold,
             participant_ids: participant_ids.clone(),
+            dealing,
+            repair,
             round1_received: HashMap::new(),
             round2_received: HashMap::new(),
-            finalized: false,
-            keygen_machine: Some(_machine),
+            round1_messages: HashMap::new(),
+            peer_results: participant_ids.iter()
+                .filter(|id| **id != self.node_id)
+                .map(|id| (id.clone(), PeerResult::Pending))
+                .collect(),
+            complaints: Vec::new(),
+            sent: HashMap::new(),
+            ciphersuite: suite,
+            round1_secret: None,
+            round2_secret: None,
+            phase: phase.clone(),
+            phase_deadline: SystemTime::now() + DKG_PHASE_TIMEOUT,
+            group_public_key: None,
+            confirmation: None,
+            confirmations: HashMap::new(),
         };
 
-        sessions.insert(group_id.clone(), DKGSession {
-            group_id: group_id.clone(),
+        let (events, _) = tokio::sync::watch::channel(phase);
+        let mut session = DKGSession {
+            group_id: group_id.to_string(),
             local: local_state,
-        });
+            events,
+        };
 
         // Broadcast Round1
-        let msg = bincode::serialize(&DKGMessage::Round1(bincode::serialize(&round1_pkg).unwrap())).unwrap();
-        for peer_id in participant_ids.iter().filter(|id| *id != &self.node_id) {
-            self.relay.send_message(&group_id, peer_id, msg.clone())?;
-        }
+        let mut outbox = if session.local.dealing.is_some() {
+            self.deal(group_id, &mut session.local, import_key)?
+        } else if session.local.repair.is_some() {
+            self.repair_parts(group_id, &mut session.local)?
+        } else {
+            let max_signers = participant_ids.len() as u16;
+            let (round1_secret, round1_pkg) = match kind {
+                DKGKind::Refresh => with_ciphersuite!(suite, |C| ciphersuite::refresh_part1::<C>(&self.node_id, max_signers, threshold as u16)),
+                _ => with_ciphersuite!(suite, |C| ciphersuite::dkg_part1::<C>(&self.node_id, max_signers, threshold as u16)),
+            }.map_err(|e| DKGError::CryptoFailure(format!("Round1 failed: {e}")))?;
+            session.local.round1_secret = Some(round1_secret);
+
+            let msg = bincode::serialize(&DKGMessage::signed_round1(&self.identity, group_id, &self.node_id, round1_pkg)).unwrap();
+            participant_ids.iter()
+                .filter(|id| *id != &self.node_id)
+                .map(|peer_id| (peer_id.clone(), msg.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        // Peers may have started first; replay what they already sent us. Anyone can
+        // send before we start, so a bad early message is dropped rather than fatal.
+        outbox.extend(self.replay_pending(&mut session));
+        self.remember_sent(&mut session, &outbox);
+        self.checkpoint(&mut session);
 
-        Ok(group_id)
+        sessions.insert(group_id.to_string(), session);
+        drop(sessions);
+
+        self.send_all(group_id, outbox);
+        Ok(())
     }
 
-    /// Handle incoming Round1 or Round2 message
+    /// Handle incoming Round1 or Round2 message and advance the session when a round completes
     pub fn handle_message(&self, group_id: &str, from: &str, msg: Vec<u8>) -> Result<(), DKGError> {
-        let dkg_msg: DKGMessage = bincode::deserialize(&msg).map_err(|_| DKGError::MessageMalformed)?;
         let mut sessions = self.sessions.lock().unwrap();
-        let session = sessions.get_mut(group_id).ok_or(DKGError::SessionNotFound)?;
+        let Some(session) = sessions.get_mut(group_id) else {
+            bincode::deserialize::<DKGMessage>(&msg).map_err(|_| DKGError::MessageMalformed)?;
+            self.pending.lock().unwrap().entry(group_id.to_string()).or_default().push((from.to_string(), msg));
+            return Ok(());
+        };
 
-        match dkg_msg {
-            DKGMessage::Round1(raw) => {
-                session.local.round1_received.insert(from.to_string(), raw);
-            }
-            DKGMessage::Round2(raw) => {
-                session.local.round2_received.insert(from.to_string(), raw);
+        self.expire_if_late(session);
+        let outbox = match self.record_message(session, from, msg) {
+            Ok(mut replies) => {
+                replies.extend(self.advance(session));
+                replies
             }
-            _ => {}
-        }
+            Err(DKGError::PeerMisbehaved { culprit, reason }) => self.complain(session, &culprit, &reason),
+            Err(e) => return Err(e),
+        };
+        self.remember_sent(session, &outbox);
+        self.checkpoint(session);
+        drop(sessions);
 
+        self.send_all(group_id, outbox);
         Ok(())
     }
 
-    /// After receiving all Round1s, broadcast our Round2
-    pub fn broadcast_round2(&self, group_id: &str) -> Result<(), DKGError> {
+    /// Current status of a session, failing it first if its deadline has passed
+    pub fn status(&self, group_id: &str) -> Result<DKGStatus, DKGError> {
         let mut sessions = self.sessions.lock().unwrap();
         let session = sessions.get_mut(group_id).ok_or(DKGError::SessionNotFound)?;
-        let machine = session.local.keygen_machine.take().ok_or(DKGError::CryptoFailure("Missing state".into()))?;
+        self.expire_if_late(session);
+
+        let local = &session.local;
+        Ok(DKGStatus {
+            kind: local.kind,
+            phase: local.phase.clone(),
+            round1_received: local.round1_received.len(),
+            round2_received: local.round2_received.len(),
+            expected: self.expected_packages(local).0,
+            deadline: local.phase_deadline,
+            group_public_key: local.group_public_key.clone(),
+            peer_results: local.peer_results.clone(),
+            complaints: local.complaints.clone(),
+        })
+    }
 
-        let mut received = Vec::new();
-        for (peer_id, raw) in &session.local.round1_received {
-            let pkg: Round1Package = bincode::deserialize(raw).map_err(|_| DKGError::MessageMalformed)?;
-            let id = frost_core::Identifier::try_from(peer_id.as_bytes()).unwrap();
-            received.push((id, pkg));
+    /// Aborts a session on this node on operator request, wiping its partial state.
+    /// Unlike a complaint this blames no one, so nothing is broadcast.
+    pub fn abort_session(&self, group_id: &str, reason: &str) -> Result<(), DKGError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(group_id).ok_or(DKGError::SessionNotFound)?;
+        if !matches!(session.local.phase, DKGPhase::Aborted(_)) {
+            self.abort(session, format!("aborted by operator: {reason}"));
         }
+        Ok(())
+    }
+
+    /// Restores the sessions sealed in every DID's vault before a restart. A session
+    /// still inside its phase deadline resumes: it resends what it had sent and asks
+    /// every peer to do the same, since anything they sent while we were down is lost.
+    /// A session past its deadline is aborted, and its peers are told so they do not
+    /// wait out their own deadlines. Returns each recovered group and its phase.
+    pub fn recover_sessions(&self) -> Vec<(String, DKGPhase)> {
+        let mut recovered = Vec::new();
 
-        let (round2_pkg, machine2) = machine.round2(&received).map_err(|e| DKGError::CryptoFailure(format!("Round2: {e:?}")))?;
-        session.local.keygen_machine = Some(machine2);
+        for op_did in self.did_registry.operational_dids() {
+            let Some(vault_id) = self.did_registry.get_vault_id_for_operational_did(&op_did) else { continue };
+            let stored = match vault::load_dkg_sessions(&vault_id) {
+                Ok(stored) => stored,
+                Err(e) => {
+                    println!("⚠️ Could not load DKG sessions from {vault_id}: {e}");
+                    continue;
+                }
+            };
 
-        let msg = bincode::serialize(&DKGMessage::Round2(bincode::serialize(&round2_pkg).unwrap())).unwrap();
-        for peer_id in session.local.participant_ids.iter().filter(|id| *id != &self.node_id) {
-            self.relay.send_message(group_id, peer_id, msg.clone())?;
+            for (group_id, state) in stored {
+                let Ok(local) = bincode::deserialize::<DKGLocalState>(&state) else {
+                    // Without the state we do not even know whom to tell
+                    println!("⚠️ Dropping unreadable DKG session {group_id}");
+                    let _ = vault::remove_dkg_session(&vault_id, &group_id);
+                    continue;
+                };
+
+                let (events, _) = tokio::sync::watch::channel(local.phase.clone());
+                let mut session = DKGSession { group_id: group_id.clone(), local, events };
+                let peers = session.local.participant_ids.iter()
+                    .filter(|id| **id != self.node_id)
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                // STEP 1: Too late to catch up; abort and let the peers know
+                let mut outbox = Vec::new();
+                if SystemTime::now() > session.local.phase_deadline {
+                    let reason = "restarted after the phase deadline";
+                    let msg = bincode::serialize(&DKGMessage::signed_abort(&self.identity, &group_id, &self.node_id, reason)).unwrap();
+                    outbox.extend(peers.iter().map(|peer| (peer.clone(), msg.clone())));
+                    self.abort(&mut session, reason.to_string());
+                } else {
+                    // STEP 2: Resume; resend our messages and ask for theirs
+                    let resend = bincode::serialize(&DKGMessage::signed_resend(&self.identity, &group_id, &self.node_id)).unwrap();
+                    for peer in &peers {
+                        for msg in session.local.sent.get(peer).into_iter().flatten() {
+                            outbox.push((peer.clone(), msg.clone()));
+                        }
+                        outbox.push((peer.clone(), resend.clone()));
+                    }
+                    // The resend request itself is not remembered, or two restarted peers
+                    // would keep asking each other forever
+                    let replayed = self.replay_pending(&mut session);
+                    self.remember_sent(&mut session, &replayed);
+                    outbox.extend(replayed);
+                    self.checkpoint(&mut session);
+                }
+
+                println!("♻️ Recovered DKG {group_id} on {}: {:?}", self.node_id, session.local.phase);
+                recovered.push((group_id.clone(), session.local.phase.clone()));
+                self.sessions.lock().unwrap().insert(group_id.clone(), session);
+                self.send_all(&group_id, outbox);
+            }
         }
 
-        Ok(())
+        recovered
     }
 
-    /// Finalize and store the share locally
-    pub fn finalize(&self, group_id: &str) -> Result<Vec<u8>, DKGError> {
-        let mut sessions = self.sessions.lock().unwrap();
-        let session = sessions.remove(group_id).ok_or(DKGError::SessionNotFound)?;
+    /// Waits until the session is finalized or fails, without polling
+    pub async fn wait_for_completion(&self, group_id: &str) -> Result<DKGStatus, DKGError> {
+        let mut events = self.sessions.lock().unwrap()
+            .get(group_id)
+            .ok_or(DKGError::SessionNotFound)?
+            .events
+            .subscribe();
+
+        loop {
+            let status = self.status(group_id)?;
+            if status.phase.is_terminal() {
+                return Ok(status);
+            }
 
-        let machine = session.local.keygen_machine.ok_or(DKGError::CryptoFailure("No state".into()))?;
-        let mut received = Vec::new();
-        for (peer_id, raw) in session.local.round2_received {
-            let pkg: Round2Package = bincode::deserialize(&raw).map_err(|_| DKGError::MessageMalformed)?;
-            let id = frost_core::Identifier::try_from(peer_id.as_bytes()).unwrap();
-            received.push((id, pkg));
+            // Wake on the next phase change, or at the deadline so it can be enforced
+            let remaining = status.deadline.duration_since(SystemTime::now()).unwrap_or_default();
+            let _ = tokio::time::timeout(remaining, events.changed()).await;
         }
+    }
+
+    /// Moves the session forward as far as the packages received so far allow and
+    /// returns the messages to send. Callers send them after releasing the session
+    /// lock, since a peer's reply can arrive while the send is in flight.
+    fn advance(&self, session: &mut DKGSession) -> Vec<(String, Vec<u8>)> {
+        let (expected1, expected2) = self.expected_packages(&session.local);
+        let mut outbox = Vec::new();
 
-        let (key_package, pubkeys) = machine.finish(&received).map_err(|e| DKGError::CryptoFailure(format!("Finalize failed: {e:?}")))?;
+        // STEP 1: Every round-1 package is in; send each peer its round-2 package
+        if session.local.phase == DKGPhase::Round1 && session.local.round1_received.len() == expected1 {
+            match self.round2_packages(session) {
+                Ok(packages) => {
+                    outbox = packages;
+                    self.set_phase(session, DKGPhase::Round2);
+                }
+                Err(DKGError::PeerMisbehaved { culprit, reason }) => return self.complain(session, &culprit, &reason),
+                Err(e) => {
+                    self.set_phase(session, DKGPhase::Failed(format!("round 2: {e:?}")));
+                    return outbox;
+                }
+            }
+        }
 
-        // added this in to query registry for vault_id since add_shard needs vault_id
-        // optionally could add a helper in vault which I will place and comment out
-        let vault_id = registry
-            .get_vault_id_for_operational_did(&session.local.operational_did)
+        // STEP 2: Every round-2 package is in; derive and seal our share, then tell
+        // every custody peer which group we ended up with
+        if session.local.phase == DKGPhase::Round2
+            && session.local.round1_received.len() == expected1
+            && session.local.round2_received.len() == expected2
+        {
+            match self.finalize(session) {
+                Ok(()) => {
+                    for result in session.local.peer_results.values_mut() {
+                        *result = PeerResult::Verified;
+                    }
+                    outbox.extend(self.confirm(session));
+                }
+                Err(DKGError::PeerMisbehaved { culprit, reason }) => outbox.extend(self.complain(session, &culprit, &reason)),
+                Err(e) => self.set_phase(session, DKGPhase::Failed(format!("finalize: {e:?}"))),
+            }
+        }
+
+        // STEP 3: The group signs only once every custody node confirmed the same one
+        if session.local.phase == DKGPhase::Confirming {
+            self.settle_confirmations(session);
+        }
+
+        outbox
+    }
+
+    /// Sends every custody peer a signed hash of the public key package we computed.
+    /// A repair changes no public data, so it has nothing to confirm.
+    fn confirm(&self, session: &mut DKGSession) -> Vec<(String, Vec<u8>)> {
+        let Some(digest) = session.local.confirmation.clone() else {
+            self.set_phase(session, DKGPhase::Finalized);
+            return Vec::new();
+        };
+        self.set_phase(session, DKGPhase::Confirming);
+
+        let msg = bincode::serialize(&DKGMessage::signed_confirmation(&self.identity, &session.group_id, &self.node_id, digest)).unwrap();
+        confirming_peers(&self.node_id, &session.local).into_iter()
+            .map(|peer_id| (peer_id, msg.clone()))
+            .collect()
+    }
+
+    /// Takes a custody peer's confirmation on record. It may arrive before we have
+    /// finalized ourselves; it is compared with ours once we have. A peer that
+    /// confirms two different groups is a mismatch as well.
+    fn accept_confirmation(&self, session: &mut DKGSession, from: &str, digest: Vec<u8>) {
+        if session.local.phase.is_terminal() || device::is_device_participant(from) {
+            return;
+        }
+        match session.local.confirmations.get(from) {
+            Some(existing) if *existing != digest => self.quarantine(session, format!("{from} confirmed two different groups")),
+            Some(_) => {}
+            None => { session.local.confirmations.insert(from.to_string(), digest); }
+        }
+    }
+
+    /// Activates the group once every custody peer confirmed the public key package we
+    /// computed, and quarantines it as soon as one confirms a different one
+    fn settle_confirmations(&self, session: &mut DKGSession) {
+        let Some(ours) = session.local.confirmation.clone() else { return };
+        let mut disagreeing = session.local.confirmations.iter()
+            .filter(|(_, digest)| **digest != ours)
+            .map(|(peer_id, _)| peer_id.clone())
+            .collect::<Vec<_>>();
+        if !disagreeing.is_empty() {
+            disagreeing.sort();
+            return self.quarantine(session, format!("{} computed a different group", disagreeing.join(", ")));
+        }
+
+        let peers = confirming_peers(&self.node_id, &session.local);
+        if !peers.iter().all(|peer_id| session.local.confirmations.contains_key(peer_id)) {
+            return;
+        }
+        let op_did = OperationalDID(session.local.operational_did.clone());
+        match self.did_registry.set_group_status(&op_did, &session.group_id, GroupStatus::Active) {
+            Ok(true) => self.set_phase(session, DKGPhase::Finalized),
+            Ok(false) => self.set_phase(session, DKGPhase::Failed("group no longer on record".into())),
+            Err(e) => self.set_phase(session, DKGPhase::Failed(format!("activate group: {e:?}"))),
+        }
+    }
+
+    /// Keeps the group and any share for an operator to look into, but never lets it sign
+    fn quarantine(&self, session: &mut DKGSession, reason: String) {
+        println!("☣️ DKG {} quarantined on {}: {reason}", session.group_id, self.node_id);
+        let op_did = OperationalDID(session.local.operational_did.clone());
+        if let Err(e) = self.did_registry.set_group_status(&op_did, &session.group_id, GroupStatus::Quarantined(reason.clone())) {
+            println!("⚠️ Could not quarantine group of DKG {}: {e:?}", session.group_id);
+        }
+        self.set_phase(session, DKGPhase::Quarantined(reason));
+    }
+
+    /// Packages a session waits for in (round 1, round 2): one per peer, or for a
+    /// reshare or import one per dealer, our own included. A dealer outside the new
+    /// committee is dealt no share.
+    fn expected_packages(&self, local: &DKGLocalState) -> (usize, usize) {
+        // A repair helper gets a part from every helper, itself included; the lost
+        // member gets one sum from each helper
+        if let Some(plan) = &local.repair {
+            return if plan.lost == self.node_id { (0, plan.helpers.len()) } else { (plan.helpers.len(), 0) };
+        }
+        match &local.dealing {
+            Some(dealing) if dealing.members.contains(&self.node_id) => (dealing.dealers.len(), dealing.dealers.len()),
+            Some(dealing) => (dealing.dealers.len(), 0),
+            None => {
+                let peers = local.participant_ids.len().saturating_sub(1);
+                (peers, peers)
+            }
+        }
+    }
+
+    /// Feeds messages that arrived before the session was (re)started into it. Anyone
+    /// can send before we start, so a bad early message is dropped rather than fatal.
+    fn replay_pending(&self, session: &mut DKGSession) -> Vec<(String, Vec<u8>)> {
+        let mut outbox = Vec::new();
+        if let Some(early) = self.pending.lock().unwrap().remove(&session.group_id) {
+            for (from, raw) in early {
+                match self.record_message(session, &from, raw) {
+                    Ok(replies) => outbox.extend(replies),
+                    Err(DKGError::PeerMisbehaved { culprit, reason }) => outbox.extend(self.complain(session, &culprit, &reason)),
+                    Err(e) => println!("⚠️ Dropped early DKG message from {from}: {e:?}"),
+                }
+            }
+        }
+        outbox.extend(self.advance(session));
+        outbox
+    }
+
+    /// Keeps our protocol messages so they can be resent to a peer that restarted
+    fn remember_sent(&self, session: &mut DKGSession, outbox: &[(String, Vec<u8>)]) {
+        if session.local.phase.is_terminal() && session.local.phase != DKGPhase::Finalized {
+            return;
+        }
+        for (peer_id, msg) in outbox {
+            let sent = session.local.sent.entry(peer_id.clone()).or_default();
+            if !sent.contains(msg) {
+                sent.push(msg.clone());
+            }
+        }
+    }
+
+    /// Seals the session into the DID's vault record, or drops it from there once
+    /// the session has ended. A live session that cannot be sealed fails, since it
+    /// could not survive a restart.
+    fn checkpoint(&self, session: &mut DKGSession) {
+        let op_did = OperationalDID(session.local.operational_did.clone());
+        let result = match self.did_registry.get_vault_id_for_operational_did(&op_did) {
+            None => Err("vault not found".to_string()),
+            Some(vault_id) if session.local.phase.is_terminal() => vault::remove_dkg_session(&vault_id, &session.group_id),
+            Some(vault_id) => bincode::serialize(&session.local)
+                .map_err(|e| format!("serialize failed: {e}"))
+                .map(Zeroizing::new)
+                .and_then(|state| vault::store_dkg_session(&vault_id, &session.group_id, &state)),
+        };
+
+        if let Err(e) = result {
+            println!("⚠️ Could not seal DKG {} state: {e}", session.group_id);
+            if !session.local.phase.is_terminal() {
+                let phase = DKGPhase::Failed(format!("sealing session state failed: {e}"));
+                session.local.round1_secret = None;
+                session.local.round2_secret = None;
+                session.local.phase = phase.clone();
+                session.events.send_replace(phase);
+            }
+        }
+    }
+
+    /// Records our own complaint against `accused`, aborts the session and returns
+    /// the signed complaint for every peer, the accused included
+    fn complain(&self, session: &mut DKGSession, accused: &str, reason: &str) -> Vec<(String, Vec<u8>)> {
+        println!("🚨 DKG {}: {} accuses {accused}: {reason}", session.group_id, self.node_id);
+        let evidence = session.local.round1_messages.get(accused).cloned();
+        let msg = DKGMessage::signed_complaint(&self.identity, &session.group_id, &self.node_id, accused, reason, evidence.clone());
+        let msg = bincode::serialize(&msg).unwrap();
+
+        session.local.peer_results.insert(accused.to_string(), PeerResult::Rejected(reason.to_string()));
+        session.local.complaints.push(DKGComplaint {
+            accuser: self.node_id.clone(),
+            accused: accused.to_string(),
+            reason: reason.to_string(),
+            evidence,
+            confirmed: true,
+        });
+        self.abort(session, format!("{accused} misbehaved: {reason}"));
+
+        session.local.participant_ids.iter()
+            .filter(|id| **id != self.node_id)
+            .map(|peer_id| (peer_id.clone(), msg.clone()))
+            .collect()
+    }
+
+    /// Takes a peer's complaint on record. Round-1 evidence is re-checked here, so the
+    /// report says whether this node could confirm the accusation or only relay it.
+    fn accept_complaint(&self, session: &mut DKGSession, mut complaint: DKGComplaint) {
+        complaint.confirmed = self.evidence_confirms(session, &complaint.accused, complaint.evidence.as_deref());
+        if complaint.confirmed {
+            session.local.peer_results.insert(complaint.accused.clone(), PeerResult::Rejected(complaint.reason.clone()));
+        }
+        println!("🚨 DKG {}: {} accuses {} ({})", session.group_id, complaint.accuser, complaint.accused, complaint.reason);
+
+        let reason = format!("{} accused {}: {}", complaint.accuser, complaint.accused, complaint.reason);
+        session.local.complaints.push(complaint);
+        if !matches!(session.local.phase, DKGPhase::Aborted(_)) {
+            self.abort(session, reason);
+        }
+    }
+
+    /// True if `evidence` is a round-1 message signed by `accused` that is invalid, or
+    /// differs from the one `accused` sent us (it told participants different things)
+    fn evidence_confirms(&self, session: &DKGSession, accused: &str, evidence: Option<&[u8]>) -> bool {
+        let (Some(evidence), Some(keys)) = (evidence, self.participant_keys(accused)) else {
+            return false;
+        };
+        let Ok(msg) = bincode::deserialize::<DKGMessage>(evidence) else {
+            return false;
+        };
+        let Ok(Some(DKGPackage::Round1(package))) = msg.open(&self.identity, &session.group_id, accused, "", &keys) else {
+            return false;
+        };
+
+        if session.local.round1_received.get(accused).is_some_and(|ours| *ours != package) {
+            return true;
+        }
+        check_round1(&session.local, &package).is_err()
+    }
+
+    /// Ends the session and wipes everything derived from it: secrets, received
+    /// packages and, if we had already finalized, the sealed share and recorded group
+    fn abort(&self, session: &mut DKGSession, reason: String) {
+        // A quarantined group is kept as it is for the operator
+        if matches!(session.local.phase, DKGPhase::Quarantined(_)) {
+            return;
+        }
+
+        let written = matches!(session.local.phase, DKGPhase::Finalized | DKGPhase::Confirming);
+        if written && session.local.kind == DKGKind::Repair {
+            // A repaired share checked out against the group's record; keeping it changes nothing
+            println!("⚠️ Repair {} aborted after it completed on this node", session.group_id);
+        } else if written && matches!(session.local.kind, DKGKind::Refresh | DKGKind::Reshare) {
+            // The old share is already gone, so there is nothing to go back to. A group
+            // that was never confirmed cannot be trusted to sign either.
+            println!("⚠️ {:?} {} aborted after this node switched to the new shares", session.local.kind, session.group_id);
+            if session.local.phase == DKGPhase::Confirming {
+                let op_did = OperationalDID(session.local.operational_did.clone());
+                let status = GroupStatus::Quarantined(format!("aborted before every node confirmed it: {reason}"));
+                if let Err(e) = self.did_registry.set_group_status(&op_did, &session.group_id, status) {
+                    println!("⚠️ Could not quarantine group of DKG {}: {e:?}", session.group_id);
+                }
+            }
+        } else if written {
+            let op_did = OperationalDID(session.local.operational_did.clone());
+            match self.did_registry.withdraw_mpc_group(&op_did, &session.group_id) {
+                Ok(true) => {
+                    if let Some(vault_id) = self.did_registry.get_vault_id_for_operational_did(&op_did) {
+                        if let Err(e) = vault::remove_shard(&vault_id) {
+                            println!("⚠️ Could not remove share of aborted DKG {}: {e}", session.group_id);
+                        }
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => println!("⚠️ Could not withdraw group of aborted DKG {}: {e:?}", session.group_id),
+            }
+            session.local.group_public_key = None;
+        }
+
+        session.local.round1_received.clear();
+        session.local.round2_received.clear();
+        session.local.round1_messages.clear();
+        self.set_phase(session, DKGPhase::Aborted(reason));
+    }
+
+    /// Hands messages to the relay, which delivers them in order and retries. A message
+    /// it gives up on fails a live session (see `watch_relay`); what an ended one still
+    /// has to say (a complaint, or the confirmation that completed it here) goes to
+    /// every peer that can be reached.
+    fn send_all(&self, group_id: &str, outbox: Vec<(String, Vec<u8>)>) {
+        let kind = self.sessions.lock().unwrap().get(group_id).map(|s| s.local.kind).unwrap_or_default();
+        for (peer_id, msg) in outbox {
+            if let Err(e) = self.relay.send(relay_channel(kind), group_id, &peer_id, msg) {
+                let mut sessions = self.sessions.lock().unwrap();
+                let Some(session) = sessions.get_mut(group_id) else { return };
+                if session.local.phase.is_terminal() {
+                    println!("⚠️ DKG {group_id}: could not deliver to {peer_id}: {e:?}");
+                    continue;
+                }
+                self.set_phase(session, DKGPhase::Failed(format!("send to {peer_id}: {e:?}")));
+                return;
+            }
+        }
+    }
+
+    /// Computes each peer's own Round2 package
+    fn round2_packages(&self, session: &mut DKGSession) -> Result<Vec<(String, Vec<u8>)>, DKGError> {
+        if let Some(plan) = session.local.repair.clone() {
+            return self.repair_sum(session, &plan);
+        }
+        let round1_secret = session.local.round1_secret.take().ok_or(DKGError::CryptoFailure("Missing state".into()))?;
+
+        let suite = session.local.ciphersuite;
+        let (round2_secret, packages) = match session.local.kind {
+            DKGKind::Generate => with_ciphersuite!(suite, |C| ciphersuite::dkg_part2::<C>(&round1_secret, &session.local.round1_received)),
+            DKGKind::Refresh => with_ciphersuite!(suite, |C| ciphersuite::refresh_part2::<C>(&round1_secret, &session.local.round1_received)),
+            DKGKind::Reshare | DKGKind::Import | DKGKind::Repair => return Err(DKGError::CryptoFailure("no round-2 packages to derive".into())),
+        }.map_err(|fault| fault_error("Round2", fault))?;
+        session.local.round2_secret = Some(round2_secret);
+
+        // Round2 packages are per-recipient secrets: each is sealed to its recipient's identity
+        packages.into_iter()
+            .map(|(peer_id, pkg)| {
+                let keys = self.participant_keys(&peer_id)
+                    .ok_or_else(|| DKGError::Unauthenticated(format!("no identity key for {peer_id}")))?;
+                let msg = DKGMessage::sealed_round2(&self.identity, &session.group_id, &self.node_id, &peer_id, &keys, &pkg)?;
+                Ok((peer_id, bincode::serialize(&msg).unwrap()))
+            })
+            .collect()
+    }
+
+    /// Deals to the new committee if this node is a dealer: its share in a reshare, or
+    /// `import_key` in an import. The commitment goes to every peer and each new member
+    /// gets its share sealed to it. Our own commitment and share are recorded as
+    /// received, so finishing sees every dealer's.
+    fn deal(&self, group_id: &str, local: &mut DKGLocalState, import_key: Option<Zeroizing<Vec<u8>>>) -> Result<Vec<(String, Vec<u8>)>, DKGError> {
+        let dealing = local.dealing.clone().ok_or(DKGError::CryptoFailure("Missing state".into()))?;
+        if !dealing.dealers.contains(&self.node_id) {
+            return Ok(Vec::new());
+        }
+
+        let threshold = local.threshold as u16;
+        let (commitment, shares) = match local.kind {
+            DKGKind::Import => {
+                let key = import_key.ok_or(DKGError::CryptoFailure("Missing key to import".into()))?;
+                with_ciphersuite!(local.ciphersuite, |C| ciphersuite::import_deal::<C>(&key, &dealing.group_public_key, &dealing.members, threshold))
+                    .map_err(|e| DKGError::CryptoFailure(format!("Import deal failed: {e}")))?
+            }
+            _ => {
+                let shard = self.load_shard(&OperationalDID(local.operational_did.clone()))?;
+                with_ciphersuite!(local.ciphersuite, |C| ciphersuite::reshare_deal::<C>(&shard, &dealing.dealers, &dealing.members, threshold))
+                    .map_err(|e| DKGError::CryptoFailure(format!("Reshare deal failed: {e}")))?
+            }
+        };
+
+        let msg = bincode::serialize(&DKGMessage::signed_round1(&self.identity, group_id, &self.node_id, commitment.clone())).unwrap();
+        let mut outbox = local.participant_ids.iter()
+            .filter(|id| **id != self.node_id)
+            .map(|peer_id| (peer_id.clone(), msg.clone()))
+            .collect::<Vec<_>>();
+        local.round1_received.insert(self.node_id.clone(), commitment);
+
+        // Shares are per-recipient secrets, sealed like round-2 packages
+        for (member, share) in shares {
+            let share = Zeroizing::new(share);
+            if member == self.node_id {
+                local.round2_received.insert(member, share.to_vec());
+                continue;
+            }
+            let keys = self.participant_keys(&member)
+                .ok_or_else(|| DKGError::Unauthenticated(format!("no identity key for {member}")))?;
+            let msg = DKGMessage::sealed_round2(&self.identity, group_id, &self.node_id, &member, &keys, &share)?;
+            outbox.push((member, bincode::serialize(&msg).unwrap()));
+        }
+        Ok(outbox)
+    }
+
+    /// Repair round 1 on a helper: splits its weighted share into one part per helper.
+    /// Parts are secrets, so each is sealed to its helper; our own is recorded as received.
+    fn repair_parts(&self, group_id: &str, local: &mut DKGLocalState) -> Result<Vec<(String, Vec<u8>)>, DKGError> {
+        let plan = local.repair.clone().ok_or(DKGError::CryptoFailure("Missing state".into()))?;
+        if plan.lost == self.node_id {
+            return Ok(Vec::new());
+        }
+
+        let shard = self.load_shard(&OperationalDID(local.operational_did.clone()))?;
+        let parts = with_ciphersuite!(local.ciphersuite, |C| ciphersuite::repair_step1::<C>(&shard, &plan.helpers, &plan.lost))
+            .map_err(|e| DKGError::CryptoFailure(format!("Repair step 1 failed: {e}")))?;
+
+        let mut outbox = Vec::new();
+        for (helper, part) in parts {
+            let part = Zeroizing::new(part);
+            if helper == self.node_id {
+                local.round1_received.insert(helper, part.to_vec());
+                continue;
+            }
+            let keys = self.participant_keys(&helper)
+                .ok_or_else(|| DKGError::Unauthenticated(format!("no identity key for {helper}")))?;
+            let msg = DKGMessage::sealed_round1(&self.identity, group_id, &self.node_id, &helper, &keys, &part)?;
+            outbox.push((helper, bincode::serialize(&msg).unwrap()));
+        }
+        Ok(outbox)
+    }
+
+    /// Repair round 2 on a helper: every part is in; send their sum to the lost member
+    fn repair_sum(&self, session: &mut DKGSession, plan: &RepairPlan) -> Result<Vec<(String, Vec<u8>)>, DKGError> {
+        let sum = with_ciphersuite!(session.local.ciphersuite, |C| ciphersuite::repair_step2::<C>(&session.local.round1_received))
+            .map_err(|fault| fault_error("Repair step 2", fault))?;
+        let sum = Zeroizing::new(sum);
+
+        let keys = self.participant_keys(&plan.lost)
+            .ok_or_else(|| DKGError::Unauthenticated(format!("no identity key for {}", plan.lost)))?;
+        let msg = DKGMessage::sealed_round2(&self.identity, &session.group_id, &self.node_id, &plan.lost, &keys, &sum)?;
+        Ok(vec![(plan.lost.clone(), bincode::serialize(&msg).unwrap())])
+    }
+
+    /// The key package sealed in the DID's vault
+    fn load_shard(&self, op_did: &OperationalDID) -> Result<Zeroizing<Vec<u8>>, DKGError> {
+        let vault_id = self.did_registry
+            .get_vault_id_for_operational_did(op_did)
             .ok_or(DKGError::VaultNotFound)?;
+        vault::load_record(&vault_id)
+            .ok()
+            .and_then(|record| record.mpc_shard)
+            .and_then(|shard| base64::decode(shard).ok())
+            .map(Zeroizing::new)
+            .ok_or(DKGError::VaultNotFound)
+    }
 
-        let shard = key_package.secret_share().serialize();
-        vault::add_shard(&vault_id, &base64::encode(&shard))
-            .map_err(|e| DKGError::VaultStorageFailed)?;
+    /// Finalize and store the share locally
+    fn finalize(&self, session: &mut DKGSession) -> Result<(), DKGError> {
+        let suite = session.local.ciphersuite;
+        let round2_secret = session.local.round2_secret.take();
+        let missing_state = || DKGError::CryptoFailure("No state".into());
+        let op_did = OperationalDID(session.local.operational_did.clone());
+        let vault_id = self.did_registry
+            .get_vault_id_for_operational_did(&op_did)
+            .ok_or(DKGError::VaultNotFound)?;
 
-        // This is what we'd use if we utilized the helper. 
-        // vault::add_shard_for_did(registry, &session.local.operational_did, &base64::encode(&shard))?;
+        match session.local.kind {
+            DKGKind::Generate => {
+                let round2_secret = round2_secret.ok_or_else(missing_state)?;
+                let output = with_ciphersuite!(suite, |C| ciphersuite::dkg_part3::<C>(
+                    &round2_secret,
+                    &session.local.round1_received,
+                    &session.local.round2_received,
+                    &session.local.participant_ids,
+                )).map_err(|fault| fault_error("Finalize failed", fault))?;
 
+                // The full key package is sealed: signing needs the verifying share alongside the secret
+                vault::add_shard(&vault_id, &base64::encode(&output.key_package))
+                    .map_err(|_| DKGError::VaultStorageFailed)?;
+                self.record_group(session, &op_did, output.public_key_package, output.group_public_key, output.verifying_shares)
+            }
+            DKGKind::Refresh => {
+                let round2_secret = round2_secret.ok_or_else(missing_state)?;
+                let old_group = self.did_registry.get_mpc_group(&op_did).ok_or(DKGError::RegistryUpdateFailed)?;
+                let old_package = old_group.public_key_package.ok_or(DKGError::RegistryUpdateFailed)?;
+                let old_shard = self.load_shard(&op_did)?;
 
-       // this is the original vault call that used did if I end up switching to the helper
-       // vault::add_shard(&session.local.operational_did, &base64::encode(&shard)).map_err(|e| DKGError::VaultStorageFailed)?;
+                let output = with_ciphersuite!(suite, |C| ciphersuite::refresh_part3::<C>(
+                    &round2_secret,
+                    &session.local.round1_received,
+                    &session.local.round2_received,
+                    &session.local.participant_ids,
+                    &old_shard,
+                    &old_package,
+                )).map_err(|fault| fault_error("Refresh failed", fault))?;
+
+                // The old share is destroyed with the write; the group key is unchanged,
+                // so the DID document stays as it is
+                let epoch = vault::replace_shard(&vault_id, &base64::encode(&output.key_package))
+                    .map_err(|_| DKGError::VaultStorageFailed)?;
+                println!("🔄 Shares of {} refreshed into vault epoch {epoch}", session.local.operational_did);
+                self.record_group(session, &op_did, output.public_key_package, output.group_public_key, output.verifying_shares)
+            }
+            DKGKind::Reshare => {
+                let reshare = session.local.dealing.clone().ok_or_else(missing_state)?;
+                let output = with_ciphersuite!(suite, |C| ciphersuite::reshare_finish::<C>(
+                    &self.node_id,
+                    &session.local.round1_received,
+                    &session.local.round2_received,
+                    &reshare.dealers,
+                    &reshare.members,
+                    session.local.threshold as u16,
+                    &reshare.old_public_key_package,
+                )).map_err(|fault| fault_error("Reshare failed", fault))?;
+
+                // A member's old share, if it had one, is destroyed with the write;
+                // a dealer leaving the committee keeps nothing
+                match &output.key_package {
+                    Some(key_package) => {
+                        let epoch = vault::replace_shard(&vault_id, &base64::encode(key_package))
+                            .map_err(|_| DKGError::VaultStorageFailed)?;
+                        println!("🔀 {} holds a reshared share of {} (vault epoch {epoch})", self.node_id, session.local.operational_did);
+                    }
+                    None => {
+                        vault::remove_shard(&vault_id).map_err(|_| DKGError::VaultStorageFailed)?;
+                        println!("👋 {} handed its share of {} over", self.node_id, session.local.operational_did);
+                    }
+                }
+                self.record_group(session, &op_did, output.public_key_package, output.group_public_key, output.verifying_shares)
+            }
+            DKGKind::Repair => {
+                let plan = session.local.repair.clone().ok_or_else(missing_state)?;
+                let package = self.did_registry.get_mpc_group(&op_did)
+                    .and_then(|group| group.public_key_package)
+                    .ok_or(DKGError::RegistryUpdateFailed)?;
+
+                // The group is unchanged; only the lost member has anything to store
+                if plan.lost == self.node_id {
+                    let key_package = with_ciphersuite!(suite, |C| ciphersuite::repair_finish::<C>(
+                        &self.node_id,
+                        &session.local.round2_received,
+                        &package,
+                        session.local.threshold as u16,
+                    )).map_err(|fault| fault_error("Repair failed", fault))?;
+
+                    let epoch = vault::replace_shard(&vault_id, &base64::encode(&key_package))
+                        .map_err(|_| DKGError::VaultStorageFailed)?;
+                    println!("🩹 {} repaired its share of {} (vault epoch {epoch})", self.node_id, session.local.operational_did);
+                }
+                let group_public_key = with_ciphersuite!(suite, |C| ciphersuite::group_verifying_key::<C>(&package))
+                    .map_err(DKGError::CryptoFailure)?;
+                session.local.group_public_key = Some(group_public_key);
+                Ok(())
+            }
+            DKGKind::Import => {
+                let import = session.local.dealing.clone().ok_or_else(missing_state)?;
+                let output = with_ciphersuite!(suite, |C| ciphersuite::import_finish::<C>(
+                    &self.node_id,
+                    &session.local.round1_received,
+                    &session.local.round2_received,
+                    &import.dealers[0],
+                    &import.members,
+                    session.local.threshold as u16,
+                    &import.group_public_key,
+                )).map_err(|fault| fault_error("Import failed", fault))?;
+
+                // The dealer keeps nothing unless it is also a member
+                if let Some(key_package) = &output.key_package {
+                    vault::add_shard(&vault_id, &base64::encode(key_package))
+                        .map_err(|_| DKGError::VaultStorageFailed)?;
+                    println!("📥 {} holds a share of the imported key of {}", self.node_id, session.local.operational_did);
+                }
+                self.record_group(session, &op_did, output.public_key_package, output.group_public_key, output.verifying_shares)
+            }
+        }
+    }
+
+    /// Records the group a finished session produced as the DID's group. It stays
+    /// pending, unable to sign, until every custody node confirms the same one.
+    fn record_group(
+        &self,
+        session: &mut DKGSession,
+        op_did: &OperationalDID,
+        public_key_package: Vec<u8>,
+        group_public_key: Vec<u8>,
+        verifying_shares: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), DKGError> {
+        let suite = session.local.ciphersuite;
+        let confirmation = confirmation_digest(&public_key_package);
+        let identity_key = |id: &str| match id == self.node_id {
+            true => Some(self.identity.public_keys().signing_key),
+            false => self.participant_keys(id).map(|keys| keys.signing_key),
+        };
+        let members = with_ciphersuite!(suite, |C| membership::describe_members::<C>(&verifying_shares, identity_key))
+            .map_err(|_| DKGError::RegistryUpdateFailed)?;
 
         let mpc_group = MPCGroupDescriptor {
-            group_id: group_id.to_string(),
-            members: pubkeys.iter().map(|(id, pk)| MPCMemberDescriptor {
-                node_id: String::from_utf8_lossy(id.serialize()).to_string(),
-                public_share: base64::encode(pk.serialize()),
-            }).collect(),
+            group_id: session.group_id.clone(),
+            members,
             threshold: session.local.threshold,
-            dkg_protocol: Some("frost-ed25519-dkg-v1".into()),
+            dkg_protocol: Some(suite.dkg_protocol().into()),
             session_state: None,
+            public_key_package: Some(public_key_package),
+            status: GroupStatus::Pending,
+        };
+
+        self.did_registry.set_mpc_group(op_did, mpc_group).map_err(|_| DKGError::RegistryUpdateFailed)?;
+        session.local.group_public_key = Some(group_public_key);
+        session.local.confirmation = Some(confirmation);
+
+        Ok(())
+    }
+
+    /// Fails the session if the current phase missed its deadline, or quarantines its
+    /// group if the confirmations did
+    fn expire_if_late(&self, session: &mut DKGSession) {
+        // Our share is already sealed; a group some node never confirmed must not sign
+        if session.local.phase == DKGPhase::Confirming && SystemTime::now() > session.local.phase_deadline {
+            let silent = confirming_peers(&self.node_id, &session.local).into_iter()
+                .filter(|peer_id| !session.local.confirmations.contains_key(peer_id))
+                .collect::<Vec<_>>();
+            return self.quarantine(session, format!("no confirmation from {}", silent.join(", ")));
+        }
+        if !session.local.phase.is_terminal() && SystemTime::now() > session.local.phase_deadline {
+            let phase = format!("{:?} deadline passed", session.local.phase);
+            self.set_phase(session, DKGPhase::Failed(phase));
+        }
+    }
+
+    fn set_phase(&self, session: &mut DKGSession, phase: DKGPhase) {
+        println!("🔑 DKG {} on {}: {:?}", session.group_id, self.node_id, phase);
+        if matches!(phase, DKGPhase::Failed(_) | DKGPhase::Aborted(_)) {
+            // Secrets of a failed session are never used again
+            session.local.round1_secret = None;
+            session.local.round2_secret = None;
+        }
+        session.local.phase = phase.clone();
+        session.local.phase_deadline = SystemTime::now() + DKG_PHASE_TIMEOUT;
+        session.events.send_replace(phase);
+        self.checkpoint(session);
+    }
+
+    /// Authenticates a peer's message. Packages are checked and stored for their round;
+    /// a redelivered package is ignored. Complaints, aborts and resend requests are
+    /// accepted in any phase. Returns the replies owed to the sender.
+    fn record_message(&self, session: &mut DKGSession, from: &str, msg: Vec<u8>) -> Result<Vec<(String, Vec<u8>)>, DKGError> {
+        if from == self.node_id || !session.local.participant_ids.iter().any(|id| id == from) {
+            return Err(DKGError::Unauthenticated(format!("{from} is not a peer in this session")));
+        }
+        let sender = self.participant_keys(from)
+            .ok_or_else(|| DKGError::Unauthenticated(format!("no identity key for {from}")))?;
+
+        let dkg_msg: DKGMessage = bincode::deserialize(&msg).map_err(|_| DKGError::MessageMalformed)?;
+        let (is_round1, raw) = match dkg_msg.open(&self.identity, &session.group_id, from, &self.node_id, &sender)? {
+            Some(DKGPackage::Round1(raw)) => (true, raw),
+            Some(DKGPackage::Round2(raw)) => (false, raw),
+            Some(DKGPackage::Complaint(complaint)) => {
+                self.accept_complaint(session, complaint);
+                return Ok(Vec::new());
+            }
+            Some(DKGPackage::Resend) => {
+                let sent = session.local.sent.get(from).cloned().unwrap_or_default();
+                return Ok(sent.into_iter().map(|msg| (from.to_string(), msg)).collect());
+            }
+            Some(DKGPackage::Confirmation(digest)) => {
+                self.accept_confirmation(session, from, digest);
+                return Ok(Vec::new());
+            }
+            Some(DKGPackage::Abort(reason)) => {
+                if !matches!(session.local.phase, DKGPhase::Aborted(_)) {
+                    self.abort(session, format!("{from} aborted: {reason}"));
+                }
+                return Ok(Vec::new());
+            }
+            None => return Ok(Vec::new()),
         };
 
-        self.did_registry.set_mpc_group(&session.local.operational_did, mpc_group).map_err(|_| DKGError::RegistryUpdateFailed)?;
+        match &session.local.phase {
+            DKGPhase::Failed(reason) => return Err(DKGError::SessionFailed(reason.clone())),
+            DKGPhase::Aborted(reason) => return Err(DKGError::SessionAborted(reason.clone())),
+            _ => {}
+        }
+
+        // In a reshare or import only dealers send packages, and shares only go to the new committee
+        if let Some(dealing) = &session.local.dealing {
+            let reason = if !dealing.dealers.iter().any(|d| d == from) {
+                Some("sent a dealing package without being a dealer")
+            } else if !is_round1 && !dealing.members.contains(&self.node_id) {
+                Some("dealt a share to a node outside the new committee")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                return Err(DKGError::PeerMisbehaved { culprit: from.to_string(), reason: reason.into() });
+            }
+        }
 
-        Ok(shard)
+        // In a repair only helpers send; parts go to helpers and sums to the lost member
+        if let Some(plan) = &session.local.repair {
+            let reason = if !plan.helpers.iter().any(|h| h == from) {
+                Some("sent a repair package without being a helper")
+            } else if is_round1 == (plan.lost == self.node_id) {
+                Some("sent a repair package this node should not get")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                return Err(DKGError::PeerMisbehaved { culprit: from.to_string(), reason: reason.into() });
+            }
+        }
+
+        let round = if is_round1 { &mut session.local.round1_received } else { &mut session.local.round2_received };
+        match round.get(from) {
+            Some(existing) if *existing == raw => return Ok(Vec::new()),
+            Some(_) => return Err(DKGError::PeerMisbehaved {
+                culprit: from.to_string(),
+                reason: "sent two different packages for the same round".into(),
+            }),
+            None => { round.insert(from.to_string(), raw.clone()); }
+        }
+
+        if is_round1 {
+            session.local.round1_messages.insert(from.to_string(), msg);
+            check_round1(&session.local, &raw)
+                .map_err(|reason| DKGError::PeerMisbehaved { culprit: from.to_string(), reason })?;
+            session.local.peer_results.insert(from.to_string(), PeerResult::Round1Verified);
+        }
+        Ok(Vec::new())
+    }
+}
+
+/// Checks a peer's round-1 package has the shape the session expects: a FROST round-1
+/// package, or for a reshare or import, a commitment to a polynomial of the new degree
+fn check_round1(local: &DKGLocalState, package: &[u8]) -> Result<(), String> {
+    let coefficients = local.kind.commitment_len(local.threshold);
+    match local.kind {
+        DKGKind::Reshare | DKGKind::Import => with_ciphersuite!(local.ciphersuite, |C| ciphersuite::check_dealing_commitment::<C>(package, coefficients)),
+        // Repair parts are secrets, not commitments; they are checked when added up
+        DKGKind::Repair => Ok(()),
+        _ => with_ciphersuite!(local.ciphersuite, |C| ciphersuite::check_round1_package::<C>(package, coefficients)),
+    }
+}
+
+/// Relay channel a session's messages travel on
+pub fn relay_channel(kind: DKGKind) -> MessageType {
+    match kind {
+        DKGKind::Reshare => MessageType::Reshare,
+        DKGKind::Repair => MessageType::ShareRepair,
+        DKGKind::Generate | DKGKind::Refresh | DKGKind::Import => MessageType::Dkg,
+    }
+}
+
+/// Custody nodes that confirm the group to us; devices keep no group record
+fn confirming_peers(node_id: &str, local: &DKGLocalState) -> Vec<String> {
+    local.participant_ids.iter()
+        .filter(|id| *id != node_id && !device::is_device_participant(id))
+        .cloned()
+        .collect()
+}
+
+/// A fault with a culprit becomes a complaint; anything else is a local failure
+fn fault_error(step: &str, fault: ciphersuite::DkgFault) -> DKGError {
+    match fault.culprit {
+        Some(culprit) => DKGError::PeerMisbehaved { culprit, reason: fault.reason },
+        None => DKGError::CryptoFailure(format!("{step}: {}", fault.reason)),
     }
-}
\ No newline at end of file
+}
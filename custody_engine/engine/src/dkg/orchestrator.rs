@@ -1,52 +1,436 @@
 use tonic::transport::Channel;
 use custodydkg::custody_dkg_client::CustodyDkgClient;
-use custodydkg::{StartDkgSessionRequest, BroadcastRound2Request, FinalizeDkgRequest};
+use custodydkg::{StartDkgSessionRequest, GetDkgStatusRequest, GetDkgStatusResponse, AbortDkgSessionRequest, DkgPhase, SealedKey};
+use custodydkg::{StartEcdsaSessionRequest, AdvanceEcdsaSessionRequest, FinalizeEcdsaSessionRequest};
+use custodydevice::custody_device_client::CustodyDeviceClient;
+use custodydevice::{DkgInvite, InviteDeviceRequest};
 
 use std::collections::HashMap;
-use std::time::Duration;
-use tokio::time::sleep;
 
-pub async fn orchestrate_dkg(op_did: &str, threshold: u32, nodes: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
-    let first = nodes[0].clone();
+use crate::ciphersuite::SuiteId;
+use crate::dkg::types::reshare_participants;
+use crate::mpc::device;
+use crate::tls;
 
-    // STEP 1: Start session by calling one node
-    let mut client = CustodyDkgClient::connect(format!("http://{}", first)).await?;
-    let start_resp = client.start_dkg_session(StartDkgSessionRequest {
-        operational_did: op_did.to_string(),
-        threshold,
-        participant_nodes: nodes.clone(),
-    }).await?.into_inner();
+/// Attempts before a DKG that keeps hitting misbehaving participants is given up
+pub const MAX_DKG_ATTEMPTS: usize = 3;
 
-    let group_id = start_resp.group_id;
+/// How one DKG attempt ended
+enum DkgAttempt {
+    Finalized(String),        // Group ID
+    Aborted(Vec<String>),     // Participants to leave out of the retry
+}
+
+/// Runs a full DKG across `nodes` for the given ciphersuite and returns the group ID.
+/// `nodes` may include device participants; they run the rounds themselves once invited.
+/// Nodes advance through the rounds on their own; this only starts them and waits.
+/// If a participant is caught misbehaving the DKG is retried without it, as long as
+/// enough participants remain for the threshold.
+pub async fn orchestrate_dkg(op_did: &str, threshold: u32, nodes: Vec<String>, suite: SuiteId) -> Result<String, Box<dyn std::error::Error>> {
+    let mut nodes = nodes;
+
+    for attempt in 1..=MAX_DKG_ATTEMPTS {
+        let culprits = match run_dkg(op_did, threshold, &nodes, suite, false).await? {
+            DkgAttempt::Finalized(group_id) => return Ok(group_id),
+            DkgAttempt::Aborted(culprits) => culprits,
+        };
+
+        nodes.retain(|n| !culprits.contains(n));
+        if nodes.len() < threshold as usize || nodes.iter().all(|n| device::is_device_participant(n)) {
+            return Err(format!("DKG aborted; too few participants left without {}", culprits.join(", ")).into());
+        }
+        println!("🔁 Retrying DKG without {} (attempt {}/{MAX_DKG_ATTEMPTS})", culprits.join(", "), attempt + 1);
+    }
+
+    Err(format!("DKG did not complete in {MAX_DKG_ATTEMPTS} attempts").into())
+}
+
+/// Refreshes the shares of `op_did`'s group on `nodes` (its members) and returns the
+/// refreshed group's ID. The group key does not change. Every member has to take
+/// part, so unlike a DKG a refresh cannot be retried without a misbehaving member;
+/// such a group should be rotated instead.
+pub async fn orchestrate_refresh(op_did: &str, nodes: Vec<String>) -> Result<String, Box<dyn std::error::Error>> {
+    match run_dkg(op_did, 0, &nodes, SuiteId::default(), true).await? {
+        DkgAttempt::Finalized(group_id) => Ok(group_id),
+        DkgAttempt::Aborted(culprits) => Err(format!("Refresh aborted; misbehaving members: {}", culprits.join(", ")).into()),
+    }
+}
+
+/// One DKG (or, with `refresh`, one share refresh, whose threshold and suite
+/// come from the existing group) across `nodes`
+async fn run_dkg(op_did: &str, threshold: u32, nodes: &[String], suite: SuiteId, refresh: bool) -> Result<DkgAttempt, Box<dyn std::error::Error>> {
+    let (devices, custody_nodes): (Vec<String>, Vec<String>) = nodes.iter().cloned().partition(|n| device::is_device_participant(n));
+    if custody_nodes.is_empty() {
+        return Err("DKG needs at least one custody node".into());
+    }
+    if refresh && !devices.is_empty() {
+        return Err("Devices cannot refresh their shares; rotate the group instead".into());
+    }
+    let group_id = uuid::Uuid::new_v4().to_string();
+
+    // STEP 1: Start the same session on every custody node; each broadcasts its Round1
+    for node in &custody_nodes {
+        let mut client = CustodyDkgClient::new(tls::channel(node).await?);
+        client.start_dkg_session(StartDkgSessionRequest {
+            operational_did: op_did.to_string(),
+            threshold,
+            participant_nodes: nodes.to_vec(),
+            ciphersuite: suite.name().to_string(),
+            group_id: group_id.clone(),
+            refresh,
+            ..Default::default()
+        }).await?;
+    }
     println!("✅ Started DKG with group ID: {group_id}");
 
-    // Optional: wait to let Round1 messages propagate
-    sleep(Duration::from_secs(1)).await;
+    // STEP 1b: Invite devices through their home nodes
+    for participant in &devices {
+        let home = device::device_home_node(participant).ok_or("Malformed device participant ID")?;
+        let mut client = CustodyDeviceClient::new(tls::channel(home).await?);
+        client.invite_device(InviteDeviceRequest {
+            participant_id: participant.clone(),
+            invite: Some(DkgInvite {
+                group_id: group_id.clone(),
+                operational_did: op_did.to_string(),
+                threshold,
+                participants: nodes.to_vec(),
+                ciphersuite: suite.name().to_string(),
+                participant_keys: vec![],
+            }),
+        }).await?;
+        println!("📱 Invited device {participant}");
+    }
 
-    // STEP 2: Broadcast Round2 on all nodes
-    for node in &nodes {
-        let mut client = CustodyDkgClient::connect(format!("http://{}", node)).await?;
-        println!("📡 Broadcasting Round2 to {node}");
-        client.broadcast_round2(BroadcastRound2Request {
+    // STEP 2: Wait for every node to finalize; all must agree on the group key
+    let mut group_key = None;
+    for node in &custody_nodes {
+        let mut client = CustodyDkgClient::new(tls::channel(node).await?);
+        let status = client.get_dkg_status(GetDkgStatusRequest {
             group_id: group_id.clone(),
+            wait: true,
+        }).await?.into_inner();
+
+        if status.phase == DkgPhase::Aborted as i32 {
+            println!("🚨 DKG {group_id} aborted on {node}: {}", status.error);
+            let culprits = collect_culprits(&group_id, &custody_nodes).await;
+            abort_everywhere(&group_id, &custody_nodes, &status.error).await;
+            if culprits.is_empty() {
+                return Err(format!("DKG aborted on {node} without naming a culprit: {}", status.error).into());
+            }
+            return Ok(DkgAttempt::Aborted(culprits));
+        }
+        // Nodes disagree on the result; the group stays on record, unable to sign
+        if status.phase == DkgPhase::Quarantined as i32 {
+            return Err(format!("DKG {group_id} quarantined on {node}: {}", status.error).into());
+        }
+        if status.phase != DkgPhase::Finalized as i32 {
+            abort_everywhere(&group_id, &custody_nodes, &status.error).await;
+            return Err(format!("DKG failed on {node}: {}", status.error).into());
+        }
+        if group_key.get_or_insert_with(|| status.group_public_key.clone()) != &status.group_public_key {
+            abort_everywhere(&group_id, &custody_nodes, "group keys differ").await;
+            return Err(format!("{node} derived a different group key").into());
+        }
+        println!("🔐 Finalized {node}");
+    }
+
+    if refresh {
+        println!("🎉 All nodes refreshed their shares; group key unchanged.");
+    } else {
+        println!("🎉 All nodes completed FROST DKG ({}).", suite.dkg_protocol());
+    }
+    Ok(DkgAttempt::Finalized(group_id))
+}
+
+/// Starts moving `op_did`'s group key from `dealers` (old members) to `members` with
+/// a new `threshold`, and returns the session's group ID once every node involved has
+/// started. A node can be both a dealer and a member; dealers outside `members` hand
+/// over their share and keep nothing. Follow it with `await_reshare` or `reshare_progress`.
+pub async fn start_reshare(
+    op_did: &str,
+    dealers: &[String],
+    members: &[String],
+    threshold: u32,
+    suite: SuiteId,
+    old_public_key_package: &[u8],
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    if dealers.iter().chain(members).any(|n| device::is_device_participant(n)) {
+        return Err("Devices cannot take part in a resharing; rotate the group instead".into());
+    }
+    if threshold == 0 || threshold as usize > members.len() {
+        return Err(format!("Cannot reshare to {threshold}-of-{}", members.len()).into());
+    }
+    let group_id = uuid::Uuid::new_v4().to_string();
+    let participants = reshare_participants(dealers, members);
+
+    // STEP 1: Start the same session on both committees; dealers send everything at once
+    for node in &participants {
+        let mut client = CustodyDkgClient::new(tls::channel(node).await?);
+        let started = client.start_dkg_session(StartDkgSessionRequest {
+            operational_did: op_did.to_string(),
+            threshold,
+            participant_nodes: members.to_vec(),
+            ciphersuite: suite.name().to_string(),
+            group_id: group_id.clone(),
+            reshare: true,
+            dealer_nodes: dealers.to_vec(),
+            old_public_key_package: old_public_key_package.to_vec(),
+            ..Default::default()
+        }).await;
+        if let Err(e) = started {
+            abort_everywhere(&group_id, &participants, "a participant could not start").await;
+            return Err(format!("{node} could not start the reshare: {}", e.message()).into());
+        }
+    }
+    println!("✅ Started reshare {group_id}: {} dealers → {threshold}-of-{}", dealers.len(), members.len());
+    Ok(group_id)
+}
+
+/// Waits for a reshare or import to finish on every participant and returns the group
+/// key, which must be the same on every node. Any failure aborts the session everywhere.
+pub async fn await_reshare(group_id: &str, participants: &[String]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut group_key = None;
+    for node in participants {
+        let mut client = CustodyDkgClient::new(tls::channel(node).await?);
+        let status = client.get_dkg_status(GetDkgStatusRequest {
+            group_id: group_id.to_string(),
+            wait: true,
+        }).await?.into_inner();
+
+        if status.phase == DkgPhase::Quarantined as i32 {
+            return Err(format!("{} {group_id} quarantined on {node}: {}", status.kind, status.error).into());
+        }
+        if status.phase != DkgPhase::Finalized as i32 {
+            abort_everywhere(group_id, participants, &status.error).await;
+            return Err(format!("{} failed on {node}: {}", status.kind, status.error).into());
+        }
+        if group_key.get_or_insert_with(|| status.group_public_key.clone()) != &status.group_public_key {
+            abort_everywhere(group_id, participants, "group keys differ").await;
+            return Err(format!("{node} ended up with a different group key").into());
+        }
+    }
+
+    println!("🎉 Session {group_id} complete; every node holds the same group key.");
+    group_key.ok_or_else(|| "no participants".into())
+}
+
+/// Splits an existing Ed25519 key over `members` with `dealer` as the only node that
+/// ever sees it, and returns the group ID once every member holds its share. The key
+/// is sealed to the dealer's identity key and only the dealer is sent the box.
+pub async fn orchestrate_import(
+    op_did: &str,
+    dealer: &str,
+    members: &[String],
+    threshold: u32,
+    public_key: &[u8],
+    sealed_key: SealedKey,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    if members.iter().any(|n| device::is_device_participant(n)) {
+        return Err("Devices cannot receive an imported key".into());
+    }
+    if threshold == 0 || threshold as usize > members.len() {
+        return Err(format!("Cannot import into {threshold}-of-{}", members.len()).into());
+    }
+    let group_id = uuid::Uuid::new_v4().to_string();
+    let dealers = vec![dealer.to_string()];
+    let participants = reshare_participants(&dealers, members);
+
+    // STEP 1: Start the session everywhere; the dealer deals as soon as it starts
+    for node in &participants {
+        let mut client = CustodyDkgClient::new(tls::channel(node).await?);
+        let started = client.start_dkg_session(StartDkgSessionRequest {
+            operational_did: op_did.to_string(),
+            threshold,
+            participant_nodes: members.to_vec(),
+            ciphersuite: SuiteId::Ed25519.name().to_string(),
+            group_id: group_id.clone(),
+            dealer_nodes: dealers.clone(),
+            import_key: true,
+            sealed_key: (node == dealer).then(|| sealed_key.clone()),
+            group_public_key: public_key.to_vec(),
+            ..Default::default()
+        }).await;
+        if let Err(e) = started {
+            abort_everywhere(&group_id, &participants, "a participant could not start").await;
+            return Err(format!("{node} could not start the import: {}", e.message()).into());
+        }
+    }
+
+    // STEP 2: Every node must end up with the imported key as its group key
+    let group_key = await_reshare(&group_id, &participants).await?;
+    if group_key != public_key {
+        abort_everywhere(&group_id, &participants, "group key is not the imported key").await;
+        return Err("The imported group key differs from the public key".into());
+    }
+    println!("📥 Imported key of {op_did} split {threshold}-of-{}", members.len());
+    Ok(group_id)
+}
+
+/// Rebuilds `lost`'s share of `op_did`'s group from `helpers`, at least the group's
+/// threshold of its other members, and returns the repair's session ID. Nothing about
+/// the group changes; the lost member checks the rebuilt share against its verifying
+/// share before sealing it, and every node must report the group's key.
+pub async fn orchestrate_repair(op_did: &str, helpers: &[String], lost: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    if helpers.iter().any(|h| h == lost) {
+        return Err("The lost member cannot help repair its own share".into());
+    }
+    let group_id = uuid::Uuid::new_v4().to_string();
+    let mut participants = helpers.to_vec();
+    participants.push(lost.to_string());
+
+    // STEP 1: Start the repair everywhere; helpers exchange their parts right away
+    for node in &participants {
+        let mut client = CustodyDkgClient::new(tls::channel(node).await?);
+        let started = client.start_dkg_session(StartDkgSessionRequest {
+            operational_did: op_did.to_string(),
+            participant_nodes: helpers.to_vec(),
+            group_id: group_id.clone(),
+            repair: true,
+            lost_node: lost.to_string(),
+            ..Default::default()
+        }).await;
+        if let Err(e) = started {
+            abort_everywhere(&group_id, &participants, "a participant could not start").await;
+            return Err(format!("{node} could not start the repair: {}", e.message()).into());
+        }
+    }
+
+    // STEP 2: Wait for the helpers' sums to reach the lost member
+    await_reshare(&group_id, &participants).await?;
+    println!("🩹 Repaired {lost}'s share of {op_did} with {} helpers", helpers.len());
+    Ok(group_id)
+}
+
+/// Current status of a reshare on each participant, without waiting
+pub async fn reshare_progress(group_id: &str, participants: &[String]) -> Vec<(String, Result<GetDkgStatusResponse, String>)> {
+    let mut progress = Vec::new();
+    for node in participants {
+        let status = match tls::channel(node).await.map(CustodyDkgClient::new) {
+            Ok(mut client) => client.get_dkg_status(GetDkgStatusRequest { group_id: group_id.to_string(), wait: false })
+                .await
+                .map(|r| r.into_inner())
+                .map_err(|e| e.message().to_string()),
+            Err(e) => Err(format!("unreachable: {e}")),
+        };
+        progress.push((node.clone(), status));
+    }
+    progress
+}
+
+/// Gathers every node's complaints. An accusation some third node confirmed from the
+/// evidence excludes only the accused; one nobody else could check excludes both
+/// sides, since the orchestrator cannot tell which of them is lying.
+async fn collect_culprits(group_id: &str, custody_nodes: &[String]) -> Vec<String> {
+    let mut complaints = Vec::new();
+    for node in custody_nodes {
+        let Ok(mut client) = tls::channel(node).await.map(CustodyDkgClient::new) else { continue };
+        if let Ok(status) = client.get_dkg_status(GetDkgStatusRequest { group_id: group_id.to_string(), wait: false }).await {
+            complaints.extend(status.into_inner().complaints.into_iter().map(|c| (node.clone(), c)));
+        }
+    }
+
+    let mut culprits = Vec::new();
+    for (_, complaint) in &complaints {
+        let confirmed_by_third_party = complaints.iter().any(|(reporter, c)| {
+            c.accuser == complaint.accuser && c.accused == complaint.accused && c.confirmed
+                && *reporter != c.accuser && *reporter != c.accused
+        });
+        let parties = if confirmed_by_third_party {
+            vec![&complaint.accused]
+        } else {
+            vec![&complaint.accuser, &complaint.accused]
+        };
+        for party in parties {
+            if !culprits.contains(party) {
+                culprits.push(party.clone());
+            }
+        }
+    }
+    culprits
+}
+
+/// Makes sure no node keeps partial state of a session that will not complete
+async fn abort_everywhere(group_id: &str, custody_nodes: &[String], reason: &str) {
+    for node in custody_nodes {
+        let Ok(mut client) = tls::channel(node).await.map(CustodyDkgClient::new) else { continue };
+        if let Err(e) = client.abort_dkg_session(AbortDkgSessionRequest {
+            group_id: group_id.to_string(),
+            reason: reason.to_string(),
+        }).await {
+            println!("⚠️ Could not abort DKG {group_id} on {node}: {}", e.message());
+        }
+    }
+}
+
+/// Runs threshold ECDSA (secp256k1) keygen across `nodes` and returns the group public key (hex)
+pub async fn orchestrate_ecdsa_keygen(op_did: &str, threshold: u32, nodes: Vec<String>) -> Result<String, Box<dyn std::error::Error>> {
+    let session_id = uuid::Uuid::new_v4().to_string();
+
+    // STEP 1: Every node deals its Feldman-committed secret
+    for node in &nodes {
+        let mut client = CustodyDkgClient::new(tls::channel(node).await?);
+        client.start_ecdsa_session(StartEcdsaSessionRequest {
+            session_id: session_id.clone(),
+            kind: "keygen".into(),
+            operational_did: op_did.to_string(),
+            threshold,
+            participant_nodes: nodes.clone(),
         }).await?;
     }
+    println!("✅ Started ECDSA keygen session {session_id}");
+
+    // STEP 2: Finalize once each node has every dealing; every node must report the same group key
+    let mut group_key = None;
+    for node in &nodes {
+        let mut client = CustodyDkgClient::new(tls::channel(node).await?);
+        let key = client.finalize_ecdsa_session(FinalizeEcdsaSessionRequest {
+            session_id: session_id.clone(),
+        }).await?.into_inner().result;
+
+        println!("🔐 Finalized {node} ECDSA key share, group key = {key}");
+        if group_key.get_or_insert_with(|| key.clone()) != &key {
+            return Err(format!("{node} derived a different ECDSA group key").into());
+        }
+    }
 
-    // Optional: wait to let Round2 messages propagate
-    sleep(Duration::from_secs(1)).await;
+    println!("🎉 All nodes completed ECDSA keygen.");
+    group_key.ok_or_else(|| "no nodes".into())
+}
+
+/// Runs one presign session across the DID's ECDSA committee and returns the presignature ID
+pub async fn orchestrate_ecdsa_presign(op_did: &str, nodes: Vec<String>) -> Result<String, Box<dyn std::error::Error>> {
+    let session_id = uuid::Uuid::new_v4().to_string();
 
-    // STEP 3: Finalize and collect result
+    // STEP 1: Deal k, a and the masking zero-sharings
     for node in &nodes {
-        let mut client = CustodyDkgClient::connect(format!("http://{}", node)).await?;
-        let resp = client.finalize_dkg_session(FinalizeDkgRequest {
-            group_id: group_id.clone(),
+        let mut client = CustodyDkgClient::new(tls::channel(node).await?);
+        client.start_ecdsa_session(StartEcdsaSessionRequest {
+            session_id: session_id.clone(),
+            kind: "presign".into(),
+            operational_did: op_did.to_string(),
+            threshold: 0,
+            participant_nodes: vec![],
+        }).await?;
+    }
+
+    // STEP 2: Open w = k·a; each node first waits for every deal
+    for node in &nodes {
+        let mut client = CustodyDkgClient::new(tls::channel(node).await?);
+        client.advance_ecdsa_session(AdvanceEcdsaSessionRequest {
+            session_id: session_id.clone(),
         }).await?;
+    }
 
-        println!("🔐 Finalized {node} shard = {}", resp.into_inner().shard_base64);
+    // STEP 3: Each node seals its presignature share once every opening is in
+    for node in &nodes {
+        let mut client = CustodyDkgClient::new(tls::channel(node).await?);
+        client.finalize_ecdsa_session(FinalizeEcdsaSessionRequest {
+            session_id: session_id.clone(),
+        }).await?;
     }
 
-    println!("🎉 All nodes completed FROST DKG.");
-    Ok(())
+    println!("🧮 Presignature {session_id} ready on {} nodes", nodes.len());
+    Ok(session_id)
 }
 
 /// Then we can call this from anywhere in our system
@@ -55,6 +439,6 @@ pub async fn orchestrate_dkg(op_did: &str, threshold: u32, nodes: Vec<String>) -
     let op_did = "did:example:123";
     let peers = discover_peer_nodes("custody-nodes.default.svc.cluster.local").await?;
 
-    orchestrator::orchestrate_dkg(op_did, 2, peers).await?;
+    orchestrator::orchestrate_dkg(op_did, 2, peers, SuiteId::Ed25519).await?;
 
 */
\ No newline at end of file
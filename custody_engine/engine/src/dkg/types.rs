@@ -4,31 +4,367 @@
 
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
+use std::time::SystemTime;
 
-/// Messages exchanged between custody nodes during FROST DKG
+use crate::ciphersuite::SuiteId;
+use crate::identity::{self, IdentityPublicKeys, NodeIdentity, SealedBox};
+
+/// Domain separator for signatures on DKG messages
+pub const DKG_MESSAGE_DOMAIN: &[u8] = b"custody-dkg-message-v1";
+
+/// KDF context for the public key package hash nodes confirm to each other
+pub const DKG_CONFIRM_CONTEXT: &str = "custody-dkg-confirm-v1 public-key-package";
+
+/// Domain separator binding an imported key's sealed box to its DID and public key
+pub const KEY_IMPORT_DOMAIN: &[u8] = b"custody-key-import-v1";
+
+/// Messages exchanged between custody nodes during FROST DKG. Every message is
+/// signed by the sender's identity key over the group, sender and recipient, so
+/// a relayed message cannot be attributed to anyone else.
 #[derive(Debug, Serialize, Deserialize)]
 pub enum DKGMessage {
+    Round1 { package: Vec<u8>, signature: Vec<u8> },   // Broadcast; signed
+    Round2 { sealed: SealedBox, signature: Vec<u8> },  // Encrypted to the recipient, then signed
+    Finalization(Vec<u8>),
+    Complaint {                                         // Broadcast; the session is aborted everywhere
+        accused: String,
+        reason: String,
+        evidence: Option<Vec<u8>>,                      // The accused's signed round-1 message, when the fault is in it
+        signature: Vec<u8>,
+    },
+    Resend { signature: Vec<u8> },                      // Sender restarted; please send it everything again
+    Abort { reason: String, signature: Vec<u8> },       // Sender cannot continue; blames no one
+    SealedRound1 { sealed: SealedBox, signature: Vec<u8> }, // Secret round-1 package (share repair); sealed like round 2
+    Confirmation { digest: Vec<u8>, signature: Vec<u8> },   // Hash of the sender's public key package once finalized
+}
+
+/// A DKG package after its signature checked out (and, for round 2, after decryption)
+pub enum DKGPackage {
     Round1(Vec<u8>),
     Round2(Vec<u8>),
-    Finalization(Vec<u8>),
+    Complaint(DKGComplaint),
+    Resend,
+    Abort(String),
+    Confirmation(Vec<u8>),
 }
 
-/// Local DKG session state for a single custody node
-#[derive(Debug)]
+/// One participant's accusation that another sent a bad package
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DKGComplaint {
+    pub accuser: String,
+    pub accused: String,
+    pub reason: String,
+    pub evidence: Option<Vec<u8>>,
+    pub confirmed: bool,              // This node saw the fault itself or re-checked the evidence
+}
+
+impl DKGMessage {
+    pub fn signed_round1(identity: &NodeIdentity, group_id: &str, from: &str, package: Vec<u8>) -> Self {
+        let signature = identity.sign(&message_signing_input(group_id, from, "", 1, &package));
+        DKGMessage::Round1 { package, signature }
+    }
+
+    pub fn sealed_round2(identity: &NodeIdentity, group_id: &str, from: &str, to: &str, recipient: &IdentityPublicKeys, package: &[u8]) -> Result<Self, DKGError> {
+        let sealed = identity::seal(&recipient.encryption_key, package, &round2_aad(group_id, from, to))
+            .map_err(DKGError::Unauthenticated)?;
+        let body = bincode::serialize(&sealed).map_err(|_| DKGError::MessageMalformed)?;
+        let signature = identity.sign(&message_signing_input(group_id, from, to, 2, &body));
+        Ok(DKGMessage::Round2 { sealed, signature })
+    }
+
+    pub fn sealed_round1(identity: &NodeIdentity, group_id: &str, from: &str, to: &str, recipient: &IdentityPublicKeys, package: &[u8]) -> Result<Self, DKGError> {
+        let sealed = identity::seal(&recipient.encryption_key, package, &round2_aad(group_id, from, to))
+            .map_err(DKGError::Unauthenticated)?;
+        let body = bincode::serialize(&sealed).map_err(|_| DKGError::MessageMalformed)?;
+        let signature = identity.sign(&message_signing_input(group_id, from, to, 6, &body));
+        Ok(DKGMessage::SealedRound1 { sealed, signature })
+    }
+
+    pub fn signed_complaint(identity: &NodeIdentity, group_id: &str, from: &str, accused: &str, reason: &str, evidence: Option<Vec<u8>>) -> Self {
+        let body = complaint_body(reason, &evidence);
+        let signature = identity.sign(&message_signing_input(group_id, from, accused, 3, &body));
+        DKGMessage::Complaint { accused: accused.to_string(), reason: reason.to_string(), evidence, signature }
+    }
+
+    pub fn signed_resend(identity: &NodeIdentity, group_id: &str, from: &str) -> Self {
+        let signature = identity.sign(&message_signing_input(group_id, from, "", 4, &[]));
+        DKGMessage::Resend { signature }
+    }
+
+    pub fn signed_abort(identity: &NodeIdentity, group_id: &str, from: &str, reason: &str) -> Self {
+        let signature = identity.sign(&message_signing_input(group_id, from, "", 5, reason.as_bytes()));
+        DKGMessage::Abort { reason: reason.to_string(), signature }
+    }
+
+    pub fn signed_confirmation(identity: &NodeIdentity, group_id: &str, from: &str, digest: Vec<u8>) -> Self {
+        let signature = identity.sign(&message_signing_input(group_id, from, "", 7, &digest));
+        DKGMessage::Confirmation { digest, signature }
+    }
+
+    /// Checks the sender's signature and opens sealed boxes addressed to `to`
+    pub fn open(self, identity: &NodeIdentity, group_id: &str, from: &str, to: &str, sender: &IdentityPublicKeys) -> Result<Option<DKGPackage>, DKGError> {
+        match self {
+            DKGMessage::Round1 { package, signature } => {
+                identity::verify(&sender.signing_key, &message_signing_input(group_id, from, "", 1, &package), &signature)
+                    .map_err(|e| DKGError::Unauthenticated(format!("round 1 from {from}: {e}")))?;
+                Ok(Some(DKGPackage::Round1(package)))
+            }
+            DKGMessage::Round2 { sealed, signature } => {
+                let body = bincode::serialize(&sealed).map_err(|_| DKGError::MessageMalformed)?;
+                identity::verify(&sender.signing_key, &message_signing_input(group_id, from, to, 2, &body), &signature)
+                    .map_err(|e| DKGError::Unauthenticated(format!("round 2 from {from}: {e}")))?;
+                let package = identity.open(&sealed, &round2_aad(group_id, from, to))
+                    .map_err(|e| DKGError::Unauthenticated(format!("round 2 from {from}: {e}")))?;
+                Ok(Some(DKGPackage::Round2(package.to_vec())))
+            }
+            DKGMessage::Complaint { accused, reason, evidence, signature } => {
+                let body = complaint_body(&reason, &evidence);
+                identity::verify(&sender.signing_key, &message_signing_input(group_id, from, &accused, 3, &body), &signature)
+                    .map_err(|e| DKGError::Unauthenticated(format!("complaint from {from}: {e}")))?;
+                Ok(Some(DKGPackage::Complaint(DKGComplaint {
+                    accuser: from.to_string(),
+                    accused,
+                    reason,
+                    evidence,
+                    confirmed: false,
+                })))
+            }
+            DKGMessage::Resend { signature } => {
+                identity::verify(&sender.signing_key, &message_signing_input(group_id, from, "", 4, &[]), &signature)
+                    .map_err(|e| DKGError::Unauthenticated(format!("resend request from {from}: {e}")))?;
+                Ok(Some(DKGPackage::Resend))
+            }
+            DKGMessage::Abort { reason, signature } => {
+                identity::verify(&sender.signing_key, &message_signing_input(group_id, from, "", 5, reason.as_bytes()), &signature)
+                    .map_err(|e| DKGError::Unauthenticated(format!("abort from {from}: {e}")))?;
+                Ok(Some(DKGPackage::Abort(reason)))
+            }
+            DKGMessage::SealedRound1 { sealed, signature } => {
+                let body = bincode::serialize(&sealed).map_err(|_| DKGError::MessageMalformed)?;
+                identity::verify(&sender.signing_key, &message_signing_input(group_id, from, to, 6, &body), &signature)
+                    .map_err(|e| DKGError::Unauthenticated(format!("round 1 from {from}: {e}")))?;
+                let package = identity.open(&sealed, &round2_aad(group_id, from, to))
+                    .map_err(|e| DKGError::Unauthenticated(format!("round 1 from {from}: {e}")))?;
+                Ok(Some(DKGPackage::Round1(package.to_vec())))
+            }
+            DKGMessage::Confirmation { digest, signature } => {
+                identity::verify(&sender.signing_key, &message_signing_input(group_id, from, "", 7, &digest), &signature)
+                    .map_err(|e| DKGError::Unauthenticated(format!("confirmation from {from}: {e}")))?;
+                Ok(Some(DKGPackage::Confirmation(digest)))
+            }
+            DKGMessage::Finalization(_) => Ok(None),
+        }
+    }
+}
+
+fn complaint_body(reason: &str, evidence: &Option<Vec<u8>>) -> Vec<u8> {
+    bincode::serialize(&(reason, evidence)).unwrap_or_default()
+}
+
+fn message_signing_input(group_id: &str, from: &str, to: &str, round: u8, body: &[u8]) -> Vec<u8> {
+    let mut input = DKG_MESSAGE_DOMAIN.to_vec();
+    for field in [group_id.as_bytes(), from.as_bytes(), to.as_bytes(), &[round], body] {
+        input.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        input.extend_from_slice(field);
+    }
+    input
+}
+
+fn round2_aad(group_id: &str, from: &str, to: &str) -> Vec<u8> {
+    format!("{group_id}\0{from}\0{to}").into_bytes()
+}
+
+/// What nodes confirm to each other after finalizing: a hash of the public key package,
+/// which holds the group key and every member's verifying share
+pub fn confirmation_digest(public_key_package: &[u8]) -> Vec<u8> {
+    blake3::derive_key(DKG_CONFIRM_CONTEXT, public_key_package).to_vec()
+}
+
+/// Associated data for a key sealed to the importing node. A box made for one DID and
+/// public key cannot be replayed into an import of another.
+pub fn key_import_aad(op_did: &str, group_public_key: &[u8]) -> Vec<u8> {
+    let mut aad = KEY_IMPORT_DOMAIN.to_vec();
+    aad.extend_from_slice(&(op_did.len() as u32).to_be_bytes());
+    aad.extend_from_slice(op_did.as_bytes());
+    aad.extend_from_slice(group_public_key);
+    aad
+}
+
+/// Where a node's DKG session is. Sessions move forward on their own as packages arrive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DKGPhase {
+    Round1,                     // Waiting for every peer's round-1 package
+    Round2,                     // Round-2 packages sent; waiting for every peer's
+    Finalized,                  // Share sealed, and every custody node confirmed the same group
+    Failed(String),             // Local crypto failure, unreachable peer or a missed deadline
+    Aborted(String),            // A participant complained; partial state wiped on every node
+    Confirming,                 // Share sealed and group recorded; waiting for every node's confirmation
+    Quarantined(String),        // Nodes computed different groups; the group never signs
+}
+
+impl DKGPhase {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, DKGPhase::Finalized | DKGPhase::Failed(_) | DKGPhase::Aborted(_) | DKGPhase::Quarantined(_))
+    }
+}
+
+/// What a session produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DKGKind {
+    #[default]
+    Generate,                   // A new group key
+    Refresh,                    // New shares of the existing group key; old shares become useless
+    Reshare,                    // The existing group key moved to a new committee and threshold
+    Import,                     // An existing key split by one dealing node
+    Repair,                     // Helpers rebuild one member's lost share; nothing else changes
+}
+
+impl DKGKind {
+    /// Name reported by the status RPC
+    pub fn name(&self) -> &'static str {
+        match self {
+            DKGKind::Generate => "generate",
+            DKGKind::Refresh => "refresh",
+            DKGKind::Reshare => "reshare",
+            DKGKind::Import => "import",
+            DKGKind::Repair => "repair",
+        }
+    }
+
+    /// Coefficients each round-1 commitment carries. A refresh deals a sharing of
+    /// zero, so its commitments leave out the constant term; a repair commits to nothing.
+    pub fn commitment_len(&self, threshold: u8) -> u16 {
+        match self {
+            DKGKind::Generate | DKGKind::Reshare | DKGKind::Import => threshold as u16,
+            DKGKind::Refresh => threshold as u16 - 1,
+            DKGKind::Repair => 0,
+        }
+    }
+}
+
+/// Who deals and who receives in a session that hands out existing key material: the
+/// old members in a reshare, or the one node holding an imported key. Each dealer
+/// sends every peer its commitment and every new member a share, in one go.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DealingCommittees {
+    pub dealers: Vec<String>,                     // Nodes dealing out what they hold
+    pub members: Vec<String>,                     // New committee
+    #[serde(default)]
+    pub old_public_key_package: Vec<u8>,          // Reshare: dealers' commitments are checked against it
+    #[serde(default)]
+    pub group_public_key: Vec<u8>,                // Import: the key being imported
+}
+
+impl DealingCommittees {
+    pub fn participants(&self) -> Vec<String> {
+        reshare_participants(&self.dealers, &self.members)
+    }
+}
+
+/// Everyone taking part in a reshare, each once, dealers first
+pub fn reshare_participants(dealers: &[String], members: &[String]) -> Vec<String> {
+    let mut participants = dealers.to_vec();
+    participants.extend(members.iter().filter(|m| !dealers.contains(m)).cloned());
+    participants
+}
+
+/// Who takes part in a share repair. Each helper splits its weighted share into parts
+/// for the other helpers (round 1), then sends the lost member the sum of the parts
+/// it got (round 2). The lost member adds the sums up into its share.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepairPlan {
+    pub helpers: Vec<String>,                     // At least the threshold of the group's other members
+    pub lost: String,                             // The member whose share is rebuilt
+}
+
+impl RepairPlan {
+    pub fn participants(&self) -> Vec<String> {
+        let mut participants = self.helpers.clone();
+        participants.push(self.lost.clone());
+        participants
+    }
+}
+
+/// What this node has verified of a peer's packages
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeerResult {
+    Pending,                    // Nothing from this peer checked yet
+    Round1Verified,             // Round-1 package decodes and has the session's degree
+    Verified,                   // Every package checked out, proofs and share included
+    Rejected(String),           // Sent a package that failed verification
+}
+
+/// Snapshot of a session for the status RPC
+#[derive(Debug, Clone)]
+pub struct DKGStatus {
+    pub kind: DKGKind,
+    pub phase: DKGPhase,
+    pub round1_received: usize,
+    pub round2_received: usize,
+    pub expected: usize,                          // Packages expected per round (peers, or dealers for a reshare)
+    pub deadline: SystemTime,                     // Deadline of the current phase
+    pub group_public_key: Option<Vec<u8>>,        // Set once finalized
+    pub peer_results: HashMap<String, PeerResult>,
+    pub complaints: Vec<DKGComplaint>,            // Every complaint raised or received for the session
+}
+
+impl DKGStatus {
+    /// Participants a retry should leave out. A confirmed complaint excludes the
+    /// accused; one this node could not check excludes both sides, since either may be lying.
+    pub fn misbehaving(&self) -> Vec<String> {
+        let mut culprits = Vec::new();
+        for complaint in &self.complaints {
+            let parties = if complaint.confirmed {
+                vec![&complaint.accused]
+            } else {
+                vec![&complaint.accuser, &complaint.accused]
+            };
+            for party in parties {
+                if !culprits.contains(party) {
+                    culprits.push(party.clone());
+                }
+            }
+        }
+        culprits
+    }
+}
+
+/// Local DKG session state for a single custody node. Sealed into the DID's vault
+/// record at every step, so a restarted node can resume where it stopped.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DKGLocalState {
     pub operational_did: String,                  // The DID this DKG is being run for
+    #[serde(default)]
+    pub kind: DKGKind,
     pub threshold: u8,                            // Signing threshold (t)
     pub participant_ids: Vec<String>,             // List of custody node identifiers
+    #[serde(default)]
+    pub dealing: Option<DealingCommittees>,       // Set for a reshare or import; participants are both committees
+    #[serde(default)]
+    pub repair: Option<RepairPlan>,               // Set for a share repair; participants are the helpers and the lost member
     pub round1_received: HashMap<String, Vec<u8>>, // Round1 packages received
     pub round2_received: HashMap<String, Vec<u8>>, // Round2 packages received
-    pub finalized: bool,                          // Whether this node finished
-    pub keygen_machine: Option<frost_ed25519::dkg::KeyGenMachine>, // Local cryptographic state
+    pub round1_messages: HashMap<String, Vec<u8>>, // Signed round-1 messages as received, kept as complaint evidence
+    pub peer_results: HashMap<String, PeerResult>, // Verification result per peer
+    pub complaints: Vec<DKGComplaint>,            // Complaints raised or received
+    pub sent: HashMap<String, Vec<Vec<u8>>>,      // Messages sent to each peer, replayed when a peer asks
+    pub ciphersuite: SuiteId,                     // FROST ciphersuite chosen at provisioning
+    pub round1_secret: Option<Vec<u8>>,           // Serialized round1 secret package (consumed by round2)
+    pub round2_secret: Option<Vec<u8>>,           // Serialized round2 secret package (consumed by finalize)
+    pub phase: DKGPhase,                          // Current phase; advanced by `handle_message`
+    pub phase_deadline: SystemTime,               // The current phase fails if not complete by then
+    pub group_public_key: Option<Vec<u8>>,        // Group verifying key once finalized
+    #[serde(default)]
+    pub confirmation: Option<Vec<u8>>,            // Our public key package hash, once finalized
+    #[serde(default)]
+    pub confirmations: HashMap<String, Vec<u8>>,  // Each custody peer's hash, as confirmed to us
 }
 
 /// Session managed by the node-local DKG engine
 pub struct DKGSession {
     pub group_id: String,             // Unique session ID
     pub local: DKGLocalState,
+    pub events: tokio::sync::watch::Sender<DKGPhase>, // Phase changes, for waiters
 }
 
 /// Errors thrown during DKG lifecycle
@@ -39,5 +375,10 @@ pub enum DKGError {
     MessageMalformed,
     CryptoFailure(String),
     RegistryUpdateFailed,
+    VaultNotFound,
     VaultStorageFailed,
+    SessionFailed(String),            // Crypto failure or missed phase deadline
+    SessionAborted(String),           // A complaint aborted the session
+    PeerMisbehaved { culprit: String, reason: String }, // A peer's package failed verification
+    Unauthenticated(String),          // Unknown sender key, bad signature or undecryptable package
 }
\ No newline at end of file
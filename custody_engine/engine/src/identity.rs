@@ -0,0 +1,179 @@
+//! Long-term identity of a custody node (or device): an Ed25519 key that signs
+//! protocol messages and a secp256k1 key that DKG secrets are encrypted to.
+//! Peers' public keys are pinned on first contact and never silently replaced.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use frost_ed25519::{Signature as Ed25519Signature, SigningKey as Ed25519SigningKey, VerifyingKey as Ed25519VerifyingKey};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// KDF context for sealed boxes
+pub const SEAL_CONTEXT: &str = "custody-seal-v1 aes-256-gcm";
+
+/// Public half of an identity, as pinned by peers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentityPublicKeys {
+    pub signing_key: Vec<u8>,    // 32-byte Ed25519 verifying key
+    pub encryption_key: Vec<u8>, // 33-byte compressed secp256k1 point
+}
+
+/// Ciphertext for one recipient (ECIES: ephemeral ECDH, BLAKE3 KDF, AES-256-GCM)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedBox {
+    pub ephemeral_key: Vec<u8>,  // Compressed secp256k1 point
+    pub ciphertext: Vec<u8>,
+}
+
+pub struct NodeIdentity {
+    signing: Ed25519SigningKey,
+    encryption: k256::SecretKey,
+}
+
+impl NodeIdentity {
+    pub fn generate() -> Self {
+        NodeIdentity {
+            signing: Ed25519SigningKey::new(&mut OsRng),
+            encryption: k256::SecretKey::random(&mut OsRng),
+        }
+    }
+
+    /// Loads the identity from `path`, creating it on first start
+    pub fn load_or_generate(path: &Path) -> Result<Self, String> {
+        if path.exists() {
+            let bytes = Zeroizing::new(std::fs::read(path).map_err(|e| format!("read identity failed: {e}"))?);
+            if bytes.len() != 64 {
+                return Err("identity file must hold 64 bytes".into());
+            }
+            return Ok(NodeIdentity {
+                signing: Ed25519SigningKey::deserialize(&bytes[..32]).map_err(|_| "bad identity signing key")?,
+                encryption: k256::SecretKey::from_slice(&bytes[32..]).map_err(|_| "bad identity encryption key")?,
+            });
+        }
+
+        let identity = NodeIdentity::generate();
+        let mut bytes = Zeroizing::new(identity.signing.serialize());
+        bytes.extend_from_slice(&identity.encryption.to_bytes());
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("create identity dir failed: {e}"))?;
+        }
+        std::fs::write(path, &*bytes).map_err(|e| format!("write identity failed: {e}"))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+                .map_err(|e| format!("chmod identity failed: {e}"))?;
+        }
+        Ok(identity)
+    }
+
+    pub fn public_keys(&self) -> IdentityPublicKeys {
+        IdentityPublicKeys {
+            signing_key: Ed25519VerifyingKey::from(&self.signing).serialize().unwrap_or_default(),
+            encryption_key: self.encryption.public_key().to_encoded_point(true).as_bytes().to_vec(),
+        }
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        self.signing.sign(OsRng, message).serialize().unwrap_or_default()
+    }
+
+    /// Decrypts a box sealed to this identity; `aad` must match what the sender bound
+    pub fn open(&self, sealed: &SealedBox, aad: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+        let ephemeral = k256::PublicKey::from_sec1_bytes(&sealed.ephemeral_key).map_err(|_| "bad ephemeral key")?;
+        let shared = k256::ecdh::diffie_hellman(self.encryption.to_nonzero_scalar(), ephemeral.as_affine());
+        let cipher = box_cipher(shared.raw_secret_bytes(), &sealed.ephemeral_key, &self.public_keys().encryption_key);
+
+        cipher.decrypt(Nonce::from_slice(&[0u8; 12]), Payload { msg: &sealed.ciphertext, aad })
+            .map(Zeroizing::new)
+            .map_err(|_| "sealed box does not open".to_string())
+    }
+}
+
+/// Checks that bytes are a valid encryption key before they are stored for later sealing
+pub fn check_encryption_key(key: &[u8]) -> Result<(), String> {
+    k256::PublicKey::from_sec1_bytes(key).map(|_| ()).map_err(|_| "not a secp256k1 point".to_string())
+}
+
+/// Encrypts `plaintext` to a recipient's encryption key, binding `aad`
+pub fn seal(recipient_key: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<SealedBox, String> {
+    let recipient = k256::PublicKey::from_sec1_bytes(recipient_key).map_err(|_| "bad recipient key")?;
+    let ephemeral = k256::ecdh::EphemeralSecret::random(&mut OsRng);
+    let ephemeral_key = ephemeral.public_key().to_encoded_point(true).as_bytes().to_vec();
+    let shared = ephemeral.diffie_hellman(&recipient);
+
+    // The key is fresh for every box, so a fixed nonce is never reused under it
+    let cipher = box_cipher(shared.raw_secret_bytes(), &ephemeral_key, recipient_key);
+    let ciphertext = cipher.encrypt(Nonce::from_slice(&[0u8; 12]), Payload { msg: plaintext, aad })
+        .map_err(|_| "seal failed")?;
+
+    Ok(SealedBox { ephemeral_key, ciphertext })
+}
+
+fn box_cipher(shared_secret: &[u8], ephemeral_key: &[u8], recipient_key: &[u8]) -> Aes256Gcm {
+    let mut kdf = blake3::Hasher::new_derive_key(SEAL_CONTEXT);
+    kdf.update(shared_secret);
+    kdf.update(ephemeral_key);
+    kdf.update(recipient_key);
+    let key = Zeroizing::new(*kdf.finalize().as_bytes());
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&*key))
+}
+
+/// Checks an Ed25519 signature made with `NodeIdentity::sign`
+pub fn verify(signing_key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), String> {
+    let key = Ed25519VerifyingKey::deserialize(signing_key).map_err(|_| "bad identity key")?;
+    let sig = Ed25519Signature::deserialize(signature).map_err(|_| "bad signature encoding")?;
+    key.verify(message, &sig).map_err(|_| "signature does not verify".to_string())
+}
+
+/// Pinned public keys of peer custody nodes
+pub struct IdentityDirectory {
+    pub peers: RwLock<HashMap<String, IdentityPublicKeys>>, // node_id → pinned keys
+}
+
+impl IdentityDirectory {
+    pub fn new() -> Self {
+        IdentityDirectory {
+            peers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Loads operator-provisioned keys (JSON object of node_id → keys), if the file exists
+    pub fn load_pinned(&self, path: &Path) -> Result<usize, String> {
+        if !path.exists() {
+            return Ok(0);
+        }
+        let json = std::fs::read(path).map_err(|e| format!("read pinned identities failed: {e}"))?;
+        let pinned: HashMap<String, IdentityPublicKeys> = serde_json::from_slice(&json)
+            .map_err(|e| format!("bad pinned identities: {e}"))?;
+
+        let count = pinned.len();
+        for (node_id, keys) in pinned {
+            self.pin(&node_id, keys)?;
+        }
+        Ok(count)
+    }
+
+    /// Pins a peer's keys. The first keys seen for a node stay; different keys later are rejected.
+    pub fn pin(&self, node_id: &str, keys: IdentityPublicKeys) -> Result<(), String> {
+        let mut peers = self.peers.write().unwrap();
+        match peers.get(node_id) {
+            Some(existing) if *existing != keys => Err(format!("{node_id} presented a different identity key")),
+            Some(_) => Ok(()),
+            None => {
+                peers.insert(node_id.to_string(), keys);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn get(&self, node_id: &str) -> Option<IdentityPublicKeys> {
+        self.peers.read().unwrap().get(node_id).cloned()
+    }
+}
@@ -1,11 +1,18 @@
 //! Custody Engine Core Library
 
 pub mod bootstrap;
+pub mod ciphersuite;
+pub mod identity;
+pub mod membership;
 pub mod vault;
 pub mod registry;
 pub mod dkg;
 pub mod mpc;
+pub mod verification;
+pub mod policy;
 pub mod relay;
+pub mod tls;
+pub mod sim;
 pub mod issuer;
 pub mod orchestrator;
 
@@ -15,6 +22,7 @@ pub mod service {
     pub mod mpc_service;
     pub mod issuer_service;
     pub mod relay_service;
+    pub mod device_service;
 }
 
 // Export client stubs if needed by external engines
@@ -23,6 +31,7 @@ pub mod api {
     tonic::include_proto!("dkg");
     tonic::include_proto!("mpc");
     tonic::include_proto!("issuer");
+    tonic::include_proto!("custodydevice");
     tonic::include_proto!("registry"); // if defined
 }
 
@@ -0,0 +1,176 @@
+//! Membership of the custody cluster. Every custody node is enrolled once and gets a
+//! FROST identifier that stays with it: a renamed host keeps its identifier, and the
+//! identifier of a retired node is never handed out again. The enrollment also records
+//! the node's identity keys, so a participant's identifier and keys come from one place.
+//! The membership file is provisioned by the operator and must be the same on every node.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use frost_core::Ciphersuite;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::ciphersuite;
+use crate::identity::{IdentityDirectory, IdentityPublicKeys};
+use crate::registry::MPCMemberDescriptor;
+
+/// An enrolled custody node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Member {
+    pub node_id: String,                // Where the node is reached; may change with a rename
+    pub identifier: u16,                // FROST identifier, fixed for the node's lifetime
+    pub identity: IdentityPublicKeys,   // Keys the node signs and receives DKG secrets with
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MembershipState {
+    next_identifier: u16,               // Lowest identifier never handed out
+    members: Vec<Member>,
+}
+
+pub struct MembershipRegistry {
+    path: RwLock<Option<PathBuf>>,      // Written back on every change once loaded from a file
+    state: RwLock<MembershipState>,
+}
+
+/// Membership every component resolves participants through
+pub static MEMBERSHIP: Lazy<MembershipRegistry> = Lazy::new(MembershipRegistry::new);
+
+impl MembershipRegistry {
+    pub fn new() -> Self {
+        MembershipRegistry {
+            path: RwLock::new(None),
+            state: RwLock::new(MembershipState { next_identifier: 1, members: Vec::new() }),
+        }
+    }
+
+    /// Loads the membership file, if it exists, and keeps it up to date from then on
+    pub fn load(&self, path: &Path) -> Result<usize, String> {
+        if path.exists() {
+            let json = std::fs::read(path).map_err(|e| format!("read membership failed: {e}"))?;
+            let mut loaded: MembershipState = serde_json::from_slice(&json)
+                .map_err(|e| format!("bad membership: {e}"))?;
+            check_consistent(&mut loaded)?;
+            *self.state.write().unwrap() = loaded;
+        }
+        *self.path.write().unwrap() = Some(path.to_path_buf());
+        Ok(self.state.read().unwrap().members.len())
+    }
+
+    /// Enrolls a node, or returns its enrollment if it is already a member with the same keys
+    pub fn enroll(&self, node_id: &str, identity: IdentityPublicKeys) -> Result<Member, String> {
+        let mut state = self.state.write().unwrap();
+
+        if let Some(member) = state.members.iter().find(|m| m.node_id == node_id) {
+            if member.identity != identity {
+                return Err(format!("{node_id} is enrolled with a different identity key"));
+            }
+            return Ok(member.clone());
+        }
+        if let Some(member) = state.members.iter().find(|m| m.identity.signing_key == identity.signing_key) {
+            return Err(format!("identity already enrolled as {}; rename it instead", member.node_id));
+        }
+
+        let identifier = state.next_identifier;
+        state.next_identifier = identifier.checked_add(1).ok_or("no identifiers left")?;
+        let member = Member { node_id: node_id.to_string(), identifier, identity };
+        state.members.push(member.clone());
+
+        self.save(&state)?;
+        Ok(member)
+    }
+
+    /// Moves a member to a new node ID; its identifier and keys stay
+    pub fn rename(&self, node_id: &str, new_node_id: &str) -> Result<Member, String> {
+        let mut state = self.state.write().unwrap();
+        if state.members.iter().any(|m| m.node_id == new_node_id) {
+            return Err(format!("{new_node_id} is already enrolled"));
+        }
+        let member = state.members.iter_mut().find(|m| m.node_id == node_id)
+            .ok_or_else(|| format!("{node_id} is not enrolled"))?;
+        member.node_id = new_node_id.to_string();
+        let member = member.clone();
+
+        self.save(&state)?;
+        Ok(member)
+    }
+
+    /// Removes a member. Its identifier is retired, not reused.
+    pub fn retire(&self, node_id: &str) -> Result<Member, String> {
+        let mut state = self.state.write().unwrap();
+        let index = state.members.iter().position(|m| m.node_id == node_id)
+            .ok_or_else(|| format!("{node_id} is not enrolled"))?;
+        let member = state.members.remove(index);
+
+        self.save(&state)?;
+        Ok(member)
+    }
+
+    pub fn get(&self, node_id: &str) -> Option<Member> {
+        self.state.read().unwrap().members.iter().find(|m| m.node_id == node_id).cloned()
+    }
+
+    pub fn identifier_of(&self, node_id: &str) -> Option<u16> {
+        self.get(node_id).map(|m| m.identifier)
+    }
+
+    pub fn members(&self) -> Vec<Member> {
+        self.state.read().unwrap().members.clone()
+    }
+
+    /// Pins every member's identity keys, so enrolled nodes are never learned on first contact
+    pub fn pin_into(&self, directory: &IdentityDirectory) -> Result<usize, String> {
+        let members = self.members();
+        for member in &members {
+            directory.pin(&member.node_id, member.identity.clone())?;
+        }
+        Ok(members.len())
+    }
+
+    fn save(&self, state: &MembershipState) -> Result<(), String> {
+        let Some(path) = self.path.read().unwrap().clone() else { return Ok(()) };
+        let json = serde_json::to_vec_pretty(state).map_err(|e| format!("serialize membership failed: {e}"))?;
+
+        // Write aside and rename, so a crash never leaves a half-written membership
+        let staged = path.with_extension("tmp");
+        std::fs::write(&staged, json).map_err(|e| format!("write membership failed: {e}"))?;
+        std::fs::rename(&staged, &path).map_err(|e| format!("write membership failed: {e}"))
+    }
+}
+
+/// A loaded file must not repeat a node, an identifier or a key, nor hand out a used identifier again
+fn check_consistent(state: &mut MembershipState) -> Result<(), String> {
+    let (mut nodes, mut identifiers, mut keys) = (HashSet::new(), HashSet::new(), HashSet::new());
+    for member in &state.members {
+        if member.identifier == 0 {
+            return Err(format!("{} has identifier 0", member.node_id));
+        }
+        if !nodes.insert(&member.node_id) || !identifiers.insert(member.identifier) || !keys.insert(&member.identity.signing_key) {
+            return Err(format!("{} is enrolled twice", member.node_id));
+        }
+    }
+    let highest = state.members.iter().map(|m| m.identifier).max().unwrap_or(0);
+    state.next_identifier = state.next_identifier.max(highest.saturating_add(1)).max(1);
+    Ok(())
+}
+
+/// Describes the members of a group from the verifying shares its session produced.
+/// `identity_key` supplies the keys of participants that are not enrolled, i.e. devices.
+pub fn describe_members<C: Ciphersuite>(
+    verifying_shares: &[(String, Vec<u8>)],
+    identity_key: impl Fn(&str) -> Option<Vec<u8>>,
+) -> Result<Vec<MPCMemberDescriptor>, String> {
+    verifying_shares.iter().map(|(node_id, share)| {
+        Ok(MPCMemberDescriptor {
+            node_id: node_id.clone(),
+            identifier: ciphersuite::participant_identifier_bytes::<C>(node_id)?,
+            share_index: None,
+            identity_key: MEMBERSHIP.get(node_id).map(|m| m.identity.signing_key)
+                .or_else(|| identity_key(node_id))
+                .unwrap_or_default(),
+            public_share: base64::encode(share),
+        })
+    }).collect()
+}
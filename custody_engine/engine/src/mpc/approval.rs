@@ -0,0 +1,238 @@
+// File: src/mpc/approval.rs
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use blake3::Hasher;
+use frost_ed25519::{Signature as Ed25519Signature, VerifyingKey as Ed25519VerifyingKey};
+
+use crate::mpc::intent::SigningIntent;
+use crate::registry::{OperationalDID, OperationalDIDRegistry, ApprovalPolicy};
+use crate::vault;
+
+/// Domain separator for the digest approvers sign
+pub const APPROVAL_DOMAIN: &[u8] = b"custody-approval-v1";
+
+/// How long a request can wait for approvals, and its bundle be signed with, before it expires
+pub const APPROVAL_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// One approver's sign-off on a request
+#[derive(Debug, Clone)]
+pub struct Approval {
+    pub approver_id: String,
+    pub signature: Vec<u8>,                     // Ed25519 over `approval_digest`
+}
+
+/// Approvals the coordinator forwards to every vault with the signing request
+#[derive(Debug, Clone)]
+pub struct ApprovalBundle {
+    pub request_id: String,
+    pub expires_at: u64,                        // Unix seconds; approvers sign it with the request
+    pub approvals: Vec<Approval>,
+}
+
+/// A signing request waiting for its quorum
+#[derive(Debug, Clone)]
+pub struct PendingApproval {
+    pub operational_did: String,
+    pub kind: String,                           // Signing intent or key operation being approved
+    pub message_hash: [u8; 32],
+    pub approvals: Vec<Approval>,
+    pub expires_at: u64,                        // Unix seconds
+}
+
+/// Bytes an approver signs: binds the request, its expiry, the DID, what kind of request
+/// it is (a signing intent's name or a key operation) and the exact message. Raw signing
+/// is untagged, so without the kind its approval could pass for a key operation's.
+pub fn approval_digest(request_id: &str, expires_at: u64, op_did: &str, kind: &str, message: &[u8]) -> [u8; 32] {
+    digest_with_hash(request_id, expires_at, op_did, kind, blake3::hash(message).as_bytes())
+}
+
+/// Key operations a DID's approval policy covers, with the parameters approvers authorize
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyOperation {
+    Rotate,                                             // New group over the committee the DID's policy names
+    Refresh,                                            // Same committee, fresh shares
+    Reshare { threshold: u32, members: Vec<String> },   // Key handed to a new committee
+    Repair { lost_node: String, helpers: Vec<String> }, // Share rebuilt for `lost_node` by `helpers`
+}
+
+impl KeyOperation {
+    /// Kind of request the operation is approved as
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyOperation::Rotate => "rotate_shards",
+            KeyOperation::Refresh => "refresh_shares",
+            KeyOperation::Reshare { .. } => "reshare_group",
+            KeyOperation::Repair { .. } => "repair_share",
+        }
+    }
+
+    /// Message that stands for the operation: the DID and everything that decides who
+    /// ends up holding a share, so an approval cannot be spent on another committee
+    pub fn message(&self, op_did: &str) -> Vec<u8> {
+        match self {
+            KeyOperation::Rotate => format!("rotate-shards:{op_did}"),
+            KeyOperation::Refresh => format!("refresh-shares:{op_did}"),
+            KeyOperation::Reshare { threshold, members } => {
+                format!("reshare:{op_did}:{threshold}:{}", sorted(members).join(","))
+            }
+            KeyOperation::Repair { lost_node, helpers } => {
+                format!("repair:{op_did}:{lost_node}:{}", sorted(helpers).join(","))
+            }
+        }.into_bytes()
+    }
+}
+
+fn sorted(nodes: &[String]) -> Vec<String> {
+    let mut nodes = nodes.to_vec();
+    nodes.sort();
+    nodes
+}
+
+fn digest_with_hash(request_id: &str, expires_at: u64, op_did: &str, kind: &str, message_hash: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Hasher::new();
+    hasher.update(APPROVAL_DOMAIN);
+    for field in [request_id.as_bytes(), op_did.as_bytes(), kind.as_bytes()] {
+        hasher.update(&(field.len() as u32).to_be_bytes());
+        hasher.update(field);
+    }
+    hasher.update(&expires_at.to_be_bytes());
+    hasher.update(message_hash);
+    *hasher.finalize().as_bytes()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// Checks one approval against the policy
+fn verify_approval(policy: &ApprovalPolicy, digest: &[u8; 32], approval: &Approval) -> Result<(), String> {
+    let approver = policy.approvers.iter()
+        .find(|a| a.approver_id == approval.approver_id)
+        .ok_or_else(|| format!("Unknown approver {}", approval.approver_id))?;
+
+    let key = Ed25519VerifyingKey::deserialize(&approver.public_key).map_err(|_| "bad approver key")?;
+    let sig = Ed25519Signature::deserialize(&approval.signature).map_err(|_| "bad approval signature")?;
+    key.verify(digest, &sig).map_err(|_| format!("Approval from {} does not verify", approval.approver_id))
+}
+
+/// Verifies a bundle against the policy: it must not have expired, every approval
+/// must verify and the number of distinct approvers must reach the quorum
+pub fn verify_bundle(policy: &ApprovalPolicy, op_did: &str, kind: &str, message: &[u8], bundle: &ApprovalBundle) -> Result<(), String> {
+    if bundle.expires_at <= unix_now() {
+        return Err(format!("Approval request {} has expired", bundle.request_id));
+    }
+    let digest = approval_digest(&bundle.request_id, bundle.expires_at, op_did, kind, message);
+
+    let mut approvers = HashSet::new();
+    for approval in &bundle.approvals {
+        verify_approval(policy, &digest, approval)?;
+        approvers.insert(approval.approver_id.as_str());
+    }
+
+    if approvers.len() < policy.quorum as usize {
+        return Err(format!("{} of {} required approvals", approvers.len(), policy.quorum));
+    }
+    Ok(())
+}
+
+/// Vault-side check: when the DID's policy requires approval for this intent,
+/// only sign with a valid bundle, and only once per approved request
+pub fn enforce_approval(
+    registry: &OperationalDIDRegistry,
+    op_did: &str,
+    intent: &SigningIntent,
+    message: &[u8],
+    bundle: Option<&ApprovalBundle>,
+) -> Result<(), String> {
+    let Some(policy) = registry.get_signing_policy(&OperationalDID(op_did.to_string())) else {
+        return Ok(());
+    };
+    let Some(approval) = policy.approval.as_ref().filter(|_| policy.needs_approval(Some(intent))) else {
+        return Ok(());
+    };
+    let bundle = bundle.ok_or("Signing for this DID requires human approval")?;
+    verify_bundle(approval, op_did, intent.name(), message, bundle)?;
+    vault::consume_approval(registry, op_did, &bundle.request_id, bundle.expires_at, unix_now())
+}
+
+/// Signing requests waiting for approvals on the coordinating node
+pub struct ApprovalQueue {
+    pub pending: Mutex<HashMap<String, PendingApproval>>, // request_id → pending request
+}
+
+impl ApprovalQueue {
+    pub fn new() -> Self {
+        ApprovalQueue {
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Queue a message of the given kind for approval; returns the request ID, the digest
+    /// approvers sign and when the request expires (unix seconds)
+    pub fn submit(&self, op_did: &str, kind: &str, message: &[u8]) -> (String, [u8; 32], u64) {
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let now = unix_now();
+        let pending = PendingApproval {
+            operational_did: op_did.to_string(),
+            kind: kind.to_string(),
+            message_hash: *blake3::hash(message).as_bytes(),
+            approvals: Vec::new(),
+            expires_at: now + APPROVAL_TTL.as_secs(),
+        };
+        let digest = digest_with_hash(&request_id, pending.expires_at, op_did, kind, &pending.message_hash);
+        let expires_at = pending.expires_at;
+
+        let mut queue = self.pending.lock().unwrap();
+        queue.retain(|_, p| p.expires_at > now);
+        queue.insert(request_id.clone(), pending);
+        (request_id, digest, expires_at)
+    }
+
+    /// Record an approval after checking it; returns (distinct approvals, quorum)
+    pub fn approve(&self, registry: &OperationalDIDRegistry, request_id: &str, approval: Approval) -> Result<(usize, u8), String> {
+        let mut queue = self.pending.lock().unwrap();
+        let pending = queue.get_mut(request_id).ok_or("Unknown or expired approval request")?;
+        let policy = registry.get_approval_policy(&OperationalDID(pending.operational_did.clone()))
+            .ok_or("DID has no approval policy")?;
+
+        let digest = digest_with_hash(request_id, pending.expires_at, &pending.operational_did, &pending.kind, &pending.message_hash);
+        verify_approval(&policy, &digest, &approval)?;
+
+        pending.approvals.retain(|a| a.approver_id != approval.approver_id);
+        pending.approvals.push(approval);
+        Ok((pending.approvals.len(), policy.quorum))
+    }
+
+    /// Operation-side check: when the DID has an approval policy, take the approved request
+    /// for exactly this operation and verify its bundle
+    pub fn authorize_operation(
+        &self,
+        registry: &OperationalDIDRegistry,
+        op_did: &str,
+        request_id: &str,
+        operation: &KeyOperation,
+    ) -> Result<(), String> {
+        let Some(policy) = registry.get_approval_policy(&OperationalDID(op_did.to_string())) else {
+            return Ok(());
+        };
+        let message = operation.message(op_did);
+        let bundle = self.take_bundle(request_id, op_did, operation.name(), &message)?;
+        verify_bundle(&policy, op_did, operation.name(), &message, &bundle)
+    }
+
+    /// Remove an approved request and hand back its bundle; the kind and message must match what was approved
+    pub fn take_bundle(&self, request_id: &str, op_did: &str, kind: &str, message: &[u8]) -> Result<ApprovalBundle, String> {
+        let mut queue = self.pending.lock().unwrap();
+        let pending = queue.get(request_id).filter(|p| p.expires_at > unix_now())
+            .ok_or("Unknown or expired approval request")?;
+        if pending.operational_did != op_did || pending.kind != kind || pending.message_hash != *blake3::hash(message).as_bytes() {
+            return Err("Approval request does not match this message".into());
+        }
+
+        let pending = queue.remove(request_id).ok_or("Unknown or expired approval request")?;
+        Ok(ApprovalBundle { request_id: request_id.to_string(), expires_at: pending.expires_at, approvals: pending.approvals })
+    }
+}
@@ -4,45 +4,90 @@ use std::time::Duration;
 use base64;
 
 use crate::mpc::signing_session::SigningSession;
-use crate::registry::{OperationalDIDRegistry, MPCGroupDescriptor};
+use crate::registry::{OperationalDID, OperationalDIDRegistry, MPCGroupDescriptor};
 use crate::vault;
 use crate::relay::RelayClient;
+use crate::tls;
 
-use frost_ed25519::prelude::*;
-use frost_ed25519::keys::PublicKeyPackage;
+use crate::ciphersuite::{self, SuiteId};
+use crate::mpc::ecdsa::{self, EcdsaMessageFormat, EcdsaSignatureShare, ECDSA_PROTOCOL};
+use crate::mpc::device;
+use crate::mpc::approval::{ApprovalBundle, ApprovalQueue};
+use crate::mpc::intent::{self, SigningIntent};
+use crate::policy::{self, SigningPolicy};
+use crate::with_ciphersuite;
 
 use vault::custody_vault_client::CustodyVaultClient;
 use vault::{
     GenerateNonceRequest, PartialSignRequest, PeerCommitment,
+    GenerateNonceBatchRequest, PartialSignBatchRequest, BatchSignItem, BatchSignResult,
+    ListEcdsaPresignaturesRequest, EcdsaPartialSignRequest,
+    RegisterDerivedKeyRequest,
+    SetPolicyRequest,
 };
+use custodydevice::custody_device_client::CustodyDeviceClient;
+use custodydevice::{DeviceCommitRequest, DeviceSignRequest};
 
 /// Drives the threshold signing flow across custody nodes
 pub struct MPCSigningCoordinator {
     pub registry: Arc<OperationalDIDRegistry>,
     pub relay: Arc<RelayClient>,
+    pub approvals: Arc<ApprovalQueue>,
     pub local_node_id: String,
 }
 
 impl MPCSigningCoordinator {
-    /// Executes a full MPC signing round
-    pub async fn sign(&self, op_did: &str, message: Vec<u8>) -> Result<Vec<u8>, String> {
+    /// Executes a full MPC signing round over the intent's domain-separated bytes.
+    /// DIDs with an approval policy need the ID of an approved request for those
+    /// bytes; every vault re-derives them and re-checks the approvals.
+    pub async fn sign(&self, op_did: &str, intent: &SigningIntent, payload: Vec<u8>, approval_request_id: Option<&str>) -> Result<Vec<u8>, String> {
         // STEP 1: Load signing group
         let group = self.registry.get_mpc_group(op_did)
             .ok_or("No MPC group for DID")?;
+        group.check_active()?;
         let participants = group.members.iter().map(|m| m.node_id.clone()).collect::<Vec<_>>();
+        let message = intent::signing_bytes(intent, &payload)?;
+
+        let approvals = match approval_request_id {
+            Some(id) => Some(self.approvals.take_bundle(id, op_did, intent.name(), &message)?),
+            None => None,
+        };
 
         // STEP 2: Initialize local session tracking
         let mut session = SigningSession::new(&self.registry, op_did, message.clone())?;
 
-        // STEP 3: Ask vaults to generate + share nonces
+        // STEP 3: Ask vaults (and devices) to generate + share nonces.
+        // A device that does not answer is left out unless the DID requires it.
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let required = self.registry.get_devices(op_did).into_iter()
+            .filter(|d| d.required_for_signing)
+            .map(|d| d.participant_id)
+            .collect::<Vec<_>>();
+        let mut signers = Vec::new();
         for peer in &participants {
-            let nonce = self.call_generate_nonce(peer, op_did).await?;
+            let nonce = if device::is_device_participant(peer) {
+                match self.call_device_commit(peer, &request_id, op_did, &message).await {
+                    Ok(nonce) => nonce,
+                    Err(e) if !required.contains(peer) => {
+                        println!("⚠️ Skipping device {peer}: {e}");
+                        continue;
+                    }
+                    Err(e) => return Err(format!("Required device {peer} did not commit: {e}")),
+                }
+            } else {
+                self.call_generate_nonce(peer, op_did).await?
+            };
             session.record_commitment(peer, nonce);
+            signers.push(peer.clone());
         }
 
         // STEP 4: Send message + commitments, collect signature shares
-        for peer in &participants {
-            let sig = self.call_partial_sign(peer, op_did, &message, &session).await?;
+        for peer in &signers {
+            let sig = if device::is_device_participant(peer) {
+                self.call_device_sign(peer, &request_id, &message, &session).await?
+            } else {
+                self.call_partial_sign(peer, op_did, intent, &payload, &session, approvals.as_ref()).await?
+            };
             session.record_partial(peer, sig);
         }
 
@@ -55,12 +100,193 @@ impl MPCSigningCoordinator {
         Ok(final_sig)
     }
 
+    /// Signs many messages for one DID using a single network exchange per round.
+    /// Every message gets its own nonce and its own result, so one bad item
+    /// does not fail the whole batch. Devices do not take part in batches.
+    pub async fn sign_batch(&self, op_did: &str, intent: &SigningIntent, payloads: Vec<Vec<u8>>) -> Result<Vec<Result<Vec<u8>, String>>, String> {
+        if payloads.is_empty() {
+            return Err("Empty batch".into());
+        }
+        if self.registry.get_devices(op_did).iter().any(|d| d.required_for_signing) {
+            return Err("DID requires a device share; sign messages individually".into());
+        }
+
+        // STEP 1: Load signing group
+        let group = self.registry.get_mpc_group(op_did)
+            .ok_or("No MPC group for DID")?;
+        group.check_active()?;
+        let participants = group.members.iter()
+            .map(|m| m.node_id.clone())
+            .filter(|id| !device::is_device_participant(id))
+            .collect::<Vec<_>>();
+
+        // STEP 2: One session per message, over the domain-separated bytes. A message that
+        // fails validation gets its error and stays out of the rounds; the rest still sign.
+        let mut results: Vec<Result<Vec<u8>, String>> = vec![Err("Not signed".into()); payloads.len()];
+        let mut indices = Vec::new();
+        let mut sessions = Vec::new();
+        for (index, payload) in payloads.iter().enumerate() {
+            match intent::signing_bytes(intent, payload).and_then(|message| SigningSession::new(&self.registry, op_did, message)) {
+                Ok(session) => {
+                    indices.push(index);
+                    sessions.push(session);
+                }
+                Err(e) => results[index] = Err(e),
+            }
+        }
+        if sessions.is_empty() {
+            return Ok(results);
+        }
+        let batch = indices.iter().map(|index| payloads[*index].clone()).collect::<Vec<_>>();
+        let mut failures: Vec<Vec<String>> = vec![Vec::new(); sessions.len()];
+
+        // STEP 3: One nonce round per vault for the whole batch
+        for peer in &participants {
+            let commitments = self.call_generate_nonce_batch(peer, op_did, sessions.len()).await?;
+            if commitments.len() != sessions.len() {
+                return Err(format!("Vault {peer} returned {} commitments for {} messages", commitments.len(), sessions.len()));
+            }
+            for (session, commitment) in sessions.iter_mut().zip(commitments) {
+                session.record_commitment(peer, commitment);
+            }
+        }
+
+        // STEP 4: One signing round per vault for the whole batch
+        for peer in &participants {
+            let signed = self.call_partial_sign_batch(peer, op_did, intent, &batch, &sessions).await?;
+            for item in signed {
+                let slot = item.index as usize;
+                let Some(session) = sessions.get_mut(slot) else { continue };
+                if item.error.is_empty() {
+                    session.record_partial(peer, item.signature);
+                } else {
+                    failures[slot].push(format!("{peer}: {}", item.error));
+                }
+            }
+        }
+
+        // STEP 5: Aggregate each message independently, back in its place in the request
+        for ((index, session), errors) in indices.into_iter().zip(&sessions).zip(failures) {
+            results[index] = if session.ready_to_aggregate() {
+                self.aggregate_signature(session, &group)
+            } else {
+                Err(format!("Not enough signature shares collected: {}", errors.join("; ")))
+            };
+        }
+
+        Ok(results)
+    }
+
+    /// Signs with a threshold ECDSA (secp256k1) group using one stored presignature.
+    /// Returns a 65-byte `r || s || v` signature over the digest of the intent's
+    /// domain-separated bytes; every vault re-derives that digest and re-checks policy.
+    pub async fn sign_ecdsa(
+        &self,
+        op_did: &str,
+        intent: &SigningIntent,
+        payload: &[u8],
+        format: EcdsaMessageFormat,
+        approval_request_id: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        // STEP 1: Load signing group
+        let group = self.registry.get_mpc_group(op_did)
+            .ok_or("No MPC group for DID")?;
+        group.check_active()?;
+        if group.dkg_protocol.as_deref() != Some(ECDSA_PROTOCOL) {
+            return Err("DID is not backed by a threshold ECDSA group".into());
+        }
+        let participants = group.members.iter().map(|m| m.node_id.clone()).collect::<Vec<_>>();
+
+        // STEP 2: Hash the signed bytes (EIP-191 / EIP-712 / already hashed)
+        let message = intent::signing_bytes(intent, payload)?;
+        let digest = ecdsa::message_digest(format, &message)?;
+        let approvals = match approval_request_id {
+            Some(id) => Some(self.approvals.take_bundle(id, op_did, intent.name(), &message)?),
+            None => None,
+        };
+
+        // STEP 3: Pick a presignature every vault still holds
+        let mut available: Option<Vec<String>> = None;
+        for peer in &participants {
+            let ids = self.call_list_ecdsa_presignatures(peer, op_did).await?;
+            available = Some(match available {
+                None => ids,
+                Some(prev) => prev.into_iter().filter(|id| ids.contains(id)).collect(),
+            });
+        }
+        let presignature_id = available.and_then(|ids| ids.into_iter().next())
+            .ok_or("No ECDSA presignature available on all nodes; run presign first")?;
+
+        // STEP 4: Collect signature shares (each vault burns the presignature)
+        let mut shares = Vec::new();
+        let mut big_r: Option<Vec<u8>> = None;
+        for peer in &participants {
+            let (share, peer_r) = self
+                .call_ecdsa_partial_sign(peer, op_did, &presignature_id, intent, payload, format, &participants, approvals.as_ref())
+                .await?;
+            if big_r.get_or_insert_with(|| peer_r.clone()) != &peer_r {
+                return Err(format!("Vault {peer} used a different presignature R"));
+            }
+            shares.push(share);
+        }
+
+        // STEP 5: Combine and verify against the group key
+        let public_key = self.recover_group_key(&group)?;
+        ecdsa::combine_signature(&big_r.unwrap_or_default(), &shares, group.threshold, public_key, &digest)
+    }
+
+    /// Registers a child DID derived from `parent_did`'s group key on every node of the
+    /// parent group, so each vault applies the same tweak when signing. Returns the child key.
+    pub async fn derive_child_did(&self, child_did: &str, root_did: &str, parent_did: &str, path: &str) -> Result<Vec<u8>, String> {
+        let parent = self.registry.get_mpc_group(parent_did)
+            .ok_or("No MPC group for parent DID")?;
+        parent.check_active()?;
+
+        let mut child_key: Option<Vec<u8>> = None;
+        for peer in parent.members.iter().map(|m| m.node_id.clone()) {
+            let key = self.call_register_derived_key(&peer, child_did, root_did, parent_did, path).await?;
+            if child_key.get_or_insert_with(|| key.clone()) != &key {
+                return Err(format!("Vault {peer} derived a different child key"));
+            }
+        }
+
+        child_key.ok_or_else(|| "Parent group has no members".into())
+    }
+
+    /// Changes a DID's signing policy: the group signs the change (POLICY_CHANGE intent)
+    /// and every vault verifies that signature before installing it. Returns the new version.
+    pub async fn update_policy(&self, op_did: &str, new_policy: SigningPolicy, approval_request_id: Option<&str>) -> Result<u64, String> {
+        new_policy.validate()?;
+        let current = self.registry.get_signing_policy(&OperationalDID(op_did.to_string())).unwrap_or_default();
+        if new_policy.version != current.version + 1 {
+            return Err(format!("Policy version must be {}", current.version + 1));
+        }
+
+        // STEP 1: Threshold-sign the change under the current policy
+        let payload = policy::change_payload(op_did, &new_policy)?;
+        let signature = self.sign(op_did, &SigningIntent::PolicyChange, payload.clone(), approval_request_id).await?;
+
+        // STEP 2: Every vault verifies and installs it
+        let group = self.registry.get_mpc_group(op_did)
+            .ok_or("No MPC group for DID")?;
+        for peer in group.members.iter().map(|m| m.node_id.clone()).filter(|id| !device::is_device_participant(id)) {
+            self.call_set_policy(&peer, &payload, &signature).await?;
+        }
+
+        // STEP 3: The coordinator's registry, if it is not itself a group member
+        if self.registry.get_signing_policy(&OperationalDID(op_did.to_string())).map_or(true, |p| p.version < new_policy.version) {
+            policy::apply_change(&self.registry, &payload, &signature)?;
+        }
+
+        Ok(new_policy.version)
+    }
+
     /// Calls a vault to generate its nonce commitment
     async fn call_generate_nonce(&self, peer: &str, op_did: &str) -> Result<Vec<u8>, String> {
-        let uri = format!("http://{peer}");
-        let mut client = CustodyVaultClient::connect(uri)
+        let channel = tls::channel(peer)
             .await
             .map_err(|e| format!("Vault connect failed: {e:?}"))?;
+        let mut client = CustodyVaultClient::new(channel);
     
         let resp = client.generate_nonce(GenerateNonceRequest {
             operational_did: op_did.to_string(),
@@ -74,13 +300,15 @@ impl MPCSigningCoordinator {
         &self,
         peer: &str,
         op_did: &str,
-        message: &[u8],
+        intent: &SigningIntent,
+        payload: &[u8],
         session: &SigningSession,
+        approvals: Option<&ApprovalBundle>,
     ) -> Result<Vec<u8>, String> {
-        let uri = format!("http://{peer}");
-        let mut client = CustodyVaultClient::connect(uri)
+        let channel = tls::channel(peer)
             .await
             .map_err(|e| format!("Vault connect failed: {e:?}"))?;
+        let mut client = CustodyVaultClient::new(channel);
     
         let commitments = session
             .nonce_commitments
@@ -93,46 +321,235 @@ impl MPCSigningCoordinator {
     
         let resp = client.partial_sign(PartialSignRequest {
             operational_did: op_did.to_string(),
-            message: message.to_vec(),
+            message: payload.to_vec(),
             commitments,
+            intent: intent.to_proto(),
+            justification: intent.justification().to_string(),
+            approvals: approvals.map(|b| vault::ApprovalBundle {
+                request_id: b.request_id.clone(),
+                expires_at: b.expires_at,
+                approvals: b.approvals.iter().map(|a| vault::Approval {
+                    approver_id: a.approver_id.clone(),
+                    signature: a.signature.clone(),
+                }).collect(),
+            }),
         }).await.map_err(|e| format!("RPC failed: {e:?}"))?;
     
         Ok(resp.into_inner().signature)
     }    
 
-    /// Aggregates valid partials into a full Schnorr signature
+    /// Calls a vault to generate one nonce commitment per batch item
+    async fn call_generate_nonce_batch(&self, peer: &str, op_did: &str, count: usize) -> Result<Vec<Vec<u8>>, String> {
+        let channel = tls::channel(peer)
+            .await
+            .map_err(|e| format!("Vault connect failed: {e:?}"))?;
+        let mut client = CustodyVaultClient::new(channel);
+
+        let resp = client.generate_nonce_batch(GenerateNonceBatchRequest {
+            operational_did: op_did.to_string(),
+            count: count as u32,
+        }).await.map_err(|e| format!("RPC failed: {e:?}"))?;
+
+        Ok(resp.into_inner().commitments)
+    }
+
+    /// Sends every batch message with its commitments to a vault in a single call
+    async fn call_partial_sign_batch(
+        &self,
+        peer: &str,
+        op_did: &str,
+        intent: &SigningIntent,
+        payloads: &[Vec<u8>],
+        sessions: &[SigningSession],
+    ) -> Result<Vec<BatchSignResult>, String> {
+        let channel = tls::channel(peer)
+            .await
+            .map_err(|e| format!("Vault connect failed: {e:?}"))?;
+        let mut client = CustodyVaultClient::new(channel);
+
+        let items = sessions.iter().zip(payloads).enumerate().map(|(index, (session, payload))| BatchSignItem {
+            index: index as u32,
+            message: payload.clone(),
+            commitments: session
+                .nonce_commitments
+                .iter()
+                .map(|(peer_id, commitment)| PeerCommitment {
+                    peer_id: peer_id.clone(),
+                    commitment: commitment.clone(),
+                })
+                .collect(),
+        }).collect::<Vec<_>>();
+
+        let resp = client.partial_sign_batch(PartialSignBatchRequest {
+            operational_did: op_did.to_string(),
+            items,
+            intent: intent.to_proto(),
+            justification: intent.justification().to_string(),
+        }).await.map_err(|e| format!("RPC failed: {e:?}"))?;
+
+        Ok(resp.into_inner().results)
+    }
+
+    /// Asks a device, through its home node, for a nonce commitment
+    async fn call_device_commit(&self, participant_id: &str, request_id: &str, op_did: &str, message: &[u8]) -> Result<Vec<u8>, String> {
+        let home = device::device_home_node(participant_id).ok_or("Malformed device participant ID")?;
+        let channel = tls::channel(home)
+            .await
+            .map_err(|e| format!("Home node connect failed: {e:?}"))?;
+        let mut client = CustodyDeviceClient::new(channel);
+
+        let resp = client.device_commit(DeviceCommitRequest {
+            participant_id: participant_id.to_string(),
+            request_id: request_id.to_string(),
+            operational_did: op_did.to_string(),
+            message: message.to_vec(),
+        }).await.map_err(|e| format!("RPC failed: {e:?}"))?;
+
+        Ok(resp.into_inner().commitment)
+    }
+
+    /// Asks a device, through its home node, for its signature share
+    async fn call_device_sign(&self, participant_id: &str, request_id: &str, message: &[u8], session: &SigningSession) -> Result<Vec<u8>, String> {
+        let home = device::device_home_node(participant_id).ok_or("Malformed device participant ID")?;
+        let channel = tls::channel(home)
+            .await
+            .map_err(|e| format!("Home node connect failed: {e:?}"))?;
+        let mut client = CustodyDeviceClient::new(channel);
+
+        let commitments = session
+            .nonce_commitments
+            .iter()
+            .map(|(peer_id, commitment)| custodydevice::PeerCommitment {
+                peer_id: peer_id.clone(),
+                commitment: commitment.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let resp = client.device_sign(DeviceSignRequest {
+            participant_id: participant_id.to_string(),
+            request_id: request_id.to_string(),
+            message: message.to_vec(),
+            commitments,
+        }).await.map_err(|e| format!("RPC failed: {e:?}"))?;
+
+        Ok(resp.into_inner().signature_share)
+    }
+
+    /// Asks a vault which ECDSA presignatures it still holds
+    async fn call_list_ecdsa_presignatures(&self, peer: &str, op_did: &str) -> Result<Vec<String>, String> {
+        let channel = tls::channel(peer)
+            .await
+            .map_err(|e| format!("Vault connect failed: {e:?}"))?;
+        let mut client = CustodyVaultClient::new(channel);
+
+        let resp = client.list_ecdsa_presignatures(ListEcdsaPresignaturesRequest {
+            operational_did: op_did.to_string(),
+        }).await.map_err(|e| format!("RPC failed: {e:?}"))?;
+
+        Ok(resp.into_inner().presignature_ids)
+    }
+
+    /// Calls a vault to produce its ECDSA signature share from a presignature
+    async fn call_ecdsa_partial_sign(
+        &self,
+        peer: &str,
+        op_did: &str,
+        presignature_id: &str,
+        intent: &SigningIntent,
+        payload: &[u8],
+        format: EcdsaMessageFormat,
+        signers: &[String],
+        approvals: Option<&ApprovalBundle>,
+    ) -> Result<(EcdsaSignatureShare, Vec<u8>), String> {
+        let channel = tls::channel(peer)
+            .await
+            .map_err(|e| format!("Vault connect failed: {e:?}"))?;
+        let mut client = CustodyVaultClient::new(channel);
+
+        let resp = client.ecdsa_partial_sign(EcdsaPartialSignRequest {
+            operational_did: op_did.to_string(),
+            presignature_id: presignature_id.to_string(),
+            message: payload.to_vec(),
+            format: format.name().to_string(),
+            intent: intent.to_proto(),
+            justification: intent.justification().to_string(),
+            approvals: approvals.map(|b| vault::ApprovalBundle {
+                request_id: b.request_id.clone(),
+                expires_at: b.expires_at,
+                approvals: b.approvals.iter().map(|a| vault::Approval {
+                    approver_id: a.approver_id.clone(),
+                    signature: a.signature.clone(),
+                }).collect(),
+            }),
+            signers: signers.to_vec(),
+        }).await.map_err(|e| format!("RPC failed: {e:?}"))?.into_inner();
+
+        Ok((EcdsaSignatureShare { index: resp.index, share: resp.share }, resp.big_r))
+    }
+
+    /// Sends a signed policy change to one vault
+    async fn call_set_policy(&self, peer: &str, payload: &[u8], signature: &[u8]) -> Result<(), String> {
+        let channel = tls::channel(peer)
+            .await
+            .map_err(|e| format!("Vault connect failed: {e:?}"))?;
+        let mut client = CustodyVaultClient::new(channel);
+
+        client.set_policy(SetPolicyRequest {
+            policy_change: payload.to_vec(),
+            signature: signature.to_vec(),
+        }).await.map_err(|e| format!("RPC failed: {e:?}"))?;
+
+        Ok(())
+    }
+
+    /// Asks a vault to record a derived child DID
+    async fn call_register_derived_key(
+        &self,
+        peer: &str,
+        child_did: &str,
+        root_did: &str,
+        parent_did: &str,
+        path: &str,
+    ) -> Result<Vec<u8>, String> {
+        let channel = tls::channel(peer)
+            .await
+            .map_err(|e| format!("Vault connect failed: {e:?}"))?;
+        let mut client = CustodyVaultClient::new(channel);
+
+        let resp = client.register_derived_key(RegisterDerivedKeyRequest {
+            operational_did: child_did.to_string(),
+            root_did: root_did.to_string(),
+            parent_operational_did: parent_did.to_string(),
+            derivation_path: path.to_string(),
+        }).await.map_err(|e| format!("RPC failed: {e:?}"))?;
+
+        Ok(resp.into_inner().group_public_key)
+    }
+
+    /// Aggregates valid partials into a full Schnorr signature for the group's ciphersuite
     fn aggregate_signature(&self, session: &SigningSession, group: &MPCGroupDescriptor) -> Result<Vec<u8>, String> {
         let threshold = group.threshold as usize;
-        let mut shares = vec![];
 
-        for (peer_id, sig_bytes) in &session.partial_signatures {
-            let sig = Signature::from_bytes(sig_bytes).map_err(|_| "Invalid sig")?;
-            let id = Identifier::try_from(peer_id.as_bytes()).map_err(|_| "Invalid ID")?;
-            shares.push((id, sig));
-        }
-
-        if shares.len() < threshold {
+        if session.partial_signatures.len() < threshold {
             return Err("Too few shares".into());
         }
 
-        let group_pubkey = self.recover_group_key(group)?;
-        let agg = frost_ed25519::aggregate(&shares, &session.message, &group_pubkey)
-            .map_err(|e| format!("Aggregation failed: {:?}", e))?;
+        let suite = SuiteId::from_dkg_protocol(group.dkg_protocol.as_deref())?;
+        let public_key_package = self.recover_group_key(group)?;
 
-        Ok(agg.to_bytes().to_vec())
-    }
+        let commitments = session.nonce_commitments.iter()
+            .map(|(peer_id, c)| (peer_id.clone(), c.clone()))
+            .collect::<Vec<_>>();
+        let shares = session.partial_signatures.iter()
+            .map(|(peer_id, s)| (peer_id.clone(), s.clone()))
+            .collect::<Vec<_>>();
 
-    /// Rebuilds group pubkey from MPCGroupDescriptor
-    fn recover_group_key(&self, group: &MPCGroupDescriptor) -> Result<PublicKeyPackage, String> {
-        let pubkeys = group.members.iter()
-            .map(|m| {
-                let id = Identifier::try_from(m.node_id.as_bytes()).map_err(|_| "bad ID")?;
-                let pk_bytes = base64::decode(&m.public_share).map_err(|_| "bad base64")?;
-                let pk = frost_ed25519::keys::VerifyingKey::from_bytes(&pk_bytes).map_err(|_| "bad key")?;
-                Ok((id, pk))
-            })
-            .collect::<Result<HashMap<_, _>, _>>()?;
+        with_ciphersuite!(suite, |C| ciphersuite::aggregate::<C>(&session.message, &commitments, &shares, public_key_package))
+    }
 
-        PublicKeyPackage::try_from(pubkeys).map_err(|e| format!("bad group pubkey: {e:?}"))
+    /// Returns the serialized group PublicKeyPackage recorded by the DKG (compressed SEC1 key for ECDSA groups)
+    fn recover_group_key<'a>(&self, group: &'a MPCGroupDescriptor) -> Result<&'a [u8], String> {
+        group.public_key_package.as_deref()
+            .ok_or_else(|| format!("No public key package for group {}", group.group_id))
     }
 }
@@ -0,0 +1,101 @@
+// File: src/mpc/derivation.rs
+
+use crate::ciphersuite::{self, SuiteId};
+use crate::registry::{OperationalDIDRegistry, OperationalDID, RootDID, KeyDerivation, GroupStatus, MPCGroupDescriptor, MPCMemberDescriptor};
+use crate::types::VaultRecord;
+use crate::vault;
+use crate::with_ciphersuite;
+
+/// Registers `child_did` on this node as a child key of `parent_did`'s DKG group.
+/// No DKG runs: the child key is the parent key plus a public tweak for `path`,
+/// and this node's existing shares sign for it. Returns the child group public key.
+pub fn register_derived_did(
+    registry: &OperationalDIDRegistry,
+    child_did: &str,
+    root_did: &str,
+    parent_did: &str,
+    path: &str,
+) -> Result<Vec<u8>, String> {
+    ciphersuite::parse_derivation_path(path)?;
+
+    // STEP 1: Resolve to the DID that actually holds the DKG shares
+    let (parent_did, path) = match registry.get_key_derivation(parent_did) {
+        Some(d) => (d.parent_did, format!("{}/{}", d.path, path.trim_start_matches("m/"))),
+        None => (parent_did.to_string(), path.to_string()),
+    };
+    let parent = registry.get_mpc_group(&parent_did).ok_or("Parent DID has no MPC group")?;
+    if parent.status != GroupStatus::Active {
+        return Err("Parent group is not active".into());
+    }
+    let suite = SuiteId::from_dkg_protocol(parent.dkg_protocol.as_deref())
+        .map_err(|_| "Child keys can only be derived from FROST groups")?;
+    let parent_package = parent.public_key_package.as_ref().ok_or("Parent group public key package missing")?;
+
+    // STEP 2: Tweak the parent's public key package
+    let participants = parent.members.iter().map(|m| m.node_id.clone()).collect::<Vec<_>>();
+    let derived = with_ciphersuite!(suite, |C| ciphersuite::derive_public_key_package::<C>(parent_package, &path, &participants))?;
+
+    // STEP 3: The child gets its own (shard-less) vault record for nonces
+    let vault_id = format!("vault-{}", uuid::Uuid::new_v4());
+    vault::store_record(&vault_id, &VaultRecord {
+        root_did: root_did.to_string(),
+        op_dids: vec![child_did.to_string()],
+        mpc_shard: None,
+        group_metadata: None,
+        public_keys: vec![],
+        vcs: vec![],
+        bbs_private_key: None,
+        bbs_public_key: None,
+        active_nonce: None,
+        batch_nonces: vec![],
+        ecdsa_share: None,
+        ecdsa_presignatures: Default::default(),
+        shard_epoch: 0,
+        dkg_sessions: Default::default(),
+        consumed_approvals: Default::default(),
+    })?;
+
+    // STEP 4: Record the child DID, its parent link and its group
+    let child = OperationalDID(child_did.to_string());
+    registry.register_operational_did(child.clone(), RootDID(root_did.to_string()), vault_id, vec![])
+        .map_err(|e| format!("register DID failed: {e:?}"))?;
+
+    registry.set_key_derivation(&child, KeyDerivation {
+        parent_did: parent_did.clone(),
+        parent_group_id: parent.group_id.clone(),
+        path: path.clone(),
+    }).map_err(|e| format!("registry update failed: {e:?}"))?;
+
+    registry.set_mpc_group(&child, MPCGroupDescriptor {
+        group_id: format!("{}#{}", parent.group_id, path),
+        members: derived.verifying_shares.iter().map(|(node_id, pk)| MPCMemberDescriptor {
+            node_id: node_id.clone(),
+            identifier: parent.members.iter().find(|m| m.node_id == *node_id).map(|m| m.identifier.clone()).unwrap_or_default(),
+            share_index: None,
+            identity_key: parent.members.iter().find(|m| m.node_id == *node_id).map(|m| m.identity_key.clone()).unwrap_or_default(),
+            public_share: base64::encode(pk),
+        }).collect(),
+        threshold: parent.threshold,
+        dkg_protocol: parent.dkg_protocol.clone(),
+        session_state: None,
+        public_key_package: Some(derived.public_key_package),
+        status: GroupStatus::Active,
+    }).map_err(|e| format!("registry update failed: {e:?}"))?;
+
+    Ok(derived.group_public_key)
+}
+
+/// Which DID's shard signs for `op_did`, and the tweak path to apply (None for DKG-owned keys)
+pub fn resolve_signing_key(registry: &OperationalDIDRegistry, op_did: &str) -> Result<(String, Option<String>), String> {
+    let Some(derivation) = registry.get_key_derivation(op_did) else {
+        return Ok((op_did.to_string(), None));
+    };
+
+    // A parent rotation changes every child key, so refuse rather than sign under a different key
+    let parent = registry.get_mpc_group(&derivation.parent_did).ok_or("Parent DID has no MPC group")?;
+    if parent.group_id != derivation.parent_group_id {
+        return Err(format!("Parent group of {op_did} was rotated; the child key must be re-derived"));
+    }
+
+    Ok((derivation.parent_did, Some(derivation.path)))
+}
@@ -0,0 +1,135 @@
+// File: src/mpc/device.rs
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use frost_ed25519::{Signature as Ed25519Signature, VerifyingKey as Ed25519VerifyingKey};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::identity::IdentityPublicKeys;
+
+/// Prefix that marks a DKG/signing participant as an external device
+pub const DEVICE_PREFIX: &str = "device:";
+
+/// Domain separator for the session challenge a device signs
+pub const HELLO_DOMAIN: &[u8] = b"custody-device-hello-v1";
+
+/// How long the coordinator waits for a device to answer a signing request
+pub const DEVICE_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Participant ID of a device: `device:<device_id>@<home_node>`.
+/// The home node is part of the ID so every node can route to the device without shared state.
+pub fn device_participant_id(device_id: &str, home_node: &str) -> String {
+    format!("{DEVICE_PREFIX}{device_id}@{home_node}")
+}
+
+pub fn is_device_participant(participant_id: &str) -> bool {
+    participant_id.starts_with(DEVICE_PREFIX)
+}
+
+/// Returns the home node a device participant is reachable through
+pub fn device_home_node(participant_id: &str) -> Option<&str> {
+    participant_id.strip_prefix(DEVICE_PREFIX)?.split_once('@').map(|(_, home)| home)
+}
+
+/// Bytes the device signs to prove it holds its identity key
+pub fn hello_message(nonce: &[u8]) -> Vec<u8> {
+    [HELLO_DOMAIN, nonce].concat()
+}
+
+/// Checks a device's answer to the session challenge
+pub fn verify_hello(identity_public_key: &[u8], nonce: &[u8], signature: &[u8]) -> Result<(), String> {
+    let key = Ed25519VerifyingKey::deserialize(identity_public_key).map_err(|_| "bad device identity key")?;
+    let sig = Ed25519Signature::deserialize(signature).map_err(|_| "bad hello signature")?;
+    key.verify(&hello_message(nonce), &sig).map_err(|_| "device failed the session challenge".to_string())
+}
+
+/// Messages a home node pushes down a device's session stream
+#[derive(Debug, Clone)]
+pub enum NodeToDevice {
+    Relay { group_id: String, from: String, payload: Vec<u8> },
+    DkgInvite {
+        group_id: String,
+        operational_did: String,
+        threshold: u16,
+        participants: Vec<String>,
+        ciphersuite: String,
+        participant_keys: Vec<(String, IdentityPublicKeys)>, // Every other participant's identity keys
+    },
+    SignRequest { request_id: String, operational_did: String, message: Vec<u8> },
+    SigningPackage { request_id: String, message: Vec<u8>, commitments: Vec<(String, Vec<u8>)> },
+}
+
+/// Messages a device sends up its session stream (after the hello)
+#[derive(Debug, Clone)]
+pub enum DeviceToNode {
+    Relay { group_id: String, to: String, payload: Vec<u8> },
+    DkgComplete { group_id: String, group_public_key: Vec<u8> },
+    Result { request_id: String, result: Result<Vec<u8>, String> },
+}
+
+/// Connected devices on this node and the signing requests waiting on them
+pub struct DeviceHub {
+    pub connections: Mutex<HashMap<String, mpsc::Sender<NodeToDevice>>>, // participant_id → session stream
+    pub pending: Mutex<HashMap<String, oneshot::Sender<Result<Vec<u8>, String>>>>, // request_id → waiting coordinator call
+}
+
+impl DeviceHub {
+    pub fn new() -> Self {
+        DeviceHub {
+            connections: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Attach a device's session stream; a reconnect replaces the previous stream
+    pub fn connect(&self, participant_id: &str) -> mpsc::Receiver<NodeToDevice> {
+        let (tx, rx) = mpsc::channel(64);
+        self.connections.lock().unwrap().insert(participant_id.to_string(), tx);
+        rx
+    }
+
+    pub fn disconnect(&self, participant_id: &str) {
+        self.connections.lock().unwrap().remove(participant_id);
+    }
+
+    pub fn is_connected(&self, participant_id: &str) -> bool {
+        self.connections.lock().unwrap().contains_key(participant_id)
+    }
+
+    /// Push a message to a connected device
+    pub async fn deliver(&self, participant_id: &str, msg: NodeToDevice) -> Result<(), String> {
+        let tx = self.connections.lock().unwrap().get(participant_id).cloned()
+            .ok_or_else(|| format!("Device {participant_id} is not connected"))?;
+
+        tx.send(msg).await.map_err(|_| format!("Device {participant_id} disconnected"))
+    }
+
+    /// Send a signing request and wait for the device's answer
+    pub async fn request(&self, participant_id: &str, request_id: &str, msg: NodeToDevice) -> Result<Vec<u8>, String> {
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(request_id.to_string(), tx);
+
+        if let Err(e) = self.deliver(participant_id, msg).await {
+            self.pending.lock().unwrap().remove(request_id);
+            return Err(e);
+        }
+
+        let answer = tokio::time::timeout(DEVICE_REQUEST_TIMEOUT, rx).await;
+        self.pending.lock().unwrap().remove(request_id);
+
+        match answer {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(format!("Device {participant_id} dropped request {request_id}")),
+            Err(_) => Err(format!("Device {participant_id} timed out on request {request_id}")),
+        }
+    }
+
+    /// Hand a device's answer to whoever is waiting on the request
+    pub fn complete(&self, request_id: &str, result: Result<Vec<u8>, String>) {
+        if let Some(waiter) = self.pending.lock().unwrap().remove(request_id) {
+            let _ = waiter.send(result);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+// File: src/mpc/device_client.rs
+
+use std::collections::HashMap;
+
+use zeroize::Zeroizing;
+
+use crate::ciphersuite::{self, SuiteId};
+use crate::dkg::types::{DKGMessage, DKGPackage};
+use crate::identity::{IdentityPublicKeys, NodeIdentity};
+use crate::mpc::device::{self, DeviceToNode, NodeToDevice};
+use crate::tls;
+use crate::with_ciphersuite;
+
+use custodydevice::custody_device_client::CustodyDeviceClient;
+use custodydevice::{
+    device_message, node_message, DeviceHello, DeviceMessage, DkgComplete, RelayPayload, RequestResult,
+};
+
+/// DKG state the device keeps while a group is being created
+struct DeviceDkg {
+    operational_did: String,
+    suite: SuiteId,
+    participants: Vec<String>,
+    participant_keys: HashMap<String, IdentityPublicKeys>,
+    round1_secret: Option<Vec<u8>>,
+    round2_secret: Option<Vec<u8>>,
+    round1_received: HashMap<String, Vec<u8>>,
+    round2_received: HashMap<String, Vec<u8>>,
+    sent: HashMap<String, Vec<Vec<u8>>>,      // Packages sent per peer, replayed if that peer restarts
+}
+
+/// Reference device participant. Holds one FROST share per operational DID and
+/// answers its home node's DKG and signing traffic. It signs every request it
+/// receives; a real wallet would ask the user before answering a `SignRequest`.
+pub struct DeviceClient {
+    pub participant_id: String,
+    identity: NodeIdentity,
+    key_packages: HashMap<String, (SuiteId, Zeroizing<Vec<u8>>)>, // op_did → this device's key package
+    dkg: HashMap<String, DeviceDkg>,                               // group_id → in-progress DKG
+    early: HashMap<String, Vec<(String, Vec<u8>)>>,                // DKG messages that beat the invite
+    nonces: HashMap<String, (String, Zeroizing<Vec<u8>>)>,         // request_id → (op_did, nonces)
+    cluster_ca: Option<Vec<u8>>,                                   // Checks the home node's certificate
+}
+
+impl DeviceClient {
+    pub fn new(device_id: &str, home_node: &str) -> Self {
+        DeviceClient {
+            participant_id: device::device_participant_id(device_id, home_node),
+            identity: NodeIdentity::generate(),
+            key_packages: HashMap::new(),
+            dkg: HashMap::new(),
+            early: HashMap::new(),
+            nonces: HashMap::new(),
+            cluster_ca: None,
+        }
+    }
+
+    /// Connects over TLS from now on, accepting a home node certified by this CA
+    pub fn trust_cluster_ca(&mut self, ca_pem: Vec<u8>) {
+        self.cluster_ca = Some(ca_pem);
+    }
+
+    /// Ed25519 identity key to send in `RegisterDevice`
+    pub fn identity_public_key(&self) -> Vec<u8> {
+        self.identity.public_keys().signing_key
+    }
+
+    /// Encryption key to send in `RegisterDevice`; peers seal DKG round-2 packages to it
+    pub fn encryption_public_key(&self) -> Vec<u8> {
+        self.identity.public_keys().encryption_key
+    }
+
+    /// Answer to the node's session challenge
+    pub fn hello(&self, nonce: &[u8]) -> Vec<u8> {
+        self.identity.sign(&device::hello_message(nonce))
+    }
+
+    /// Whether the device holds a share for the DID
+    pub fn has_share(&self, op_did: &str) -> bool {
+        self.key_packages.contains_key(op_did)
+    }
+
+    /// Processes one message from the home node and returns the replies to send
+    pub fn handle(&mut self, msg: NodeToDevice) -> Result<Vec<DeviceToNode>, String> {
+        match msg {
+            NodeToDevice::DkgInvite { group_id, operational_did, threshold, participants, ciphersuite, participant_keys } => {
+                self.start_dkg(group_id, operational_did, threshold, participants, participant_keys.into_iter().collect(), &ciphersuite)
+            }
+            NodeToDevice::Relay { group_id, from, payload } => self.on_dkg_message(&group_id, from, payload),
+            NodeToDevice::SignRequest { request_id, operational_did, message: _ } => {
+                let result = self.commit(&request_id, &operational_did);
+                Ok(vec![DeviceToNode::Result { request_id, result }])
+            }
+            NodeToDevice::SigningPackage { request_id, message, commitments } => {
+                let result = self.sign(&request_id, &message, &commitments);
+                Ok(vec![DeviceToNode::Result { request_id, result }])
+            }
+        }
+    }
+
+    /// Connects to the home node and serves the session until the stream closes
+    pub async fn run(mut self, home_endpoint: &str) -> Result<(), String> {
+        let channel = tls::device_channel(home_endpoint, self.cluster_ca.as_deref())
+            .await
+            .map_err(|e| format!("Connect failed: {e:?}"))?;
+        let mut client = CustodyDeviceClient::new(channel);
+
+        let (tx, rx) = tokio::sync::mpsc::channel::<DeviceMessage>(64);
+        let mut inbound = client.session(tokio_stream::wrappers::ReceiverStream::new(rx))
+            .await
+            .map_err(|e| format!("Session failed: {e:?}"))?
+            .into_inner();
+
+        while let Some(msg) = inbound.message().await.map_err(|e| format!("Stream error: {e:?}"))? {
+            let replies = match msg.body {
+                Some(node_message::Body::Challenge(challenge)) => vec![device_message::Body::Hello(DeviceHello {
+                    participant_id: self.participant_id.clone(),
+                    challenge_signature: self.hello(&challenge.nonce),
+                })],
+                Some(body) => self.handle(from_proto(body)?)?.into_iter().map(to_proto).collect(),
+                None => continue,
+            };
+
+            for body in replies {
+                tx.send(DeviceMessage { body: Some(body) }).await.map_err(|_| "Session closed")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn start_dkg(
+        &mut self,
+        group_id: String,
+        operational_did: String,
+        threshold: u16,
+        participants: Vec<String>,
+        participant_keys: HashMap<String, IdentityPublicKeys>,
+        ciphersuite: &str,
+    ) -> Result<Vec<DeviceToNode>, String> {
+        if let Some(unknown) = participants.iter().find(|p| **p != self.participant_id && !participant_keys.contains_key(*p)) {
+            return Err(format!("Invite has no identity key for {unknown}"));
+        }
+
+        let suite = SuiteId::from_name(ciphersuite)?;
+        let max_signers = participants.len() as u16;
+        let (secret, package) = with_ciphersuite!(suite, |C| ciphersuite::dkg_part1::<C>(&self.participant_id, max_signers, threshold))?;
+
+        let round1 = DKGMessage::signed_round1(&self.identity, &group_id, &self.participant_id, package);
+        let payload = bincode::serialize(&round1).map_err(|_| "serialize failed")?;
+        let mut out = participants.iter()
+            .filter(|p| **p != self.participant_id)
+            .map(|peer| DeviceToNode::Relay { group_id: group_id.clone(), to: peer.clone(), payload: payload.clone() })
+            .collect::<Vec<_>>();
+
+        self.dkg.insert(group_id.clone(), DeviceDkg {
+            operational_did,
+            suite,
+            participants,
+            participant_keys,
+            round1_secret: Some(secret),
+            round2_secret: None,
+            round1_received: HashMap::new(),
+            round2_received: HashMap::new(),
+            sent: sent_by_peer(&out),
+        });
+
+        for (from, payload) in self.early.remove(&group_id).unwrap_or_default() {
+            out.extend(self.on_dkg_message(&group_id, from, payload)?);
+        }
+        Ok(out)
+    }
+
+    /// Records a DKG package and moves to the next round as soon as every peer's package is in
+    fn on_dkg_message(&mut self, group_id: &str, from: String, payload: Vec<u8>) -> Result<Vec<DeviceToNode>, String> {
+        let Some(dkg) = self.dkg.get_mut(group_id) else {
+            self.early.entry(group_id.to_string()).or_default().push((from, payload));
+            return Ok(vec![]);
+        };
+
+        // Only participants' signed packages count; round-2 packages are opened with our identity
+        let sender = dkg.participant_keys.get(&from).ok_or_else(|| format!("{from} is not a participant"))?;
+        let msg: DKGMessage = bincode::deserialize(&payload).map_err(|_| "malformed DKG message")?;
+        match msg.open(&self.identity, group_id, &from, &self.participant_id, sender).map_err(|e| format!("{e:?}"))? {
+            Some(DKGPackage::Round1(pkg)) => { dkg.round1_received.insert(from, pkg); }
+            Some(DKGPackage::Round2(pkg)) => { dkg.round2_received.insert(from, pkg); }
+            Some(DKGPackage::Complaint(complaint)) => {
+                // The session is aborted everywhere; drop our secrets with it
+                println!("🚨 DKG {group_id} aborted: {} accuses {} ({})", complaint.accuser, complaint.accused, complaint.reason);
+                self.dkg.remove(group_id);
+                return Ok(Vec::new());
+            }
+            Some(DKGPackage::Abort(reason)) => {
+                println!("🛑 DKG {group_id} aborted by {from}: {reason}");
+                self.dkg.remove(group_id);
+                return Ok(Vec::new());
+            }
+            Some(DKGPackage::Resend) => {
+                let sent = dkg.sent.get(&from).cloned().unwrap_or_default();
+                return Ok(sent.into_iter()
+                    .map(|payload| DeviceToNode::Relay { group_id: group_id.to_string(), to: from.clone(), payload })
+                    .collect());
+            }
+            None => {}
+        }
+
+        let peers = dkg.participants.len() - 1;
+        let mut out = Vec::new();
+
+        if dkg.round1_received.len() == peers {
+            if let Some(secret) = dkg.round1_secret.take() {
+                let (secret2, packages) = with_ciphersuite!(dkg.suite, |C| ciphersuite::dkg_part2::<C>(&secret, &dkg.round1_received))?;
+                dkg.round2_secret = Some(secret2);
+                for (peer, pkg) in packages {
+                    let keys = &dkg.participant_keys[&peer];
+                    let round2 = DKGMessage::sealed_round2(&self.identity, group_id, &self.participant_id, &peer, keys, &pkg)
+                        .map_err(|e| format!("{e:?}"))?;
+                    let payload = bincode::serialize(&round2).map_err(|_| "serialize failed")?;
+                    dkg.sent.entry(peer.clone()).or_default().push(payload.clone());
+                    out.push(DeviceToNode::Relay { group_id: group_id.to_string(), to: peer, payload });
+                }
+            }
+        }
+
+        if dkg.round2_received.len() == peers && dkg.round2_secret.is_some() {
+            let dkg = self.dkg.remove(group_id).ok_or("DKG state missing")?;
+            let output = with_ciphersuite!(dkg.suite, |C| ciphersuite::dkg_part3::<C>(
+                dkg.round2_secret.as_deref().unwrap_or_default(),
+                &dkg.round1_received,
+                &dkg.round2_received,
+                &dkg.participants,
+            ))?;
+
+            self.key_packages.insert(dkg.operational_did, (dkg.suite, Zeroizing::new(output.key_package)));
+            out.push(DeviceToNode::DkgComplete { group_id: group_id.to_string(), group_public_key: output.group_public_key });
+        }
+
+        Ok(out)
+    }
+
+    fn commit(&mut self, request_id: &str, op_did: &str) -> Result<Vec<u8>, String> {
+        let (suite, key_package) = self.key_packages.get(op_did).ok_or("Device holds no share for this DID")?;
+        let (nonces, commitment) = with_ciphersuite!(*suite, |C| ciphersuite::commit::<C>(key_package))?;
+
+        self.nonces.insert(request_id.to_string(), (op_did.to_string(), Zeroizing::new(nonces)));
+        Ok(commitment)
+    }
+
+    fn sign(&mut self, request_id: &str, message: &[u8], commitments: &[(String, Vec<u8>)]) -> Result<Vec<u8>, String> {
+        // Nonces are single-use: removed before signing
+        let (op_did, nonces) = self.nonces.remove(request_id).ok_or("Unknown signing request")?;
+        let (suite, key_package) = self.key_packages.get(&op_did).ok_or("Device holds no share for this DID")?;
+
+        with_ciphersuite!(*suite, |C| ciphersuite::sign::<C>(key_package, &nonces, message, commitments))
+    }
+}
+
+fn from_proto(body: node_message::Body) -> Result<NodeToDevice, String> {
+    Ok(match body {
+        node_message::Body::Relay(r) => NodeToDevice::Relay { group_id: r.group_id, from: r.peer, payload: r.payload },
+        node_message::Body::DkgInvite(i) => NodeToDevice::DkgInvite {
+            group_id: i.group_id,
+            operational_did: i.operational_did,
+            threshold: i.threshold as u16,
+            participants: i.participants,
+            ciphersuite: i.ciphersuite,
+            participant_keys: i.participant_keys.into_iter()
+                .map(|k| (k.participant_id, IdentityPublicKeys { signing_key: k.signing_key, encryption_key: k.encryption_key }))
+                .collect(),
+        },
+        node_message::Body::SignRequest(r) => NodeToDevice::SignRequest {
+            request_id: r.request_id,
+            operational_did: r.operational_did,
+            message: r.message,
+        },
+        node_message::Body::SigningPackage(p) => NodeToDevice::SigningPackage {
+            request_id: p.request_id,
+            message: p.message,
+            commitments: p.commitments.into_iter().map(|c| (c.peer_id, c.commitment)).collect(),
+        },
+        node_message::Body::Challenge(_) => return Err("Unexpected challenge".into()),
+    })
+}
+
+fn to_proto(msg: DeviceToNode) -> device_message::Body {
+    match msg {
+        DeviceToNode::Relay { group_id, to, payload } => device_message::Body::Relay(RelayPayload { group_id, peer: to, payload }),
+        DeviceToNode::DkgComplete { group_id, group_public_key } => device_message::Body::DkgComplete(DkgComplete { group_id, group_public_key }),
+        DeviceToNode::Result { request_id, result } => device_message::Body::Result(match result {
+            Ok(value) => RequestResult { request_id, value, error: String::new() },
+            Err(error) => RequestResult { request_id, value: vec![], error },
+        }),
+    }
+}
+
+fn sent_by_peer(out: &[DeviceToNode]) -> HashMap<String, Vec<Vec<u8>>> {
+    let mut sent: HashMap<String, Vec<Vec<u8>>> = HashMap::new();
+    for msg in out {
+        if let DeviceToNode::Relay { to, payload, .. } = msg {
+            sent.entry(to.clone()).or_default().push(payload.clone());
+        }
+    }
+    sent
+}
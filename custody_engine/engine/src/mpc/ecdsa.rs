@@ -0,0 +1,577 @@
+// File: src/mpc/ecdsa.rs
+
+//! Threshold ECDSA over secp256k1 with presignatures.
+//!
+//! Honest-majority protocol built only from Shamir sharing and Feldman commitments:
+//!   - Keygen: every party deals a degree-(t-1) sharing of a random secret; x = Σ secrets.
+//!   - Presign: parties jointly share random k and a, open w = k·a, and derive
+//!     shares of k⁻¹ = w⁻¹·a and of x·k⁻¹ (degree 2(t-1)). R = g^k comes from the
+//!     Feldman commitments, so no extra round is needed for it.
+//!   - Sign: one non-interactive share per party, s = Σ λᵢ sᵢ over 2t-1 parties.
+//!
+//! Because the x·k⁻¹ product doubles the polynomial degree, signing needs
+//! 2·threshold − 1 parties online (all 3 parties for a 2-of-3 group).
+//! Every function here is node-local and pure so the whole protocol can run
+//! with all parties in one process for testing.
+
+use std::collections::HashMap;
+
+use k256::{AffinePoint, EncodedPoint, FieldBytes, ProjectivePoint, Scalar, U256};
+use k256::ecdsa::{Signature as K256Signature, VerifyingKey as K256VerifyingKey};
+use k256::ecdsa::signature::hazmat::PrehashVerifier;
+use k256::elliptic_curve::{Field, PrimeField};
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::point::AffineCoordinates;
+use k256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::identity::{self, IdentityPublicKeys, NodeIdentity, SealedBox};
+
+/// Value recorded in `MPCGroupDescriptor.dkg_protocol` for ECDSA groups
+pub const ECDSA_PROTOCOL: &str = "ecdsa-secp256k1-hm-v1";
+
+/// Name accepted in provisioning requests to select threshold ECDSA
+pub const ECDSA_SUITE_NAME: &str = "ecdsa-secp256k1";
+
+/// Domain separator for signatures on ECDSA relay messages
+pub const ECDSA_MESSAGE_DOMAIN: &[u8] = b"custody-ecdsa-message-v1";
+
+/// Key derivation context of the group hash nodes confirm to each other after keygen
+pub const ECDSA_CONFIRM_CONTEXT: &str = "custody-ecdsa-confirm-v1 group";
+
+/// How the caller's message is turned into the 32-byte digest that gets signed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EcdsaMessageFormat {
+    /// Message is already a 32-byte digest
+    Prehashed,
+    /// EIP-191 personal_sign: keccak256("\x19Ethereum Signed Message:\n" || len || message)
+    Eip191,
+    /// EIP-712 typed data: message = domainSeparator (32) || hashStruct(message) (32)
+    Eip712,
+}
+
+impl EcdsaMessageFormat {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "" | "prehashed" => Ok(EcdsaMessageFormat::Prehashed),
+            "eip191" => Ok(EcdsaMessageFormat::Eip191),
+            "eip712" => Ok(EcdsaMessageFormat::Eip712),
+            other => Err(format!("Unknown ECDSA message format: {other}")),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EcdsaMessageFormat::Prehashed => "prehashed",
+            EcdsaMessageFormat::Eip191 => "eip191",
+            EcdsaMessageFormat::Eip712 => "eip712",
+        }
+    }
+}
+
+/// Computes the digest to sign for the given message format
+pub fn message_digest(format: EcdsaMessageFormat, message: &[u8]) -> Result<[u8; 32], String> {
+    match format {
+        EcdsaMessageFormat::Prehashed => message.try_into().map_err(|_| "Prehashed message must be 32 bytes".to_string()),
+        EcdsaMessageFormat::Eip191 => {
+            let mut hasher = Keccak256::new();
+            hasher.update(format!("\x19Ethereum Signed Message:\n{}", message.len()).as_bytes());
+            hasher.update(message);
+            Ok(hasher.finalize().into())
+        }
+        EcdsaMessageFormat::Eip712 => {
+            if message.len() != 64 {
+                return Err("EIP-712 message must be domainSeparator || structHash (64 bytes)".into());
+            }
+            let mut hasher = Keccak256::new();
+            hasher.update([0x19, 0x01]);
+            hasher.update(message);
+            Ok(hasher.finalize().into())
+        }
+    }
+}
+
+// ==============================
+// Messages exchanged over the relay
+// ==============================
+
+/// Messages exchanged between custody nodes during ECDSA keygen and presigning
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EcdsaMessage {
+    /// Feldman commitments (broadcast part) plus the recipient's private share
+    KeygenDeal { commitments: Vec<Vec<u8>>, share: Vec<u8> },
+    /// Shares of random k, a and two zero-sharings for the recipient
+    PresignDeal {
+        k_commitments: Vec<Vec<u8>>,
+        a_commitments: Vec<Vec<u8>>,
+        k_share: Vec<u8>,
+        a_share: Vec<u8>,
+        w_mask_share: Vec<u8>,
+        sig_mask_share: Vec<u8>,
+    },
+    /// Opening share of w = k·a
+    PresignOpen { w_share: Vec<u8> },
+    /// Hash of the group the sender computed once its keygen finished
+    KeygenConfirm { digest: Vec<u8> },
+}
+
+/// An `EcdsaMessage` as it travels over the relay: encrypted to the recipient's identity
+/// key, since deals carry the recipient's secret shares, then signed by the sender over
+/// the session, sender and recipient, as DKG round-2 packages are
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SealedEcdsaMessage {
+    pub sealed: SealedBox,
+    pub signature: Vec<u8>,
+}
+
+impl SealedEcdsaMessage {
+    pub fn seal(identity: &NodeIdentity, session_id: &str, from: &str, to: &str, recipient: &IdentityPublicKeys, msg: &EcdsaMessage) -> Result<Self, String> {
+        let plaintext = Zeroizing::new(bincode::serialize(msg).map_err(|_| "serialize failed")?);
+        let sealed = identity::seal(&recipient.encryption_key, &plaintext, &message_aad(session_id, from, to))?;
+        let body = bincode::serialize(&sealed).map_err(|_| "serialize failed")?;
+        let signature = identity.sign(&message_signing_input(session_id, from, to, &body));
+        Ok(SealedEcdsaMessage { sealed, signature })
+    }
+
+    /// Checks the sender's signature and opens the message addressed to `to`
+    pub fn open(self, identity: &NodeIdentity, session_id: &str, from: &str, to: &str, sender: &IdentityPublicKeys) -> Result<EcdsaMessage, String> {
+        let body = bincode::serialize(&self.sealed).map_err(|_| "serialize failed")?;
+        identity::verify(&sender.signing_key, &message_signing_input(session_id, from, to, &body), &self.signature)
+            .map_err(|e| format!("ECDSA message from {from}: {e}"))?;
+        let plaintext = identity.open(&self.sealed, &message_aad(session_id, from, to))
+            .map_err(|e| format!("ECDSA message from {from}: {e}"))?;
+        bincode::deserialize(&plaintext).map_err(|_| "malformed ECDSA message".to_string())
+    }
+}
+
+fn message_signing_input(session_id: &str, from: &str, to: &str, body: &[u8]) -> Vec<u8> {
+    let mut input = ECDSA_MESSAGE_DOMAIN.to_vec();
+    for field in [session_id.as_bytes(), from.as_bytes(), to.as_bytes(), body] {
+        input.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        input.extend_from_slice(field);
+    }
+    input
+}
+
+fn message_aad(session_id: &str, from: &str, to: &str) -> Vec<u8> {
+    format!("{session_id}\0{from}\0{to}").into_bytes()
+}
+
+// ==============================
+// Persistent party state
+// ==============================
+
+/// One party's long-term ECDSA key share (sealed into the vault)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EcdsaKeyShare {
+    pub index: u32,                              // Shamir x-coordinate of this party
+    pub threshold: u8,
+    pub participants: Vec<String>,               // Sorted node IDs; index = position + 1
+    pub secret_share: Vec<u8>,                   // xᵢ
+    pub public_key: Vec<u8>,                     // Compressed SEC1 group key
+    pub verifying_shares: HashMap<u32, Vec<u8>>, // g^xⱼ per party
+}
+
+/// One party's share of a single-use presignature (sealed into the vault)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Presignature {
+    pub id: String,
+    pub index: u32,
+    pub big_r: Vec<u8>,    // R = g^k (compressed)
+    pub k_inv: Vec<u8>,    // share of k⁻¹
+    pub x_k_inv: Vec<u8>,  // share of x·k⁻¹ (degree 2t)
+    pub sig_mask: Vec<u8>, // zero-sharing used to rerandomize the signature share
+}
+
+impl Drop for Presignature {
+    fn drop(&mut self) {
+        self.k_inv.zeroize();
+        self.x_k_inv.zeroize();
+        self.sig_mask.zeroize();
+    }
+}
+
+/// A signature share produced from one presignature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EcdsaSignatureShare {
+    pub index: u32,
+    pub share: Vec<u8>,
+}
+
+/// In-progress keygen state for one party
+pub struct KeygenState {
+    index: u32,
+    threshold: u8,
+    participants: Vec<String>,
+}
+
+/// In-progress presign state for one party
+pub struct PresignState {
+    id: String,
+    key_share: EcdsaKeyShare,
+    k_i: Option<Scalar>,
+    a_i: Option<Scalar>,
+    sig_mask_i: Option<Scalar>,
+    big_r: Option<ProjectivePoint>,
+}
+
+// ==============================
+// Keygen
+// ==============================
+
+/// Returns the Shamir index of a node in the (sorted) participant list
+pub fn party_index(participants: &[String], node_id: &str) -> Result<u32, String> {
+    let mut sorted = participants.to_vec();
+    sorted.sort();
+    sorted.iter().position(|p| p == node_id)
+        .map(|i| i as u32 + 1)
+        .ok_or_else(|| format!("{node_id} is not a participant"))
+}
+
+/// Keygen round 1: deal a random secret. Returns the state and one message per participant (including self).
+pub fn keygen_deal(node_id: &str, participants: &[String], threshold: u8) -> Result<(KeygenState, HashMap<String, EcdsaMessage>), String> {
+    if threshold < 1 || threshold as usize > participants.len() {
+        return Err("Invalid threshold".into());
+    }
+    let mut sorted = participants.to_vec();
+    sorted.sort();
+    let index = party_index(&sorted, node_id)?;
+
+    let poly = Polynomial::random(Scalar::random(&mut OsRng), threshold as usize - 1);
+    let commitments = poly.commitments().iter().map(point_to_bytes).collect::<Vec<_>>();
+
+    let mut outgoing = HashMap::new();
+    for (i, peer) in sorted.iter().enumerate() {
+        outgoing.insert(peer.clone(), EcdsaMessage::KeygenDeal {
+            commitments: commitments.clone(),
+            share: scalar_to_bytes(&poly.eval(i as u32 + 1)),
+        });
+    }
+
+    Ok((KeygenState { index, threshold, participants: sorted }, outgoing))
+}
+
+/// Hash of everything public a keygen produced: the committee, the group key and every
+/// verifying share. Nodes that were dealt different commitments end up with different ones.
+pub fn keygen_confirmation_digest(key_share: &EcdsaKeyShare) -> Vec<u8> {
+    let mut shares = key_share.verifying_shares.iter().collect::<Vec<_>>();
+    shares.sort();
+    let public = bincode::serialize(&(&key_share.participants, key_share.threshold, &key_share.public_key, shares))
+        .expect("serializable");
+    blake3::derive_key(ECDSA_CONFIRM_CONTEXT, &public).to_vec()
+}
+
+/// Keygen finish: verify every dealt share and combine into this party's key share
+pub fn keygen_finish(state: KeygenState, received: &HashMap<String, EcdsaMessage>) -> Result<EcdsaKeyShare, String> {
+    if received.len() != state.participants.len() {
+        return Err(format!("Expected {} keygen deals, got {}", state.participants.len(), received.len()));
+    }
+
+    let mut secret = Scalar::ZERO;
+    let mut summed_commitments = vec![ProjectivePoint::IDENTITY; state.threshold as usize];
+
+    for (from, msg) in received {
+        let EcdsaMessage::KeygenDeal { commitments, share } = msg else {
+            return Err(format!("Unexpected message from {from}"));
+        };
+        let commitments = decode_points(commitments)?;
+        if commitments.len() != state.threshold as usize {
+            return Err(format!("Wrong commitment count from {from}"));
+        }
+        let share = scalar_from_bytes(share)?;
+        if !verify_feldman(&share, state.index, &commitments) {
+            return Err(format!("Invalid keygen share from {from}"));
+        }
+        secret += share;
+        for (acc, c) in summed_commitments.iter_mut().zip(commitments) {
+            *acc += c;
+        }
+    }
+
+    let verifying_shares = (1..=state.participants.len() as u32)
+        .map(|j| (j, point_to_bytes(&eval_commitments(&summed_commitments, j))))
+        .collect();
+
+    Ok(EcdsaKeyShare {
+        index: state.index,
+        threshold: state.threshold,
+        participants: state.participants,
+        secret_share: scalar_to_bytes(&secret),
+        public_key: point_to_bytes(&summed_commitments[0]),
+        verifying_shares,
+    })
+}
+
+// ==============================
+// Presign
+// ==============================
+
+/// Presign round 1: deal shares of random k and a, plus two degree-2t zero-sharings
+pub fn presign_deal(id: &str, key_share: EcdsaKeyShare) -> Result<(PresignState, HashMap<String, EcdsaMessage>), String> {
+    let degree = key_share.threshold as usize - 1;
+    if key_share.participants.len() < 2 * degree + 1 {
+        return Err(format!("Presigning needs at least {} parties", 2 * degree + 1));
+    }
+    let k_poly = Polynomial::random(Scalar::random(&mut OsRng), degree);
+    let a_poly = Polynomial::random(Scalar::random(&mut OsRng), degree);
+    let w_mask = Polynomial::random(Scalar::ZERO, 2 * degree);
+    let sig_mask = Polynomial::random(Scalar::ZERO, 2 * degree);
+
+    let k_commitments = k_poly.commitments().iter().map(point_to_bytes).collect::<Vec<_>>();
+    let a_commitments = a_poly.commitments().iter().map(point_to_bytes).collect::<Vec<_>>();
+
+    let mut outgoing = HashMap::new();
+    for (i, peer) in key_share.participants.iter().enumerate() {
+        let x = i as u32 + 1;
+        outgoing.insert(peer.clone(), EcdsaMessage::PresignDeal {
+            k_commitments: k_commitments.clone(),
+            a_commitments: a_commitments.clone(),
+            k_share: scalar_to_bytes(&k_poly.eval(x)),
+            a_share: scalar_to_bytes(&a_poly.eval(x)),
+            w_mask_share: scalar_to_bytes(&w_mask.eval(x)),
+            sig_mask_share: scalar_to_bytes(&sig_mask.eval(x)),
+        });
+    }
+
+    Ok((PresignState {
+        id: id.to_string(),
+        key_share,
+        k_i: None,
+        a_i: None,
+        sig_mask_i: None,
+        big_r: None,
+    }, outgoing))
+}
+
+/// Presign round 2: combine the deals and broadcast this party's share of w = k·a
+pub fn presign_open(state: &mut PresignState, received: &HashMap<String, EcdsaMessage>) -> Result<EcdsaMessage, String> {
+    let n = state.key_share.participants.len();
+    if received.len() != n {
+        return Err(format!("Expected {n} presign deals, got {}", received.len()));
+    }
+    let index = state.key_share.index;
+    let degree = state.key_share.threshold as usize - 1;
+
+    let (mut k_i, mut a_i, mut w_mask_i, mut sig_mask_i) = (Scalar::ZERO, Scalar::ZERO, Scalar::ZERO, Scalar::ZERO);
+    let mut big_r = ProjectivePoint::IDENTITY;
+
+    for (from, msg) in received {
+        let EcdsaMessage::PresignDeal { k_commitments, a_commitments, k_share, a_share, w_mask_share, sig_mask_share } = msg else {
+            return Err(format!("Unexpected message from {from}"));
+        };
+        let k_commitments = decode_points(k_commitments)?;
+        let a_commitments = decode_points(a_commitments)?;
+        if k_commitments.len() != degree + 1 || a_commitments.len() != degree + 1 {
+            return Err(format!("Wrong commitment count from {from}"));
+        }
+
+        let k_share = scalar_from_bytes(k_share)?;
+        let a_share = scalar_from_bytes(a_share)?;
+        if !verify_feldman(&k_share, index, &k_commitments) || !verify_feldman(&a_share, index, &a_commitments) {
+            return Err(format!("Invalid presign share from {from}"));
+        }
+
+        k_i += k_share;
+        a_i += a_share;
+        w_mask_i += scalar_from_bytes(w_mask_share)?;
+        sig_mask_i += scalar_from_bytes(sig_mask_share)?;
+        big_r += k_commitments[0];
+    }
+
+    if big_r == ProjectivePoint::IDENTITY {
+        return Err("Degenerate nonce point".into());
+    }
+
+    let w_i = k_i * a_i + w_mask_i;
+    state.k_i = Some(k_i);
+    state.a_i = Some(a_i);
+    state.sig_mask_i = Some(sig_mask_i);
+    state.big_r = Some(big_r);
+
+    Ok(EcdsaMessage::PresignOpen { w_share: scalar_to_bytes(&w_i) })
+}
+
+/// Presign finish: open w and derive this party's presignature
+pub fn presign_finish(state: PresignState, received: &HashMap<String, EcdsaMessage>) -> Result<Presignature, String> {
+    let mut points = Vec::new();
+    for (from, msg) in received {
+        let EcdsaMessage::PresignOpen { w_share } = msg else {
+            return Err(format!("Unexpected message from {from}"));
+        };
+        let index = party_index(&state.key_share.participants, from)?;
+        points.push((index, scalar_from_bytes(w_share)?));
+    }
+
+    let degree = 2 * (state.key_share.threshold as usize - 1);
+    if points.len() < degree + 1 {
+        return Err(format!("Need {} w shares, got {}", degree + 1, points.len()));
+    }
+
+    let w = interpolate_at_zero(&points);
+    let w_inv = Option::<Scalar>::from(w.invert()).ok_or("w is zero, retry presigning")?;
+
+    let a_i = state.a_i.ok_or("Presign round 2 not run")?;
+    let sig_mask_i = state.sig_mask_i.ok_or("Presign round 2 not run")?;
+    let big_r = state.big_r.ok_or("Presign round 2 not run")?;
+    let x_i = scalar_from_bytes(&state.key_share.secret_share)?;
+
+    let k_inv_i = w_inv * a_i;
+    let x_k_inv_i = x_i * k_inv_i;
+
+    Ok(Presignature {
+        id: state.id.clone(),
+        index: state.key_share.index,
+        big_r: point_to_bytes(&big_r),
+        k_inv: scalar_to_bytes(&k_inv_i),
+        x_k_inv: scalar_to_bytes(&x_k_inv_i),
+        sig_mask: scalar_to_bytes(&sig_mask_i),
+    })
+}
+
+// ==============================
+// Sign
+// ==============================
+
+/// Computes this party's signature share for a digest. The presignature must not be reused.
+pub fn sign_share(presignature: &Presignature, digest: &[u8; 32]) -> Result<EcdsaSignatureShare, String> {
+    let big_r = point_from_bytes(&presignature.big_r)?;
+    let r = x_coordinate_scalar(&big_r);
+    let e = digest_scalar(digest);
+
+    let s_i = e * scalar_from_bytes(&presignature.k_inv)?
+        + r * scalar_from_bytes(&presignature.x_k_inv)?
+        + scalar_from_bytes(&presignature.sig_mask)?;
+
+    Ok(EcdsaSignatureShare { index: presignature.index, share: scalar_to_bytes(&s_i) })
+}
+
+/// Combines signature shares into a 65-byte `r || s || v` signature (low-s, v = 27 + recovery id)
+/// and verifies it against the group public key.
+pub fn combine_signature(
+    big_r: &[u8],
+    shares: &[EcdsaSignatureShare],
+    threshold: u8,
+    public_key: &[u8],
+    digest: &[u8; 32],
+) -> Result<Vec<u8>, String> {
+    let needed = 2 * threshold as usize - 1;
+    if shares.len() < needed {
+        return Err(format!("Need {needed} ECDSA signature shares, got {}", shares.len()));
+    }
+
+    let points = shares.iter()
+        .map(|s| Ok((s.index, scalar_from_bytes(&s.share)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut s = interpolate_at_zero(&points);
+
+    let big_r = point_from_bytes(big_r)?.to_affine();
+    let r = x_coordinate_scalar(&big_r.into());
+    let mut recovery_id = u8::from(big_r.y_is_odd());
+    if r.to_bytes() != big_r.x() {
+        recovery_id |= 2; // R.x was reduced mod n
+    }
+    if bool::from(s.is_high()) {
+        s = -s;
+        recovery_id ^= 1;
+    }
+
+    let signature = K256Signature::from_scalars(r.to_bytes(), s.to_bytes())
+        .map_err(|e| format!("Invalid signature scalars: {e:?}"))?;
+    let verifying_key = K256VerifyingKey::from_sec1_bytes(public_key)
+        .map_err(|e| format!("Invalid group public key: {e:?}"))?;
+    verifying_key.verify_prehash(digest, &signature)
+        .map_err(|e| format!("Combined ECDSA signature failed verification: {e:?}"))?;
+
+    let mut out = signature.to_bytes().to_vec();
+    out.push(27 + recovery_id);
+    Ok(out)
+}
+
+// ==============================
+// Shamir / Feldman helpers
+// ==============================
+
+struct Polynomial(Vec<Scalar>);
+
+impl Polynomial {
+    fn random(constant: Scalar, degree: usize) -> Self {
+        let mut coeffs = vec![constant];
+        coeffs.extend((0..degree).map(|_| Scalar::random(&mut OsRng)));
+        Polynomial(coeffs)
+    }
+
+    fn eval(&self, x: u32) -> Scalar {
+        let x = Scalar::from(x as u64);
+        self.0.iter().rev().fold(Scalar::ZERO, |acc, c| acc * x + c)
+    }
+
+    fn commitments(&self) -> Vec<ProjectivePoint> {
+        self.0.iter().map(|c| ProjectivePoint::GENERATOR * c).collect()
+    }
+}
+
+impl Drop for Polynomial {
+    fn drop(&mut self) {
+        for c in self.0.iter_mut() {
+            *c = Scalar::ZERO;
+        }
+    }
+}
+
+fn eval_commitments(commitments: &[ProjectivePoint], x: u32) -> ProjectivePoint {
+    let x = Scalar::from(x as u64);
+    commitments.iter().rev().fold(ProjectivePoint::IDENTITY, |acc, c| acc * x + c)
+}
+
+fn verify_feldman(share: &Scalar, x: u32, commitments: &[ProjectivePoint]) -> bool {
+    ProjectivePoint::GENERATOR * share == eval_commitments(commitments, x)
+}
+
+fn interpolate_at_zero(points: &[(u32, Scalar)]) -> Scalar {
+    let indices = points.iter().map(|(i, _)| *i).collect::<Vec<_>>();
+    points.iter().fold(Scalar::ZERO, |acc, (i, y)| acc + lagrange_at_zero(*i, &indices) * y)
+}
+
+fn lagrange_at_zero(index: u32, indices: &[u32]) -> Scalar {
+    let xi = Scalar::from(index as u64);
+    let (num, den) = indices.iter().filter(|j| **j != index).fold((Scalar::ONE, Scalar::ONE), |(num, den), j| {
+        let xj = Scalar::from(*j as u64);
+        (num * xj, den * (xj - xi))
+    });
+    num * den.invert().unwrap()
+}
+
+fn x_coordinate_scalar(point: &ProjectivePoint) -> Scalar {
+    <Scalar as Reduce<U256>>::reduce_bytes(&point.to_affine().x())
+}
+
+fn digest_scalar(digest: &[u8; 32]) -> Scalar {
+    <Scalar as Reduce<U256>>::reduce_bytes(FieldBytes::from_slice(digest))
+}
+
+fn scalar_to_bytes(s: &Scalar) -> Vec<u8> {
+    s.to_bytes().to_vec()
+}
+
+fn scalar_from_bytes(bytes: &[u8]) -> Result<Scalar, String> {
+    let repr: [u8; 32] = bytes.try_into().map_err(|_| "bad scalar length")?;
+    Option::from(Scalar::from_repr(repr.into())).ok_or_else(|| "bad scalar".to_string())
+}
+
+fn point_to_bytes(p: &ProjectivePoint) -> Vec<u8> {
+    p.to_affine().to_encoded_point(true).as_bytes().to_vec()
+}
+
+fn point_from_bytes(bytes: &[u8]) -> Result<ProjectivePoint, String> {
+    let encoded = EncodedPoint::from_bytes(bytes).map_err(|_| "bad point encoding")?;
+    Option::<AffinePoint>::from(AffinePoint::from_encoded_point(&encoded))
+        .map(ProjectivePoint::from)
+        .ok_or_else(|| "bad point".to_string())
+}
+
+fn decode_points(raw: &[Vec<u8>]) -> Result<Vec<ProjectivePoint>, String> {
+    raw.iter().map(|b| point_from_bytes(b)).collect()
+}
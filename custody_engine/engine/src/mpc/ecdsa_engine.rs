@@ -0,0 +1,398 @@
+// File: src/mpc/ecdsa_engine.rs
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::identity::{IdentityDirectory, IdentityPublicKeys, NodeIdentity};
+use crate::membership::MEMBERSHIP;
+use crate::mpc::ecdsa::{self, EcdsaKeyShare, EcdsaMessage, KeygenState, PresignState, SealedEcdsaMessage, ECDSA_PROTOCOL};
+use crate::relay::{MessageType, RelayClient, RelayHandlers};
+use crate::registry::{OperationalDID, OperationalDIDRegistry, GroupStatus, MPCGroupDescriptor, MPCMemberDescriptor};
+use crate::vault;
+
+/// How long a session waits for every participant's message of a round, and a new
+/// group for every node's confirmation
+pub const ECDSA_ROUND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Which ECDSA sub-protocol a session is running
+pub enum EcdsaSessionState {
+    Keygen(Option<KeygenState>),
+    Presign(Option<PresignState>),
+}
+
+/// Node-local state of one ECDSA keygen or presign session
+pub struct EcdsaSession {
+    pub operational_did: String,
+    pub participant_ids: Vec<String>,
+    pub state: EcdsaSessionState,
+    pub round1_received: HashMap<String, EcdsaMessage>, // Deals (keygen or presign)
+    pub round2_received: HashMap<String, EcdsaMessage>, // Presign openings of w
+    pub confirmation: Option<Vec<u8>>,                  // Hash of the group our keygen produced
+    pub confirmations: HashMap<String, Vec<u8>>,        // Peer → hash of the group it produced
+    pub mismatch: Option<String>,                       // Set when a peer confirmed two different groups
+    pub events: tokio::sync::watch::Sender<usize>,      // Messages recorded so far, for waiters
+}
+
+/// Node-local threshold ECDSA engine. Mirrors `DKGEngine`: the orchestrator starts the
+/// same session ID on every node, then advances and finalizes it once messages have arrived.
+pub struct EcdsaEngine {
+    pub sessions: Mutex<HashMap<String, EcdsaSession>>,
+    pub pending: Mutex<HashMap<String, Vec<(String, Vec<u8>)>>>, // Messages that arrived before the local session started
+    pub did_registry: OperationalDIDRegistry,
+    pub relay: RelayClient,
+    pub identity: Arc<NodeIdentity>,          // Signs our messages and opens those sealed to us
+    pub directory: Arc<IdentityDirectory>,    // Pinned identity keys of peer nodes
+    pub node_id: String,
+}
+
+impl EcdsaEngine {
+    pub fn new(
+        did_registry: OperationalDIDRegistry,
+        relay: RelayClient,
+        identity: Arc<NodeIdentity>,
+        directory: Arc<IdentityDirectory>,
+        node_id: String,
+    ) -> Self {
+        EcdsaEngine {
+            sessions: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+            did_registry,
+            relay,
+            identity,
+            directory,
+            node_id,
+        }
+    }
+
+    /// Start a keygen session and send our dealing to every participant
+    pub fn start_keygen(&self, session_id: &str, op_did: &str, threshold: u8, participant_ids: Vec<String>) -> Result<(), String> {
+        let (state, outgoing) = ecdsa::keygen_deal(&self.node_id, &participant_ids, threshold)?;
+        self.open_session(session_id, op_did, participant_ids, EcdsaSessionState::Keygen(Some(state)), outgoing)
+    }
+
+    /// Start a presign session using the key share sealed in this node's vault
+    pub fn start_presign(&self, session_id: &str, op_did: &str) -> Result<(), String> {
+        self.did_registry.get_mpc_group(op_did).ok_or("No MPC group for DID")?.check_active()?;
+        let key_share = load_key_share(&self.did_registry, op_did)?;
+        let participant_ids = key_share.participants.clone();
+
+        let (state, outgoing) = ecdsa::presign_deal(session_id, key_share)?;
+        self.open_session(session_id, op_did, participant_ids, EcdsaSessionState::Presign(Some(state)), outgoing)
+    }
+
+    /// Takes the relay channel of ECDSA keygen and presign traffic
+    pub fn register_relay_handlers(self: &Arc<Self>, handlers: &RelayHandlers) -> Result<(), String> {
+        let engine = self.clone();
+        handlers.register(MessageType::Signing, move |session_id, from, payload| {
+            engine.handle_message(session_id, from, payload)
+                .map_err(|e| format!("ECDSA handling failed: {}", e))
+        })
+    }
+
+    /// Handle an incoming ECDSA relay message
+    pub fn handle_message(&self, session_id: &str, from: &str, payload: Vec<u8>) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let Some(session) = sessions.get_mut(session_id) else {
+            // Peers may start before we do; keep the message until our session opens
+            self.pending.lock().unwrap().entry(session_id.to_string()).or_default().push((from.to_string(), payload));
+            return Ok(());
+        };
+
+        self.record_message(session_id, session, from, payload)?;
+        self.settle_confirmations(&mut sessions, session_id);
+        Ok(())
+    }
+
+    /// Waits, without polling, until every participant's message of `round` (1: deals,
+    /// 2: presign openings) has arrived, or fails naming those still missing
+    pub async fn wait_for_round(&self, session_id: &str, round: u8) -> Result<(), String> {
+        let mut events = self.sessions.lock().unwrap()
+            .get(session_id)
+            .ok_or("Session not found")?
+            .events
+            .subscribe();
+        let deadline = tokio::time::Instant::now() + ECDSA_ROUND_TIMEOUT;
+
+        loop {
+            let missing = {
+                let sessions = self.sessions.lock().unwrap();
+                let session = sessions.get(session_id).ok_or("Session not found")?;
+                let received = if round == 1 { &session.round1_received } else { &session.round2_received };
+                session.participant_ids.iter().filter(|p| !received.contains_key(*p)).cloned().collect::<Vec<_>>()
+            };
+            if missing.is_empty() {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(format!("round {round} timed out waiting for {}", missing.join(", ")));
+            }
+
+            // Wake on the next recorded message, or at the deadline
+            let _ = tokio::time::timeout_at(deadline, events.changed()).await;
+        }
+    }
+
+    pub fn is_presign(&self, session_id: &str) -> bool {
+        self.sessions.lock().unwrap()
+            .get(session_id)
+            .is_some_and(|s| matches!(s.state, EcdsaSessionState::Presign(_)))
+    }
+
+    /// Presign round 2: broadcast our opening of w once every deal has arrived
+    pub fn advance(&self, session_id: &str) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(session_id).ok_or("Session not found")?;
+
+        let EcdsaSessionState::Presign(Some(state)) = &mut session.state else {
+            return Err("Only presign sessions have a second round".into());
+        };
+        let opening = ecdsa::presign_open(state, &session.round1_received)?;
+
+        for peer_id in session.participant_ids.iter().filter(|id| *id != &self.node_id) {
+            self.send(session_id, peer_id, &opening)?;
+        }
+        session.round2_received.insert(self.node_id.clone(), opening);
+
+        Ok(())
+    }
+
+    /// Finish the session: keygen seals the key share, records the group as pending and
+    /// sends every peer a signed hash of it; presign seals the presignature. Returns the
+    /// group key (hex) or presignature ID.
+    pub fn finalize(&self, session_id: &str) -> Result<String, String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let mut session = sessions.remove(session_id).ok_or("Session not found")?;
+
+        let vault_id = self.did_registry
+            .get_vault_id_for_operational_did(&session.operational_did)
+            .ok_or("Vault not found")?;
+
+        match session.state {
+            EcdsaSessionState::Keygen(state) => {
+                let state = state.ok_or("Missing keygen state")?;
+                let key_share = ecdsa::keygen_finish(state, &session.round1_received)?;
+
+                let encoded = bincode::serialize(&key_share).map_err(|_| "serialize failed")?;
+                vault::set_ecdsa_share(&vault_id, &base64::encode(encoded))?;
+
+                let group = MPCGroupDescriptor {
+                    group_id: session_id.to_string(),
+                    // ECDSA shares are indexed by position in the sorted committee
+                    members: key_share.participants.iter().enumerate().map(|(i, node_id)| {
+                        let member = MEMBERSHIP.get(node_id)
+                            .ok_or_else(|| format!("{node_id} is not an enrolled custody node"))?;
+                        Ok(MPCMemberDescriptor {
+                            node_id: node_id.clone(),
+                            identifier: u32::from(member.identifier).to_be_bytes().to_vec(),
+                            share_index: Some(i as u32 + 1),
+                            identity_key: member.identity.signing_key,
+                            public_share: base64::encode(&key_share.verifying_shares[&(i as u32 + 1)]),
+                        })
+                    }).collect::<Result<_, String>>()?,
+                    threshold: key_share.threshold,
+                    dkg_protocol: Some(ECDSA_PROTOCOL.to_string()),
+                    session_state: None,
+                    public_key_package: Some(key_share.public_key.clone()),
+                    // A dealer can hand nodes different commitments: nothing signs
+                    // until every node confirmed it computed the same group
+                    status: GroupStatus::Pending,
+                };
+                self.did_registry.set_mpc_group(&session.operational_did, group)
+                    .map_err(|e| format!("registry update failed: {e:?}"))?;
+
+                let digest = ecdsa::keygen_confirmation_digest(&key_share);
+                let confirmation = EcdsaMessage::KeygenConfirm { digest: digest.clone() };
+                for peer_id in session.participant_ids.iter().filter(|id| *id != &self.node_id) {
+                    self.send(session_id, peer_id, &confirmation)?;
+                }
+
+                // Kept open for the peers' confirmations, which may already be in
+                session.state = EcdsaSessionState::Keygen(None);
+                session.confirmation = Some(digest);
+                sessions.insert(session_id.to_string(), session);
+                self.settle_confirmations(&mut sessions, session_id);
+
+                Ok(hex::encode(&key_share.public_key))
+            }
+            EcdsaSessionState::Presign(state) => {
+                let state = state.ok_or("Missing presign state")?;
+                let presignature = ecdsa::presign_finish(state, &session.round2_received)?;
+
+                let encoded = bincode::serialize(&presignature).map_err(|_| "serialize failed")?;
+                vault::add_ecdsa_presignature(&vault_id, &presignature.id, &base64::encode(encoded))?;
+
+                Ok(presignature.id.clone())
+            }
+        }
+    }
+
+    /// Waits until the group our keygen produced is settled. A peer that has not
+    /// confirmed it once the round timeout passes gets it quarantined, like a mismatch.
+    pub async fn await_confirmations(&self, session_id: &str) {
+        let Some(mut events) = self.sessions.lock().unwrap().get(session_id).map(|s| s.events.subscribe()) else {
+            return;
+        };
+        let deadline = tokio::time::Instant::now() + ECDSA_ROUND_TIMEOUT;
+
+        loop {
+            {
+                let mut sessions = self.sessions.lock().unwrap();
+                let Some(session) = sessions.get(session_id) else { return };
+                if tokio::time::Instant::now() >= deadline {
+                    let silent = session.participant_ids.iter()
+                        .filter(|p| **p != self.node_id && !session.confirmations.contains_key(*p))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    let session = sessions.remove(session_id).expect("checked above");
+                    let reason = format!("no confirmation from {}", silent.join(", "));
+                    return self.set_group_status(&session, session_id, GroupStatus::Quarantined(reason));
+                }
+            }
+
+            // Wake on the next recorded message, once the session is settled, or at the deadline
+            let _ = tokio::time::timeout_at(deadline, events.changed()).await;
+        }
+    }
+
+    /// Activates the group once every peer confirmed the one we computed, and quarantines
+    /// it as soon as one confirms a different one. Either way the session is done.
+    fn settle_confirmations(&self, sessions: &mut HashMap<String, EcdsaSession>, session_id: &str) {
+        let Some(session) = sessions.get(session_id) else { return };
+        let Some(ours) = &session.confirmation else { return };
+
+        let mut disagreeing = session.confirmations.iter()
+            .filter(|(_, digest)| *digest != ours)
+            .map(|(peer_id, _)| peer_id.clone())
+            .collect::<Vec<_>>();
+        disagreeing.sort();
+        let status = if let Some(reason) = &session.mismatch {
+            GroupStatus::Quarantined(reason.clone())
+        } else if !disagreeing.is_empty() {
+            GroupStatus::Quarantined(format!("{} computed a different group", disagreeing.join(", ")))
+        } else if session.participant_ids.iter().all(|p| *p == self.node_id || session.confirmations.contains_key(p)) {
+            GroupStatus::Active
+        } else {
+            return;
+        };
+
+        let session = sessions.remove(session_id).expect("checked above");
+        self.set_group_status(&session, session_id, status);
+    }
+
+    /// Keeps a quarantined group and its share for an operator to look into, but never lets it sign
+    fn set_group_status(&self, session: &EcdsaSession, session_id: &str, status: GroupStatus) {
+        if let GroupStatus::Quarantined(reason) = &status {
+            println!("☣️ ECDSA keygen {session_id} quarantined on {}: {reason}", self.node_id);
+        }
+        let op_did = OperationalDID(session.operational_did.clone());
+        if let Err(e) = self.did_registry.set_group_status(&op_did, session_id, status) {
+            println!("⚠️ Could not update group of ECDSA keygen {session_id}: {e:?}");
+        }
+    }
+
+    fn open_session(
+        &self,
+        session_id: &str,
+        op_did: &str,
+        participant_ids: Vec<String>,
+        state: EcdsaSessionState,
+        mut outgoing: HashMap<String, EcdsaMessage>,
+    ) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.contains_key(session_id) {
+            return Err("Session already exists".into());
+        }
+
+        let mut session = EcdsaSession {
+            operational_did: op_did.to_string(),
+            participant_ids,
+            state,
+            round1_received: HashMap::new(),
+            round2_received: HashMap::new(),
+            confirmation: None,
+            confirmations: HashMap::new(),
+            mismatch: None,
+            events: tokio::sync::watch::channel(0).0,
+        };
+
+        // Our own dealing is delivered locally, everything else goes over the relay
+        if let Some(own) = outgoing.remove(&self.node_id) {
+            session.round1_received.insert(self.node_id.clone(), own);
+        }
+        for (peer_id, msg) in outgoing {
+            self.send(session_id, &peer_id, &msg)?;
+        }
+
+        if let Some(early) = self.pending.lock().unwrap().remove(session_id) {
+            for (from, payload) in early {
+                self.record_message(session_id, &mut session, &from, payload)?;
+            }
+        }
+
+        sessions.insert(session_id.to_string(), session);
+        Ok(())
+    }
+
+    /// Seals `msg` to `peer_id` and hands it to the relay
+    fn send(&self, session_id: &str, peer_id: &str, msg: &EcdsaMessage) -> Result<(), String> {
+        let recipient = self.peer_keys(peer_id)?;
+        let sealed = SealedEcdsaMessage::seal(&self.identity, session_id, &self.node_id, peer_id, &recipient, msg)?;
+        let payload = bincode::serialize(&sealed).map_err(|_| "serialize failed")?;
+        self.relay.send(MessageType::Signing, session_id, peer_id, payload)
+            .map_err(|e| format!("relay failed: {e:?}"))
+    }
+
+    fn peer_keys(&self, node_id: &str) -> Result<IdentityPublicKeys, String> {
+        self.directory.get(node_id).ok_or_else(|| format!("No pinned identity for {node_id}"))
+    }
+
+    /// Checks a message is from a participant and signed by it, and opens it
+    fn record_message(&self, session_id: &str, session: &mut EcdsaSession, from: &str, payload: Vec<u8>) -> Result<(), String> {
+        if !session.participant_ids.iter().any(|p| p == from) {
+            return Err(format!("{from} is not a session participant"));
+        }
+
+        let sealed: SealedEcdsaMessage = bincode::deserialize(&payload).map_err(|_| "malformed ECDSA message")?;
+        let msg = sealed.open(&self.identity, session_id, from, &self.node_id, &self.peer_keys(from)?)?;
+        let round = match msg {
+            EcdsaMessage::KeygenDeal { .. } | EcdsaMessage::PresignDeal { .. } => &mut session.round1_received,
+            EcdsaMessage::PresignOpen { .. } => &mut session.round2_received,
+            EcdsaMessage::KeygenConfirm { digest } => return Self::record_confirmation(session, from, digest),
+        };
+
+        // A resent message is ignored; a different one for the same round is equivocation
+        match round.get(from) {
+            Some(existing) if *existing == msg => Ok(()),
+            Some(_) => Err(format!("{from} sent two different messages for the same round")),
+            None => {
+                round.insert(from.to_string(), msg);
+                session.events.send_modify(|recorded| *recorded += 1);
+                Ok(())
+            }
+        }
+    }
+
+    /// Takes a peer's keygen confirmation on record. It may arrive before we have finalized
+    /// ourselves; it is compared with ours once we have. A peer that confirms two
+    /// different groups is a mismatch as well.
+    fn record_confirmation(session: &mut EcdsaSession, from: &str, digest: Vec<u8>) -> Result<(), String> {
+        if !matches!(session.state, EcdsaSessionState::Keygen(_)) {
+            return Err(format!("{from} confirmed a group in a presign session"));
+        }
+        match session.confirmations.get(from) {
+            Some(existing) if *existing == digest => return Ok(()),
+            Some(_) => { session.mismatch.get_or_insert(format!("{from} confirmed two different groups")); }
+            None => { session.confirmations.insert(from.to_string(), digest); }
+        }
+        session.events.send_modify(|recorded| *recorded += 1);
+        Ok(())
+    }
+}
+
+/// Loads this node's ECDSA key share for an operational DID from the vault
+pub fn load_key_share(registry: &OperationalDIDRegistry, op_did: &str) -> Result<EcdsaKeyShare, String> {
+    let encoded = vault::get_ecdsa_share(registry, op_did)?;
+    let bytes = base64::decode(&encoded).map_err(|_| "bad base64")?;
+    bincode::deserialize(&bytes).map_err(|_| "bad ECDSA key share".to_string())
+}
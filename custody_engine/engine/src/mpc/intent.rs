@@ -0,0 +1,160 @@
+// File: src/mpc/intent.rs
+
+use serde_json::Value;
+
+use crate::policy::PolicyChange;
+use crate::registry::{OperationalDID, OperationalDIDRegistry};
+use crate::verification;
+
+/// Domain tags prepended to the payload before signing. JWS signing inputs start
+/// with a base64url JSON header ("ey..."), so no tagged message can look like one.
+pub const DID_AUTH_DOMAIN: &[u8] = b"custody-did-auth-v1\0";
+pub const VC_PROOF_DOMAIN: &[u8] = b"custody-vc-proof-v1\0";
+pub const VP_PROOF_DOMAIN: &[u8] = b"custody-vp-proof-v1\0";
+pub const POLICY_DOMAIN: &[u8] = b"custody-policy-v1\0";
+
+/// Every registered domain tag; raw payloads may not start with any of them
+pub const DOMAIN_TAGS: &[&[u8]] = &[DID_AUTH_DOMAIN, VC_PROOF_DOMAIN, VP_PROOF_DOMAIN, POLICY_DOMAIN];
+
+/// Intent names as used in signing policies
+pub const INTENT_NAMES: &[&str] = &["did_auth", "vc_proof", "vp_proof", "jws", "raw", "policy_change"];
+
+/// Longest DID-auth challenge a vault will sign
+pub const MAX_CHALLENGE_LEN: usize = 512;
+
+/// What a signing request is for. The vault validates the payload for the
+/// intent and signs domain-separated bytes, so one kind of payload cannot be
+/// passed off as another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SigningIntent {
+    DidAuth,                           // Payload: the verifier's challenge
+    VcProof,                           // Payload: VC JSON (any existing proof is ignored)
+    VpProof,                           // Payload: VP JSON (any existing proof is ignored)
+    Jws,                               // Payload: JWS signing input `header.payload`
+    Raw { justification: String },     // Payload signed as-is; needs policy and a reason
+    PolicyChange,                      // Payload: PolicyChange JSON for this DID
+}
+
+impl SigningIntent {
+    /// Maps the proto enum (shared numbering in mpc.proto and vault.proto)
+    pub fn from_proto(code: i32, justification: &str) -> Result<Self, String> {
+        match code {
+            1 => Ok(SigningIntent::DidAuth),
+            2 => Ok(SigningIntent::VcProof),
+            3 => Ok(SigningIntent::VpProof),
+            4 => Ok(SigningIntent::Jws),
+            5 => Ok(SigningIntent::Raw { justification: justification.to_string() }),
+            6 => Ok(SigningIntent::PolicyChange),
+            0 => Err("Signing intent is required".into()),
+            other => Err(format!("Unknown signing intent {other}")),
+        }
+    }
+
+    pub fn to_proto(&self) -> i32 {
+        match self {
+            SigningIntent::DidAuth => 1,
+            SigningIntent::VcProof => 2,
+            SigningIntent::VpProof => 3,
+            SigningIntent::Jws => 4,
+            SigningIntent::Raw { .. } => 5,
+            SigningIntent::PolicyChange => 6,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        INTENT_NAMES[self.to_proto() as usize - 1]
+    }
+
+    pub fn justification(&self) -> &str {
+        match self {
+            SigningIntent::Raw { justification } => justification,
+            _ => "",
+        }
+    }
+}
+
+/// Validates `payload` for the intent and returns the bytes the group actually signs
+pub fn signing_bytes(intent: &SigningIntent, payload: &[u8]) -> Result<Vec<u8>, String> {
+    match intent {
+        SigningIntent::DidAuth => {
+            if payload.is_empty() || payload.len() > MAX_CHALLENGE_LEN {
+                return Err(format!("DID auth challenge must be 1..={MAX_CHALLENGE_LEN} bytes"));
+            }
+            Ok([DID_AUTH_DOMAIN, payload].concat())
+        }
+        SigningIntent::VcProof => Ok([VC_PROOF_DOMAIN, &credential_input(payload, "VerifiableCredential")?].concat()),
+        SigningIntent::VpProof => Ok([VP_PROOF_DOMAIN, &credential_input(payload, "VerifiablePresentation")?].concat()),
+        SigningIntent::Jws => {
+            validate_jws_input(payload)?;
+            Ok(payload.to_vec())
+        }
+        SigningIntent::Raw { justification } => {
+            if justification.trim().is_empty() {
+                return Err("Raw signing requires a justification".into());
+            }
+            // Raw bytes are signed untagged, so they must not pass for another intent's input
+            if DOMAIN_TAGS.iter().any(|tag| payload.starts_with(tag)) {
+                return Err("Raw payload starts with a reserved domain tag".into());
+            }
+            if validate_jws_input(payload).is_ok() {
+                return Err("Raw payload is a JWS signing input; use the jws intent".into());
+            }
+            Ok(payload.to_vec())
+        }
+        SigningIntent::PolicyChange => {
+            let change: PolicyChange = serde_json::from_slice(payload).map_err(|e| format!("Invalid policy change: {e}"))?;
+            change.policy.validate()?;
+            Ok([POLICY_DOMAIN, payload].concat())
+        }
+    }
+}
+
+/// Vault-side gate for intents that depend on the DID: raw signing is audited
+/// (the policy check decides whether it is allowed at all) and a policy change
+/// may only be signed by the DID it changes
+pub fn authorize(registry: &OperationalDIDRegistry, op_did: &str, intent: &SigningIntent, payload: &[u8]) -> Result<(), String> {
+    match intent {
+        SigningIntent::Raw { justification } => {
+            registry.audit_event(&OperationalDID(op_did.to_string()), format!("raw signing: {justification}"));
+        }
+        SigningIntent::PolicyChange => {
+            let change: PolicyChange = serde_json::from_slice(payload).map_err(|e| format!("Invalid policy change: {e}"))?;
+            if change.operational_did != op_did {
+                return Err("Policy change is for a different DID".into());
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Checks the JSON is the expected credential type and returns its canonical signing input
+fn credential_input(payload: &[u8], expected_type: &str) -> Result<Vec<u8>, String> {
+    let json = std::str::from_utf8(payload).map_err(|_| "Credential is not UTF-8")?;
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Invalid credential JSON: {e}"))?;
+
+    let typed = match &value["type"] {
+        Value::String(t) => t == expected_type,
+        Value::Array(types) => types.iter().any(|t| t.as_str() == Some(expected_type)),
+        _ => false,
+    };
+    if !typed {
+        return Err(format!("Payload is not a {expected_type}"));
+    }
+
+    verification::vc_signing_input(json)
+}
+
+/// A JWS signing input is `base64url(header) '.' payload` with an EdDSA JSON header
+fn validate_jws_input(payload: &[u8]) -> Result<(), String> {
+    let input = std::str::from_utf8(payload).map_err(|_| "JWS signing input is not UTF-8")?;
+    let (header_b64, _) = input.split_once('.').ok_or("JWS signing input must be header.payload")?;
+
+    let header_bytes = base64::decode_config(header_b64, base64::URL_SAFE_NO_PAD)
+        .map_err(|_| "bad JWS header encoding")?;
+    let header: Value = serde_json::from_slice(&header_bytes).map_err(|_| "bad JWS header")?;
+    if !header.is_object() || header["alg"].as_str() != Some("EdDSA") {
+        return Err("JWS header must be a JSON object with alg EdDSA".into());
+    }
+    Ok(())
+}
@@ -0,0 +1,174 @@
+//! Declarative signing policy for an operational DID (issuer DIDs are operational
+//! DIDs too). Every vault evaluates it locally before releasing a partial
+//! signature, and only accepts a new policy signed by the DID's current group key.
+
+use chrono::Timelike;
+use serde::{Deserialize, Serialize};
+
+use crate::mpc::intent::{self, SigningIntent};
+use crate::registry::{ApprovalPolicy, OperationalDID, OperationalDIDRegistry};
+use crate::verification;
+
+/// Caps how many signatures a DID's vault releases per sliding window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub max_signatures: u32,
+    pub window_secs: u64,
+}
+
+/// UTC hours `[start_hour, end_hour)` in which signing is allowed; wraps past midnight when start > end
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start_hour: u8,
+    pub end_hour: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SigningPolicy {
+    pub version: u64,                      // Must increase with every change
+    pub threshold: u8,                     // Signers required; also the DKG threshold
+    pub participants: Vec<String>,         // Custody nodes the group is built from; empty = all discovered
+    pub allowed_intents: Vec<String>,      // Intent names, see `SigningIntent::name`
+    pub rate_limit: Option<RateLimit>,
+    pub time_windows: Vec<TimeWindow>,     // Empty = any time
+    pub approval: Option<ApprovalPolicy>,  // Human approvers, if any
+    pub approval_intents: Vec<String>,     // Intents that need the approval quorum; empty = all
+}
+
+impl Default for SigningPolicy {
+    fn default() -> Self {
+        SigningPolicy {
+            version: 0,
+            threshold: 2,
+            participants: vec![],
+            allowed_intents: ["did_auth", "vc_proof", "vp_proof", "jws"].map(String::from).to_vec(),
+            rate_limit: None,
+            time_windows: vec![],
+            approval: None,
+            approval_intents: vec![],
+        }
+    }
+}
+
+/// The document a group signs to change its own policy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyChange {
+    pub operational_did: String,
+    pub policy: SigningPolicy,
+}
+
+impl SigningPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.threshold == 0 {
+            return Err("threshold must be at least 1".into());
+        }
+        if !self.participants.is_empty() && self.participants.len() < self.threshold as usize {
+            return Err("fewer participants than the threshold".into());
+        }
+        for name in self.allowed_intents.iter().chain(&self.approval_intents) {
+            if !intent::INTENT_NAMES.contains(&name.as_str()) {
+                return Err(format!("unknown intent {name}"));
+            }
+        }
+        if self.time_windows.iter().any(|w| w.start_hour > 23 || w.end_hour > 24) {
+            return Err("time window hours must be within 0..=24".into());
+        }
+        if let Some(limit) = &self.rate_limit {
+            if limit.max_signatures == 0 || limit.window_secs == 0 {
+                return Err("rate limit must allow at least one signature per window".into());
+            }
+        }
+        if let Some(approval) = &self.approval {
+            if approval.quorum == 0 || approval.quorum as usize > approval.approvers.len() {
+                return Err("approval quorum must be between 1 and the number of approvers".into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Policy changes are always signable, or a DID could lock itself out
+    pub fn allows_intent(&self, intent: &SigningIntent) -> bool {
+        *intent == SigningIntent::PolicyChange || self.allowed_intents.iter().any(|i| i == intent.name())
+    }
+
+    /// Whether the approval quorum applies; `None` stands for untyped requests (ECDSA digests)
+    pub fn needs_approval(&self, intent: Option<&SigningIntent>) -> bool {
+        self.approval.is_some() && match intent {
+            Some(intent) => self.approval_intents.is_empty() || self.approval_intents.iter().any(|i| i == intent.name()),
+            None => true,
+        }
+    }
+
+    pub fn in_time_window(&self, hour: u8) -> bool {
+        self.time_windows.is_empty() || self.time_windows.iter().any(|w| match w.start_hour <= w.end_hour {
+            true => (w.start_hour..w.end_hour).contains(&hour),
+            false => hour >= w.start_hour || hour < w.end_hour,
+        })
+    }
+}
+
+/// Vault-side check run before every partial signature
+pub fn check_signing(registry: &OperationalDIDRegistry, op_did: &str, intent: &SigningIntent, signers: &[String]) -> Result<(), String> {
+    let op_did = OperationalDID(op_did.to_string());
+    let policy = registry.get_signing_policy(&op_did).unwrap_or_default();
+
+    if !policy.allows_intent(intent) {
+        return Err(format!("Policy does not allow {} signing", intent.name()));
+    }
+    if !policy.in_time_window(chrono::Utc::now().hour() as u8) {
+        return Err("Signing is outside the policy's time window".into());
+    }
+    if signers.len() < policy.threshold as usize {
+        return Err(format!("Policy requires {} signers, got {}", policy.threshold, signers.len()));
+    }
+    if !policy.participants.is_empty() {
+        if let Some(outsider) = signers.iter().find(|s| !policy.participants.contains(s) && !s.starts_with(crate::mpc::device::DEVICE_PREFIX)) {
+            return Err(format!("{outsider} is not a policy participant"));
+        }
+    }
+    Ok(())
+}
+
+/// Counts a signature against the policy's rate limit. Vaults take the slot last, once
+/// every other check passed, so refused requests do not use up the window.
+pub fn take_rate_slot(registry: &OperationalDIDRegistry, op_did: &str) -> Result<(), String> {
+    let op_did = OperationalDID(op_did.to_string());
+    let policy = registry.get_signing_policy(&op_did).unwrap_or_default();
+
+    if let Some(limit) = &policy.rate_limit {
+        if !registry.take_rate_slot(&op_did, limit.max_signatures, limit.window_secs) {
+            return Err("Policy rate limit reached".into());
+        }
+    }
+    Ok(())
+}
+
+/// Bytes the group signs (POLICY_CHANGE intent) to authorize a new policy
+pub fn change_payload(op_did: &str, policy: &SigningPolicy) -> Result<Vec<u8>, String> {
+    serde_json::to_vec(&PolicyChange { operational_did: op_did.to_string(), policy: policy.clone() })
+        .map_err(|e| format!("Serialize failed: {e}"))
+}
+
+/// Installs a policy change after checking it is newer than the current policy
+/// and signed by the DID's current group key
+pub fn apply_change(registry: &OperationalDIDRegistry, change_payload: &[u8], signature: &[u8]) -> Result<SigningPolicy, String> {
+    let change: PolicyChange = serde_json::from_slice(change_payload).map_err(|e| format!("Invalid policy change: {e}"))?;
+    change.policy.validate()?;
+
+    let op_did = OperationalDID(change.operational_did.clone());
+    let current = registry.get_signing_policy(&op_did).unwrap_or_default();
+    if change.policy.version <= current.version {
+        return Err(format!("Policy version {} is not newer than {}", change.policy.version, current.version));
+    }
+
+    let message = intent::signing_bytes(&SigningIntent::PolicyChange, change_payload)?;
+    let verified = verification::verify_raw(registry, &change.operational_did, &message, signature)?;
+    if !verified.current {
+        return Err("Policy change was signed by a retired group key".into());
+    }
+
+    registry.set_signing_policy(&op_did, change.policy.clone())
+        .map_err(|e| format!("registry update failed: {e:?}"))?;
+    Ok(change.policy)
+}
@@ -5,6 +5,8 @@ use std::sync::Mutex;
 use crate::vault::Vault;
 use crate::types::{OperationalDID, RootDID, VerifiableCredential};
 use crate::error::CustodyError;
+use crate::policy::SigningPolicy;
+use serde::{Serialize, Deserialize};
 use crate::audit::{AuditRecord, AuditEventType, AUDIT, now_rfc3339};
 use blake3::Hasher;
 
@@ -15,6 +17,11 @@ pub struct OperationalDIDEntry {
     pub mpc_group: Option<MPCGroupDescriptor>,   // NEW: Group-wide MPC info
     pub audit_trail: VecDeque<AuditRecord>,  // Local in-memory audit trail for VC changes (rotation, revocation)
     pub did_document: Option<Vec<u8>>, // Stores raw DID document (JSON-LD)
+    pub key_history: Vec<KeyEpoch>,    // Retired group keys, oldest first
+    pub derivation: Option<KeyDerivation>, // Set when the DID's key is a child of another DID's group key
+    pub devices: Vec<DeviceRecord>,    // External devices holding a share for this DID
+    pub policy: Option<SigningPolicy>, // Signing policy every vault enforces; None = defaults
+    pub signing_log: VecDeque<u64>,    // Unix times of recent signatures, for the policy rate limit
 }
 
 /// Central registry for managing operational DIDs and their vaults.
@@ -27,14 +34,83 @@ pub struct MPCGroupDescriptor {
     pub group_id: String,                       // Unique identifier for the MPC group
     pub members: Vec<MPCMemberDescriptor>,      // All vaults/nodes in the group
     pub threshold: u8,                          // Minimum signatures required
-    pub dkg_protocol: Option<String>, // e.g., "frost-dkg-v1"
+    pub dkg_protocol: Option<String>, // e.g., "frost-ed25519-dkg-v1", selects the ciphersuite
     pub session_state: Option<Vec<u8>>, // optional serialized DKG or signing session state
+    pub public_key_package: Option<Vec<u8>>, // serialized FROST PublicKeyPackage from the DKG
+    pub status: GroupStatus,                    // Only an active group signs
 }
 
+/// Whether a group may sign. Each node finalizes a DKG by itself, so a new group waits
+/// until every custody node has confirmed it computed the same public key package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupStatus {
+    Pending,                                    // Finalized here; confirmations outstanding
+    Active,                                     // Every custody node confirmed the same group
+    Quarantined(String),                        // Nodes disagree; kept for the operator, never signs
+}
+
+impl MPCGroupDescriptor {
+    /// Fails unless the group may sign
+    pub fn check_active(&self) -> Result<(), String> {
+        match &self.status {
+            GroupStatus::Active => Ok(()),
+            GroupStatus::Pending => Err(format!("Group {} is waiting for every node to confirm it", self.group_id)),
+            GroupStatus::Quarantined(reason) => Err(format!("Group {} is quarantined: {reason}", self.group_id)),
+        }
+    }
+}
+
+/// A group key the DID has signed with, kept after rotation so old signatures still verify
+#[derive(Debug, Clone)]
+pub struct KeyEpoch {
+    pub epoch: u32,                             // 0 = first provisioned group
+    pub group_id: String,
+    pub dkg_protocol: Option<String>,
+    pub public_key_package: Option<Vec<u8>>,
+    pub retired_at: Option<String>,             // None for the active group
+}
+
+/// Links a derived DID to the DKG group whose shares sign for it
+#[derive(Debug, Clone)]
+pub struct KeyDerivation {
+    pub parent_did: String,                     // DID that owns the DKG shares
+    pub parent_group_id: String,                // Parent group the tweak was computed from
+    pub path: String,                           // Public derivation path, e.g. "m/dids/42"
+}
+
+/// An external device (phone, hardware key) that holds its own share
+#[derive(Debug, Clone)]
+pub struct DeviceRecord {
+    pub device_id: String,
+    pub participant_id: String,                 // "device:<device_id>@<home_node>", used in DKG and signing
+    pub identity_public_key: Vec<u8>,           // Ed25519 key the device proves on every session; signs its DKG messages
+    pub encryption_public_key: Vec<u8>,         // secp256k1 key DKG round-2 packages to the device are sealed to
+    pub required_for_signing: bool,             // Vaults refuse to sign without this device's share
+    pub registered_at: String,
+}
+
+/// A human approver and the Ed25519 key they approve with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Approver {
+    pub approver_id: String,
+    pub public_key: Vec<u8>,                    // 32-byte Ed25519 verifying key
+}
+
+/// M-of-N approvals every vault requires before releasing a partial signature
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    pub approvers: Vec<Approver>,
+    pub quorum: u8,                             // M: distinct approvals required
+}
+
+/// A participant holding a share of a group, as every component resolves it
+#[derive(Debug, Clone)]
 pub struct MPCMemberDescriptor {
-    pub vault_reference: String,                // Vault ID or address
-    pub custody_node_id: String,                // Node identifier (if multi-node)
-    pub shard_index: u8,                        // Index in the threshold scheme
+    pub node_id: String,                        // Participant ID the node is reached under
+    pub identifier: Vec<u8>,                    // Serialized FROST identifier the share is under
+    pub share_index: Option<u32>,               // Shamir index of an ECDSA share, by committee position
+    pub identity_key: Vec<u8>,                  // Ed25519 identity key of the participant
+    pub public_share: String,                   // Base64 verifying share
 }
 
 
@@ -72,6 +148,11 @@ impl OperationalDIDRegistry {
             mpc_group: None,
             audit_trail: VecDeque::new(),
             did_document: Some(did_doc),
+            key_history: Vec::new(),
+            derivation: None,
+            devices: Vec::new(),
+            policy: None,
+            signing_log: VecDeque::new(),
         };
     
         entries.insert(op_did, entry);
@@ -172,6 +253,11 @@ impl OperationalDIDRegistry {
         Some(record.vcs.iter().map(|vc| vc.vc_json.clone()).collect())
     }
 
+    /// Every registered operational DID
+    pub fn operational_dids(&self) -> Vec<OperationalDID> {
+        self.entries.read().unwrap().keys().cloned().collect()
+    }
+
     pub fn get_mpc_group(&self, op_did: &OperationalDID) -> Option<MPCGroupDescriptor> {
         self.entries.lock().unwrap().get(op_did).and_then(|entry| entry.mpc_group.clone())
     }
@@ -179,10 +265,129 @@ impl OperationalDIDRegistry {
     pub fn set_mpc_group(&self, op_did: &OperationalDID, group: MPCGroupDescriptor) -> Result<(), CustodyError> {
         let mut entries = self.entries.lock().unwrap();
         let entry = entries.get_mut(op_did).ok_or_else(|| CustodyError::NotFound("DID not found".into()))?;
+
+        // A new group retires the old key; keep it so earlier signatures still verify
+        if let Some(old) = entry.mpc_group.take().filter(|old| old.group_id != group.group_id) {
+            entry.key_history.push(KeyEpoch {
+                epoch: entry.key_history.len() as u32,
+                group_id: old.group_id,
+                dkg_protocol: old.dkg_protocol,
+                public_key_package: old.public_key_package,
+                retired_at: Some(now_rfc3339()),
+            });
+        }
+
         entry.mpc_group = Some(group);
         Ok(())
     }
 
+    /// Sets the status of the current group if it is `group_id`. Returns whether it was.
+    pub fn set_group_status(&self, op_did: &OperationalDID, group_id: &str, status: GroupStatus) -> Result<bool, CustodyError> {
+        let mut entries = self.entries.write().unwrap();
+        let entry = entries.get_mut(op_did).ok_or_else(|| CustodyError::NotFound("DID not found".into()))?;
+        match entry.mpc_group.as_mut().filter(|group| group.group_id == group_id) {
+            Some(group) => {
+                group.status = status;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Drops the current group if it is `group_id`, e.g. after its DKG was aborted.
+    /// Returns whether anything was removed.
+    pub fn withdraw_mpc_group(&self, op_did: &OperationalDID, group_id: &str) -> Result<bool, CustodyError> {
+        let mut entries = self.entries.write().unwrap();
+        let entry = entries.get_mut(op_did).ok_or_else(|| CustodyError::NotFound("DID not found".into()))?;
+        if entry.mpc_group.as_ref().is_some_and(|group| group.group_id == group_id) {
+            entry.mpc_group = None;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    pub fn set_key_derivation(&self, op_did: &OperationalDID, derivation: KeyDerivation) -> Result<(), CustodyError> {
+        let mut entries = self.entries.write().unwrap();
+        let entry = entries.get_mut(op_did).ok_or_else(|| CustodyError::NotFound("DID not found".into()))?;
+        entry.derivation = Some(derivation);
+        Ok(())
+    }
+
+    pub fn get_key_derivation(&self, op_did: &OperationalDID) -> Option<KeyDerivation> {
+        self.entries.lock().unwrap().get(op_did).and_then(|entry| entry.derivation.clone())
+    }
+
+    /// Register (or re-register) a device for a DID
+    pub fn register_device(&self, op_did: &OperationalDID, device: DeviceRecord) -> Result<(), CustodyError> {
+        let mut entries = self.entries.write().unwrap();
+        let entry = entries.get_mut(op_did).ok_or_else(|| CustodyError::NotFound("DID not found".into()))?;
+        entry.devices.retain(|d| d.device_id != device.device_id);
+        entry.devices.push(device);
+        Ok(())
+    }
+
+    pub fn get_devices(&self, op_did: &OperationalDID) -> Vec<DeviceRecord> {
+        self.entries.lock().unwrap().get(op_did).map(|entry| entry.devices.clone()).unwrap_or_default()
+    }
+
+    /// Looks up a device by participant ID across all DIDs
+    pub fn find_device(&self, participant_id: &str) -> Option<DeviceRecord> {
+        self.entries.lock().unwrap().values()
+            .flat_map(|entry| entry.devices.iter())
+            .find(|d| d.participant_id == participant_id)
+            .cloned()
+    }
+
+    pub fn set_signing_policy(&self, op_did: &OperationalDID, policy: SigningPolicy) -> Result<(), CustodyError> {
+        let mut entries = self.entries.write().unwrap();
+        let entry = entries.get_mut(op_did).ok_or_else(|| CustodyError::NotFound("DID not found".into()))?;
+        entry.policy = Some(policy);
+        Ok(())
+    }
+
+    pub fn get_signing_policy(&self, op_did: &OperationalDID) -> Option<SigningPolicy> {
+        self.entries.lock().unwrap().get(op_did).and_then(|entry| entry.policy.clone())
+    }
+
+    /// Approvers from the DID's signing policy
+    pub fn get_approval_policy(&self, op_did: &OperationalDID) -> Option<ApprovalPolicy> {
+        self.get_signing_policy(op_did).and_then(|policy| policy.approval)
+    }
+
+    /// Records a signature if fewer than `max` were made in the last `window_secs`; false when the limit is hit
+    pub fn take_rate_slot(&self, op_did: &OperationalDID, max: u32, window_secs: u64) -> bool {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let mut entries = self.entries.write().unwrap();
+        let Some(entry) = entries.get_mut(op_did) else { return false };
+
+        while entry.signing_log.front().map_or(false, |t| now.saturating_sub(*t) >= window_secs) {
+            entry.signing_log.pop_front();
+        }
+        if entry.signing_log.len() >= max as usize {
+            return false;
+        }
+        entry.signing_log.push_back(now);
+        true
+    }
+
+    /// All group keys for a DID: retired epochs first, then the active group
+    pub fn get_key_epochs(&self, op_did: &OperationalDID) -> Option<Vec<KeyEpoch>> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(op_did)?;
+
+        let mut epochs = entry.key_history.clone();
+        if let Some(group) = &entry.mpc_group {
+            epochs.push(KeyEpoch {
+                epoch: entry.key_history.len() as u32,
+                group_id: group.group_id.clone(),
+                dkg_protocol: group.dkg_protocol.clone(),
+                public_key_package: group.public_key_package.clone(),
+                retired_at: None,
+            });
+        }
+        Some(epochs)
+    }
+
     pub fn set_vault_id(&self, op_did: &OperationalDID, vault_id: String) -> Result<(), CustodyError> {
         let mut entries = self.entries.write().unwrap();
         let entry = entries.get_mut(op_did).ok_or_else(|| CustodyError::NotFound("DID not found".into()))?;
@@ -0,0 +1,311 @@
+//! A cluster of complete custody nodes in one process. Each node has its own registry,
+//! vault records, identity, relay and engines, wired as `main` wires a real node, and
+//! serves the node's gRPC services on a `SimNetwork`. Everything that reaches a node
+//! goes through `tls::channel`, so the orchestrator, the coordinator and the relay
+//! run unchanged: a test drives the cluster with the same calls an operator makes.
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Once};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::transport::Server;
+
+use crate::dkg::engine::DKGEngine;
+use crate::identity::{IdentityDirectory, NodeIdentity};
+use crate::membership::MEMBERSHIP;
+use crate::mpc::approval::ApprovalQueue;
+use crate::mpc::coordinator::MPCSigningCoordinator;
+use crate::mpc::device::DeviceHub;
+use crate::mpc::ecdsa_engine::EcdsaEngine;
+use crate::registry::{OperationalDID, OperationalDIDRegistry, RootDID};
+use crate::relay::{self, RelayClient, RelayHandlers, RelayService, RetryPolicy};
+use crate::service::device_service::CustodyDeviceService;
+use crate::service::dkg_service::CustodyDkgService;
+use crate::service::issuer_service::IssuerService;
+use crate::service::mpc_service::CustodyMpcService;
+use crate::service::vault_service::VaultService;
+use crate::sim::network::{self, SimNetwork};
+use crate::types::VaultRecord;
+use crate::vault;
+
+use vault::custody_vault_server::CustodyVaultServer;
+use mpc::custody_mpc_server::CustodyMpcServer;
+use custodydkg::custody_dkg_server::CustodyDkgServer;
+use issuer::custody_issuer_server::CustodyIssuerServer;
+use custodyrelay::custody_relay_server::CustodyRelayServer;
+use custodydevice::custody_device_server::CustodyDeviceServer;
+
+static VAULT: Once = Once::new();
+
+/// Numbers clusters, so the hosts of concurrently running tests never collide
+static CLUSTERS: AtomicUsize = AtomicUsize::new(0);
+
+/// How to build a simulated cluster
+#[derive(Debug, Clone)]
+pub struct SimConfig {
+    pub nodes: usize,
+    pub seed: u64,                                // Seeds the network's losses
+    pub retry: RetryPolicy,                       // Every node's relay policy
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            nodes: 3,
+            seed: 0,
+            // A simulated peer is either up within milliseconds or cut off on purpose
+            retry: RetryPolicy {
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(100),
+                max_attempts: 8,
+                attempt_timeout: Duration::from_secs(5),
+            },
+        }
+    }
+}
+
+/// One simulated custody node and the state its services share
+pub struct SimNode {
+    pub node_id: String,
+    pub registry: Arc<OperationalDIDRegistry>,
+    pub identity: Arc<NodeIdentity>,
+    pub directory: Arc<IdentityDirectory>,
+    pub relay: Arc<RelayClient>,
+    pub dkg_engine: Arc<DKGEngine>,
+    pub ecdsa_engine: Arc<EcdsaEngine>,
+    pub relay_handlers: Arc<RelayHandlers>,
+    pub device_hub: Arc<DeviceHub>,
+    pub approvals: Arc<ApprovalQueue>,
+    shutdown: Option<oneshot::Sender<()>>,        // Stops the node's server
+}
+
+/// A running cluster. Dropping it stops every node and takes them off the network.
+pub struct SimCluster {
+    pub network: Arc<SimNetwork>,
+    pub nodes: Vec<SimNode>,
+}
+
+impl SimCluster {
+    /// Starts `config.nodes` nodes. Each is enrolled in the membership and knows every
+    /// other node's identity key, as after `custody membership add` on a real cluster.
+    /// Must be called inside a tokio runtime; the nodes' servers run on it.
+    pub fn start(config: SimConfig) -> Result<Self, String> {
+        VAULT.call_once(vault::init_vault);
+        let cluster = CLUSTERS.fetch_add(1, Ordering::SeqCst);
+        let network = SimNetwork::new(config.seed);
+
+        // Step 1: Every node's identity, enrolled and pinned everywhere before anyone talks
+        let node_ids = (1..=config.nodes).map(|i| format!("node-{i}.sim{cluster}")).collect::<Vec<_>>();
+        let identities = node_ids.iter().map(|_| Arc::new(NodeIdentity::generate())).collect::<Vec<_>>();
+        for (node_id, identity) in node_ids.iter().zip(&identities) {
+            MEMBERSHIP.enroll(node_id, identity.public_keys())?;
+        }
+
+        // Step 2: Build and serve each node
+        let mut nodes = Vec::new();
+        for (node_id, identity) in node_ids.iter().zip(&identities) {
+            let directory = Arc::new(IdentityDirectory::new());
+            for (peer, keys) in node_ids.iter().zip(&identities).filter(|(peer, _)| *peer != node_id) {
+                directory.pin(peer, keys.public_keys())?;
+            }
+            let node = SimNode::build(node_id, identity.clone(), directory, &config)?;
+            let node = node.serve(&network)?;
+            nodes.push(node);
+        }
+        println!("🧪 Simulated cluster of {} nodes started", nodes.len());
+
+        Ok(SimCluster { network, nodes })
+    }
+
+    pub fn node_ids(&self) -> Vec<String> {
+        self.nodes.iter().map(|n| n.node_id.clone()).collect()
+    }
+
+    pub fn node(&self, node_id: &str) -> Option<&SimNode> {
+        self.nodes.iter().find(|n| n.node_id == node_id)
+    }
+
+    /// Registers `op_did` on every node, each with a fresh vault record of its own,
+    /// as provisioning does before a DKG
+    pub fn register_did(&self, op_did: &str, root_did: &str) -> Result<(), String> {
+        for node in &self.nodes {
+            let vault_id = format!("vault-{}", uuid::Uuid::new_v4());
+            vault::store_record(&vault_id, &VaultRecord {
+                root_did: root_did.to_string(),
+                op_dids: vec![op_did.to_string()],
+                mpc_shard: None,
+                group_metadata: None,
+                public_keys: vec![],
+                vcs: vec![],
+                bbs_private_key: None,
+                bbs_public_key: None,
+                active_nonce: None,
+                batch_nonces: vec![],
+                ecdsa_share: None,
+                ecdsa_presignatures: Default::default(),
+                shard_epoch: 0,
+                dkg_sessions: Default::default(),
+                consumed_approvals: Default::default(),
+            })?;
+            node.registry
+                .register_operational_did(OperationalDID(op_did.to_string()), RootDID(root_did.to_string()), vault_id, vec![])
+                .map_err(|e| format!("register {op_did} on {} failed: {e:?}", node.node_id))?;
+        }
+        Ok(())
+    }
+
+    /// Stops `node_id`'s server; calls to it fail from then on, as if it crashed
+    pub fn stop(&mut self, node_id: &str) -> Result<(), String> {
+        let node = self.nodes.iter_mut().find(|n| n.node_id == node_id)
+            .ok_or_else(|| format!("{node_id} is not in the cluster"))?;
+        if let Some(shutdown) = node.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        self.network.isolate(node_id);
+        Ok(())
+    }
+}
+
+impl Drop for SimCluster {
+    fn drop(&mut self) {
+        for node in &mut self.nodes {
+            if let Some(shutdown) = node.shutdown.take() {
+                let _ = shutdown.send(());
+            }
+        }
+        self.network.shut_down();
+    }
+}
+
+impl SimNode {
+    /// Wires a node the way `main` does, with fresh in-memory state
+    fn build(node_id: &str, identity: Arc<NodeIdentity>, directory: Arc<IdentityDirectory>, config: &SimConfig) -> Result<Self, String> {
+        let registry = Arc::new(OperationalDIDRegistry::new());
+        let relay = Arc::new(RelayClient::with_policy(identity.clone(), node_id, config.retry.clone()));
+
+        let dkg_engine = Arc::new(DKGEngine::new(
+            registry.clone(),
+            relay.clone(),
+            identity.clone(),
+            directory.clone(),
+            node_id.to_string(),
+        ));
+        let ecdsa_engine = Arc::new(EcdsaEngine::new(
+            registry.clone(),
+            relay.clone(),
+            identity.clone(),
+            directory.clone(),
+            node_id.to_string(),
+        ));
+
+        let relay_handlers = Arc::new(RelayHandlers::new());
+        dkg_engine.register_relay_handlers(&relay_handlers)?;
+        ecdsa_engine.register_relay_handlers(&relay_handlers)?;
+        dkg_engine.watch_relay();
+
+        Ok(SimNode {
+            node_id: node_id.to_string(),
+            registry,
+            identity,
+            directory,
+            relay,
+            dkg_engine,
+            ecdsa_engine,
+            relay_handlers,
+            device_hub: Arc::new(DeviceHub::new()),
+            approvals: Arc::new(ApprovalQueue::new()),
+            shutdown: None,
+        })
+    }
+
+    /// Mounts the node's services, as `main` does, on `network` under its node ID.
+    /// Every call the node handles runs on its behalf, so the calls it makes in turn
+    /// leave from it.
+    fn serve(mut self, network: &Arc<SimNetwork>) -> Result<Self, String> {
+        let vault_service = VaultService { registry: self.registry.clone() };
+        let relay_service = RelayService {
+            handlers: self.relay_handlers.clone(),
+            device_hub: self.device_hub.clone(),
+            identity: self.identity.clone(),
+            directory: self.directory.clone(),
+            local_node_id: self.node_id.clone(),
+            inbox: Arc::new(relay::RelayInbox::new()),
+            relay: self.relay.clone(),
+        };
+        let dkg_service = CustodyDkgService {
+            dkg_engine: self.dkg_engine.clone(),
+            ecdsa_engine: self.ecdsa_engine.clone(),
+        };
+        let mpc_service = CustodyMpcService {
+            coordinator: MPCSigningCoordinator {
+                registry: self.registry.clone(),
+                relay: self.relay.clone(),
+                approvals: self.approvals.clone(),
+                local_node_id: self.node_id.clone(),
+            },
+        };
+        let device_service = CustodyDeviceService {
+            registry: self.registry.clone(),
+            hub: self.device_hub.clone(),
+            relay: self.relay.clone(),
+            dkg_engine: self.dkg_engine.clone(),
+            local_node_id: self.node_id.clone(),
+        };
+        let issuer_service = IssuerService {};
+
+        let incoming = UnboundedReceiverStream::new(network.listen(&self.node_id)?).map(Ok::<_, io::Error>);
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        let node_id = self.node_id.clone();
+        let server = Server::builder()
+            .layer(tower::layer::layer_fn(move |inner| OnBehalfOf { node_id: node_id.clone(), inner }))
+            .add_service(CustodyRelayServer::new(relay_service))
+            .add_service(CustodyDkgServer::new(dkg_service))
+            .add_service(CustodyVaultServer::new(vault_service))
+            .add_service(CustodyMpcServer::new(mpc_service))
+            .add_service(CustodyIssuerServer::new(issuer_service))
+            .add_service(CustodyDeviceServer::new(device_service))
+            .serve_with_incoming_shutdown(incoming, async { let _ = stopped.await; });
+
+        let node_id = self.node_id.clone();
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                println!("❌ Simulated node {node_id} stopped: {e}");
+            }
+        });
+        self.shutdown = Some(shutdown);
+        Ok(self)
+    }
+}
+
+/// Runs each call a node serves on the node's behalf (see `network::on_behalf_of`)
+#[derive(Clone)]
+struct OnBehalfOf<S> {
+    node_id: String,
+    inner: S,
+}
+
+impl<S, R> tower::Service<R> for OnBehalfOf<S>
+where
+    S: tower::Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let (node_id, response) = (self.node_id.clone(), self.inner.call(request));
+        Box::pin(async move { network::on_behalf_of(&node_id, response).await })
+    }
+}
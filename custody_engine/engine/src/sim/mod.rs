@@ -0,0 +1,21 @@
+//! In-process custody clusters for end-to-end tests. `network` simulates the links
+//! between nodes; `cluster` starts complete nodes on it.
+
+pub mod cluster;
+pub mod network;
+
+pub use cluster::{SimCluster, SimConfig, SimNode};
+pub use network::SimNetwork;
+
+use crate::identity::NodeIdentity;
+use crate::membership::MEMBERSHIP;
+
+/// Enrolls custody nodes for tests that run the protocols without a cluster, each under
+/// a fresh identity. A node that is enrolled already keeps its enrollment.
+pub fn enroll_nodes<S: AsRef<str>>(nodes: &[S]) {
+    for node in nodes.iter().map(AsRef::as_ref) {
+        if MEMBERSHIP.get(node).is_none() {
+            let _ = MEMBERSHIP.enroll(node, NodeIdentity::generate().public_keys());
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! In-memory network for simulated nodes. Every connection is a pair of in-process
+//! pipes; the network decides what happens to the bytes on them. A link can be slow
+//! (every write arrives `latency` later), lossy (a write is lost with probability
+//! `loss`, which breaks the connection and fails the calls on it, the way a reset TCP
+//! connection would) or cut by a partition (connecting fails, open connections break).
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::mpsc;
+use tokio::time::{Instant, Sleep};
+use tonic::transport::server::Connected;
+use tonic::transport::{self, Channel, Endpoint, Uri};
+
+tokio::task_local! {
+    /// Simulated node the current task acts for; its outbound connections start there
+    static LOCAL_NODE: String;
+}
+
+/// Which network each simulated host is on. Hosts are unique across networks, so
+/// clusters of concurrently running tests never reach each other.
+static HOSTS: Lazy<RwLock<HashMap<String, Arc<SimNetwork>>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Runs `fut` as simulated node `node_id`: connections it opens leave from that node
+pub async fn on_behalf_of<F: Future>(node_id: &str, fut: F) -> F::Output {
+    LOCAL_NODE.scope(node_id.to_string(), fut).await
+}
+
+/// Carries the current task's node, if any, into a task about to be spawned
+pub fn inherit<F: Future>(fut: F) -> impl Future<Output = F::Output> {
+    let node = LOCAL_NODE.try_with(|node| node.clone()).ok();
+    async move {
+        match node {
+            Some(node) => LOCAL_NODE.scope(node, fut).await,
+            None => fut.await,
+        }
+    }
+}
+
+/// Simulated node the current task acts for
+pub fn local_node() -> Option<String> {
+    LOCAL_NODE.try_with(|node| node.clone()).ok()
+}
+
+/// The network `host` is on, if it is a simulated host
+pub fn network_of(host: &str) -> Option<Arc<SimNetwork>> {
+    HOSTS.read().unwrap().get(host).cloned()
+}
+
+/// Conditions on the links of a network
+#[derive(Debug, Clone, Default)]
+struct Links {
+    latency: Duration,                              // Every link, unless overridden below
+    link_latency: HashMap<(String, String), Duration>,
+    loss: f64,                                      // Chance a write is lost
+    cut: HashSet<(String, String)>,                 // Unordered pairs, smaller host first
+}
+
+fn pair(a: &str, b: &str) -> (String, String) {
+    if a <= b { (a.to_string(), b.to_string()) } else { (b.to_string(), a.to_string()) }
+}
+
+/// A simulated network. Calls made outside any node (e.g. by a test acting as the
+/// operator) come from outside the cluster over a reliable link: they see the latency
+/// of the network, but no loss, and no partition between nodes keeps them out.
+pub struct SimNetwork {
+    listeners: Mutex<HashMap<String, mpsc::UnboundedSender<SimStream>>>,   // Host → its server
+    links: RwLock<Links>,
+    rng: Mutex<StdRng>,                             // Seeded, so a run's losses repeat
+}
+
+impl SimNetwork {
+    pub fn new(seed: u64) -> Arc<Self> {
+        Arc::new(SimNetwork {
+            listeners: Mutex::new(HashMap::new()),
+            links: RwLock::new(Links::default()),
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        })
+    }
+
+    /// Puts `host` on this network; its server accepts the connections yielded here
+    pub fn listen(self: &Arc<Self>, host: &str) -> Result<mpsc::UnboundedReceiver<SimStream>, String> {
+        let mut listeners = self.listeners.lock().unwrap();
+        let mut hosts = HOSTS.write().unwrap();
+        if hosts.contains_key(host) {
+            return Err(format!("{host} is already on a simulated network"));
+        }
+        let (sender, receiver) = mpsc::unbounded_channel();
+        listeners.insert(host.to_string(), sender);
+        hosts.insert(host.to_string(), self.clone());
+        Ok(receiver)
+    }
+
+    /// Takes every host of this network off it
+    pub fn shut_down(&self) {
+        let mut listeners = self.listeners.lock().unwrap();
+        let mut hosts = HOSTS.write().unwrap();
+        for host in listeners.keys() {
+            hosts.remove(host);
+        }
+        listeners.clear();
+    }
+
+    pub fn hosts(&self) -> Vec<String> {
+        let mut hosts = self.listeners.lock().unwrap().keys().cloned().collect::<Vec<_>>();
+        hosts.sort();
+        hosts
+    }
+
+    /// One-way latency of every link without its own
+    pub fn set_latency(&self, latency: Duration) {
+        self.links.write().unwrap().latency = latency;
+    }
+
+    /// One-way latency between `a` and `b`, both ways
+    pub fn set_link_latency(&self, a: &str, b: &str, latency: Duration) {
+        self.links.write().unwrap().link_latency.insert(pair(a, b), latency);
+    }
+
+    /// Chance, from 0 to 1, that any one write between two nodes is lost
+    pub fn set_loss(&self, loss: f64) {
+        self.links.write().unwrap().loss = loss.clamp(0.0, 1.0);
+    }
+
+    /// Splits the network: no host in one side reaches a host in another. Hosts in no
+    /// side keep reaching everyone.
+    pub fn partition(&self, sides: &[&[&str]]) {
+        let mut links = self.links.write().unwrap();
+        for (i, side) in sides.iter().enumerate() {
+            for other in &sides[i + 1..] {
+                for a in side.iter() {
+                    for b in other.iter() {
+                        links.cut.insert(pair(a, b));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Cuts `host` off from every other host
+    pub fn isolate(&self, host: &str) {
+        let others = self.hosts().into_iter().filter(|h| h != host).collect::<Vec<_>>();
+        let others = others.iter().map(String::as_str).collect::<Vec<_>>();
+        self.partition(&[&[host], &others]);
+    }
+
+    /// Ends every partition
+    pub fn heal(&self) {
+        self.links.write().unwrap().cut.clear();
+    }
+
+    fn is_cut(&self, from: Option<&str>, to: &str) -> bool {
+        from.is_some_and(|from| self.links.read().unwrap().cut.contains(&pair(from, to)))
+    }
+
+    fn latency(&self, from: Option<&str>, to: &str) -> Duration {
+        let links = self.links.read().unwrap();
+        from.and_then(|from| links.link_latency.get(&pair(from, to)).copied()).unwrap_or(links.latency)
+    }
+
+    fn lose(&self, from: Option<&str>, to: &str) -> bool {
+        if from.is_none() || to.is_empty() {
+            return false;
+        }
+        let loss = self.links.read().unwrap().loss;
+        loss > 0.0 && self.rng.lock().unwrap().gen_bool(loss)
+    }
+
+    /// Opens a connection from `from` (`None`: from outside the cluster) to `to`
+    pub async fn connect(self: &Arc<Self>, from: Option<&str>, to: &str) -> io::Result<SimStream> {
+        let listener = self.listeners.lock().unwrap().get(to).cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::ConnectionRefused, format!("{to} is not on the network")))?;
+        if self.is_cut(from, to) {
+            return Err(io::Error::new(io::ErrorKind::ConnectionRefused, format!("{to} is partitioned away")));
+        }
+
+        // The handshake crosses the link once each way
+        tokio::time::sleep(self.latency(from, to) * 2).await;
+
+        let (client, server) = SimStream::pair(self.clone(), from.map(str::to_string), to.to_string());
+        listener.send(server)
+            .map_err(|_| io::Error::new(io::ErrorKind::ConnectionRefused, format!("{to} is down")))?;
+        Ok(client)
+    }
+
+    /// A channel to `host` over this network, from the node the current task acts for
+    pub async fn channel(self: &Arc<Self>, host: &str) -> Result<Channel, transport::Error> {
+        let (network, from, to) = (self.clone(), local_node(), host.to_string());
+        Endpoint::from_shared(format!("http://{host}"))?
+            .connect_with_connector(tower::service_fn(move |_: Uri| {
+                let (network, from, to) = (network.clone(), from.clone(), to.clone());
+                async move { network.connect(from.as_deref(), &to).await }
+            }))
+            .await
+    }
+}
+
+/// Bytes in flight on one direction of a connection
+type Segment = (Instant, Vec<u8>);             // Arrival time, data
+
+/// One end of a simulated connection
+pub struct SimStream {
+    network: Arc<SimNetwork>,
+    from: Option<String>,                      // This end's host; `None` outside the cluster
+    to: String,                                // The other end's host
+    outbound: Option<mpsc::UnboundedSender<Segment>>,
+    inbound: mpsc::UnboundedReceiver<Segment>,
+    arriving: Option<(Pin<Box<Sleep>>, Vec<u8>)>,
+    readable: VecDeque<u8>,
+    broken: Arc<AtomicBool>,                   // Shared by both ends
+}
+
+impl SimStream {
+    fn pair(network: Arc<SimNetwork>, client: Option<String>, server: String) -> (SimStream, SimStream) {
+        let (to_server, from_client) = mpsc::unbounded_channel();
+        let (to_client, from_server) = mpsc::unbounded_channel();
+        let broken = Arc::new(AtomicBool::new(false));
+        let client_end = SimStream {
+            network: network.clone(),
+            from: client.clone(),
+            to: server.clone(),
+            outbound: Some(to_server),
+            inbound: from_server,
+            arriving: None,
+            readable: VecDeque::new(),
+            broken: broken.clone(),
+        };
+        let server_end = SimStream {
+            network,
+            from: Some(server),
+            to: client.unwrap_or_default(),
+            outbound: Some(to_client),
+            inbound: from_client,
+            arriving: None,
+            readable: VecDeque::new(),
+            broken,
+        };
+        (client_end, server_end)
+    }
+
+    /// Fails once the connection broke or a partition came between its ends
+    fn check(&self) -> io::Result<()> {
+        if !self.to.is_empty() && self.network.is_cut(self.from.as_deref(), &self.to) {
+            self.broken.store(true, Ordering::SeqCst);
+        }
+        if self.broken.load(Ordering::SeqCst) {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "connection lost on the simulated network"));
+        }
+        Ok(())
+    }
+}
+
+impl AsyncRead for SimStream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        loop {
+            self.check()?;
+            if !self.readable.is_empty() {
+                let n = buf.remaining().min(self.readable.len());
+                let chunk = self.readable.drain(..n).collect::<Vec<_>>();
+                buf.put_slice(&chunk);
+                return Poll::Ready(Ok(()));
+            }
+            if let Some((arrival, _)) = self.arriving.as_mut() {
+                if arrival.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                let (_, data) = self.arriving.take().expect("arriving above");
+                self.readable.extend(data);
+                continue;
+            }
+            match self.inbound.poll_recv(cx) {
+                Poll::Ready(Some((at, data))) => self.arriving = Some((Box::pin(tokio::time::sleep_until(at)), data)),
+                Poll::Ready(None) => return Poll::Ready(Ok(())),   // The other end shut down
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for SimStream {
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.check()?;
+        if self.network.lose(self.from.as_deref(), &self.to) {
+            self.broken.store(true, Ordering::SeqCst);
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::ConnectionReset, "write lost on the simulated network")));
+        }
+        let at = Instant::now() + self.network.latency(self.from.as_deref(), &self.to);
+        let sent = self.outbound.as_ref().is_some_and(|outbound| outbound.send((at, buf.to_vec())).is_ok());
+        if !sent {
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::BrokenPipe, "other end closed")));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.check())
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.outbound = None;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Connected for SimStream {
+    type ConnectInfo = ();
+
+    fn connect_info(&self) -> Self::ConnectInfo {}
+}
@@ -0,0 +1,95 @@
+use custody_engine::mpc::approval::{self, Approval, ApprovalBundle, KeyOperation};
+use custody_engine::registry::{Approver, ApprovalPolicy};
+use frost_ed25519::{SigningKey, VerifyingKey};
+use rand_core::OsRng;
+
+/// Three approvers, two required
+fn policy_with_keys() -> (ApprovalPolicy, Vec<SigningKey>) {
+    let keys = (0..3).map(|_| SigningKey::new(&mut OsRng)).collect::<Vec<_>>();
+    let approvers = keys.iter().enumerate().map(|(i, k)| Approver {
+        approver_id: format!("approver-{i}"),
+        public_key: VerifyingKey::from(k).serialize().unwrap(),
+    }).collect();
+
+    (ApprovalPolicy { approvers, quorum: 2 }, keys)
+}
+
+/// Far enough ahead that no test bundle expires
+const EXPIRES_AT: u64 = 4_102_444_800;
+
+fn approve(keys: &[SigningKey], i: usize, digest: &[u8]) -> Approval {
+    Approval {
+        approver_id: format!("approver-{i}"),
+        signature: keys[i].sign(OsRng, digest).serialize().unwrap(),
+    }
+}
+
+#[test]
+fn test_approval_quorum() {
+    let (policy, keys) = policy_with_keys();
+    let op_did = "did:op:root-issuer";
+    let message = b"root VC payload";
+    let digest = approval::approval_digest("req-1", EXPIRES_AT, op_did, "raw", message);
+
+    // One approval is not enough, even if repeated
+    let single = ApprovalBundle { request_id: "req-1".into(), expires_at: EXPIRES_AT, approvals: vec![approve(&keys, 0, &digest), approve(&keys, 0, &digest)] };
+    assert!(approval::verify_bundle(&policy, op_did, "raw", message, &single).is_err());
+
+    let quorum = ApprovalBundle { request_id: "req-1".into(), expires_at: EXPIRES_AT, approvals: vec![approve(&keys, 0, &digest), approve(&keys, 2, &digest)] };
+    approval::verify_bundle(&policy, op_did, "raw", message, &quorum).expect("2-of-3 should pass");
+
+    // Approvals are bound to the message, the DID and the request
+    assert!(approval::verify_bundle(&policy, op_did, "raw", b"a different payload", &quorum).is_err());
+    assert!(approval::verify_bundle(&policy, "did:op:other", "raw", message, &quorum).is_err());
+    let moved = ApprovalBundle { request_id: "req-2".into(), ..quorum.clone() };
+    assert!(approval::verify_bundle(&policy, op_did, "raw", message, &moved).is_err());
+    let extended = ApprovalBundle { expires_at: EXPIRES_AT + 1, ..quorum.clone() };
+    assert!(approval::verify_bundle(&policy, op_did, "raw", message, &extended).is_err());
+
+    // ...and to the kind of request: raw signing bytes cannot pass for a key operation
+    assert!(approval::verify_bundle(&policy, op_did, KeyOperation::Rotate.name(), message, &quorum).is_err());
+}
+
+#[test]
+fn test_reshare_approval_binds_committee_and_threshold() {
+    let (policy, keys) = policy_with_keys();
+    let op_did = "did:op:root-issuer";
+    let reshare = KeyOperation::Reshare { threshold: 2, members: vec!["node-b".into(), "node-a".into(), "node-c".into()] };
+    let message = reshare.message(op_did);
+    let digest = approval::approval_digest("req-1", EXPIRES_AT, op_did, reshare.name(), &message);
+    let bundle = ApprovalBundle { request_id: "req-1".into(), expires_at: EXPIRES_AT, approvals: vec![approve(&keys, 0, &digest), approve(&keys, 1, &digest)] };
+    approval::verify_bundle(&policy, op_did, reshare.name(), &message, &bundle).expect("approved reshare should pass");
+
+    // Member order does not matter, but the members and the threshold do
+    let reordered = KeyOperation::Reshare { threshold: 2, members: vec!["node-a".into(), "node-b".into(), "node-c".into()] };
+    assert_eq!(reordered.message(op_did), message);
+    for other in [
+        KeyOperation::Reshare { threshold: 1, members: vec!["node-a".into(), "node-b".into(), "node-c".into()] },
+        KeyOperation::Reshare { threshold: 2, members: vec!["node-a".into(), "node-b".into(), "node-x".into()] },
+        KeyOperation::Repair { lost_node: "node-a".into(), helpers: vec!["node-b".into(), "node-c".into()] },
+        KeyOperation::Rotate,
+    ] {
+        assert!(approval::verify_bundle(&policy, op_did, other.name(), &other.message(op_did), &bundle).is_err());
+    }
+}
+
+#[test]
+fn test_approval_rejects_expired_bundle() {
+    let (policy, keys) = policy_with_keys();
+    let expired_at = 1_000_000;
+    let digest = approval::approval_digest("req-1", expired_at, "did:op:x", "raw", b"msg");
+
+    let bundle = ApprovalBundle { request_id: "req-1".into(), expires_at: expired_at, approvals: vec![approve(&keys, 0, &digest), approve(&keys, 1, &digest)] };
+    assert!(approval::verify_bundle(&policy, "did:op:x", "raw", b"msg", &bundle).is_err());
+}
+
+#[test]
+fn test_approval_rejects_unknown_approver() {
+    let (policy, keys) = policy_with_keys();
+    let digest = approval::approval_digest("req-1", EXPIRES_AT, "did:op:x", "raw", b"msg");
+
+    let mut stranger = approve(&keys, 1, &digest);
+    stranger.approver_id = "approver-9".into();
+    let bundle = ApprovalBundle { request_id: "req-1".into(), expires_at: EXPIRES_AT, approvals: vec![approve(&keys, 0, &digest), stranger] };
+    assert!(approval::verify_bundle(&policy, "did:op:x", "raw", b"msg", &bundle).is_err());
+}
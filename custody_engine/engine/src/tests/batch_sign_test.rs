@@ -0,0 +1,23 @@
+#[tokio::test]
+async fn test_mpc_sign_batch() {
+    use mpc::custody_mpc_client::CustodyMpcClient;
+    use mpc::{SignBatchRequest, SigningIntent};
+
+    let mut client = CustodyMpcClient::connect("http://[::1]:50051").await.expect("connect failed");
+
+    let messages = vec![b"vc one".to_vec(), b"vc two".to_vec(), b"vc three".to_vec()];
+    let response = client.sign_batch(SignBatchRequest {
+        operational_did: "did:op:test".into(),
+        messages: messages.clone(),
+        intent: SigningIntent::DidAuth as i32,
+        ..Default::default()
+    }).await.expect("rpc failed");
+
+    let results = &response.get_ref().results;
+    assert_eq!(results.len(), messages.len());
+    for (i, item) in results.iter().enumerate() {
+        assert_eq!(item.index as usize, i);
+        assert!(item.error.is_empty());
+        assert!(item.signature.len() > 0);
+    }
+}
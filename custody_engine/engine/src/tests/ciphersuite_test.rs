@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use custody_engine::ciphersuite::{self, SuiteId};
+use custody_engine::sim;
+use custody_engine::with_ciphersuite;
+
+/// Runs DKG, signing and aggregation for 2-of-3 nodes entirely in-process
+fn run_local_flow(suite: SuiteId) {
+    let nodes = vec!["node-a".to_string(), "node-b".to_string(), "node-c".to_string()];
+    sim::enroll_nodes(&nodes);
+    let message = b"suite round trip";
+
+    with_ciphersuite!(suite, |C| {
+        // DKG round 1
+        let mut secrets1 = HashMap::new();
+        let mut round1 = HashMap::new();
+        for node in &nodes {
+            let (secret, pkg) = ciphersuite::dkg_part1::<C>(node, 3, 2).unwrap();
+            secrets1.insert(node.clone(), secret);
+            round1.insert(node.clone(), pkg);
+        }
+
+        // DKG round 2: every node sees the others' round1 packages
+        let mut secrets2 = HashMap::new();
+        let mut inbox2: HashMap<String, HashMap<String, Vec<u8>>> = HashMap::new();
+        for node in &nodes {
+            let others = round1.iter().filter(|(n, _)| *n != node).map(|(n, p)| (n.clone(), p.clone())).collect();
+            let (secret, outgoing) = ciphersuite::dkg_part2::<C>(&secrets1[node], &others).unwrap();
+            secrets2.insert(node.clone(), secret);
+            for (to, pkg) in outgoing {
+                inbox2.entry(to).or_default().insert(node.clone(), pkg);
+            }
+        }
+
+        // DKG round 3
+        let mut outputs = HashMap::new();
+        for node in &nodes {
+            let others = round1.iter().filter(|(n, _)| *n != node).map(|(n, p)| (n.clone(), p.clone())).collect();
+            let out = ciphersuite::dkg_part3::<C>(&secrets2[node], &others, &inbox2[node], &nodes).unwrap();
+            outputs.insert(node.clone(), out);
+        }
+
+        let group_key = outputs["node-a"].group_public_key.clone();
+        assert!(outputs.values().all(|o| o.group_public_key == group_key));
+
+        // Sign with two of three
+        let signers = &nodes[..2];
+        let mut nonces = HashMap::new();
+        let mut commitments = Vec::new();
+        for node in signers {
+            let (n, c) = ciphersuite::commit::<C>(&outputs[node].key_package).unwrap();
+            nonces.insert(node.clone(), n);
+            commitments.push((node.clone(), c));
+        }
+
+        let shares = signers.iter().map(|node| {
+            let share = ciphersuite::sign::<C>(&outputs[node].key_package, &nonces[node], message, &commitments).unwrap();
+            (node.clone(), share)
+        }).collect::<Vec<_>>();
+
+        let signature = ciphersuite::aggregate::<C>(message, &commitments, &shares, &outputs["node-a"].public_key_package).unwrap();
+        ciphersuite::verify::<C>(&group_key, message, &signature).expect("Signature should verify");
+    });
+}
+
+#[test]
+fn test_suite_protocol_round_trip() {
+    for suite in [SuiteId::Ed25519, SuiteId::Secp256k1Tr, SuiteId::P256, SuiteId::Ristretto255] {
+        assert_eq!(SuiteId::from_dkg_protocol(Some(suite.dkg_protocol())).unwrap(), suite);
+        assert_eq!(SuiteId::from_name(suite.name()).unwrap(), suite);
+    }
+    assert_eq!(SuiteId::from_dkg_protocol(Some("frost-dkg-v1")).unwrap(), SuiteId::Ed25519);
+    assert_eq!(SuiteId::from_name("").unwrap(), SuiteId::Ed25519);
+    assert!(SuiteId::from_name("bls12-381").is_err());
+}
+
+#[test]
+fn test_local_flow_all_suites() {
+    run_local_flow(SuiteId::Ed25519);
+    run_local_flow(SuiteId::Secp256k1Tr);
+    run_local_flow(SuiteId::P256);
+    run_local_flow(SuiteId::Ristretto255);
+}
+
+#[test]
+fn test_invalid_proof_of_knowledge_names_the_culprit() {
+    type C = frost_ed25519::Ed25519Sha512;
+    sim::enroll_nodes(&["node-a", "node-b", "node-c"]);
+    let (secret_a, _) = ciphersuite::dkg_part1::<C>("node-a", 3, 2).unwrap();
+    let (_, pkg_c) = ciphersuite::dkg_part1::<C>("node-c", 3, 2).unwrap();
+
+    // node-b replays node-c's package; its proof is bound to node-c's identifier
+    let received = HashMap::from([("node-b".to_string(), pkg_c.clone()), ("node-c".to_string(), pkg_c.clone())]);
+    assert!(ciphersuite::check_round1_package::<C>(&pkg_c, 2).is_ok());
+    assert!(ciphersuite::check_round1_package::<C>(&pkg_c, 3).is_err());
+
+    let fault = ciphersuite::dkg_part2::<C>(&secret_a, &received).unwrap_err();
+    assert_eq!(fault.culprit.as_deref(), Some("node-b"));
+}
+
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use custody_engine::ciphersuite::{self, DkgOutput, SuiteId};
+use custody_engine::sim;
+use custody_engine::with_ciphersuite;
+
+/// Runs a 2-of-3 DKG in-process for the given suite
+fn local_dkg<C: frost_core::Ciphersuite>(nodes: &[String]) -> HashMap<String, DkgOutput> {
+    sim::enroll_nodes(nodes);
+    let mut secrets1 = HashMap::new();
+    let mut round1 = HashMap::new();
+    for node in nodes {
+        let (secret, pkg) = ciphersuite::dkg_part1::<C>(node, 3, 2).unwrap();
+        secrets1.insert(node.clone(), secret);
+        round1.insert(node.clone(), pkg);
+    }
+
+    let others = |node: &String| round1.iter()
+        .filter(|(n, _)| *n != node)
+        .map(|(n, p)| (n.clone(), p.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut secrets2 = HashMap::new();
+    let mut inbox2: HashMap<String, HashMap<String, Vec<u8>>> = HashMap::new();
+    for node in nodes {
+        let (secret, outgoing) = ciphersuite::dkg_part2::<C>(&secrets1[node], &others(node)).unwrap();
+        secrets2.insert(node.clone(), secret);
+        for (to, pkg) in outgoing {
+            inbox2.entry(to).or_default().insert(node.clone(), pkg);
+        }
+    }
+
+    nodes.iter()
+        .map(|node| (node.clone(), ciphersuite::dkg_part3::<C>(&secrets2[node], &others(node), &inbox2[node], nodes).unwrap()))
+        .collect()
+}
+
+/// Derives a child key, signs with two tweaked shares and verifies under the child group key
+fn run_derived_flow(suite: SuiteId) {
+    let nodes = vec!["node-a".to_string(), "node-b".to_string(), "node-c".to_string()];
+    let message = b"signed by a child key";
+    let path = "m/dids/42";
+
+    with_ciphersuite!(suite, |C| {
+        let outputs = local_dkg::<C>(&nodes);
+        let child = ciphersuite::derive_public_key_package::<C>(&outputs["node-a"].public_key_package, path, &nodes).unwrap();
+        assert_ne!(child.group_public_key, outputs["node-a"].group_public_key);
+
+        // Every signer tweaks its own shard independently
+        let signers = &nodes[1..];
+        let derived = signers.iter()
+            .map(|n| (n.clone(), ciphersuite::derive_key_package::<C>(&outputs[n].key_package, path).unwrap()))
+            .collect::<HashMap<_, _>>();
+
+        let mut nonces = HashMap::new();
+        let mut commitments = Vec::new();
+        for node in signers {
+            let (n, c) = ciphersuite::commit::<C>(&derived[node]).unwrap();
+            nonces.insert(node.clone(), n);
+            commitments.push((node.clone(), c));
+        }
+        let shares = signers.iter()
+            .map(|n| (n.clone(), ciphersuite::sign::<C>(&derived[n], &nonces[n], message, &commitments).unwrap()))
+            .collect::<Vec<_>>();
+
+        let signature = ciphersuite::aggregate::<C>(message, &commitments, &shares, &child.public_key_package).unwrap();
+        ciphersuite::verify::<C>(&child.group_public_key, message, &signature).expect("child signature should verify");
+        assert!(ciphersuite::verify::<C>(&outputs["node-a"].group_public_key, message, &signature).is_err());
+
+        // Deriving in two steps lands on the same key as one combined path
+        let step = ciphersuite::derive_public_key_package::<C>(&outputs["node-a"].public_key_package, "m/dids", &nodes).unwrap();
+        let nested = ciphersuite::derive_public_key_package::<C>(&step.public_key_package, "m/42", &nodes).unwrap();
+        assert_eq!(nested.group_public_key, child.group_public_key);
+    });
+}
+
+#[test]
+fn test_derived_keys_all_suites() {
+    run_derived_flow(SuiteId::Ed25519);
+    run_derived_flow(SuiteId::Secp256k1Tr);
+    run_derived_flow(SuiteId::P256);
+    run_derived_flow(SuiteId::Ristretto255);
+}
+
+#[test]
+fn test_derivation_path_parsing() {
+    assert_eq!(ciphersuite::parse_derivation_path("m/dids/42").unwrap(), vec!["dids", "42"]);
+    assert!(ciphersuite::parse_derivation_path("m").is_err());
+    assert!(ciphersuite::parse_derivation_path("m//1").is_err());
+    assert!(ciphersuite::parse_derivation_path("dids/42").is_err());
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use custody_engine::ciphersuite;
+use custody_engine::dkg::types::{DKGMessage, DKGPackage};
+use custody_engine::identity::{IdentityPublicKeys, NodeIdentity};
+use custody_engine::mpc::device::{self, DeviceToNode, NodeToDevice};
+use custody_engine::mpc::device_client::DeviceClient;
+use custody_engine::sim;
+
+type C = frost_ed25519::Ed25519Sha512;
+
+/// Unwraps a device's DKG relay output into (recipient, package bytes), checking
+/// each message as the receiving node would
+fn relayed(out: Vec<DeviceToNode>, device: &DeviceClient, identities: &HashMap<String, NodeIdentity>) -> Vec<(String, Vec<u8>)> {
+    let sender = IdentityPublicKeys { signing_key: device.identity_public_key(), encryption_key: device.encryption_public_key() };
+    out.into_iter().filter_map(|msg| match msg {
+        DeviceToNode::Relay { to, payload, .. } => {
+            let msg: DKGMessage = bincode::deserialize(&payload).unwrap();
+            match msg.open(&identities[&to], "group-1", &device.participant_id, &to, &sender).unwrap() {
+                Some(DKGPackage::Round1(pkg)) | Some(DKGPackage::Round2(pkg)) => Some((to, pkg)),
+                None => None,
+            }
+        }
+        _ => None,
+    }).collect()
+}
+
+fn relay_to_device(device: &mut DeviceClient, from: &str, msg: DKGMessage) -> Vec<DeviceToNode> {
+    device.handle(NodeToDevice::Relay {
+        group_id: "group-1".into(),
+        from: from.into(),
+        payload: bincode::serialize(&msg).unwrap(),
+    }).unwrap()
+}
+
+fn result_value(out: Vec<DeviceToNode>) -> Vec<u8> {
+    match out.into_iter().next() {
+        Some(DeviceToNode::Result { result, .. }) => result.unwrap(),
+        other => panic!("expected a result, got {other:?}"),
+    }
+}
+
+/// Two in-process custody nodes and one device run a 2-of-3 DKG, then a node and the device sign
+#[test]
+fn test_device_dkg_and_sign() {
+    let mut device = DeviceClient::new("phone-1", "node-a");
+    let nodes = vec!["node-a".to_string(), "node-b".to_string()];
+    sim::enroll_nodes(&nodes);
+    let mut all = nodes.clone();
+    all.push(device.participant_id.clone());
+    let identities = nodes.iter().map(|n| (n.clone(), NodeIdentity::generate())).collect::<HashMap<_, _>>();
+    let device_keys = IdentityPublicKeys { signing_key: device.identity_public_key(), encryption_key: device.encryption_public_key() };
+
+    // Round 1: nodes locally, device through its invite
+    let mut secrets1 = HashMap::new();
+    let mut round1 = HashMap::new();
+    for node in &nodes {
+        let (secret, pkg) = ciphersuite::dkg_part1::<C>(node, 3, 2).unwrap();
+        secrets1.insert(node.clone(), secret);
+        round1.insert(node.clone(), pkg);
+    }
+    let out = device.handle(NodeToDevice::DkgInvite {
+        group_id: "group-1".into(),
+        operational_did: "did:op:device".into(),
+        threshold: 2,
+        participants: all.clone(),
+        ciphersuite: "ed25519".into(),
+        participant_keys: identities.iter().map(|(n, id)| (n.clone(), id.public_keys())).collect(),
+    }).unwrap();
+    let device_round1 = relayed(out, &device, &identities);
+    assert_eq!(device_round1.len(), 2);
+    round1.insert(device.participant_id.clone(), device_round1[0].1.clone());
+
+    // A round-1 package that claims the wrong sender is rejected
+    let forged = DKGMessage::signed_round1(&identities["node-b"], "group-1", "node-b", round1["node-a"].clone());
+    assert!(device.handle(NodeToDevice::Relay {
+        group_id: "group-1".into(),
+        from: "node-a".into(),
+        payload: bincode::serialize(&forged).unwrap(),
+    }).is_err());
+
+    // The device moves to round 2 on its own once both node packages arrive
+    let mut device_round2 = Vec::new();
+    for node in &nodes {
+        let signed = DKGMessage::signed_round1(&identities[node], "group-1", node, round1[node].clone());
+        device_round2.extend(relayed(relay_to_device(&mut device, node, signed), &device, &identities));
+    }
+    assert_eq!(device_round2.len(), 2);
+
+    let others = |me: &String| round1.iter()
+        .filter(|(n, _)| *n != me)
+        .map(|(n, p)| (n.clone(), p.clone()))
+        .collect::<HashMap<_, _>>();
+
+    // Round 2: nodes send the device its packages; the device finishes after the last one
+    let mut secrets2 = HashMap::new();
+    let mut inbox2: HashMap<String, HashMap<String, Vec<u8>>> = HashMap::new();
+    for (to, pkg) in device_round2 {
+        inbox2.entry(to).or_default().insert(device.participant_id.clone(), pkg);
+    }
+    let mut device_done = Vec::new();
+    for node in &nodes {
+        let (secret, outgoing) = ciphersuite::dkg_part2::<C>(&secrets1[node], &others(node)).unwrap();
+        secrets2.insert(node.clone(), secret);
+        for (to, pkg) in outgoing {
+            if to == device.participant_id {
+                let sealed = DKGMessage::sealed_round2(&identities[node], "group-1", node, &to, &device_keys, &pkg).unwrap();
+                device_done.extend(relay_to_device(&mut device, node, sealed));
+            } else {
+                inbox2.entry(to).or_default().insert(node.clone(), pkg);
+            }
+        }
+    }
+
+    let device_key = match device_done.as_slice() {
+        [DeviceToNode::DkgComplete { group_public_key, .. }] => group_public_key.clone(),
+        other => panic!("device did not complete the DKG: {other:?}"),
+    };
+    assert!(device.has_share("did:op:device"));
+
+    let outputs = nodes.iter()
+        .map(|n| (n.clone(), ciphersuite::dkg_part3::<C>(&secrets2[n], &others(n), &inbox2[n], &all).unwrap()))
+        .collect::<HashMap<_, _>>();
+    assert_eq!(outputs["node-a"].group_public_key, device_key);
+
+    // Signing with node-a and the device
+    let message = b"approved on the phone".to_vec();
+    let (node_nonces, node_commitment) = ciphersuite::commit::<C>(&outputs["node-a"].key_package).unwrap();
+    let device_commitment = result_value(device.handle(NodeToDevice::SignRequest {
+        request_id: "req-1".into(),
+        operational_did: "did:op:device".into(),
+        message: message.clone(),
+    }).unwrap());
+
+    let commitments = vec![
+        ("node-a".to_string(), node_commitment),
+        (device.participant_id.clone(), device_commitment),
+    ];
+    let device_share = result_value(device.handle(NodeToDevice::SigningPackage {
+        request_id: "req-1".into(),
+        message: message.clone(),
+        commitments: commitments.clone(),
+    }).unwrap());
+    let node_share = ciphersuite::sign::<C>(&outputs["node-a"].key_package, &node_nonces, &message, &commitments).unwrap();
+
+    let shares = vec![("node-a".to_string(), node_share), (device.participant_id.clone(), device_share)];
+    let signature = ciphersuite::aggregate::<C>(&message, &commitments, &shares, &outputs["node-a"].public_key_package).unwrap();
+    ciphersuite::verify::<C>(&device_key, &message, &signature).expect("device-assisted signature should verify");
+
+    // Nonces are single-use
+    let replay = device.handle(NodeToDevice::SigningPackage { request_id: "req-1".into(), message, commitments }).unwrap();
+    assert!(matches!(replay.as_slice(), [DeviceToNode::Result { result: Err(_), .. }]));
+}
+
+#[test]
+fn test_device_hello_and_routing() {
+    let device = DeviceClient::new("phone-1", "node-a:50051");
+    assert_eq!(device.participant_id, "device:phone-1@node-a:50051");
+    assert!(device::is_device_participant(&device.participant_id));
+    assert_eq!(device::device_home_node(&device.participant_id), Some("node-a:50051"));
+    assert_eq!(device::device_home_node("node-a:50051"), None);
+
+    let key = device.identity_public_key();
+    let signature = device.hello(b"nonce-1");
+    assert!(device::verify_hello(&key, b"nonce-1", &signature).is_ok());
+    assert!(device::verify_hello(&key, b"nonce-2", &signature).is_err());
+}
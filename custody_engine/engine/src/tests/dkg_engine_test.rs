@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Once};
+use std::time::{Duration, SystemTime};
+
+use custody_engine::ciphersuite::{self, SuiteId};
+use custody_engine::dkg::engine::DKGEngine;
+use custody_engine::dkg::types::{self, DKGError, DKGKind, DKGLocalState, DKGMessage, DKGPhase, PeerResult};
+use custody_engine::identity::{IdentityDirectory, NodeIdentity};
+use custody_engine::registry::{GroupStatus, OperationalDID, OperationalDIDRegistry, RootDID};
+use custody_engine::relay::{MessageType, RelayClient, RelayHandlers, RetryPolicy};
+use custody_engine::sim;
+use custody_engine::types::VaultRecord;
+use custody_engine::vault;
+
+static VAULT: Once = Once::new();
+
+/// An engine whose registry holds `did:op:dkg`, backed by a fresh vault record
+fn engine(node_id: &str) -> Arc<DKGEngine> {
+    engine_with(node_id, Arc::new(registry_with_vault()), Arc::new(NodeIdentity::generate()))
+}
+
+fn engine_with(node_id: &str, registry: Arc<OperationalDIDRegistry>, identity: Arc<NodeIdentity>) -> Arc<DKGEngine> {
+    sim::enroll_nodes(&[node_id, "node-a", "node-b", "node-c"]);
+    // Give up on unreachable peers quickly
+    let policy = RetryPolicy {
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(10),
+        max_attempts: 2,
+        attempt_timeout: Duration::from_secs(5),
+    };
+    let dkg = Arc::new(DKGEngine::new(
+        registry,
+        Arc::new(RelayClient::with_policy(identity.clone(), node_id, policy)),
+        identity,
+        Arc::new(IdentityDirectory::new()),
+        node_id.to_string(),
+    ));
+    dkg.watch_relay();
+    dkg
+}
+
+fn registry_with_vault() -> OperationalDIDRegistry {
+    VAULT.call_once(vault::init_vault);
+    let vault_id = format!("vault-dkg-{}", uuid::Uuid::new_v4());
+    vault::store_record(&vault_id, &VaultRecord {
+        root_did: "did:example:root".into(),
+        op_dids: vec!["did:op:dkg".into()],
+        mpc_shard: None,
+        group_metadata: None,
+        public_keys: vec![],
+        vcs: vec![],
+        bbs_private_key: None,
+        bbs_public_key: None,
+        active_nonce: None,
+        batch_nonces: vec![],
+        ecdsa_share: None,
+        ecdsa_presignatures: Default::default(),
+        shard_epoch: 0,
+        dkg_sessions: Default::default(),
+        consumed_approvals: Default::default(),
+    }).unwrap();
+
+    let registry = OperationalDIDRegistry::new();
+    registry.register_operational_did(OperationalDID("did:op:dkg".into()), RootDID("did:example:root".into()), vault_id, vec![]).unwrap();
+    registry
+}
+
+#[test]
+fn test_early_messages_are_buffered_until_session_starts() {
+    let dkg = engine("node-a");
+
+    // A peer started first: its package waits for our session instead of being dropped
+    let early = bincode::serialize(&DKGMessage::signed_round1(&NodeIdentity::generate(), "group-1", "node-b", vec![1, 2, 3])).unwrap();
+    dkg.handle_message("group-1", "node-b", early).unwrap();
+    assert_eq!(dkg.pending.lock().unwrap()["group-1"].len(), 1);
+
+    assert!(matches!(dkg.handle_message("group-1", "node-b", b"garbage".to_vec()), Err(DKGError::MessageMalformed)));
+    assert!(matches!(dkg.status("group-1"), Err(DKGError::SessionNotFound)));
+}
+
+#[tokio::test]
+async fn test_unreachable_peer_fails_the_session() {
+    let dkg = engine("node-a");
+    let nodes = vec!["node-a".to_string(), "unreachable.invalid".to_string()];
+    sim::enroll_nodes(&nodes);
+    dkg.directory.pin("unreachable.invalid", NodeIdentity::generate().public_keys()).unwrap();
+
+    dkg.start_session("group-2", "did:op:dkg".into(), 2, nodes.clone(), SuiteId::Ed25519).unwrap();
+    assert!(matches!(dkg.start_session("group-2", "did:op:dkg".into(), 2, nodes, SuiteId::Ed25519), Err(DKGError::SessionAlreadyExists)));
+
+    // The waiter returns as soon as the session fails rather than at the deadline
+    let status = dkg.wait_for_completion("group-2").await.unwrap();
+    assert!(matches!(status.phase, DKGPhase::Failed(_)));
+    assert_eq!(status.expected, 1);
+}
+
+#[test]
+fn test_messages_must_come_from_the_signing_peer() {
+    let dkg = engine("node-a");
+    let (node_b, node_c) = (NodeIdentity::generate(), NodeIdentity::generate());
+    dkg.directory.pin("node-b", node_b.public_keys()).unwrap();
+    dkg.directory.pin("node-c", node_c.public_keys()).unwrap();
+    let nodes = ["node-a", "node-b", "node-c"].map(String::from).to_vec();
+
+    // Sessions cannot start with a participant whose identity is unknown
+    let strangers = vec!["node-a".to_string(), "node-x".to_string()];
+    assert!(matches!(dkg.start_session("group-0", "did:op:dkg".into(), 2, strangers, SuiteId::Ed25519), Err(DKGError::Unauthenticated(_))));
+
+    // Buffered until the session starts, then checked: node-c's package relayed as node-b is dropped
+    let forged = DKGMessage::signed_round1(&node_c, "group-3", "node-c", vec![1, 2, 3]);
+    dkg.handle_message("group-3", "node-b", bincode::serialize(&forged).unwrap()).unwrap();
+    dkg.start_session("group-3", "did:op:dkg".into(), 2, nodes, SuiteId::Ed25519).unwrap();
+    assert_eq!(dkg.status("group-3").unwrap().round1_received, 0);
+
+    // Keys pinned for a node cannot be swapped later
+    assert!(dkg.directory.pin("node-b", node_c.public_keys()).is_err());
+}
+
+#[test]
+fn test_bad_round1_package_aborts_and_names_the_sender() {
+    let dkg = engine("node-a");
+    let (node_b, node_c) = (NodeIdentity::generate(), NodeIdentity::generate());
+    dkg.directory.pin("node-b", node_b.public_keys()).unwrap();
+    dkg.directory.pin("node-c", node_c.public_keys()).unwrap();
+    let nodes = ["node-a", "node-b", "node-c"].map(String::from).to_vec();
+
+    // node-c's package is fine; node-b signs something that is not a FROST package at all.
+    // Both arrive before we start, so they are checked before our own broadcast goes out.
+    let (_, good) = ciphersuite::dkg_part1::<frost_ed25519::Ed25519Sha512>("node-c", 3, 2).unwrap();
+    dkg.handle_message("group-4", "node-c", bincode::serialize(&DKGMessage::signed_round1(&node_c, "group-4", "node-c", good)).unwrap()).unwrap();
+    dkg.handle_message("group-4", "node-b", bincode::serialize(&DKGMessage::signed_round1(&node_b, "group-4", "node-b", vec![1, 2, 3])).unwrap()).unwrap();
+    dkg.start_session("group-4", "did:op:dkg".into(), 2, nodes, SuiteId::Ed25519).unwrap();
+
+    let status = dkg.status("group-4").unwrap();
+    assert!(matches!(status.phase, DKGPhase::Aborted(_)));
+    assert_eq!(status.round1_received, 0, "partial state is wiped");
+    assert!(matches!(status.peer_results["node-b"], PeerResult::Rejected(_)));
+    assert_eq!(status.peer_results["node-c"], PeerResult::Round1Verified);
+    assert_eq!(status.misbehaving(), vec!["node-b".to_string()]);
+
+    // Nothing more is accepted for the aborted session
+    let (_, late) = ciphersuite::dkg_part1::<frost_ed25519::Ed25519Sha512>("node-c", 3, 2).unwrap();
+    let late = DKGMessage::signed_round1(&node_c, "group-4", "node-c", late);
+    assert!(matches!(dkg.handle_message("group-4", "node-c", bincode::serialize(&late).unwrap()), Err(DKGError::SessionAborted(_))));
+}
+
+#[test]
+fn test_unverifiable_complaint_blames_both_sides() {
+    let dkg = engine("node-a");
+    let (node_b, node_c) = (NodeIdentity::generate(), NodeIdentity::generate());
+    dkg.directory.pin("node-b", node_b.public_keys()).unwrap();
+    dkg.directory.pin("node-c", node_c.public_keys()).unwrap();
+    let nodes = ["node-a", "node-b", "node-c"].map(String::from).to_vec();
+
+    // A complaint must carry the accuser's own signature: node-c cannot complain as node-b
+    let forged = DKGMessage::signed_complaint(&node_c, "group-5", "node-b", "node-c", "bad share", None);
+    dkg.handle_message("group-5", "node-b", bincode::serialize(&forged).unwrap()).unwrap();
+
+    // node-b accuses node-c of a round-2 fault we cannot check; the session still aborts
+    let complaint = DKGMessage::signed_complaint(&node_b, "group-5", "node-b", "node-c", "bad share", None);
+    dkg.handle_message("group-5", "node-b", bincode::serialize(&complaint).unwrap()).unwrap();
+    dkg.start_session("group-5", "did:op:dkg".into(), 2, nodes, SuiteId::Ed25519).unwrap();
+
+    let status = dkg.status("group-5").unwrap();
+    assert!(matches!(status.phase, DKGPhase::Aborted(_)));
+    assert_eq!(status.complaints.len(), 1);
+    assert!(!status.complaints[0].confirmed);
+    assert_eq!(status.misbehaving(), vec!["node-b".to_string(), "node-c".to_string()]);
+}
+
+/// Seals a round-1 session for node-a as if the node had stopped mid-round
+fn seal_interrupted_session(registry: &OperationalDIDRegistry, group_id: &str, deadline: SystemTime) {
+    let nodes = ["node-a", "node-b", "node-c"].map(String::from).to_vec();
+    sim::enroll_nodes(&nodes);
+    let (secret, _) = ciphersuite::dkg_part1::<frost_ed25519::Ed25519Sha512>("node-a", 3, 2).unwrap();
+    let state = DKGLocalState {
+        operational_did: "did:op:dkg".into(),
+        kind: DKGKind::Generate,
+        threshold: 2,
+        participant_ids: nodes,
+        dealing: None,
+        repair: None,
+        round1_received: HashMap::new(),
+        round2_received: HashMap::new(),
+        round1_messages: HashMap::new(),
+        peer_results: HashMap::new(),
+        complaints: vec![],
+        sent: HashMap::new(),
+        ciphersuite: SuiteId::Ed25519,
+        round1_secret: Some(secret),
+        round2_secret: None,
+        phase: DKGPhase::Round1,
+        phase_deadline: deadline,
+        group_public_key: None,
+        confirmation: None,
+        confirmations: HashMap::new(),
+    };
+    let vault_id = registry.get_vault_id_for_operational_did(&OperationalDID("did:op:dkg".into())).unwrap();
+    vault::store_dkg_session(&vault_id, group_id, &bincode::serialize(&state).unwrap()).unwrap();
+}
+
+#[tokio::test]
+async fn test_session_state_is_sealed_and_dropped_when_it_ends() {
+    let registry = Arc::new(registry_with_vault());
+    let dkg = engine_with("node-a", registry.clone(), Arc::new(NodeIdentity::generate()));
+    let vault_id = registry.get_vault_id_for_operational_did(&OperationalDID("did:op:dkg".into())).unwrap();
+
+    // Without a vault to seal into, a session does not start
+    let unknown = ["node-a", "node-b"].map(String::from).to_vec();
+    assert!(matches!(dkg.start_session("group-6", "did:op:none".into(), 2, unknown, SuiteId::Ed25519), Err(DKGError::VaultNotFound)));
+
+    // A live session is in the vault; once it fails (peers unreachable) it is gone
+    seal_interrupted_session(&registry, "group-7", SystemTime::now() + Duration::from_secs(60));
+    assert!(vault::load_dkg_sessions(&vault_id).unwrap().contains_key("group-7"));
+    let recovered = dkg.recover_sessions();
+    assert_eq!(recovered, vec![("group-7".to_string(), DKGPhase::Round1)]);
+    let status = dkg.wait_for_completion("group-7").await.unwrap();
+    assert!(matches!(status.phase, DKGPhase::Failed(_)));
+    assert!(vault::load_dkg_sessions(&vault_id).unwrap().is_empty());
+}
+
+#[test]
+fn test_session_messages_must_use_the_channel_of_its_kind() {
+    let dkg = engine("node-a");
+    let handlers = RelayHandlers::new();
+    dkg.register_relay_handlers(&handlers).unwrap();
+    assert_eq!(handlers.channels(), [MessageType::Dkg, MessageType::Reshare, MessageType::ShareRepair]);
+
+    dkg.directory.pin("node-b", NodeIdentity::generate().public_keys()).unwrap();
+    let nodes = ["node-a", "node-b"].map(String::from).to_vec();
+    dkg.start_session("group-ch", "did:op:dkg".into(), 2, nodes, SuiteId::Ed25519).unwrap();
+
+    let refused = handlers.dispatch(MessageType::Reshare, "group-ch", "node-b", vec![1]).unwrap_err();
+    assert!(refused.contains("takes no"), "{refused}");
+    let malformed = handlers.dispatch(MessageType::Dkg, "group-ch", "node-b", b"garbage".to_vec()).unwrap_err();
+    assert!(malformed.contains("DKG handling failed"), "{malformed}");
+}
+
+#[test]
+fn test_restart_past_the_deadline_aborts_the_session() {
+    let registry = Arc::new(registry_with_vault());
+    seal_interrupted_session(&registry, "group-8", SystemTime::now() - Duration::from_secs(1));
+
+    // The restarted node has the same vault and identity but no sessions in memory
+    let restarted = engine_with("node-a", registry.clone(), Arc::new(NodeIdentity::generate()));
+    let recovered = restarted.recover_sessions();
+    assert_eq!(recovered.len(), 1);
+    assert!(matches!(recovered[0].1, DKGPhase::Aborted(_)));
+
+    let vault_id = registry.get_vault_id_for_operational_did(&OperationalDID("did:op:dkg".into())).unwrap();
+    assert!(vault::load_dkg_sessions(&vault_id).unwrap().is_empty(), "aborted state is wiped");
+}
+
+#[test]
+fn test_abort_notice_from_a_peer_ends_the_session() {
+    let dkg = engine("node-a");
+    let node_b = NodeIdentity::generate();
+    dkg.directory.pin("node-b", node_b.public_keys()).unwrap();
+    dkg.directory.pin("node-c", NodeIdentity::generate().public_keys()).unwrap();
+    let nodes = ["node-a", "node-b", "node-c"].map(String::from).to_vec();
+
+    let notice = DKGMessage::signed_abort(&node_b, "group-9", "node-b", "restarted after the phase deadline");
+    dkg.handle_message("group-9", "node-b", bincode::serialize(&notice).unwrap()).unwrap();
+    dkg.start_session("group-9", "did:op:dkg".into(), 2, nodes, SuiteId::Ed25519).unwrap();
+
+    let status = dkg.status("group-9").unwrap();
+    assert!(matches!(status.phase, DKGPhase::Aborted(reason) if reason.contains("node-b")));
+    assert!(status.complaints.is_empty(), "an abort notice blames no one");
+}
+
+
+#[test]
+fn test_reshare_needs_a_group_and_a_place_in_it() {
+    let dkg = engine("node-z");
+    let dealers = vec!["node-a".to_string(), "node-b".to_string()];
+    let members = vec!["node-b".to_string(), "node-c".to_string()];
+
+    // No group on record and none from the orchestrator
+    let start = dkg.start_reshare("group-r", "did:op:dkg".into(), dealers.clone(), members.clone(), 2, SuiteId::Ed25519, vec![]);
+    assert!(matches!(start, Err(DKGError::SessionFailed(_))));
+
+    // A threshold the new committee cannot meet, and a node in neither committee
+    let start = dkg.start_reshare("group-r", "did:op:dkg".into(), dealers.clone(), members.clone(), 3, SuiteId::Ed25519, vec![1]);
+    assert!(matches!(start, Err(DKGError::SessionFailed(_))));
+    let start = dkg.start_reshare("group-r", "did:op:dkg".into(), dealers, members, 2, SuiteId::Ed25519, vec![1]);
+    assert!(matches!(start, Err(DKGError::SessionFailed(_))));
+    assert!(matches!(dkg.status("group-r"), Err(DKGError::SessionNotFound)));
+}
+
+#[test]
+fn test_repair_needs_a_group() {
+    let dkg = engine("node-b");
+    let helpers = vec!["node-b".to_string(), "node-c".to_string()];
+
+    let start = dkg.start_repair("group-fix", "did:op:dkg".into(), helpers, "node-a".into());
+    assert!(matches!(start, Err(DKGError::SessionFailed(_))));
+    assert!(matches!(dkg.status("group-fix"), Err(DKGError::SessionNotFound)));
+}
+
+const RFC8032_SEED: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+const RFC8032_PUBLIC: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+
+/// node-b deals an imported key to itself and node-a, and confirms the public key
+/// package `confirmed` maps its own to. Everything node-b sends is buffered before
+/// node-a starts, so node-a runs the whole session without reaching anyone.
+fn import_with_confirmation(group_id: &str, confirmed: impl Fn(&[u8]) -> Vec<u8>) -> (Arc<DKGEngine>, Arc<OperationalDIDRegistry>) {
+    type C = frost_ed25519::Ed25519Sha512;
+    let registry = Arc::new(registry_with_vault());
+    let node_a = Arc::new(NodeIdentity::generate());
+    let node_b = NodeIdentity::generate();
+    let dkg = engine_with("node-a", registry.clone(), node_a.clone());
+    dkg.directory.pin("node-b", node_b.public_keys()).unwrap();
+
+    let members = ["node-a", "node-b"].map(String::from).to_vec();
+    let public = hex::decode(RFC8032_PUBLIC).unwrap();
+    let signing_key = ciphersuite::ed25519_signing_key(&hex::decode(RFC8032_SEED).unwrap()).unwrap();
+    let (commitment, shares) = ciphersuite::import_deal::<C>(&signing_key, &public, &members, 2).unwrap();
+
+    let own = ciphersuite::import_finish::<C>(
+        "node-b",
+        &HashMap::from([("node-b".to_string(), commitment.clone())]),
+        &HashMap::from([("node-b".to_string(), shares["node-b"].clone())]),
+        "node-b", &members, 2, &public,
+    ).unwrap();
+
+    let messages = [
+        DKGMessage::signed_round1(&node_b, group_id, "node-b", commitment),
+        DKGMessage::sealed_round2(&node_b, group_id, "node-b", "node-a", &node_a.public_keys(), &shares["node-a"]).unwrap(),
+        DKGMessage::signed_confirmation(&node_b, group_id, "node-b", confirmed(&own.public_key_package)),
+    ];
+    for msg in messages {
+        dkg.handle_message(group_id, "node-b", bincode::serialize(&msg).unwrap()).unwrap();
+    }
+
+    dkg.start_import(group_id, "did:op:dkg".into(), "node-b".into(), members, 2, public, None).unwrap();
+    (dkg, registry)
+}
+
+#[test]
+fn test_group_activates_once_every_node_confirms_it() {
+    let (dkg, registry) = import_with_confirmation("group-c1", types::confirmation_digest);
+
+    assert_eq!(dkg.status("group-c1").unwrap().phase, DKGPhase::Finalized);
+    let group = registry.get_mpc_group(&OperationalDID("did:op:dkg".into())).unwrap();
+    assert_eq!(group.status, GroupStatus::Active);
+    assert!(group.check_active().is_ok());
+}
+
+#[test]
+fn test_mismatched_confirmation_quarantines_the_group() {
+    let (dkg, registry) = import_with_confirmation("group-c2", |_| types::confirmation_digest(b"another package"));
+
+    let status = dkg.status("group-c2").unwrap();
+    assert!(matches!(status.phase, DKGPhase::Quarantined(reason) if reason.contains("node-b")));
+
+    // The group stays on record for the operator but refuses to sign
+    let group = registry.get_mpc_group(&OperationalDID("did:op:dkg".into())).unwrap();
+    assert!(matches!(group.status, GroupStatus::Quarantined(_)));
+    assert!(group.check_active().is_err());
+
+    // An abort does not roll it back or take it out of quarantine
+    dkg.abort_session("group-c2", "operator").unwrap();
+    assert!(matches!(dkg.status("group-c2").unwrap().phase, DKGPhase::Quarantined(_)));
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use custody_engine::mpc::ecdsa::{self, EcdsaMessage, EcdsaMessageFormat};
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+/// Delivers every party's outgoing messages into the recipients' inboxes
+fn route(outgoing: Vec<(String, HashMap<String, EcdsaMessage>)>) -> HashMap<String, HashMap<String, EcdsaMessage>> {
+    let mut inbox: HashMap<String, HashMap<String, EcdsaMessage>> = HashMap::new();
+    for (from, messages) in outgoing {
+        for (to, msg) in messages {
+            inbox.entry(to).or_default().insert(from.clone(), msg);
+        }
+    }
+    inbox
+}
+
+#[test]
+fn test_ecdsa_keygen_presign_sign_2_of_3() {
+    let nodes = vec!["node-a".to_string(), "node-b".to_string(), "node-c".to_string()];
+
+    // Keygen: every node deals, then combines what it received
+    let mut keygen_states = HashMap::new();
+    let mut outgoing = Vec::new();
+    for node in &nodes {
+        let (state, msgs) = ecdsa::keygen_deal(node, &nodes, 2).unwrap();
+        keygen_states.insert(node.clone(), state);
+        outgoing.push((node.clone(), msgs));
+    }
+    let inbox = route(outgoing);
+    let key_shares = nodes.iter()
+        .map(|n| (n.clone(), ecdsa::keygen_finish(keygen_states.remove(n).unwrap(), &inbox[n]).unwrap()))
+        .collect::<HashMap<_, _>>();
+
+    let public_key = key_shares["node-a"].public_key.clone();
+    assert!(key_shares.values().all(|s| s.public_key == public_key), "all nodes agree on the group key");
+
+    // Presign: deal, open w, finish
+    let mut presign_states = HashMap::new();
+    let mut outgoing = Vec::new();
+    for node in &nodes {
+        let (state, msgs) = ecdsa::presign_deal("presig-1", key_shares[node].clone()).unwrap();
+        presign_states.insert(node.clone(), state);
+        outgoing.push((node.clone(), msgs));
+    }
+    let deals = route(outgoing);
+
+    let mut openings = HashMap::new();
+    for node in &nodes {
+        let opening = ecdsa::presign_open(presign_states.get_mut(node).unwrap(), &deals[node]).unwrap();
+        openings.insert(node.clone(), opening);
+    }
+    let opened = route(nodes.iter().map(|from| {
+        let msgs = nodes.iter().map(|to| {
+            let EcdsaMessage::PresignOpen { w_share } = &openings[from] else { unreachable!() };
+            (to.clone(), EcdsaMessage::PresignOpen { w_share: w_share.clone() })
+        }).collect();
+        (from.clone(), msgs)
+    }).collect());
+
+    let presignatures = nodes.iter()
+        .map(|n| ecdsa::presign_finish(presign_states.remove(n).unwrap(), &opened[n]).unwrap())
+        .collect::<Vec<_>>();
+
+    // Sign an EIP-191 message and check the result recovers to the group key
+    let digest = ecdsa::message_digest(EcdsaMessageFormat::Eip191, b"hello custody").unwrap();
+    let shares = presignatures.iter().map(|p| ecdsa::sign_share(p, &digest).unwrap()).collect::<Vec<_>>();
+    let sig = ecdsa::combine_signature(&presignatures[0].big_r, &shares, 2, &public_key, &digest).unwrap();
+    assert_eq!(sig.len(), 65);
+
+    let signature = Signature::from_slice(&sig[..64]).unwrap();
+    let recovery_id = RecoveryId::from_byte(sig[64] - 27).unwrap();
+    let recovered = VerifyingKey::recover_from_prehash(&digest, &signature, recovery_id).unwrap();
+    assert_eq!(recovered.to_sec1_bytes().to_vec(), public_key);
+
+    // Fewer than 2t-1 shares cannot produce a signature
+    assert!(ecdsa::combine_signature(&presignatures[0].big_r, &shares[..2], 2, &public_key, &digest).is_err());
+}
+
+#[test]
+fn test_ecdsa_message_formats() {
+    // EIP-191 test vector: personal_sign("hello")
+    let digest = ecdsa::message_digest(EcdsaMessageFormat::Eip191, b"hello").unwrap();
+    assert_eq!(hex::encode(digest), "50b2c43fd39106bafbba0da34fc430e1f91e3c96ea2acee2bc34119f92b37750");
+
+    assert!(ecdsa::message_digest(EcdsaMessageFormat::Eip712, &[0u8; 63]).is_err());
+    assert!(ecdsa::message_digest(EcdsaMessageFormat::Eip712, &[0u8; 64]).is_ok());
+    assert!(ecdsa::message_digest(EcdsaMessageFormat::Prehashed, &[1u8; 31]).is_err());
+
+    assert_eq!(EcdsaMessageFormat::from_name("eip712").unwrap(), EcdsaMessageFormat::Eip712);
+    assert!(EcdsaMessageFormat::from_name("bogus").is_err());
+}
+
+#[test]
+fn test_ecdsa_keygen_confirmation_catches_equivocating_dealer() {
+    let nodes = vec!["node-a".to_string(), "node-b".to_string(), "node-c".to_string()];
+
+    // node-a deals twice and hands node-c its second dealing; every share still verifies
+    let mut keygen_states = HashMap::new();
+    let mut outgoing = Vec::new();
+    for node in &nodes {
+        let (state, msgs) = ecdsa::keygen_deal(node, &nodes, 2).unwrap();
+        keygen_states.insert(node.clone(), state);
+        outgoing.push((node.clone(), msgs));
+    }
+    let (_, mut second) = ecdsa::keygen_deal("node-a", &nodes, 2).unwrap();
+    let mut inbox = route(outgoing);
+    inbox.get_mut("node-c").unwrap().insert("node-a".into(), second.remove("node-c").unwrap());
+
+    let digests = nodes.iter()
+        .map(|n| ecdsa::keygen_confirmation_digest(&ecdsa::keygen_finish(keygen_states.remove(n).unwrap(), &inbox[n]).unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(digests[0], digests[1]);
+    assert_ne!(digests[1], digests[2], "the node dealt other commitments confirms another group");
+}
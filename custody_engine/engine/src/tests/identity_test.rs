@@ -0,0 +1,32 @@
+use custody_engine::dkg::types::{DKGMessage, DKGPackage};
+use custody_engine::identity::{self, NodeIdentity};
+
+#[test]
+fn test_sealed_box_opens_only_for_recipient_and_context() {
+    let (alice, bob) = (NodeIdentity::generate(), NodeIdentity::generate());
+
+    let sealed = identity::seal(&bob.public_keys().encryption_key, b"round-2 share", b"group-1").unwrap();
+    assert_eq!(&bob.open(&sealed, b"group-1").unwrap()[..], b"round-2 share");
+
+    assert!(alice.open(&sealed, b"group-1").is_err());
+    assert!(bob.open(&sealed, b"group-2").is_err());
+}
+
+#[test]
+fn test_round2_is_bound_to_sender_and_recipient() {
+    let (node_a, node_b, node_c) = (NodeIdentity::generate(), NodeIdentity::generate(), NodeIdentity::generate());
+
+    let msg = DKGMessage::sealed_round2(&node_a, "group-1", "node-a", "node-b", &node_b.public_keys(), b"secret").unwrap();
+    let bytes = bincode::serialize(&msg).unwrap();
+    let reopen = || bincode::deserialize::<DKGMessage>(&bytes).unwrap();
+
+    match reopen().open(&node_b, "group-1", "node-a", "node-b", &node_a.public_keys()).unwrap() {
+        Some(DKGPackage::Round2(pkg)) => assert_eq!(pkg, b"secret"),
+        _ => panic!("expected a round-2 package"),
+    }
+
+    // Claimed by another sender, delivered to another node, or replayed into another group
+    assert!(reopen().open(&node_b, "group-1", "node-c", "node-b", &node_c.public_keys()).is_err());
+    assert!(reopen().open(&node_c, "group-1", "node-a", "node-c", &node_a.public_keys()).is_err());
+    assert!(reopen().open(&node_b, "group-2", "node-a", "node-b", &node_a.public_keys()).is_err());
+}
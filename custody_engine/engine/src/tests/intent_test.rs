@@ -0,0 +1,65 @@
+use custody_engine::mpc::intent::{self, SigningIntent};
+use custody_engine::policy::{self, SigningPolicy};
+use custody_engine::registry::{OperationalDID, OperationalDIDRegistry, RootDID};
+
+const VC: &str = r#"{"id":"urn:vc:1","type":["VerifiableCredential"],"credentialSubject":{"name":"Alice"}}"#;
+
+#[test]
+fn test_intents_are_domain_separated() {
+    let challenge = intent::signing_bytes(&SigningIntent::DidAuth, VC.as_bytes()).unwrap();
+    let vc = intent::signing_bytes(&SigningIntent::VcProof, VC.as_bytes()).unwrap();
+
+    // The same payload signed under two intents never yields the same bytes
+    assert_ne!(challenge, vc);
+    assert!(challenge.starts_with(intent::DID_AUTH_DOMAIN));
+    assert!(vc.starts_with(intent::VC_PROOF_DOMAIN));
+
+    // A VC cannot be signed as a presentation, and a challenge cannot be signed as a VC
+    assert!(intent::signing_bytes(&SigningIntent::VpProof, VC.as_bytes()).is_err());
+    assert!(intent::signing_bytes(&SigningIntent::VcProof, b"nonce-123").is_err());
+
+    // Any existing proof is ignored when computing the VC input
+    let mut with_proof: serde_json::Value = serde_json::from_str(VC).unwrap();
+    with_proof["proof"] = serde_json::json!({ "type": "MPCSignature2023", "signature": "AA" });
+    assert_eq!(intent::signing_bytes(&SigningIntent::VcProof, with_proof.to_string().as_bytes()).unwrap(), vc);
+}
+
+#[test]
+fn test_jws_intent_validates_header() {
+    let header = base64::encode_config(br#"{"alg":"EdDSA"}"#, base64::URL_SAFE_NO_PAD);
+    let input = format!("{header}.eyJzdWIiOiJhIn0");
+    assert_eq!(intent::signing_bytes(&SigningIntent::Jws, input.as_bytes()).unwrap(), input.as_bytes());
+
+    let es256 = base64::encode_config(br#"{"alg":"ES256"}"#, base64::URL_SAFE_NO_PAD);
+    assert!(intent::signing_bytes(&SigningIntent::Jws, format!("{es256}.e30").as_bytes()).is_err());
+    assert!(intent::signing_bytes(&SigningIntent::Jws, b"custody-did-auth-v1").is_err());
+}
+
+#[test]
+fn test_raw_signing_needs_policy_and_justification() {
+    let registry = OperationalDIDRegistry::new();
+    let op_did = OperationalDID("did:op:raw".into());
+    registry.register_operational_did(op_did.clone(), RootDID("did:example:root".into()), "vault-raw".into(), vec![]).unwrap();
+    let signers = vec!["node-a".to_string(), "node-b".to_string()];
+
+    let raw = SigningIntent::Raw { justification: "legacy partner integration".into() };
+    assert!(intent::signing_bytes(&SigningIntent::Raw { justification: " ".into() }, b"bytes").is_err());
+
+    // Raw bytes cannot stand in for a tagged or JWS input
+    let challenge = intent::signing_bytes(&SigningIntent::DidAuth, b"nonce-123").unwrap();
+    assert!(intent::signing_bytes(&raw, &challenge).is_err());
+    let header = base64::encode_config(br#"{"alg":"EdDSA"}"#, base64::URL_SAFE_NO_PAD);
+    assert!(intent::signing_bytes(&raw, format!("{header}.e30").as_bytes()).is_err());
+    assert_eq!(intent::signing_bytes(&raw, b"bytes").unwrap(), b"bytes");
+    assert!(policy::check_signing(&registry, "did:op:raw", &raw, &signers).is_err());
+
+    let mut raw_policy = SigningPolicy::default();
+    raw_policy.allowed_intents.push("raw".into());
+    registry.set_signing_policy(&op_did, raw_policy).unwrap();
+    assert!(intent::authorize(&registry, "did:op:raw", &raw, b"bytes").is_ok());
+    assert!(policy::check_signing(&registry, "did:op:raw", &raw, &signers).is_ok());
+    assert!(policy::check_signing(&registry, "did:op:raw", &SigningIntent::DidAuth, &signers).is_ok());
+
+    assert_eq!(SigningIntent::from_proto(raw.to_proto(), raw.justification()).unwrap(), raw);
+    assert!(SigningIntent::from_proto(0, "").is_err());
+}
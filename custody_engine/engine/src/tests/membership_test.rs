@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use custody_engine::ciphersuite;
+use custody_engine::identity::NodeIdentity;
+use custody_engine::membership::{MembershipRegistry, MEMBERSHIP};
+use custody_engine::mpc::device;
+use frost_core::Identifier;
+use frost_ed25519::Ed25519Sha512;
+
+#[test]
+fn test_identifiers_persist_and_are_never_reused() {
+    let path = std::env::temp_dir().join(format!("membership-{}.json", uuid::Uuid::new_v4()));
+    let registry = MembershipRegistry::new();
+    registry.load(&path).unwrap();
+
+    let (a, b, c) = (NodeIdentity::generate(), NodeIdentity::generate(), NodeIdentity::generate());
+    assert_eq!(registry.enroll("node-a", a.public_keys()).unwrap().identifier, 1);
+    assert_eq!(registry.enroll("node-b", b.public_keys()).unwrap().identifier, 2);
+
+    // Enrolling again is a no-op; other keys under the same name, or the same keys under another, are not
+    assert_eq!(registry.enroll("node-a", a.public_keys()).unwrap().identifier, 1);
+    assert!(registry.enroll("node-a", c.public_keys()).is_err());
+    assert!(registry.enroll("node-a2", a.public_keys()).is_err());
+
+    // A rename keeps the identifier; a retired identifier is not handed out again
+    assert_eq!(registry.rename("node-a", "node-a2").unwrap().identifier, 1);
+    registry.retire("node-b").unwrap();
+    assert_eq!(registry.enroll("node-c", c.public_keys()).unwrap().identifier, 3);
+
+    // Another process sees the same membership
+    let reloaded = MembershipRegistry::new();
+    assert_eq!(reloaded.load(&path).unwrap(), 2);
+    assert_eq!(reloaded.identifier_of("node-a2"), Some(1));
+    assert_eq!(reloaded.identifier_of("node-a"), None);
+    assert_eq!(reloaded.enroll("node-b", b.public_keys()).unwrap().identifier, 4);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_enrolled_node_keeps_its_share_across_a_rename() {
+    let suffix = uuid::Uuid::new_v4();
+    let nodes = vec![format!("node-a-{suffix}"), format!("node-b-{suffix}")];
+    let enrolled = nodes.iter()
+        .map(|node| MEMBERSHIP.enroll(node, NodeIdentity::generate().public_keys()).unwrap().identifier)
+        .collect::<Vec<_>>();
+
+    // Shares are under the enrolled identifiers, not ones derived from host names
+    for (node, identifier) in nodes.iter().zip(&enrolled) {
+        assert_eq!(
+            ciphersuite::participant_identifier::<Ed25519Sha512>(node).unwrap(),
+            Identifier::try_from(*identifier).unwrap(),
+        );
+    }
+
+    // 2-of-2 DKG
+    let mut secrets1 = HashMap::new();
+    let mut round1 = HashMap::new();
+    for node in &nodes {
+        let (secret, pkg) = ciphersuite::dkg_part1::<Ed25519Sha512>(node, 2, 2).unwrap();
+        secrets1.insert(node.clone(), secret);
+        round1.insert(node.clone(), pkg);
+    }
+    let others = |node: &String| round1.iter()
+        .filter(|(n, _)| *n != node)
+        .map(|(n, p)| (n.clone(), p.clone()))
+        .collect::<HashMap<_, _>>();
+    let mut secrets2 = HashMap::new();
+    let mut inbox2: HashMap<String, HashMap<String, Vec<u8>>> = HashMap::new();
+    for node in &nodes {
+        let (secret, outgoing) = ciphersuite::dkg_part2::<Ed25519Sha512>(&secrets1[node], &others(node)).unwrap();
+        secrets2.insert(node.clone(), secret);
+        for (to, pkg) in outgoing {
+            inbox2.entry(to).or_default().insert(node.clone(), pkg);
+        }
+    }
+    let outputs = nodes.iter()
+        .map(|node| ciphersuite::dkg_part3::<Ed25519Sha512>(&secrets2[node], &others(node), &inbox2[node], &nodes).unwrap())
+        .collect::<Vec<_>>();
+
+    // The first node moves host; it still signs with the share it was dealt
+    let renamed = format!("node-a-moved-{suffix}");
+    MEMBERSHIP.rename(&nodes[0], &renamed).unwrap();
+    let signers = [renamed, nodes[1].clone()];
+
+    let message = b"signed after a rename";
+    let mut nonces = Vec::new();
+    let mut commitments = Vec::new();
+    for (node, output) in signers.iter().zip(&outputs) {
+        let (n, c) = ciphersuite::commit::<Ed25519Sha512>(&output.key_package).unwrap();
+        nonces.push(n);
+        commitments.push((node.clone(), c));
+    }
+    let shares = signers.iter().zip(&outputs).zip(&nonces)
+        .map(|((node, output), n)| (node.clone(), ciphersuite::sign::<Ed25519Sha512>(&output.key_package, n, message, &commitments).unwrap()))
+        .collect::<Vec<_>>();
+
+    let signature = ciphersuite::aggregate::<Ed25519Sha512>(message, &commitments, &shares, &outputs[0].public_key_package).unwrap();
+    ciphersuite::verify::<Ed25519Sha512>(&outputs[0].group_public_key, message, &signature).unwrap();
+}
+
+#[test]
+fn test_only_devices_derive_an_identifier() {
+    let stranger = format!("node-stranger-{}", uuid::Uuid::new_v4());
+    assert!(ciphersuite::participant_identifier::<Ed25519Sha512>(&stranger).is_err());
+    assert!(ciphersuite::dkg_part1::<Ed25519Sha512>(&stranger, 2, 2).is_err());
+
+    let phone = device::device_participant_id("phone-1", "node-a");
+    assert_eq!(
+        ciphersuite::participant_identifier::<Ed25519Sha512>(&phone).unwrap(),
+        Identifier::derive(phone.as_bytes()).unwrap(),
+    );
+}
@@ -0,0 +1,81 @@
+use custody_engine::mpc::intent::SigningIntent;
+use custody_engine::policy::{self, RateLimit, SigningPolicy, TimeWindow};
+use custody_engine::registry::{ApprovalPolicy, Approver, OperationalDID, OperationalDIDRegistry, RootDID};
+
+fn registry_with(op_did: &str, policy: SigningPolicy) -> OperationalDIDRegistry {
+    let registry = OperationalDIDRegistry::new();
+    let did = OperationalDID(op_did.into());
+    registry.register_operational_did(did.clone(), RootDID("did:example:root".into()), "vault-policy".into(), vec![]).unwrap();
+    registry.set_signing_policy(&did, policy).unwrap();
+    registry
+}
+
+#[test]
+fn test_policy_validation_and_rules() {
+    assert!(SigningPolicy::default().validate().is_ok());
+    assert!(SigningPolicy { threshold: 0, ..Default::default() }.validate().is_err());
+    assert!(SigningPolicy { participants: vec!["node-a".into()], ..Default::default() }.validate().is_err());
+    assert!(SigningPolicy { allowed_intents: vec!["anything".into()], ..Default::default() }.validate().is_err());
+
+    let approval = ApprovalPolicy {
+        approvers: vec![Approver { approver_id: "alice".into(), public_key: vec![0; 32] }],
+        quorum: 2,
+    };
+    assert!(SigningPolicy { approval: Some(approval.clone()), ..Default::default() }.validate().is_err());
+
+    // Approval only for the listed intents; untyped requests always need it
+    let gated = SigningPolicy {
+        approval: Some(ApprovalPolicy { quorum: 1, ..approval }),
+        approval_intents: vec!["vc_proof".into()],
+        ..Default::default()
+    };
+    assert!(gated.needs_approval(Some(&SigningIntent::VcProof)));
+    assert!(!gated.needs_approval(Some(&SigningIntent::DidAuth)));
+    assert!(gated.needs_approval(None));
+
+    // A policy change is always allowed so a DID cannot lock itself out
+    let locked = SigningPolicy { allowed_intents: vec![], ..Default::default() };
+    assert!(!locked.allows_intent(&SigningIntent::Jws));
+    assert!(locked.allows_intent(&SigningIntent::PolicyChange));
+
+    // Overnight window wraps past midnight
+    let night = SigningPolicy { time_windows: vec![TimeWindow { start_hour: 22, end_hour: 6 }], ..Default::default() };
+    assert!(night.in_time_window(23));
+    assert!(night.in_time_window(3));
+    assert!(!night.in_time_window(12));
+}
+
+#[test]
+fn test_check_signing_enforces_threshold_participants_and_rate() {
+    let registry = registry_with("did:op:policy", SigningPolicy {
+        participants: vec!["node-a".into(), "node-b".into(), "node-c".into()],
+        rate_limit: Some(RateLimit { max_signatures: 2, window_secs: 3600 }),
+        ..Default::default()
+    });
+    let signers = vec!["node-a".to_string(), "node-b".to_string()];
+
+    assert!(policy::check_signing(&registry, "did:op:policy", &SigningIntent::DidAuth, &signers[..1]).is_err());
+    assert!(policy::check_signing(&registry, "did:op:policy", &SigningIntent::DidAuth, &["node-a".into(), "node-x".into()]).is_err());
+    assert!(policy::check_signing(&registry, "did:op:policy", &SigningIntent::Raw { justification: "ops".into() }, &signers).is_err());
+
+    // Refused requests leave the rate limit untouched; only taken slots count
+    assert!(policy::check_signing(&registry, "did:op:policy", &SigningIntent::DidAuth, &signers).is_ok());
+    assert!(policy::take_rate_slot(&registry, "did:op:policy").is_ok());
+    assert!(policy::check_signing(&registry, "did:op:policy", &SigningIntent::Jws, &signers).is_ok());
+    assert!(policy::take_rate_slot(&registry, "did:op:policy").is_ok());
+    assert!(policy::check_signing(&registry, "did:op:policy", &SigningIntent::DidAuth, &signers).is_ok());
+    assert!(policy::take_rate_slot(&registry, "did:op:policy").is_err());
+}
+
+#[test]
+fn test_policy_change_must_be_newer_and_signed() {
+    let registry = registry_with("did:op:policy", SigningPolicy { version: 3, ..Default::default() });
+
+    let stale = policy::change_payload("did:op:policy", &SigningPolicy { version: 3, ..Default::default() }).unwrap();
+    assert!(policy::apply_change(&registry, &stale, &[0; 64]).is_err());
+
+    // Newer, but not signed by the DID's group
+    let next = policy::change_payload("did:op:policy", &SigningPolicy { version: 4, ..Default::default() }).unwrap();
+    assert!(policy::apply_change(&registry, &next, &[0; 64]).is_err());
+    assert_eq!(registry.get_signing_policy(&OperationalDID("did:op:policy".into())).unwrap().version, 3);
+}
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use custody_engine::ciphersuite::{self, DkgOutput, SuiteId};
+use custody_engine::sim;
+use custody_engine::with_ciphersuite;
+
+/// Runs a 2-of-3 DKG in-process, or with `old` set, a refresh of that group's shares
+fn local_round<C: frost_core::Ciphersuite>(nodes: &[String], old: Option<&HashMap<String, DkgOutput>>) -> HashMap<String, DkgOutput> {
+    sim::enroll_nodes(nodes);
+    let mut secrets1 = HashMap::new();
+    let mut round1 = HashMap::new();
+    for node in nodes {
+        let (secret, pkg) = match old {
+            None => ciphersuite::dkg_part1::<C>(node, 3, 2).unwrap(),
+            Some(_) => ciphersuite::refresh_part1::<C>(node, 3, 2).unwrap(),
+        };
+        secrets1.insert(node.clone(), secret);
+        round1.insert(node.clone(), pkg);
+    }
+
+    let others = |node: &String| round1.iter()
+        .filter(|(n, _)| *n != node)
+        .map(|(n, p)| (n.clone(), p.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut secrets2 = HashMap::new();
+    let mut inbox2: HashMap<String, HashMap<String, Vec<u8>>> = HashMap::new();
+    for node in nodes {
+        let (secret, outgoing) = match old {
+            None => ciphersuite::dkg_part2::<C>(&secrets1[node], &others(node)).unwrap(),
+            Some(_) => ciphersuite::refresh_part2::<C>(&secrets1[node], &others(node)).unwrap(),
+        };
+        secrets2.insert(node.clone(), secret);
+        for (to, pkg) in outgoing {
+            inbox2.entry(to).or_default().insert(node.clone(), pkg);
+        }
+    }
+
+    nodes.iter()
+        .map(|node| {
+            let output = match old {
+                None => ciphersuite::dkg_part3::<C>(&secrets2[node], &others(node), &inbox2[node], nodes),
+                Some(old) => ciphersuite::refresh_part3::<C>(
+                    &secrets2[node], &others(node), &inbox2[node], nodes,
+                    &old[node].key_package, &old[node].public_key_package,
+                ),
+            };
+            (node.clone(), output.unwrap())
+        })
+        .collect()
+}
+
+fn sign_with<C: frost_core::Ciphersuite>(key_packages: &[(&String, &Vec<u8>)], message: &[u8], public_key_package: &[u8]) -> Result<Vec<u8>, String> {
+    let mut nonces = HashMap::new();
+    let mut commitments = Vec::new();
+    for (node, key_package) in key_packages {
+        let (n, c) = ciphersuite::commit::<C>(key_package).unwrap();
+        nonces.insert((*node).clone(), n);
+        commitments.push(((*node).clone(), c));
+    }
+    let shares = key_packages.iter()
+        .map(|(node, key_package)| Ok(((*node).clone(), ciphersuite::sign::<C>(key_package, &nonces[*node], message, &commitments)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    ciphersuite::aggregate::<C>(message, &commitments, &shares, public_key_package)
+}
+
+/// Refreshes a group, then signs with the new shares under the unchanged group key
+fn run_refresh_flow(suite: SuiteId) {
+    let nodes = vec!["node-a".to_string(), "node-b".to_string(), "node-c".to_string()];
+    let message = b"signed after a refresh";
+
+    with_ciphersuite!(suite, |C| {
+        let old = local_round::<C>(&nodes, None);
+        let new = local_round::<C>(&nodes, Some(&old));
+
+        // Same group key, different shares
+        for node in &nodes {
+            assert_eq!(new[node].group_public_key, old[node].group_public_key);
+            assert_ne!(new[node].key_package, old[node].key_package);
+        }
+
+        let fresh = [(&nodes[0], &new[&nodes[0]].key_package), (&nodes[1], &new[&nodes[1]].key_package)];
+        let signature = sign_with::<C>(&fresh, message, &new[&nodes[0]].public_key_package).unwrap();
+        ciphersuite::verify::<C>(&old[&nodes[0]].group_public_key, message, &signature).expect("refreshed shares sign for the old key");
+
+        // An old share is useless next to a new one
+        let mixed = [(&nodes[0], &old[&nodes[0]].key_package), (&nodes[1], &new[&nodes[1]].key_package)];
+        let mixed = sign_with::<C>(&mixed, message, &new[&nodes[0]].public_key_package);
+        assert!(mixed.map_or(true, |sig| ciphersuite::verify::<C>(&old[&nodes[0]].group_public_key, message, &sig).is_err()));
+    });
+}
+
+#[test]
+fn test_refresh_keeps_group_key_all_suites() {
+    run_refresh_flow(SuiteId::Ed25519);
+    run_refresh_flow(SuiteId::Secp256k1Tr);
+    run_refresh_flow(SuiteId::P256);
+    run_refresh_flow(SuiteId::Ristretto255);
+}
+
+#[test]
+fn test_refresh_round1_has_no_constant_term() {
+    type C = frost_ed25519::Ed25519Sha512;
+    sim::enroll_nodes(&["node-a"]);
+    let (_, package) = ciphersuite::refresh_part1::<C>("node-a", 3, 2).unwrap();
+    assert!(ciphersuite::check_round1_package::<C>(&package, 1).is_ok());
+    assert!(ciphersuite::check_round1_package::<C>(&package, 2).is_err());
+}
+
+/// Every dealer deals; every participant finishes with what it was sent
+fn local_reshare<C: frost_core::Ciphersuite>(
+    old: &HashMap<String, DkgOutput>,
+    dealers: &[String],
+    members: &[String],
+    threshold: u16,
+) -> HashMap<String, Result<ciphersuite::DealtOutput, ciphersuite::DkgFault>> {
+    let mut commitments = HashMap::new();
+    let mut inbox: HashMap<String, HashMap<String, Vec<u8>>> = HashMap::new();
+    for dealer in dealers {
+        let (commitment, shares) = ciphersuite::reshare_deal::<C>(&old[dealer].key_package, dealers, members, threshold).unwrap();
+        commitments.insert(dealer.clone(), commitment);
+        for (member, share) in shares {
+            inbox.entry(member).or_default().insert(dealer.clone(), share);
+        }
+    }
+
+    let mut participants = dealers.to_vec();
+    participants.extend(members.iter().filter(|m| !dealers.contains(m)).cloned());
+    participants.into_iter()
+        .map(|node| {
+            let shares = inbox.get(&node).cloned().unwrap_or_default();
+            let output = ciphersuite::reshare_finish::<C>(
+                &node, &commitments, &shares, dealers, members, threshold,
+                &old[&dealers[0]].public_key_package,
+            );
+            (node, output)
+        })
+        .collect()
+}
+
+/// Moves a 2-of-3 group to 3-of-4, with node-a retiring and two new nodes joining
+fn run_reshare_flow(suite: SuiteId) {
+    let old_nodes = ["node-a", "node-b", "node-c"].map(String::from).to_vec();
+    let dealers = old_nodes[..2].to_vec();
+    let members = ["node-b", "node-c", "node-d", "node-e"].map(String::from).to_vec();
+    sim::enroll_nodes(&members);
+    let message = b"signed by the new committee";
+
+    with_ciphersuite!(suite, |C| {
+        let old = local_round::<C>(&old_nodes, None);
+        let outputs = local_reshare::<C>(&old, &dealers, &members, 3)
+            .into_iter()
+            .map(|(node, output)| (node, output.unwrap()))
+            .collect::<HashMap<_, _>>();
+
+        // Everyone agrees on the new committee and keeps the group key; node-a keeps nothing
+        for output in outputs.values() {
+            assert_eq!(output.group_public_key, old[&old_nodes[0]].group_public_key);
+            assert_eq!(output.public_key_package, outputs["node-b"].public_key_package);
+            assert_eq!(output.verifying_shares.len(), members.len());
+        }
+        assert!(outputs["node-a"].key_package.is_none());
+
+        let key_packages = ["node-c", "node-d", "node-e"].map(|n| outputs[n].key_package.clone().unwrap());
+        let signers = ["node-c", "node-d", "node-e"].map(String::from);
+        let signing = signers.iter().zip(&key_packages).collect::<Vec<_>>();
+        let signature = sign_with::<C>(&signing, message, &outputs["node-b"].public_key_package).unwrap();
+        ciphersuite::verify::<C>(&old[&old_nodes[0]].group_public_key, message, &signature).expect("new committee signs for the old key");
+
+        // The new threshold holds: two new shares are not enough
+        let too_few = sign_with::<C>(&signing[..2], message, &outputs["node-b"].public_key_package);
+        assert!(too_few.map_or(true, |sig| ciphersuite::verify::<C>(&old[&old_nodes[0]].group_public_key, message, &sig).is_err()));
+    });
+}
+
+#[test]
+fn test_reshare_to_new_committee_all_suites() {
+    run_reshare_flow(SuiteId::Ed25519);
+    run_reshare_flow(SuiteId::Secp256k1Tr);
+    run_reshare_flow(SuiteId::P256);
+    run_reshare_flow(SuiteId::Ristretto255);
+}
+
+#[test]
+fn test_reshare_rejects_a_dealer_that_deals_another_secret() {
+    type C = frost_ed25519::Ed25519Sha512;
+    let old_nodes = ["node-a", "node-b", "node-c"].map(String::from).to_vec();
+    let old = local_round::<C>(&old_nodes, None);
+    let members = ["node-c", "node-d"].map(String::from).to_vec();
+    sim::enroll_nodes(&members);
+
+    // node-b deals its own share but claims to be dealing alongside node-c instead of node-a
+    let dealers = old_nodes[..2].to_vec();
+    let (honest, honest_shares) = ciphersuite::reshare_deal::<C>(&old["node-a"].key_package, &dealers, &members, 2).unwrap();
+    let wrong_set = vec!["node-b".to_string(), "node-c".to_string()];
+    let (dishonest, dishonest_shares) = ciphersuite::reshare_deal::<C>(&old["node-b"].key_package, &wrong_set, &members, 2).unwrap();
+
+    let commitments = HashMap::from([("node-a".to_string(), honest), ("node-b".to_string(), dishonest)]);
+    let shares = HashMap::from([
+        ("node-a".to_string(), honest_shares["node-c"].clone()),
+        ("node-b".to_string(), dishonest_shares["node-c"].clone()),
+    ]);
+    let fault = ciphersuite::reshare_finish::<C>(
+        "node-c", &commitments, &shares, &dealers, &members, 2, &old["node-a"].public_key_package,
+    ).err().expect("node-b's commitment should not check out");
+    assert_eq!(fault.culprit.as_deref(), Some("node-b"));
+
+    // A threshold above the size of the new committee is refused up front
+    assert!(ciphersuite::reshare_deal::<C>(&old["node-a"].key_package, &dealers, &members, 3).is_err());
+}
+
+/// RFC 8032 test 1: the seed and its public key
+const RFC8032_SEED: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+const RFC8032_PUBLIC: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+
+#[test]
+fn test_imported_key_signs_for_its_original_public_key() {
+    type C = frost_ed25519::Ed25519Sha512;
+    let seed = hex::decode(RFC8032_SEED).unwrap();
+    let public = hex::decode(RFC8032_PUBLIC).unwrap();
+    let members = ["node-b", "node-c", "node-d"].map(String::from).to_vec();
+    sim::enroll_nodes(&["node-a", "node-b", "node-c", "node-d"]);
+    let message = b"signed with an imported key";
+
+    // Seed alone, or seed followed by its public key, give the same signing key
+    let signing_key = ciphersuite::ed25519_signing_key(&seed).unwrap();
+    assert_eq!(*ciphersuite::ed25519_signing_key(&[seed.clone(), public.clone()].concat()).unwrap(), *signing_key);
+
+    // node-a deals but is not a member, so it keeps nothing
+    let (commitment, shares) = ciphersuite::import_deal::<C>(&signing_key, &public, &members, 2).unwrap();
+    let commitments = HashMap::from([("node-a".to_string(), commitment)]);
+    let outputs = ["node-a", "node-b", "node-c", "node-d"].map(|node| {
+        let inbox = shares.get(node)
+            .map(|share| HashMap::from([("node-a".to_string(), share.clone())]))
+            .unwrap_or_default();
+        ciphersuite::import_finish::<C>(node, &commitments, &inbox, "node-a", &members, 2, &public).unwrap()
+    });
+    assert!(outputs[0].key_package.is_none());
+    for output in &outputs {
+        assert_eq!(output.group_public_key, public);
+        assert_eq!(output.public_key_package, outputs[1].public_key_package);
+    }
+
+    let signers = ["node-b", "node-d"].map(String::from);
+    let key_packages = [outputs[1].key_package.clone().unwrap(), outputs[3].key_package.clone().unwrap()];
+    let signing = signers.iter().zip(&key_packages).collect::<Vec<_>>();
+    let signature = sign_with::<C>(&signing, message, &outputs[1].public_key_package).unwrap();
+    ciphersuite::verify::<C>(&public, message, &signature).expect("the group signs for the imported key");
+}
+
+#[test]
+fn test_import_refuses_a_key_that_does_not_match() {
+    type C = frost_ed25519::Ed25519Sha512;
+    let seed = hex::decode(RFC8032_SEED).unwrap();
+    let public = hex::decode(RFC8032_PUBLIC).unwrap();
+    let members = ["node-b", "node-c"].map(String::from).to_vec();
+
+    // An appended public key must belong to the seed, and keys have a fixed size
+    let mut wrong_public = public.clone();
+    wrong_public[0] ^= 1;
+    assert!(ciphersuite::ed25519_signing_key(&[seed.clone(), wrong_public].concat()).is_err());
+    assert!(ciphersuite::ed25519_signing_key(&seed[..31]).is_err());
+
+    // The dealer will not deal a key for someone else's public key
+    let signing_key = ciphersuite::ed25519_signing_key(&seed).unwrap();
+    let other = ciphersuite::ed25519_signing_key(&[7u8; 32]).unwrap();
+    assert!(ciphersuite::import_deal::<C>(&other, &public, &members, 2).is_err());
+
+    // A member told to expect another key (RFC 8032 test 2) catches the dealer
+    let (commitment, shares) = ciphersuite::import_deal::<C>(&signing_key, &public, &members, 2).unwrap();
+    let forged = hex::decode("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c").unwrap();
+    let fault = ciphersuite::import_finish::<C>(
+        "node-b",
+        &HashMap::from([("node-a".to_string(), commitment)]),
+        &HashMap::from([("node-a".to_string(), shares["node-b"].clone())]),
+        "node-a", &members, 2, &forged,
+    ).err().expect("the commitment does not open to the claimed key");
+    assert_eq!(fault.culprit.as_deref(), Some("node-a"));
+}
+
+/// Rebuilds node-a's share of a 2-of-3 group from node-b and node-c
+fn run_repair_flow(suite: SuiteId) {
+    let nodes = ["node-a", "node-b", "node-c"].map(String::from).to_vec();
+    let helpers = nodes[1..].to_vec();
+
+    with_ciphersuite!(suite, |C| {
+        let group = local_round::<C>(&nodes, None);
+
+        let mut inbox: HashMap<String, HashMap<String, Vec<u8>>> = HashMap::new();
+        for helper in &helpers {
+            for (to, part) in ciphersuite::repair_step1::<C>(&group[helper].key_package, &helpers, "node-a").unwrap() {
+                inbox.entry(to).or_default().insert(helper.clone(), part);
+            }
+        }
+        let sums = helpers.iter()
+            .map(|helper| (helper.clone(), ciphersuite::repair_step2::<C>(&inbox[helper]).unwrap()))
+            .collect::<HashMap<_, _>>();
+
+        // The rebuilt share is the one node-a lost
+        let repaired = ciphersuite::repair_finish::<C>("node-a", &sums, &group["node-a"].public_key_package, 2).unwrap();
+        assert_eq!(repaired, group["node-a"].key_package);
+
+        // A helper that sends a wrong sum makes the repair fail instead of sealing a bad share
+        let mut tampered = sums.clone();
+        tampered.insert("node-b".into(), sums["node-c"].clone());
+        assert!(ciphersuite::repair_finish::<C>("node-a", &tampered, &group["node-a"].public_key_package, 2).is_err());
+    });
+}
+
+#[test]
+fn test_repair_rebuilds_a_lost_share_all_suites() {
+    run_repair_flow(SuiteId::Ed25519);
+    run_repair_flow(SuiteId::Secp256k1Tr);
+    run_repair_flow(SuiteId::P256);
+    run_repair_flow(SuiteId::Ristretto255);
+}
+
+#[test]
+fn test_repair_parts_reveal_nothing_alone() {
+    type C = frost_ed25519::Ed25519Sha512;
+    let nodes = ["node-a", "node-b", "node-c"].map(String::from).to_vec();
+    let group = local_round::<C>(&nodes, None);
+    let helpers = nodes[1..].to_vec();
+
+    // Fresh randomness every time, and the lost member cannot be one of its helpers
+    let first = ciphersuite::repair_step1::<C>(&group["node-b"].key_package, &helpers, "node-a").unwrap();
+    let second = ciphersuite::repair_step1::<C>(&group["node-b"].key_package, &helpers, "node-a").unwrap();
+    assert_ne!(first["node-b"], second["node-b"]);
+    assert!(ciphersuite::repair_step1::<C>(&group["node-b"].key_package, &nodes, "node-a").is_err());
+}
@@ -0,0 +1,165 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use custody_engine::identity::NodeIdentity;
+use custody_engine::relay::custody::RelayMessage;
+use custody_engine::relay::{self, MessageType, RelayClient, RelayHandlers, RelayInbox, RetryPolicy, Undelivered};
+use custody_engine::sim::{SimCluster, SimConfig};
+
+fn envelope(identity: &NodeIdentity, sender: &str, from: &str, to_host: &str, sequence: u64) -> RelayMessage {
+    let mut msg = RelayMessage {
+        group_id: "group-1".into(),
+        from_node: from.into(),
+        payload: vec![1, 2, 3],
+        message_type: MessageType::Dkg as i32,
+        message_id: uuid::Uuid::new_v4().to_string(),
+        ..Default::default()
+    };
+    relay::sign_envelope(identity, to_host, sender, sequence, &mut msg);
+    msg
+}
+
+#[test]
+fn test_envelope_must_be_signed_by_its_sender_for_this_node() {
+    let inbox = RelayInbox::new();
+    let node_b = NodeIdentity::generate();
+    let key = node_b.public_keys().signing_key;
+
+    let msg = envelope(&node_b, "node-b", "node-b", "node-a", 1);
+    inbox.check_envelope("node-a", &msg, &key).unwrap();
+
+    // Redirected to another node, altered, moved to another channel, or checked against another key
+    assert!(inbox.check_envelope("node-c", &msg, &key).is_err());
+    let altered = RelayMessage { payload: vec![9], ..msg.clone() };
+    assert!(inbox.check_envelope("node-a", &altered, &key).is_err());
+    let rerouted = RelayMessage { message_type: MessageType::Reshare as i32, ..msg.clone() };
+    assert!(inbox.check_envelope("node-a", &rerouted, &key).is_err());
+    assert!(inbox.check_envelope("node-a", &msg, &NodeIdentity::generate().public_keys().signing_key).is_err());
+
+    // A node speaks for devices homed on it, and for no other node
+    let device = envelope(&node_b, "node-b", "device:phone-1@node-b", "node-a", 2);
+    inbox.check_envelope("node-a", &device, &key).unwrap();
+    let elsewhere = envelope(&node_b, "node-b", "device:phone-2@node-c", "node-a", 3);
+    assert!(inbox.check_envelope("node-a", &elsewhere, &key).is_err());
+    let impostor = envelope(&node_b, "node-b", "node-c", "node-a", 4);
+    assert!(inbox.check_envelope("node-a", &impostor, &key).is_err());
+}
+
+#[test]
+fn test_messages_go_to_the_handler_of_their_channel() {
+    let handlers = RelayHandlers::new();
+    let received: Arc<Mutex<Vec<(String, String, Vec<u8>)>>> = Arc::new(Mutex::new(Vec::new()));
+    let sink = received.clone();
+    handlers.register(MessageType::HealthGossip, move |group_id, from, payload| {
+        sink.lock().unwrap().push((group_id.to_string(), from.to_string(), payload));
+        Ok(())
+    }).unwrap();
+    handlers.register(MessageType::Signing, |_, _, _| Err("bad presign".into())).unwrap();
+
+    // One handler per channel, and none for an untyped message
+    assert!(handlers.register(MessageType::HealthGossip, |_, _, _| Ok(())).is_err());
+    assert!(handlers.register(MessageType::Unspecified, |_, _, _| Ok(())).is_err());
+    assert_eq!(handlers.channels(), [MessageType::Signing, MessageType::HealthGossip]);
+
+    handlers.dispatch(MessageType::HealthGossip, "", "node-b", vec![7]).unwrap();
+    assert_eq!(*received.lock().unwrap(), [(String::new(), "node-b".to_string(), vec![7])]);
+    assert_eq!(handlers.dispatch(MessageType::Signing, "s-1", "node-b", vec![]), Err("bad presign".to_string()));
+    assert!(handlers.dispatch(MessageType::Dkg, "group-1", "node-b", vec![]).is_err());
+}
+
+#[test]
+fn test_stale_envelopes_and_replays_are_refused() {
+    let inbox = RelayInbox::new();
+    let node_b = NodeIdentity::generate();
+    let key = node_b.public_keys().signing_key;
+
+    // Re-signed with a timestamp past the acceptance window
+    let mut stale = envelope(&node_b, "node-b", "node-b", "node-a", 1);
+    stale.sent_at_ms -= relay::MAX_ENVELOPE_AGE.as_millis() as u64 + 1_000;
+    stale.signature = node_b.sign(&relay::envelope_signing_input("node-a", &stale));
+    assert!(inbox.check_envelope("node-a", &stale, &key).is_err());
+
+    let first = envelope(&node_b, "node-b", "node-b", "node-a", 10);
+    inbox.check_envelope("node-a", &first, &key).unwrap();
+    assert!(!inbox.is_duplicate(&first.message_id) && !inbox.is_replay(&first));
+    inbox.record(&first);
+
+    // The same message again is a redelivery; an older number under a new ID is a replay
+    assert!(inbox.is_duplicate(&first.message_id));
+    let older = envelope(&node_b, "node-b", "node-b", "node-a", 9);
+    assert!(!inbox.is_duplicate(&older.message_id) && inbox.is_replay(&older));
+    assert!(!inbox.is_replay(&envelope(&node_b, "node-b", "node-b", "node-a", 11)));
+}
+
+#[tokio::test]
+async fn test_unreachable_peer_is_retried_then_given_up() {
+    let policy = RetryPolicy {
+        initial_backoff: Duration::from_millis(5),
+        max_backoff: Duration::from_millis(20),
+        max_attempts: 3,
+        attempt_timeout: Duration::from_secs(5),
+    };
+    let relay = RelayClient::with_policy(Arc::new(NodeIdentity::generate()), "node-a", policy);
+    let given_up: Arc<Mutex<Vec<Undelivered>>> = Arc::new(Mutex::new(Vec::new()));
+    let sink = given_up.clone();
+    relay.on_undeliverable(move |undelivered| sink.lock().unwrap().push(undelivered.clone()));
+
+    // Sending only queues; the caller never waits on the peer
+    relay.send(MessageType::Dkg, "group-1", "unreachable.invalid", vec![1]).unwrap();
+    relay.send(MessageType::Signing, "group-1", "unreachable.invalid", vec![2]).unwrap();
+
+    for _ in 0..500 {
+        if given_up.lock().unwrap().len() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    // Given up in the order they were queued, each after every attempt
+    let given_up = given_up.lock().unwrap();
+    assert_eq!(given_up.iter().map(|u| u.channel).collect::<Vec<_>>(), [MessageType::Dkg, MessageType::Signing]);
+    assert!(given_up.iter().all(|u| u.to_node == "unreachable.invalid" && u.group_id == "group-1"));
+    assert_ne!(given_up[0].message_id, given_up[1].message_id);
+
+    let health = relay.health();
+    assert_eq!(health.len(), 1);
+    assert_eq!(health[0].peer, "unreachable.invalid");
+    assert_eq!((health[0].queued, health[0].delivered, health[0].retries, health[0].dropped), (0, 0, 4, 2));
+    assert!(health[0].last_error.is_some());
+    assert!(health[0].last_delivered.is_none());
+}
+
+#[tokio::test]
+async fn test_stream_delivers_in_order_across_reconnects() {
+    let cluster = SimCluster::start(SimConfig { nodes: 2, seed: 3, ..Default::default() }).unwrap();
+    let (node_a, node_b) = (&cluster.nodes[0], &cluster.nodes[1]);
+    let received: Arc<Mutex<Vec<(String, Vec<u8>)>>> = Arc::new(Mutex::new(Vec::new()));
+    let sink = received.clone();
+    node_b.relay_handlers.register(MessageType::HealthGossip, move |_, from, payload| {
+        sink.lock().unwrap().push((from.to_string(), payload));
+        Ok(())
+    }).unwrap();
+
+    // Lost writes break the stream; each new stream resends from the oldest unacknowledged message
+    cluster.network.set_loss(0.01);
+    for i in 0u32..500 {
+        node_a.relay.send(MessageType::HealthGossip, "", &node_b.node_id, i.to_be_bytes().to_vec()).unwrap();
+    }
+
+    for _ in 0..1000 {
+        if node_a.relay.health().iter().any(|p| p.peer == node_b.node_id && p.queued == 0) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    // Every message handled once, in the order sent
+    let received = received.lock().unwrap();
+    assert!(received.iter().all(|(from, _)| *from == node_a.node_id));
+    let payloads = received.iter().map(|(_, payload)| payload.clone()).collect::<Vec<_>>();
+    assert_eq!(payloads, (0u32..500).map(|i| i.to_be_bytes().to_vec()).collect::<Vec<_>>());
+
+    let health = node_a.relay.health();
+    let to_b = health.iter().find(|p| p.peer == node_b.node_id).unwrap();
+    assert_eq!((to_b.queued, to_b.delivered, to_b.dropped), (0, 500, 0));
+}
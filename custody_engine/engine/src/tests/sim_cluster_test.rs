@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use custody_engine::ciphersuite::SuiteId;
+use custody_engine::orchestrator;
+use custody_engine::registry::{GroupStatus, OperationalDID};
+use custody_engine::sim::{SimCluster, SimConfig};
+use custody_engine::tls;
+
+use mpc::custody_mpc_client::CustodyMpcClient;
+use mpc::verify_signature_request::Payload;
+use mpc::{RawSignature, SignMessageRequest, SigningIntent, VerifySignatureRequest};
+
+const OP_DID: &str = "did:op:sim";
+const ROOT_DID: &str = "did:example:root";
+
+/// A started cluster with `OP_DID` registered on every node
+fn cluster(config: SimConfig) -> SimCluster {
+    let cluster = SimCluster::start(config).unwrap();
+    cluster.register_did(OP_DID, ROOT_DID).unwrap();
+    cluster
+}
+
+/// Waits until every node holds `group_id` as the DID's active group
+async fn wait_until_active(cluster: &SimCluster, group_id: &str) {
+    for _ in 0..500 {
+        let active = cluster.nodes.iter().all(|node| {
+            node.registry.get_mpc_group(&OperationalDID(OP_DID.into()))
+                .is_some_and(|g| g.group_id == group_id && g.status == GroupStatus::Active)
+        });
+        if active {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("group {group_id} never became active on every node");
+}
+
+/// Signs a DID auth challenge through `node_id`'s coordinator, as an operator would
+async fn sign(node_id: &str, challenge: &[u8]) -> Vec<u8> {
+    let mut client = CustodyMpcClient::new(tls::channel(node_id).await.unwrap());
+    client.sign_message(SignMessageRequest {
+        operational_did: OP_DID.into(),
+        message: challenge.to_vec(),
+        intent: SigningIntent::DidAuth as i32,
+        ..Default::default()
+    }).await.expect("sign failed").into_inner().signature
+}
+
+async fn verify(node_id: &str, challenge: &[u8], signature: Vec<u8>) -> bool {
+    let mut client = CustodyMpcClient::new(tls::channel(node_id).await.unwrap());
+    client.verify_signature(VerifySignatureRequest {
+        operational_did: OP_DID.into(),
+        payload: Some(Payload::Raw(RawSignature { message: challenge.to_vec(), signature })),
+        intent: SigningIntent::DidAuth as i32,
+        ..Default::default()
+    }).await.expect("verify failed").into_inner().valid
+}
+
+#[tokio::test]
+async fn test_dkg_signing_and_refresh_across_the_cluster() {
+    let cluster = cluster(SimConfig::default());
+    let nodes = cluster.node_ids();
+
+    let group_id = orchestrator::orchestrate_dkg(OP_DID, 2, nodes.clone(), SuiteId::default()).await.unwrap();
+    wait_until_active(&cluster, &group_id).await;
+
+    // Any node coordinates; every node verifies
+    let signature = sign(&nodes[0], b"challenge-1").await;
+    assert!(verify(&nodes[2], b"challenge-1", signature.clone()).await);
+    assert!(!verify(&nodes[1], b"challenge-2", signature).await);
+
+    // Refreshed shares still sign for the same key
+    let refreshed = orchestrator::orchestrate_refresh(OP_DID, nodes.clone()).await.unwrap();
+    wait_until_active(&cluster, &refreshed).await;
+    let signature = sign(&nodes[1], b"challenge-3").await;
+    assert!(verify(&nodes[0], b"challenge-3", signature).await);
+}
+
+#[tokio::test]
+async fn test_dkg_completes_over_a_slow_lossy_network() {
+    let cluster = cluster(SimConfig { seed: 7, ..Default::default() });
+    let nodes = cluster.node_ids();
+
+    // Lost writes break connections; the relay resends until each message is acknowledged
+    cluster.network.set_latency(Duration::from_millis(5));
+    cluster.network.set_link_latency(&nodes[0], &nodes[2], Duration::from_millis(40));
+    cluster.network.set_loss(0.02);
+
+    let group_id = orchestrator::orchestrate_dkg(OP_DID, 2, nodes.clone(), SuiteId::default()).await.unwrap();
+    wait_until_active(&cluster, &group_id).await;
+
+    // Signing calls vaults directly, without the relay's retries
+    cluster.network.set_loss(0.0);
+    let signature = sign(&nodes[2], b"challenge").await;
+    assert!(verify(&nodes[0], b"challenge", signature).await);
+}
+
+#[tokio::test]
+async fn test_partitioned_node_fails_the_dkg_until_healed() {
+    let cluster = cluster(SimConfig::default());
+    let nodes = cluster.node_ids();
+
+    // The operator still reaches every node; the nodes cannot reach the cut-off one
+    cluster.network.isolate(&nodes[2]);
+    assert!(orchestrator::orchestrate_dkg(OP_DID, 2, nodes.clone(), SuiteId::default()).await.is_err());
+    assert!(cluster.nodes.iter().all(|n| n.registry.get_mpc_group(&OperationalDID(OP_DID.into())).is_none()));
+
+    let to_isolated = cluster.nodes[0].relay.health().into_iter().find(|p| p.peer == nodes[2]).unwrap();
+    assert!(to_isolated.dropped > 0 && to_isolated.delivered == 0);
+
+    cluster.network.heal();
+    let group_id = orchestrator::orchestrate_dkg(OP_DID, 2, nodes.clone(), SuiteId::default()).await.unwrap();
+    wait_until_active(&cluster, &group_id).await;
+}
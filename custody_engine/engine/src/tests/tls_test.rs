@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use custody_engine::identity::{IdentityDirectory, NodeIdentity};
+use custody_engine::tls::{self, PeerRole};
+
+const YEAR: Duration = Duration::from_secs(365 * 86_400);
+
+fn der(cert_pem: &str) -> Vec<u8> {
+    let (_, pem) = x509_parser::pem::parse_x509_pem(cert_pem.as_bytes()).unwrap();
+    pem.contents
+}
+
+#[test]
+fn test_node_certificate_names_the_node_and_its_identity_key() {
+    let ca = tls::create_ca("test-ca", YEAR).unwrap();
+    let node = NodeIdentity::generate();
+    let cert = tls::issue_node_certificate(&ca, "node-a", &node.public_keys().signing_key, YEAR).unwrap();
+
+    let holder = tls::certificate_identity(cert.cert_pem.as_bytes()).unwrap();
+    assert_eq!(holder.name, "node-a");
+    assert_eq!(holder.role, PeerRole::Node);
+    assert_eq!(holder.identity_key, node.public_keys().signing_key);
+
+    // Rotating issues a new TLS key for the same holder
+    let rotated = tls::issue_node_certificate(&ca, &holder.name, &holder.identity_key, YEAR).unwrap();
+    assert_ne!(rotated.key_pem, cert.key_pem);
+    assert_eq!(tls::certificate_identity(rotated.cert_pem.as_bytes()).unwrap(), holder);
+
+    // Operators carry a name only; a CA certificate names no one
+    let operator = tls::issue_operator_certificate(&ca, "alice", YEAR).unwrap();
+    let holder = tls::certificate_identity(operator.cert_pem.as_bytes()).unwrap();
+    assert_eq!((holder.name.as_str(), holder.role), ("alice", PeerRole::Operator));
+    assert!(holder.identity_key.is_empty());
+    assert!(tls::certificate_identity(ca.cert_pem.as_bytes()).is_err());
+}
+
+#[test]
+fn test_node_certificate_must_match_the_pinned_identity() {
+    let ca = tls::create_ca("test-ca", YEAR).unwrap();
+    let directory = IdentityDirectory::new();
+    let node_a = NodeIdentity::generate();
+    directory.pin("node-a", node_a.public_keys()).unwrap();
+
+    let genuine = tls::issue_node_certificate(&ca, "node-a", &node_a.public_keys().signing_key, YEAR).unwrap();
+    assert_eq!(tls::authorize_peer(&der(&genuine.cert_pem), &directory).unwrap().name, "node-a");
+
+    // A certificate for node-a with someone else's identity key
+    let other = NodeIdentity::generate();
+    let forged = tls::issue_node_certificate(&ca, "node-a", &other.public_keys().signing_key, YEAR).unwrap();
+    assert!(tls::authorize_peer(&der(&forged.cert_pem), &directory).is_err());
+
+    // A node not pinned yet is admitted on the CA's word
+    let fresh = tls::issue_node_certificate(&ca, "node-b", &other.public_keys().signing_key, YEAR).unwrap();
+    assert_eq!(tls::authorize_peer(&der(&fresh.cert_pem), &directory).unwrap().role, PeerRole::Node);
+}
+
+#[test]
+fn test_loaded_material_must_belong_to_this_node() {
+    let ca = tls::create_ca("test-ca", YEAR).unwrap();
+    let node = NodeIdentity::generate();
+    let cert = tls::issue_node_certificate(&ca, "node-a", &node.public_keys().signing_key, YEAR).unwrap();
+
+    let dir = std::env::temp_dir().join(format!("tls-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join(tls::CA_CERT_FILE), &ca.cert_pem).unwrap();
+    std::fs::write(dir.join(tls::NODE_CERT_FILE), &cert.cert_pem).unwrap();
+    std::fs::write(dir.join(tls::NODE_KEY_FILE), &cert.key_pem).unwrap();
+
+    let loaded = tls::ClusterTls::load(&dir).unwrap();
+    loaded.check_local("node-a", &node.public_keys()).unwrap();
+    assert!(loaded.check_local("node-b", &node.public_keys()).is_err());
+    assert!(loaded.check_local("node-a", &NodeIdentity::generate().public_keys()).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
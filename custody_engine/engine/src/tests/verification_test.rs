@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use custody_engine::ciphersuite::{self, DkgOutput, SuiteId};
+use custody_engine::registry::{GroupStatus, MPCGroupDescriptor, OperationalDID, OperationalDIDRegistry, RootDID};
+use custody_engine::mpc::intent::{self, SigningIntent};
+use custody_engine::sim;
+use custody_engine::verification;
+use frost_ed25519::Ed25519Sha512 as C;
+
+/// Runs a 2-of-3 Ed25519 DKG in-process and returns each node's output
+fn local_dkg(nodes: &[String]) -> HashMap<String, DkgOutput> {
+    sim::enroll_nodes(nodes);
+    let mut secrets1 = HashMap::new();
+    let mut round1 = HashMap::new();
+    for node in nodes {
+        let (secret, pkg) = ciphersuite::dkg_part1::<C>(node, 3, 2).unwrap();
+        secrets1.insert(node.clone(), secret);
+        round1.insert(node.clone(), pkg);
+    }
+
+    let others = |node: &String| round1.iter()
+        .filter(|(n, _)| *n != node)
+        .map(|(n, p)| (n.clone(), p.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut secrets2 = HashMap::new();
+    let mut inbox2: HashMap<String, HashMap<String, Vec<u8>>> = HashMap::new();
+    for node in nodes {
+        let (secret, outgoing) = ciphersuite::dkg_part2::<C>(&secrets1[node], &others(node)).unwrap();
+        secrets2.insert(node.clone(), secret);
+        for (to, pkg) in outgoing {
+            inbox2.entry(to).or_default().insert(node.clone(), pkg);
+        }
+    }
+
+    nodes.iter()
+        .map(|node| (node.clone(), ciphersuite::dkg_part3::<C>(&secrets2[node], &others(node), &inbox2[node], nodes).unwrap()))
+        .collect()
+}
+
+/// Signs with the given nodes and aggregates
+fn group_sign(outputs: &HashMap<String, DkgOutput>, signers: &[String], message: &[u8]) -> Vec<u8> {
+    let mut nonces = HashMap::new();
+    let mut commitments = Vec::new();
+    for node in signers {
+        let (n, c) = ciphersuite::commit::<C>(&outputs[node].key_package).unwrap();
+        nonces.insert(node.clone(), n);
+        commitments.push((node.clone(), c));
+    }
+
+    let shares = signers.iter()
+        .map(|node| (node.clone(), ciphersuite::sign::<C>(&outputs[node].key_package, &nonces[node], message, &commitments).unwrap()))
+        .collect::<Vec<_>>();
+
+    ciphersuite::aggregate::<C>(message, &commitments, &shares, &outputs[&signers[0]].public_key_package).unwrap()
+}
+
+fn group_descriptor(group_id: &str, output: &DkgOutput) -> MPCGroupDescriptor {
+    MPCGroupDescriptor {
+        group_id: group_id.to_string(),
+        members: vec![],
+        threshold: 2,
+        dkg_protocol: Some(SuiteId::Ed25519.dkg_protocol().to_string()),
+        session_state: None,
+        public_key_package: Some(output.public_key_package.clone()),
+        status: GroupStatus::Active,
+    }
+}
+
+#[test]
+fn test_verify_across_key_epochs() {
+    let nodes = vec!["node-a".to_string(), "node-b".to_string(), "node-c".to_string()];
+    let op_did = "did:example:verify";
+    let registry = OperationalDIDRegistry::new();
+    registry.register_operational_did(
+        OperationalDID(op_did.into()),
+        RootDID("did:example:root".into()),
+        "vault-verify".into(),
+        vec![],
+    ).unwrap();
+
+    // Epoch 0
+    let first = local_dkg(&nodes);
+    registry.set_mpc_group(&OperationalDID(op_did.into()), group_descriptor("group-0", &first["node-a"])).unwrap();
+    let old_sig = group_sign(&first, &nodes[..2], b"signed before rotation");
+
+    // Epoch 1 after rotation
+    let second = local_dkg(&nodes);
+    registry.set_mpc_group(&OperationalDID(op_did.into()), group_descriptor("group-1", &second["node-a"])).unwrap();
+    let new_sig = group_sign(&second, &nodes[1..], b"signed after rotation");
+
+    let old = verification::verify_raw(&registry, op_did, b"signed before rotation", &old_sig).unwrap();
+    assert_eq!((old.key_epoch, old.group_id.as_str(), old.current), (0, "group-0", false));
+
+    let new = verification::verify_raw(&registry, op_did, b"signed after rotation", &new_sig).unwrap();
+    assert_eq!((new.key_epoch, new.group_id.as_str(), new.current), (1, "group-1", true));
+
+    assert!(verification::verify_raw(&registry, op_did, b"tampered", &new_sig).is_err());
+}
+
+#[test]
+fn test_verify_jws_and_vc_proof() {
+    let nodes = vec!["node-a".to_string(), "node-b".to_string(), "node-c".to_string()];
+    let op_did = "did:example:jws";
+    let registry = OperationalDIDRegistry::new();
+    registry.register_operational_did(
+        OperationalDID(op_did.into()),
+        RootDID("did:example:root".into()),
+        "vault-jws".into(),
+        vec![],
+    ).unwrap();
+
+    let outputs = local_dkg(&nodes);
+    registry.set_mpc_group(&OperationalDID(op_did.into()), group_descriptor("group-0", &outputs["node-a"])).unwrap();
+
+    // Compact JWS
+    let header = base64::encode_config(br#"{"alg":"EdDSA"}"#, base64::URL_SAFE_NO_PAD);
+    let payload = base64::encode_config(br#"{"challenge":"abc"}"#, base64::URL_SAFE_NO_PAD);
+    let signing_input = format!("{header}.{payload}");
+    let sig = group_sign(&outputs, &nodes[..2], signing_input.as_bytes());
+    let jws = format!("{signing_input}.{}", base64::encode_config(&sig, base64::URL_SAFE_NO_PAD));
+
+    let verified = verification::verify_jws(&registry, op_did, &jws, None).unwrap();
+    assert_eq!(verified.key_epoch, 0);
+
+    // VC with an MPCSignature2023 proof over its domain-separated canonical form
+    let unsigned = r#"{"id":"urn:vc:1","type":["VerifiableCredential"],"credentialSubject":{"name":"Alice"}}"#;
+    let vc_sig = group_sign(&outputs, &nodes[..2], &intent::signing_bytes(&SigningIntent::VcProof, unsigned.as_bytes()).unwrap());
+    let mut vc: serde_json::Value = serde_json::from_str(unsigned).unwrap();
+    vc["proof"] = serde_json::json!({ "type": "MPCSignature2023", "signature": base64::encode(&vc_sig) });
+
+    assert!(verification::verify_vc_proof(&registry, op_did, &vc.to_string()).is_ok());
+
+    vc["credentialSubject"]["name"] = serde_json::json!("Mallory");
+    assert!(verification::verify_vc_proof(&registry, op_did, &vc.to_string()).is_err());
+}
@@ -0,0 +1,353 @@
+//! Mutual TLS between custody nodes. A local cluster CA issues every node a
+//! certificate naming the node and binding it to the node's identity signing key;
+//! operators get certificates naming only themselves. Each node serves with its own
+//! certificate and presents it on every call it makes to another node, and each
+//! service decides which kinds of caller it admits (see `Authorize`).
+
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose,
+    IsCa, KeyPair, KeyUsagePurpose, SanType,
+};
+use tonic::service::Interceptor;
+use tonic::transport::{self, Channel, ClientTlsConfig, Endpoint, Identity, ServerTlsConfig};
+use tonic::{Request, Status};
+use x509_parser::extensions::GeneralName;
+
+use crate::identity::{IdentityDirectory, IdentityPublicKeys};
+use crate::sim;
+
+/// URI SAN naming the custody node a certificate belongs to
+const NODE_URI_PREFIX: &str = "urn:custody:node:";
+
+/// URI SAN carrying the node's identity signing key, hex encoded
+const IDENTITY_URI_PREFIX: &str = "urn:custody:identity:";
+
+/// URI SAN naming an operator
+const OPERATOR_URI_PREFIX: &str = "urn:custody:operator:";
+
+/// File names in a node's TLS directory, as written by `custody ca issue`
+pub const CA_CERT_FILE: &str = "ca.pem";
+pub const NODE_CERT_FILE: &str = "node.pem";
+pub const NODE_KEY_FILE: &str = "node.key";
+
+/// What a certificate says its holder is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerRole {
+    Node,       // A custody node
+    Operator,   // A person or tool driving the cluster, e.g. the CLI
+}
+
+/// The holder of a certificate the cluster CA issued
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerIdentity {
+    pub name: String,               // Node ID, or operator name
+    pub role: PeerRole,
+    pub identity_key: Vec<u8>,      // Node's Ed25519 identity key; empty for operators
+}
+
+/// A certificate and its private key, PEM encoded
+#[derive(Debug, Clone)]
+pub struct CertifiedKey {
+    pub cert_pem: String,
+    pub key_pem: String,
+}
+
+/// This node's TLS material
+pub struct ClusterTls {
+    ca_pem: Vec<u8>,
+    cert_pem: Vec<u8>,
+    key_pem: Vec<u8>,
+    pub local: PeerIdentity,        // Read from our own certificate
+}
+
+/// TLS material every outbound call uses once installed
+static CLUSTER_TLS: OnceLock<ClusterTls> = OnceLock::new();
+
+impl ClusterTls {
+    /// Loads the CA certificate, node certificate and node key from `dir`
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let read = |file: &str| std::fs::read(dir.join(file))
+            .map_err(|e| format!("read {} failed: {e}", dir.join(file).display()));
+        let (ca_pem, cert_pem, key_pem) = (read(CA_CERT_FILE)?, read(NODE_CERT_FILE)?, read(NODE_KEY_FILE)?);
+
+        let local = certificate_identity(&cert_pem)?;
+        if local.role != PeerRole::Node {
+            return Err(format!("{} is not a node certificate", dir.join(NODE_CERT_FILE).display()));
+        }
+        Ok(ClusterTls { ca_pem, cert_pem, key_pem, local })
+    }
+
+    /// A node's certificate must name it and carry the identity key it actually holds
+    pub fn check_local(&self, node_id: &str, keys: &IdentityPublicKeys) -> Result<(), String> {
+        if self.local.name != node_id {
+            return Err(format!("TLS certificate is for {}, not {node_id}", self.local.name));
+        }
+        if self.local.identity_key != keys.signing_key {
+            return Err(format!("TLS certificate of {node_id} carries another identity key"));
+        }
+        Ok(())
+    }
+
+    /// Serves with the node certificate and refuses any caller without a certificate
+    /// from the cluster CA; `Authorize` then decides which holders a service admits
+    pub fn server_config(&self) -> ServerTlsConfig {
+        ServerTlsConfig::new()
+            .identity(Identity::from_pem(&self.cert_pem, &self.key_pem))
+            .client_ca_root(transport::Certificate::from_pem(&self.ca_pem))
+    }
+
+    /// Serves the device listener with the node certificate. Devices hold no cluster
+    /// certificate, so none is asked for: only services that admit anonymous callers
+    /// may be mounted behind this config.
+    pub fn device_server_config(&self) -> ServerTlsConfig {
+        ServerTlsConfig::new().identity(Identity::from_pem(&self.cert_pem, &self.key_pem))
+    }
+
+    /// Presents the node certificate and accepts only a server certified as `server_name`
+    pub fn client_config(&self, server_name: &str) -> ClientTlsConfig {
+        ClientTlsConfig::new()
+            .ca_certificate(transport::Certificate::from_pem(&self.ca_pem))
+            .identity(Identity::from_pem(&self.cert_pem, &self.key_pem))
+            .domain_name(server_name)
+    }
+}
+
+/// Makes `tls` the material of every outbound call from this process
+pub fn install(tls: ClusterTls) -> Result<(), String> {
+    CLUSTER_TLS.set(tls).map_err(|_| "cluster TLS is already installed".to_string())
+}
+
+pub fn installed() -> Option<&'static ClusterTls> {
+    CLUSTER_TLS.get()
+}
+
+/// Why a channel to a node could not be opened
+#[derive(Debug)]
+pub enum ChannelError {
+    NoClusterTls,                   // Nodes are only ever called over mutual TLS
+    Transport(transport::Error),
+}
+
+impl std::fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChannelError::NoClusterTls => write!(f, "cluster TLS is not installed"),
+            ChannelError::Transport(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ChannelError {}
+
+impl From<transport::Error> for ChannelError {
+    fn from(e: transport::Error) -> Self {
+        ChannelError::Transport(e)
+    }
+}
+
+/// Opens a channel to another node at `addr` (`host` or `host:port`). The channel is
+/// mutually authenticated and the server must hold a certificate for `host`; without
+/// installed cluster TLS there is no channel, never a plaintext one. A host on a
+/// simulated network (see `sim`) is reached over that network.
+pub async fn channel(addr: &str) -> Result<Channel, ChannelError> {
+    let addr = addr.trim_start_matches("http://").trim_start_matches("https://");
+    if let Some(network) = sim::network::network_of(host_of(addr)) {
+        return Ok(network.channel(host_of(addr)).await?);
+    }
+    channel_as(addr, host_of(addr)).await
+}
+
+/// Opens a channel to this node's own services at `addr`
+pub async fn local_channel(addr: &str) -> Result<Channel, ChannelError> {
+    if let Some(node_id) = sim::network::local_node().filter(|node| sim::network::network_of(node).is_some()) {
+        return channel(&node_id).await;
+    }
+    let tls = installed().ok_or(ChannelError::NoClusterTls)?;
+    channel_as(addr, &tls.local.name).await
+}
+
+async fn channel_as(addr: &str, server_name: &str) -> Result<Channel, ChannelError> {
+    let tls = installed().ok_or(ChannelError::NoClusterTls)?;
+    Ok(Endpoint::from_shared(format!("https://{addr}"))?
+        .tls_config(tls.client_config(server_name))?
+        .connect()
+        .await?)
+}
+
+/// Opens a device's channel to its home node, checking the node's certificate against
+/// `cluster_ca_pem` when the device has been given the cluster CA
+pub async fn device_channel(home_endpoint: &str, cluster_ca_pem: Option<&[u8]>) -> Result<Channel, transport::Error> {
+    let addr = home_endpoint.trim_start_matches("http://").trim_start_matches("https://");
+    match cluster_ca_pem {
+        Some(ca_pem) => Endpoint::from_shared(format!("https://{addr}"))?
+            .tls_config(ClientTlsConfig::new()
+                .ca_certificate(transport::Certificate::from_pem(ca_pem))
+                .domain_name(host_of(addr)))?
+            .connect()
+            .await,
+        None => Endpoint::from_shared(format!("http://{addr}"))?.connect().await,
+    }
+}
+
+/// Host part of `host`, `host:port` or `[v6]:port`
+fn host_of(addr: &str) -> &str {
+    if let Some(rest) = addr.strip_prefix('[') {
+        return rest.split(']').next().unwrap_or(rest);
+    }
+    addr.split(':').next().unwrap_or(addr)
+}
+
+/// Admits a call only from a holder of a cluster certificate, and attaches its
+/// `PeerIdentity` to the request. A node's certificate must carry the identity key
+/// the node is pinned with; operators are admitted only where `operators` is set.
+#[derive(Clone)]
+pub struct Authorize {
+    directory: Arc<IdentityDirectory>,
+    operators: bool,
+}
+
+impl Authorize {
+    /// For services only custody nodes call: relay and vault
+    pub fn nodes(directory: Arc<IdentityDirectory>) -> Self {
+        Authorize { directory, operators: false }
+    }
+
+    /// For services operators drive as well: DKG, MPC, issuer, VC and management
+    pub fn nodes_and_operators(directory: Arc<IdentityDirectory>) -> Self {
+        Authorize { directory, operators: true }
+    }
+
+    /// Identity of the caller, if it may call the service
+    pub fn check<T>(&self, request: &Request<T>) -> Result<PeerIdentity, Status> {
+        let certs = request.peer_certs().ok_or_else(|| Status::unauthenticated("client certificate required"))?;
+        let leaf = certs.first().ok_or_else(|| Status::unauthenticated("client certificate required"))?;
+
+        let peer = authorize_peer(leaf.as_ref(), &self.directory).map_err(Status::permission_denied)?;
+        if peer.role == PeerRole::Operator && !self.operators {
+            return Err(Status::permission_denied(format!("operator {} may not call this service", peer.name)));
+        }
+        Ok(peer)
+    }
+}
+
+impl Interceptor for Authorize {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let peer = self.check(&request)?;
+        request.extensions_mut().insert(peer);
+        Ok(request)
+    }
+}
+
+/// Reads the identity from a DER certificate the TLS layer already checked against the
+/// cluster CA. A node pinned with a different identity key is refused; a node not yet
+/// pinned is admitted on the CA's word, and the caller checks its key on first contact.
+pub fn authorize_peer(cert_der: &[u8], directory: &IdentityDirectory) -> Result<PeerIdentity, String> {
+    let (_, cert) = x509_parser::parse_x509_certificate(cert_der).map_err(|e| format!("bad certificate: {e}"))?;
+    let peer = identity_from(&cert)?;
+
+    if peer.role == PeerRole::Node {
+        if let Some(pinned) = directory.get(&peer.name) {
+            if pinned.signing_key != peer.identity_key {
+                return Err(format!("certificate of {} carries another identity key", peer.name));
+            }
+        }
+    }
+    Ok(peer)
+}
+
+/// Reads the identity from a PEM certificate
+pub fn certificate_identity(cert_pem: &[u8]) -> Result<PeerIdentity, String> {
+    let (_, pem) = x509_parser::pem::parse_x509_pem(cert_pem).map_err(|e| format!("bad certificate PEM: {e}"))?;
+    let cert = pem.parse_x509().map_err(|e| format!("bad certificate: {e}"))?;
+    identity_from(&cert)
+}
+
+fn identity_from(cert: &x509_parser::certificate::X509Certificate) -> Result<PeerIdentity, String> {
+    let san = cert.subject_alternative_name()
+        .map_err(|e| format!("bad subject alternative names: {e}"))?
+        .ok_or("certificate names no custody identity")?;
+
+    let (mut node, mut identity_key, mut operator) = (None, None, None);
+    for name in &san.value.general_names {
+        let GeneralName::URI(uri) = name else { continue };
+        if let Some(id) = uri.strip_prefix(NODE_URI_PREFIX) {
+            node = Some(id.to_string());
+        } else if let Some(key) = uri.strip_prefix(IDENTITY_URI_PREFIX) {
+            identity_key = Some(hex::decode(key).map_err(|_| "bad identity key in certificate")?);
+        } else if let Some(name) = uri.strip_prefix(OPERATOR_URI_PREFIX) {
+            operator = Some(name.to_string());
+        }
+    }
+
+    match (node, identity_key, operator) {
+        (Some(name), Some(identity_key), None) => Ok(PeerIdentity { name, role: PeerRole::Node, identity_key }),
+        (None, None, Some(name)) => Ok(PeerIdentity { name, role: PeerRole::Operator, identity_key: Vec::new() }),
+        _ => Err("certificate names no single custody identity".into()),
+    }
+}
+
+/// Creates a self-signed cluster CA
+pub fn create_ca(name: &str, valid_for: Duration) -> Result<CertifiedKey, String> {
+    let mut params = CertificateParams::default();
+    params.distinguished_name = distinguished_name(name);
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+    set_validity(&mut params, valid_for);
+
+    let ca = Certificate::from_params(params).map_err(|e| format!("create CA failed: {e}"))?;
+    Ok(CertifiedKey {
+        cert_pem: ca.serialize_pem().map_err(|e| format!("create CA failed: {e}"))?,
+        key_pem: ca.serialize_private_key_pem(),
+    })
+}
+
+/// Issues a node a certificate for its node ID and identity signing key, with a fresh
+/// TLS key. Issuing again for the same node rotates its certificate.
+pub fn issue_node_certificate(ca: &CertifiedKey, node_id: &str, identity_key: &[u8], valid_for: Duration) -> Result<CertifiedKey, String> {
+    let mut params = CertificateParams::new(vec![node_id.to_string()]);
+    params.subject_alt_names.push(SanType::URI(format!("{NODE_URI_PREFIX}{node_id}")));
+    params.subject_alt_names.push(SanType::URI(format!("{IDENTITY_URI_PREFIX}{}", hex::encode(identity_key))));
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth, ExtendedKeyUsagePurpose::ClientAuth];
+    issue(ca, node_id, params, valid_for)
+}
+
+/// Issues an operator a client certificate
+pub fn issue_operator_certificate(ca: &CertifiedKey, name: &str, valid_for: Duration) -> Result<CertifiedKey, String> {
+    let mut params = CertificateParams::default();
+    params.subject_alt_names.push(SanType::URI(format!("{OPERATOR_URI_PREFIX}{name}")));
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+    issue(ca, name, params, valid_for)
+}
+
+fn issue(ca: &CertifiedKey, name: &str, mut params: CertificateParams, valid_for: Duration) -> Result<CertifiedKey, String> {
+    let ca_key = KeyPair::from_pem(&ca.key_pem).map_err(|e| format!("bad CA key: {e}"))?;
+    let ca_params = CertificateParams::from_ca_cert_pem(&ca.cert_pem, ca_key).map_err(|e| format!("bad CA certificate: {e}"))?;
+    let signer = Certificate::from_params(ca_params).map_err(|e| format!("bad CA certificate: {e}"))?;
+
+    params.distinguished_name = distinguished_name(name);
+    params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+    set_validity(&mut params, valid_for);
+
+    let cert = Certificate::from_params(params).map_err(|e| format!("issue certificate failed: {e}"))?;
+    Ok(CertifiedKey {
+        cert_pem: cert.serialize_pem_with_signer(&signer).map_err(|e| format!("issue certificate failed: {e}"))?,
+        key_pem: cert.serialize_private_key_pem(),
+    })
+}
+
+fn distinguished_name(common_name: &str) -> DistinguishedName {
+    let mut name = DistinguishedName::new();
+    name.push(DnType::CommonName, common_name);
+    name
+}
+
+/// Valid from an hour ago, to allow for clock skew between nodes
+fn set_validity(params: &mut CertificateParams, valid_for: Duration) {
+    let now = time::OffsetDateTime::now_utc();
+    params.not_before = now - time::Duration::hours(1);
+    params.not_after = now + time::Duration::seconds(valid_for.as_secs() as i64);
+}
@@ -1,5 +1,7 @@
 //! Shared data types for custody engine: Participant IDs, Shard IDs, and Custody Shards.
 
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use zeroize::Zeroize;
 
@@ -43,4 +45,16 @@ pub struct VaultRecord {
     pub bbs_private_key: Option<String>,          // Issuer key if this vault belongs to an issuer
     pub bbs_public_key: Option<String>,
     pub active_nonce: Option<Vec<u8>>, // Binary nonce blob (bincode serialized)
+    #[serde(default)]
+    pub batch_nonces: Vec<Vec<u8>>,    // One nonce blob per item of a pending batch signing round
+    #[serde(default)]
+    pub ecdsa_share: Option<String>,   // Threshold ECDSA key share (secp256k1), kept apart from the FROST shard
+    #[serde(default)]
+    pub ecdsa_presignatures: HashMap<String, String>, // Unused presignatures by ID, each consumed by exactly one signature
+    #[serde(default)]
+    pub shard_epoch: u32,              // Bumped each time the shard is refreshed in place
+    #[serde(default)]
+    pub dkg_sessions: HashMap<String, Vec<u8>>, // In-progress DKG state by group ID, round secrets included
+    #[serde(default)]
+    pub consumed_approvals: HashMap<String, u64>, // Approval request IDs signed under, until they expire (unix seconds)
 }
\ No newline at end of file
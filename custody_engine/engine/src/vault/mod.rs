@@ -8,6 +8,7 @@ use crate::vault::backend::{VaultBackend, simulated::SimulatedTEEBackend};
 use lazy_static::lazy_static;
 use std::sync::{Arc, OnceLock};
 use std::collections::HashMap;
+use zeroize::{Zeroize, Zeroizing};
 pub mod backend;
 pub mod types;
 //use serde;
@@ -79,6 +80,48 @@ pub fn add_shard(vault_id: &str, shard: &str) -> Result<(), String> {
     store_record(vault_id, &record)
 }
 
+/// Replace the MPC shard with refreshed shares of the same group key and start a new
+/// shard epoch. The old shard is wiped, along with nonces committed under it.
+pub fn replace_shard(vault_id: &str, shard: &str) -> Result<u32, String> {
+    let mut record = load_record(vault_id)?;
+    if let Some(mut old) = record.mpc_shard.replace(shard.to_string()) {
+        old.zeroize();
+    }
+    record.active_nonce = None;
+    record.batch_nonces.clear();
+    record.shard_epoch += 1;
+    store_record(vault_id, &record)?;
+    Ok(record.shard_epoch)
+}
+
+/// Remove the MPC shard, e.g. when the DKG that produced it was aborted
+pub fn remove_shard(vault_id: &str) -> Result<(), String> {
+    let mut record = load_record(vault_id)?;
+    record.mpc_shard = None;
+    store_record(vault_id, &record)
+}
+
+/// Seal the state of an in-progress DKG session, replacing any earlier state
+pub fn store_dkg_session(vault_id: &str, group_id: &str, state: &[u8]) -> Result<(), String> {
+    let mut record = load_record(vault_id)?;
+    record.dkg_sessions.insert(group_id.to_string(), state.to_vec());
+    store_record(vault_id, &record)
+}
+
+/// All DKG sessions sealed in a vault, by group ID
+pub fn load_dkg_sessions(vault_id: &str) -> Result<HashMap<String, Vec<u8>>, String> {
+    Ok(load_record(vault_id)?.dkg_sessions)
+}
+
+/// Drop a DKG session's state once it has finished, failed or been aborted
+pub fn remove_dkg_session(vault_id: &str, group_id: &str) -> Result<(), String> {
+    let mut record = load_record(vault_id)?;
+    if record.dkg_sessions.remove(group_id).is_some() {
+        store_record(vault_id, &record)?;
+    }
+    Ok(())
+}
+
 /// Get MPC shard from vault for signing session
 pub fn get_shard(registry: &OperationalDIDRegistry, op_did: &str) -> Result<String, String> {
     // Lookup vault ID
@@ -100,12 +143,103 @@ pub fn set_nonce(registry: &OperationalDIDRegistry, op_did: &str, nonce_bytes: V
     store_record(&vault_id, &record)
 }
 
-pub fn get_nonce(registry: &OperationalDIDRegistry, op_did: &str) -> Result<Vec<u8>, String> {
+/// Remove and return the stored nonce; one nonce signing two messages leaks the key share
+pub fn take_nonce(registry: &OperationalDIDRegistry, op_did: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+    let vault_id = registry.get_vault_id_for_op_did(op_did)
+        .ok_or("Vault not found")?;
+
+    let mut record = load_record(&vault_id)?;
+    let nonce = record.active_nonce.take().ok_or("Nonce not found")?;
+    store_record(&vault_id, &record)?;
+    Ok(Zeroizing::new(nonce))
+}
+
+/// Store the nonces for a batch signing round (one per message, in batch order)
+pub fn set_batch_nonces(registry: &OperationalDIDRegistry, op_did: &str, nonces: Vec<Vec<u8>>) -> Result<(), String> {
+    let vault_id = registry.get_vault_id_for_op_did(op_did)
+        .ok_or("Vault not found")?;
+
+    let mut record = load_record(&vault_id)?;
+    record.batch_nonces = nonces;
+    let stored = store_record(&vault_id, &record);
+    record.batch_nonces.zeroize();
+    stored
+}
+
+/// Remove and return the batch nonces so they can never be used twice
+pub fn take_batch_nonces(registry: &OperationalDIDRegistry, op_did: &str) -> Result<Vec<Zeroizing<Vec<u8>>>, String> {
+    let vault_id = registry.get_vault_id_for_op_did(op_did)
+        .ok_or("Vault not found")?;
+
+    let mut record = load_record(&vault_id)?;
+    if record.batch_nonces.is_empty() {
+        return Err("Batch nonces not found".to_string());
+    }
+
+    // Moved, not copied, into buffers that wipe themselves
+    let nonces = std::mem::take(&mut record.batch_nonces).into_iter().map(Zeroizing::new).collect::<Vec<_>>();
+    store_record(&vault_id, &record)?;
+    Ok(nonces)
+}
+
+/// Records that this vault signed under an approval request, refusing one it already
+/// signed under. Entries are dropped once their bundle has expired, since an expired
+/// bundle is refused anyway.
+pub fn consume_approval(registry: &OperationalDIDRegistry, op_did: &str, request_id: &str, expires_at: u64, now: u64) -> Result<(), String> {
+    let vault_id = registry.get_vault_id_for_op_did(op_did)
+        .ok_or("Vault not found")?;
+
+    let mut record = load_record(&vault_id)?;
+    record.consumed_approvals.retain(|_, expiry| *expiry > now);
+    if record.consumed_approvals.contains_key(request_id) {
+        return Err(format!("Approval request {request_id} has already been used"));
+    }
+    record.consumed_approvals.insert(request_id.to_string(), expires_at);
+    store_record(&vault_id, &record)
+}
+
+/// Store the threshold ECDSA key share for this vault
+pub fn set_ecdsa_share(vault_id: &str, share: &str) -> Result<(), String> {
+    let mut record = load_record(vault_id)?;
+    record.ecdsa_share = Some(share.to_string());
+    store_record(vault_id, &record)
+}
+
+/// Get the threshold ECDSA key share for an operational DID
+pub fn get_ecdsa_share(registry: &OperationalDIDRegistry, op_did: &str) -> Result<String, String> {
     let vault_id = registry.get_vault_id_for_op_did(op_did)
         .ok_or("Vault not found")?;
 
     let record = load_record(&vault_id)?;
-    record.active_nonce.clone().ok_or("Nonce not found".to_string())
+    record.ecdsa_share.clone().ok_or("ECDSA share not found".to_string())
+}
+
+/// Add a finished ECDSA presignature
+pub fn add_ecdsa_presignature(vault_id: &str, presig_id: &str, presig: &str) -> Result<(), String> {
+    let mut record = load_record(vault_id)?;
+    record.ecdsa_presignatures.insert(presig_id.to_string(), presig.to_string());
+    store_record(vault_id, &record)
+}
+
+/// List the IDs of unused ECDSA presignatures
+pub fn list_ecdsa_presignatures(registry: &OperationalDIDRegistry, op_did: &str) -> Result<Vec<String>, String> {
+    let vault_id = registry.get_vault_id_for_op_did(op_did)
+        .ok_or("Vault not found")?;
+
+    let record = load_record(&vault_id)?;
+    Ok(record.ecdsa_presignatures.keys().cloned().collect())
+}
+
+/// Remove and return a presignature so it can never sign twice
+pub fn take_ecdsa_presignature(registry: &OperationalDIDRegistry, op_did: &str, presig_id: &str) -> Result<String, String> {
+    let vault_id = registry.get_vault_id_for_op_did(op_did)
+        .ok_or("Vault not found")?;
+
+    let mut record = load_record(&vault_id)?;
+    let presig = record.ecdsa_presignatures.remove(presig_id)
+        .ok_or("Presignature not found")?;
+    store_record(&vault_id, &record)?;
+    Ok(presig)
 }
 
 /// Add a verifiable credential to the vault
@@ -1,63 +1,183 @@
+use crate::ciphersuite::{self, SuiteId};
+use crate::mpc::derivation;
+use crate::mpc::approval::{self, ApprovalBundle};
+use crate::mpc::intent::{self, SigningIntent};
+use crate::policy;
+use crate::mpc::ecdsa::{self, EcdsaMessageFormat, EcdsaSignatureShare, Presignature};
+use crate::with_ciphersuite;
 
-
-use frost_ed25519::prelude::*;
-use frost_ed25519::round1::generate_nonce;
-use frost_core::Group;
-use frost_ed25519::SigningNonces;
-
-use rand_core::OsRng;
 use base64;
-use bincode;
-
-use frost_ed25519::round2::sign;
-use frost_ed25519::keys::{SigningPackage, SecretShare};
 use zeroize::Zeroizing;
+use std::collections::HashSet;
 
 /// Generates a new FROST nonce and stores the sealed result in the vault
 pub fn generate_nonce(
     registry: &OperationalDIDRegistry,
     op_did: &str,
 ) -> Result<Vec<u8>, String> {
-    let shard_b64 = get_shard(registry, op_did)?;
-    let shard_bytes = base64::decode(&shard_b64).map_err(|_| "bad base64")?;
-    let _share = frost_ed25519::keys::SecretShare::deserialize(&shard_bytes)
-        .map_err(|_| "bad shard")?;
+    let (suite, key_package) = load_key_package(registry, op_did)?;
 
-    let mut rng = rand_core::OsRng;
-    let nonces = Zeroizing::new(generate_nonce(&mut rng));
-    let commitment = nonces.commitment.serialize();
+    let (nonces, commitment) = with_ciphersuite!(suite, |C| ciphersuite::commit::<C>(&key_package))?;
+    let nonces = Zeroizing::new(nonces);
 
-    // 🔐 Serialize and store securely in vault
-    let encoded = bincode::serialize(&*nonces).map_err(|_| "serialize failed")?;
-    set_nonce(registry, op_did, encoded)?;
+    // 🔐 Store serialized nonces securely in vault
+    set_nonce(registry, op_did, nonces.to_vec())?;
 
     Ok(commitment)
 }
 
-/// Uses stored share + nonce to compute a real signature share
+/// Uses stored share + nonce to compute a real signature share.
+/// The vault derives the signed bytes from the intent itself instead of trusting the coordinator.
 pub fn partial_sign(
     registry: &OperationalDIDRegistry,
     op_did: &str,
-    message: &[u8],
+    intent: &SigningIntent,
+    payload: &[u8],
     incoming_commitments: &[(String, Vec<u8>)],
+    approvals: Option<&ApprovalBundle>,
 ) -> Result<Vec<u8>, String> {
-    let shard_b64 = get_shard(registry, op_did)?;
-    let shard_bytes = base64::decode(&shard_b64).map_err(|_| "bad base64")?;
-    let share = SecretShare::deserialize(&shard_bytes).map_err(|_| "bad shard")?;
-
-    let nonce_bytes = get_nonce(registry, op_did)?;
-    let nonces: SigningNonces = bincode::deserialize(&nonce_bytes).map_err(|_| "bad nonce format")?;
-
-    let mut commitments = HashMap::new();
-    for (peer_id, raw) in incoming_commitments {
-        let id = Identifier::try_from(peer_id.as_bytes()).map_err(|_| "bad id")?;
-        let c = frost_ed25519::keys::NonceCommitment::deserialize(raw).map_err(|_| "bad commitment")?;
-        commitments.insert(id, c);
+    let message = intent::signing_bytes(intent, payload)?;
+    intent::authorize(registry, op_did, intent, payload)?;
+
+    // Checked here rather than trusted from the coordinator
+    let signers = incoming_commitments.iter().map(|(peer, _)| peer.clone()).collect::<Vec<_>>();
+    policy::check_signing(registry, op_did, intent, &signers)?;
+    approval::enforce_approval(registry, op_did, intent, &message, approvals)?;
+    enforce_device_policy(registry, op_did, &signers)?;
+    let (suite, key_package) = load_key_package(registry, op_did)?;
+    policy::take_rate_slot(registry, op_did)?;
+
+    // Taken before signing, so a second request cannot sign another message with it
+    let nonce_bytes = take_nonce(registry, op_did)?;
+
+    with_ciphersuite!(suite, |C| ciphersuite::sign::<C>(&key_package, &nonce_bytes, &message, incoming_commitments))
+}
+
+/// Refuses to sign unless every device the DID requires is in the signing set
+fn enforce_device_policy(registry: &OperationalDIDRegistry, op_did: &str, signers: &[String]) -> Result<(), String> {
+    for device in registry.get_devices(op_did).iter().filter(|d| d.required_for_signing) {
+        if !signers.contains(&device.participant_id) {
+            return Err(format!("Signing requires device {}", device.participant_id));
+        }
+    }
+    Ok(())
+}
+
+/// Generates one FROST nonce per batch item and stores them sealed in the vault.
+/// Returns the commitments in batch order.
+pub fn generate_nonces_batch(
+    registry: &OperationalDIDRegistry,
+    op_did: &str,
+    count: usize,
+) -> Result<Vec<Vec<u8>>, String> {
+    if count == 0 {
+        return Err("empty batch".to_string());
     }
+    let (suite, key_package) = load_key_package(registry, op_did)?;
+
+    let mut commitments = Vec::with_capacity(count);
+    let mut sealed = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let (nonces, commitment) = with_ciphersuite!(suite, |C| ciphersuite::commit::<C>(&key_package))?;
+        commitments.push(commitment);
+        sealed.push(nonces);
+    }
+
+    // 🔐 All batch nonces are sealed together and consumed together; handed over
+    // without copies, and wiped from memory once stored
+    set_batch_nonces(registry, op_did, sealed)?;
+
+    Ok(commitments)
+}
+
+/// Signs every batch item with its own stored nonce.
+/// A failing item is reported in its slot and does not stop the rest of the batch.
+pub fn partial_sign_batch(
+    registry: &OperationalDIDRegistry,
+    op_did: &str,
+    intent: &SigningIntent,
+    items: &[(u32, Vec<u8>, Vec<(String, Vec<u8>)>)],
+) -> Result<Vec<(u32, Result<Vec<u8>, String>)>, String> {
+    let (suite, key_package) = load_key_package(registry, op_did)?;
+
+    // Nonces are removed from the vault before signing so a replayed batch cannot reuse them
+    let nonce_batch = take_batch_nonces(registry, op_did)?;
+
+    let mut used = HashSet::new();
+    let results = items.iter().map(|(index, payload, commitments)| {
+        let result = if !used.insert(*index) {
+            Err(format!("duplicate batch index {index}"))
+        } else {
+            intent::signing_bytes(intent, payload).and_then(|message| {
+                intent::authorize(registry, op_did, intent, payload)?;
+                let signers = commitments.iter().map(|(peer, _)| peer.clone()).collect::<Vec<_>>();
+                policy::check_signing(registry, op_did, intent, &signers)?;
+                approval::enforce_approval(registry, op_did, intent, &message, None)?;
+                enforce_device_policy(registry, op_did, &signers)?;
+                let nonce_bytes = nonce_batch.get(*index as usize)
+                    .ok_or_else(|| format!("no nonce for batch index {index}"))?;
+                policy::take_rate_slot(registry, op_did)?;
+                with_ciphersuite!(suite, |C| ciphersuite::sign::<C>(&key_package, nonce_bytes, &message, commitments))
+            })
+        };
+        (*index, result)
+    }).collect();
+
+    Ok(results)
+}
+
+/// Signs with one ECDSA presignature, which is removed from the vault first.
+/// As with FROST, the vault derives the signed bytes from the intent and hashes them
+/// for `format` itself, so it never signs a digest it was merely handed.
+/// Returns the signature share and the presignature's R.
+pub fn ecdsa_partial_sign(
+    registry: &OperationalDIDRegistry,
+    op_did: &str,
+    presignature_id: &str,
+    intent: &SigningIntent,
+    payload: &[u8],
+    format: EcdsaMessageFormat,
+    signers: &[String],
+    approvals: Option<&ApprovalBundle>,
+) -> Result<(EcdsaSignatureShare, Vec<u8>), String> {
+    registry.get_mpc_group(op_did).ok_or("No MPC group for DID")?.check_active()?;
+    let message = intent::signing_bytes(intent, payload)?;
+    let digest = ecdsa::message_digest(format, &message)?;
+    intent::authorize(registry, op_did, intent, payload)?;
+
+    // Checked here rather than trusted from the coordinator
+    policy::check_signing(registry, op_did, intent, signers)?;
+    approval::enforce_approval(registry, op_did, intent, &message, approvals)?;
+    enforce_device_policy(registry, op_did, signers)?;
+    policy::take_rate_slot(registry, op_did)?;
 
-    let signing_pkg = SigningPackage::new(message.to_vec(), commitments);
-    let sig = sign(&signing_pkg, &share, &nonces)
-        .map_err(|e| format!("signing failed: {e:?}"))?;
+    // Taken before signing: a presignature used for two digests leaks the key share
+    let sealed = take_ecdsa_presignature(registry, op_did, presignature_id)?;
+    let bytes = Zeroizing::new(base64::decode(&sealed).map_err(|_| "bad base64")?);
+    let presignature: Presignature = bincode::deserialize(&bytes).map_err(|_| "bad presignature")?;
+
+    let share = ecdsa::sign_share(&presignature, &digest)?;
+    Ok((share, presignature.big_r.clone()))
+}
 
-    Ok(sig.to_bytes().to_vec())
-}
\ No newline at end of file
+/// Loads this node's key package for an operational DID together with the group's ciphersuite.
+/// For derived DIDs this is the parent's key package with the child tweak applied.
+fn load_key_package(registry: &OperationalDIDRegistry, op_did: &str) -> Result<(SuiteId, Zeroizing<Vec<u8>>), String> {
+    let group = registry.get_mpc_group(op_did).ok_or("No MPC group for DID")?;
+    let suite = SuiteId::from_dkg_protocol(group.dkg_protocol.as_deref())?;
+
+    // Derived DIDs sign with the parent's shard plus the public tweak for their path
+    let (shard_did, path) = derivation::resolve_signing_key(registry, op_did)?;
+    registry.get_mpc_group(&shard_did).ok_or("No MPC group for DID")?.check_active()?;
+    let shard_b64 = get_shard(registry, &shard_did)?;
+    let shard_bytes = Zeroizing::new(base64::decode(&shard_b64).map_err(|_| "bad base64")?);
+
+    match path {
+        Some(path) => {
+            let derived = with_ciphersuite!(suite, |C| ciphersuite::derive_key_package::<C>(&shard_bytes, &path))?;
+            Ok((suite, Zeroizing::new(derived)))
+        }
+        None => Ok((suite, shard_bytes)),
+    }
+}
@@ -0,0 +1,130 @@
+//! Verifies signatures produced by an operational DID's custody group.
+//! Every key epoch the DID has used is tried, so signatures made before a
+//! shard rotation still verify and the caller learns which epoch matched.
+
+use serde_json::Value;
+
+use crate::ciphersuite::{self, SuiteId};
+use crate::mpc::intent::{self, SigningIntent};
+use crate::registry::{KeyEpoch, OperationalDID, OperationalDIDRegistry};
+use crate::with_ciphersuite;
+
+/// The key epoch a signature was verified against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedSignature {
+    pub key_epoch: u32,
+    pub group_id: String,
+    pub current: bool, // false if the matching key has since been rotated out
+}
+
+/// Verify a signature over raw message bytes
+pub fn verify_raw(
+    registry: &OperationalDIDRegistry,
+    op_did: &str,
+    message: &[u8],
+    signature: &[u8],
+) -> Result<VerifiedSignature, String> {
+    let epochs = registry.get_key_epochs(&OperationalDID(op_did.to_string()))
+        .ok_or("Operational DID not found")?;
+
+    verify_against_epochs(&epochs, message, signature)
+}
+
+/// Verify a compact JWS (`header.payload.signature`, alg `EdDSA`).
+/// `detached_payload` is used when the payload segment is empty.
+pub fn verify_jws(
+    registry: &OperationalDIDRegistry,
+    op_did: &str,
+    jws: &str,
+    detached_payload: Option<&[u8]>,
+) -> Result<VerifiedSignature, String> {
+    let parts: Vec<&str> = jws.split('.').collect();
+    let [header_b64, payload_b64, signature_b64] = parts[..] else {
+        return Err("JWS must have three segments".into());
+    };
+
+    let header_bytes = base64::decode_config(header_b64, base64::URL_SAFE_NO_PAD)
+        .map_err(|_| "bad JWS header encoding")?;
+    let header: Value = serde_json::from_slice(&header_bytes).map_err(|_| "bad JWS header")?;
+    if header["alg"].as_str() != Some("EdDSA") {
+        return Err(format!("Unsupported JWS alg: {}", header["alg"]));
+    }
+
+    // RFC 7797: with "b64": false the payload is signed as-is instead of base64url-encoded
+    let unencoded = header["b64"].as_bool() == Some(false);
+    let payload = match (payload_b64.is_empty(), detached_payload) {
+        (true, Some(detached)) if unencoded => detached.to_vec(),
+        (true, Some(detached)) => base64::encode_config(detached, base64::URL_SAFE_NO_PAD).into_bytes(),
+        (true, None) => return Err("Detached JWS requires a payload".into()),
+        (false, _) => payload_b64.as_bytes().to_vec(),
+    };
+
+    let mut signing_input = header_b64.as_bytes().to_vec();
+    signing_input.push(b'.');
+    signing_input.extend_from_slice(&payload);
+
+    let signature = base64::decode_config(signature_b64, base64::URL_SAFE_NO_PAD)
+        .map_err(|_| "bad JWS signature encoding")?;
+
+    verify_raw(registry, op_did, &signing_input, &signature)
+}
+
+/// Verify the proof embedded in a VC signed by the custody group.
+/// Accepts `MPCSignature2023` proofs (`signature`, base64, VC_PROOF intent) and detached-JWS proofs (`jws`).
+pub fn verify_vc_proof(
+    registry: &OperationalDIDRegistry,
+    op_did: &str,
+    vc_json: &str,
+) -> Result<VerifiedSignature, String> {
+    let vc: Value = serde_json::from_str(vc_json).map_err(|e| format!("Invalid VC JSON: {e}"))?;
+    let proof = vc.get("proof").ok_or("VC has no proof")?;
+    let signing_input = vc_signing_input(vc_json)?;
+
+    if let Some(jws) = proof["jws"].as_str() {
+        return verify_jws(registry, op_did, jws, Some(&signing_input));
+    }
+
+    // MPCSignature2023 proofs are signed with the VC_PROOF intent
+    let signature_b64 = proof["signature"].as_str().ok_or("VC proof has no signature")?;
+    let signature = base64::decode(signature_b64).map_err(|_| "bad proof signature encoding")?;
+    let message = intent::signing_bytes(&SigningIntent::VcProof, vc_json.as_bytes())?;
+    verify_raw(registry, op_did, &message, &signature)
+}
+
+/// Canonical VC input: the VC without its `proof`, serialized by serde_json (sorted keys)
+pub fn vc_signing_input(vc_json: &str) -> Result<Vec<u8>, String> {
+    let mut vc: Value = serde_json::from_str(vc_json).map_err(|e| format!("Invalid VC JSON: {e}"))?;
+    if let Some(obj) = vc.as_object_mut() {
+        obj.remove("proof");
+    }
+
+    serde_json::to_vec(&vc).map_err(|e| format!("Serialize failed: {e}"))
+}
+
+/// Tries the newest epoch first; returns the first key the signature verifies under
+fn verify_against_epochs(epochs: &[KeyEpoch], message: &[u8], signature: &[u8]) -> Result<VerifiedSignature, String> {
+    if epochs.is_empty() {
+        return Err("DID has no group key".into());
+    }
+
+    for epoch in epochs.iter().rev() {
+        // Threshold ECDSA groups are not FROST and have no key package to check here
+        let Ok(suite) = SuiteId::from_dkg_protocol(epoch.dkg_protocol.as_deref()) else { continue };
+        let Some(package) = &epoch.public_key_package else { continue };
+
+        let verified = with_ciphersuite!(suite, |C| {
+            ciphersuite::group_verifying_key::<C>(package)
+                .and_then(|vk| ciphersuite::verify::<C>(&vk, message, signature))
+        });
+
+        if verified.is_ok() {
+            return Ok(VerifiedSignature {
+                key_epoch: epoch.epoch,
+                group_id: epoch.group_id.clone(),
+                current: epoch.retired_at.is_none(),
+            });
+        }
+    }
+
+    Err("Signature does not match any group key for this DID".into())
+}
@@ -12,7 +12,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             "proto/dkg.proto",
             "proto/vault.proto",
             "proto/mpc.proto",
-            "proto/issuer.proto"
+            "proto/issuer.proto",
+            "proto/device.proto"
         ],
         &["proto"],
     )
@@ -7,6 +7,7 @@ use crate::registry::OperationalDIDRegistry;
 use crate::types::{OperationalDID as InternalOperationalDID, RootDID as InternalRootDID, VerifiableCredential as InternalVC};
 use crate::vault::Vault;
 use crate::error::CustodyError;
+use crate::verification;
 
 /// Core gRPC service implementation
 pub struct CustodyManagementServer {
@@ -277,14 +278,12 @@ impl CustodyManagementService for CustodyManagementServer {
         request: Request<VerifyVCIntegrityRequest>,
     ) -> Result<Response<VerifyVCIntegrityResponse>, Status> {
         let req = request.into_inner();
-        let op_did = InternalOperationalDID(req.operational_did.unwrap().id);
-        let vc = InternalVC(req.vc.unwrap().payload);
-
-        let vault = self.registry.get_vault_for_operational_did(&op_did)
-            .map_err(|e| Status::internal(format!("Failed to get vault: {}", e)))?;
+        let op_did = req.operational_did.ok_or(Status::invalid_argument("Missing operational DID"))?.id;
+        let vc = req.vc.ok_or(Status::invalid_argument("Missing VC"))?.payload;
 
-        let is_valid = vault.verify_vc_integrity(&vc)
-            .map_err(|e| Status::internal(format!("Failed to verify VC: {}", e)))?;
+        // Check the VC's proof against every group key this DID has held
+        let vc_json = String::from_utf8(vc).map_err(|_| Status::invalid_argument("VC is not UTF-8"))?;
+        let is_valid = verification::verify_vc_proof(&self.registry, &op_did, &vc_json).is_ok();
 
         Ok(Response::new(VerifyVCIntegrityResponse { valid: is_valid }))
     }
@@ -0,0 +1,280 @@
+// File: src/service/device_service.rs
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use rand_core::{OsRng, RngCore};
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::audit::now_rfc3339;
+use crate::discover;
+use crate::dkg::engine::DKGEngine;
+use crate::identity;
+use crate::mpc::device::{self, DeviceHub, NodeToDevice};
+use crate::registry::{OperationalDIDRegistry, OperationalDID, DeviceRecord};
+use crate::relay::RelayClient;
+use crate::tls;
+
+use custodydevice::custody_device_server::{CustodyDevice, CustodyDeviceServer};
+use custodydevice::custody_device_client::CustodyDeviceClient;
+use custodydevice::{
+    device_message, node_message, Challenge, DeviceMessage, NodeMessage, RelayPayload,
+    DkgInvite, ParticipantKey, SignRequest, SigningPackage, PeerCommitment,
+    RegisterDeviceRequest, RegisterDeviceResponse,
+    InviteDeviceRequest, InviteDeviceResponse,
+    DeviceCommitRequest, DeviceCommitResponse,
+    DeviceSignRequest, DeviceSignResponse,
+};
+
+pub mod custody {
+    tonic::include_proto!("custodydevice");
+}
+
+type NodeStream = Pin<Box<dyn Stream<Item = Result<NodeMessage, Status>> + Send>>;
+
+/// Serves devices that hold their own share, and lets coordinators on other nodes reach them
+#[derive(Clone)]
+pub struct CustodyDeviceService {
+    pub registry: Arc<OperationalDIDRegistry>,
+    pub hub: Arc<DeviceHub>,
+    pub relay: Arc<RelayClient>,
+    pub dkg_engine: Arc<DKGEngine>,           // Source of participants' identity keys for DKG invites
+    pub local_node_id: String,
+}
+
+impl CustodyDeviceService {
+    /// Devices reach this service without a certificate, so it admits callers per call:
+    /// anyone may open a device session, everything else needs a cluster certificate
+    fn authorize<T>(&self, request: &Request<T>, operators: bool) -> Result<tls::PeerIdentity, Status> {
+        let directory = self.dkg_engine.directory.clone();
+        let authorize = if operators { tls::Authorize::nodes_and_operators(directory) } else { tls::Authorize::nodes(directory) };
+        authorize.check(request)
+    }
+}
+
+#[tonic::async_trait]
+impl CustodyDevice for CustodyDeviceService {
+    type SessionStream = NodeStream;
+
+    async fn register_device(
+        &self,
+        request: Request<RegisterDeviceRequest>,
+    ) -> Result<Response<RegisterDeviceResponse>, Status> {
+        // Only another node forwards a registration
+        let caller = self.authorize(&request, true)?;
+        let req = request.into_inner();
+        if req.forwarded && caller.role != tls::PeerRole::Node {
+            return Err(Status::permission_denied(format!("{} cannot forward a registration", caller.name)));
+        }
+        let op_did = OperationalDID(req.operational_did.clone());
+
+        if self.registry.get_vault_id_for_operational_did(&op_did).is_none() {
+            return Err(Status::not_found("Operational DID not found"));
+        }
+        if req.identity_public_key.len() != 32 {
+            return Err(Status::invalid_argument("identity_public_key must be a 32-byte Ed25519 key"));
+        }
+        identity::check_encryption_key(&req.encryption_public_key)
+            .map_err(|e| Status::invalid_argument(format!("encryption_public_key: {e}")))?;
+
+        let home_node = if req.home_node.is_empty() { self.local_node_id.clone() } else { req.home_node.clone() };
+        let participant_id = device::device_participant_id(&req.device_id, &home_node);
+
+        self.registry.register_device(&op_did, DeviceRecord {
+            device_id: req.device_id.clone(),
+            participant_id: participant_id.clone(),
+            identity_public_key: req.identity_public_key.clone(),
+            encryption_public_key: req.encryption_public_key.clone(),
+            required_for_signing: req.required_for_signing,
+            registered_at: now_rfc3339(),
+        }).map_err(|e| Status::internal(format!("register device failed: {e:?}")))?;
+
+        // Every vault checks the device policy, so every node needs the record
+        if !req.forwarded {
+            let peers = discover::discover_peer_nodes("custody-nodes.default.svc.cluster.local")
+                .await.map_err(|e| Status::internal(format!("peer discovery failed: {e}")))?;
+
+            for peer in peers.iter().filter(|p| **p != self.local_node_id) {
+                let channel = tls::channel(peer)
+                    .await
+                    .map_err(|e| Status::unavailable(format!("connect to {peer} failed: {e:?}")))?;
+                let mut client = CustodyDeviceClient::new(channel);
+                client.register_device(RegisterDeviceRequest {
+                    home_node: home_node.clone(),
+                    forwarded: true,
+                    ..req.clone()
+                }).await?;
+            }
+        }
+
+        Ok(Response::new(RegisterDeviceResponse { participant_id }))
+    }
+
+    async fn session(
+        &self,
+        request: Request<Streaming<DeviceMessage>>,
+    ) -> Result<Response<Self::SessionStream>, Status> {
+        let mut inbound = request.into_inner();
+        let (tx, rx) = tokio::sync::mpsc::channel::<Result<NodeMessage, Status>>(64);
+
+        // STEP 1: Challenge the device to prove its identity key
+        let mut nonce = [0u8; 32];
+        OsRng.fill_bytes(&mut nonce);
+        tx.send(Ok(NodeMessage { body: Some(node_message::Body::Challenge(Challenge { nonce: nonce.to_vec() })) }))
+            .await
+            .map_err(|_| Status::internal("session closed"))?;
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            let hello = match inbound.next().await {
+                Some(Ok(DeviceMessage { body: Some(device_message::Body::Hello(hello)) })) => hello,
+                _ => {
+                    let _ = tx.send(Err(Status::unauthenticated("expected hello"))).await;
+                    return;
+                }
+            };
+
+            // STEP 2: Only registered devices homed on this node may attach
+            let participant_id = hello.participant_id;
+            let verified = service.registry.find_device(&participant_id)
+                .filter(|_| device::device_home_node(&participant_id) == Some(service.local_node_id.as_str()))
+                .ok_or_else(|| "unknown device".to_string())
+                .and_then(|d| device::verify_hello(&d.identity_public_key, &nonce, &hello.challenge_signature));
+            if let Err(e) = verified {
+                let _ = tx.send(Err(Status::unauthenticated(e))).await;
+                return;
+            }
+
+            // STEP 3: Pump node → device traffic from the hub into the stream
+            let mut outbound = service.hub.connect(&participant_id);
+            let out_tx = tx.clone();
+            tokio::spawn(async move {
+                while let Some(msg) = outbound.recv().await {
+                    if out_tx.send(Ok(NodeMessage { body: Some(to_proto(msg)) })).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            println!("📱 Device {participant_id} connected");
+
+            // STEP 4: Handle device → node traffic until the stream ends
+            while let Some(Ok(msg)) = inbound.next().await {
+                match msg.body {
+                    Some(device_message::Body::Relay(r)) => {
+                        if service.relay.forward_message(&participant_id, &r.group_id, &r.peer, r.payload).is_err() {
+                            println!("⚠️ Relay from device {participant_id} failed");
+                        }
+                    }
+                    Some(device_message::Body::Result(r)) => {
+                        let result = if r.error.is_empty() { Ok(r.value) } else { Err(r.error) };
+                        service.hub.complete(&r.request_id, result);
+                    }
+                    Some(device_message::Body::DkgComplete(c)) => {
+                        println!("🔐 Device {participant_id} finished DKG for group {}", c.group_id);
+                    }
+                    Some(device_message::Body::Hello(_)) | None => {}
+                }
+            }
+
+            service.hub.disconnect(&participant_id);
+            println!("📴 Device {participant_id} disconnected");
+        });
+
+        Ok(Response::new(Box::pin(tokio_stream::wrappers::ReceiverStream::new(rx)) as Self::SessionStream))
+    }
+
+    async fn invite_device(
+        &self,
+        request: Request<InviteDeviceRequest>,
+    ) -> Result<Response<InviteDeviceResponse>, Status> {
+        self.authorize(&request, true)?;
+        let req = request.into_inner();
+        let invite = req.invite.ok_or(Status::invalid_argument("invite missing"))?;
+
+        // The device trusts its home node for the other participants' identity keys
+        self.dkg_engine.learn_participants(&invite.participants).await
+            .map_err(|e| Status::unavailable(format!("identity lookup failed: {e:?}")))?;
+        let mut participant_keys = Vec::new();
+        for participant in invite.participants.iter().filter(|p| **p != req.participant_id) {
+            let keys = if *participant == self.local_node_id {
+                self.dkg_engine.identity.public_keys()
+            } else {
+                self.dkg_engine.participant_keys(participant)
+                    .ok_or_else(|| Status::failed_precondition(format!("no identity key for {participant}")))?
+            };
+            participant_keys.push((participant.clone(), keys));
+        }
+
+        self.hub.deliver(&req.participant_id, NodeToDevice::DkgInvite {
+            group_id: invite.group_id,
+            operational_did: invite.operational_did,
+            threshold: invite.threshold as u16,
+            participants: invite.participants,
+            ciphersuite: invite.ciphersuite,
+            participant_keys,
+        }).await.map_err(|e| Status::unavailable(e))?;
+
+        Ok(Response::new(InviteDeviceResponse {}))
+    }
+
+    async fn device_commit(
+        &self,
+        request: Request<DeviceCommitRequest>,
+    ) -> Result<Response<DeviceCommitResponse>, Status> {
+        self.authorize(&request, false)?;
+        let req = request.into_inner();
+
+        let commitment = self.hub.request(&req.participant_id, &req.request_id, NodeToDevice::SignRequest {
+            request_id: req.request_id.clone(),
+            operational_did: req.operational_did,
+            message: req.message,
+        }).await.map_err(|e| Status::unavailable(e))?;
+
+        Ok(Response::new(DeviceCommitResponse { commitment }))
+    }
+
+    async fn device_sign(
+        &self,
+        request: Request<DeviceSignRequest>,
+    ) -> Result<Response<DeviceSignResponse>, Status> {
+        self.authorize(&request, false)?;
+        let req = request.into_inner();
+
+        let signature_share = self.hub.request(&req.participant_id, &req.request_id, NodeToDevice::SigningPackage {
+            request_id: req.request_id.clone(),
+            message: req.message,
+            commitments: req.commitments.into_iter().map(|c| (c.peer_id, c.commitment)).collect(),
+        }).await.map_err(|e| Status::unavailable(e))?;
+
+        Ok(Response::new(DeviceSignResponse { signature_share }))
+    }
+}
+
+fn to_proto(msg: NodeToDevice) -> node_message::Body {
+    match msg {
+        NodeToDevice::Relay { group_id, from, payload } => node_message::Body::Relay(RelayPayload { group_id, peer: from, payload }),
+        NodeToDevice::DkgInvite { group_id, operational_did, threshold, participants, ciphersuite, participant_keys } => {
+            node_message::Body::DkgInvite(DkgInvite {
+                group_id,
+                operational_did,
+                threshold: threshold as u32,
+                participants,
+                ciphersuite,
+                participant_keys: participant_keys.into_iter().map(|(participant_id, keys)| ParticipantKey {
+                    participant_id,
+                    signing_key: keys.signing_key,
+                    encryption_key: keys.encryption_key,
+                }).collect(),
+            })
+        }
+        NodeToDevice::SignRequest { request_id, operational_did, message } => {
+            node_message::Body::SignRequest(SignRequest { request_id, operational_did, message })
+        }
+        NodeToDevice::SigningPackage { request_id, message, commitments } => node_message::Body::SigningPackage(SigningPackage {
+            request_id,
+            message,
+            commitments: commitments.into_iter().map(|(peer_id, commitment)| PeerCommitment { peer_id, commitment }).collect(),
+        }),
+    }
+}
@@ -1,13 +1,20 @@
 
 use tonic::{Request, Response, Status};
 use crate::dkg::engine::DKGEngine;
-use crate::dkg::types::DKGError;
+use crate::mpc::ecdsa_engine::EcdsaEngine;
+use crate::dkg::types::{DKGError, DKGPhase, DKGStatus, PeerResult};
+use crate::ciphersuite::SuiteId;
+use crate::identity::SealedBox;
+use crate::sim;
 
 use std::sync::Arc;
 use custodydkg::custody_dkg_server::{CustodyDkg, CustodyDkgServer};
 use custodydkg::{
     StartDkgSessionRequest, StartDkgSessionResponse,
-    BroadcastRound2Request, FinalizeDkgRequest, FinalizeDkgResponse,
+    GetDkgStatusRequest, GetDkgStatusResponse, DkgPhase,
+    AbortDkgSessionRequest, DkgComplaint, PeerVerification,
+    StartEcdsaSessionRequest, AdvanceEcdsaSessionRequest,
+    FinalizeEcdsaSessionRequest, FinalizeEcdsaSessionResponse,
 };
 use prost_types::Empty;
 
@@ -18,6 +25,7 @@ pub mod custody {
 #[derive(Clone)]
 pub struct CustodyDkgService {
     pub dkg_engine: Arc<DKGEngine>,
+    pub ecdsa_engine: Arc<EcdsaEngine>,
 }
 
 #[tonic::async_trait]
@@ -27,38 +35,213 @@ impl CustodyDkg for CustodyDkgService {
         request: Request<StartDkgSessionRequest>,
     ) -> Result<Response<StartDkgSessionResponse>, Status> {
         let req = request.into_inner();
-        let group_id = self.dkg_engine
-            .start_session(req.operational_did, req.threshold as u8, req.participant_nodes)
-            .map_err(|e| Status::internal(format!("start_session failed: {:?}", e)))?;
+        let suite = SuiteId::from_name(&req.ciphersuite)
+            .map_err(|e| Status::invalid_argument(e))?;
 
-        Ok(Response::new(StartDkgSessionResponse { group_id }))
+        if req.group_id.is_empty() {
+            return Err(Status::invalid_argument("group_id is required"));
+        }
+
+        // Pin every peer's identity before any package is sent or accepted
+        self.dkg_engine
+            .learn_participants(&req.participant_nodes)
+            .await
+            .map_err(|e| Status::unavailable(format!("identity lookup failed: {:?}", e)))?;
+
+        if req.refresh {
+            self.dkg_engine
+                .start_refresh(&req.group_id, req.operational_did)
+                .map_err(|e| Status::failed_precondition(format!("start_refresh failed: {:?}", e)))?;
+        } else if req.reshare {
+            self.dkg_engine
+                .learn_participants(&req.dealer_nodes)
+                .await
+                .map_err(|e| Status::unavailable(format!("identity lookup failed: {:?}", e)))?;
+            self.dkg_engine
+                .start_reshare(
+                    &req.group_id,
+                    req.operational_did,
+                    req.dealer_nodes,
+                    req.participant_nodes,
+                    req.threshold as u8,
+                    suite,
+                    req.old_public_key_package,
+                )
+                .map_err(|e| Status::failed_precondition(format!("start_reshare failed: {:?}", e)))?;
+        } else if req.repair {
+            self.dkg_engine
+                .learn_participants(std::slice::from_ref(&req.lost_node))
+                .await
+                .map_err(|e| Status::unavailable(format!("identity lookup failed: {:?}", e)))?;
+            self.dkg_engine
+                .start_repair(&req.group_id, req.operational_did, req.participant_nodes, req.lost_node)
+                .map_err(|e| Status::failed_precondition(format!("start_repair failed: {:?}", e)))?;
+        } else if req.import_key {
+            let [dealer] = <[String; 1]>::try_from(req.dealer_nodes)
+                .map_err(|_| Status::invalid_argument("an import has exactly one dealer"))?;
+            self.dkg_engine
+                .learn_participants(std::slice::from_ref(&dealer))
+                .await
+                .map_err(|e| Status::unavailable(format!("identity lookup failed: {:?}", e)))?;
+            let sealed_key = req.sealed_key.map(|k| SealedBox { ephemeral_key: k.ephemeral_key, ciphertext: k.ciphertext });
+            self.dkg_engine
+                .start_import(
+                    &req.group_id,
+                    req.operational_did,
+                    dealer,
+                    req.participant_nodes,
+                    req.threshold as u8,
+                    req.group_public_key,
+                    sealed_key,
+                )
+                .map_err(|e| Status::failed_precondition(format!("start_import failed: {:?}", e)))?;
+        } else {
+            self.dkg_engine
+                .start_session(&req.group_id, req.operational_did, req.threshold as u8, req.participant_nodes, suite)
+                .map_err(|e| Status::internal(format!("start_session failed: {:?}", e)))?;
+        }
+
+        Ok(Response::new(StartDkgSessionResponse { group_id: req.group_id }))
     }
 
-    async fn broadcast_round2(
+    async fn get_dkg_status(
         &self,
-        request: Request<BroadcastRound2Request>,
+        request: Request<GetDkgStatusRequest>,
+    ) -> Result<Response<GetDkgStatusResponse>, Status> {
+        let req = request.into_inner();
+
+        let status = if req.wait {
+            self.dkg_engine.wait_for_completion(&req.group_id).await
+        } else {
+            self.dkg_engine.status(&req.group_id)
+        }.map_err(|e| match e {
+            DKGError::SessionNotFound => Status::not_found("DKG session not found"),
+            other => Status::internal(format!("status failed: {:?}", other)),
+        })?;
+
+        Ok(Response::new(status_response(status)))
+    }
+
+    async fn abort_dkg_session(
+        &self,
+        request: Request<AbortDkgSessionRequest>,
     ) -> Result<Response<Empty>, Status> {
-        let group_id = request.into_inner().group_id;
+        let req = request.into_inner();
 
         self.dkg_engine
-            .broadcast_round2(&group_id)
-            .map_err(|e| Status::internal(format!("round2 failed: {:?}", e)))?;
+            .abort_session(&req.group_id, &req.reason)
+            .map_err(|e| match e {
+                DKGError::SessionNotFound => Status::not_found("DKG session not found"),
+                other => Status::internal(format!("abort failed: {:?}", other)),
+            })?;
 
         Ok(Response::new(Empty {}))
     }
 
-    async fn finalize_dkg_session(
+    async fn start_ecdsa_session(
         &self,
-        request: Request<FinalizeDkgRequest>,
-    ) -> Result<Response<FinalizeDkgResponse>, Status> {
-        let group_id = request.into_inner().group_id;
+        request: Request<StartEcdsaSessionRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let req = request.into_inner();
+
+        match req.kind.as_str() {
+            "keygen" => self.ecdsa_engine
+                .start_keygen(&req.session_id, &req.operational_did, req.threshold as u8, req.participant_nodes),
+            "presign" => self.ecdsa_engine
+                .start_presign(&req.session_id, &req.operational_did),
+            other => return Err(Status::invalid_argument(format!("Unknown ECDSA session kind: {other}"))),
+        }.map_err(|e| Status::internal(format!("start_ecdsa_session failed: {e}")))?;
+
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn advance_ecdsa_session(
+        &self,
+        request: Request<AdvanceEcdsaSessionRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let session_id = request.into_inner().session_id;
+
+        // Every deal has to be in before w can be opened
+        self.ecdsa_engine
+            .wait_for_round(&session_id, 1)
+            .await
+            .map_err(|e| Status::deadline_exceeded(format!("advance failed: {e}")))?;
+        self.ecdsa_engine
+            .advance(&session_id)
+            .map_err(|e| Status::internal(format!("advance failed: {e}")))?;
+
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn finalize_ecdsa_session(
+        &self,
+        request: Request<FinalizeEcdsaSessionRequest>,
+    ) -> Result<Response<FinalizeEcdsaSessionResponse>, Status> {
+        let session_id = request.into_inner().session_id;
+
+        // Keygen finishes on the deals, presign on the openings of w
+        let round = if self.ecdsa_engine.is_presign(&session_id) { 2 } else { 1 };
+        self.ecdsa_engine
+            .wait_for_round(&session_id, round)
+            .await
+            .map_err(|e| Status::deadline_exceeded(format!("finalize failed: {e}")))?;
+        let result = self.ecdsa_engine
+            .finalize(&session_id)
+            .map_err(|e| Status::internal(format!("finalize failed: {e}")))?;
+
+        // A new group signs once every node confirmed it; this node settles it in the background
+        if round == 1 {
+            let engine = self.ecdsa_engine.clone();
+            tokio::spawn(sim::network::inherit(async move { engine.await_confirmations(&session_id).await }));
+        }
+
+        Ok(Response::new(FinalizeEcdsaSessionResponse { result }))
+    }
+}
+
+fn status_response(status: DKGStatus) -> GetDkgStatusResponse {
+    let (phase, error) = match status.phase {
+        DKGPhase::Round1 => (DkgPhase::Round1, String::new()),
+        DKGPhase::Round2 => (DkgPhase::Round2, String::new()),
+        DKGPhase::Finalized => (DkgPhase::Finalized, String::new()),
+        DKGPhase::Failed(reason) => (DkgPhase::Failed, reason),
+        DKGPhase::Aborted(reason) => (DkgPhase::Aborted, reason),
+        DKGPhase::Confirming => (DkgPhase::Confirming, String::new()),
+        DKGPhase::Quarantined(reason) => (DkgPhase::Quarantined, reason),
+    };
+    let misbehaving = status.misbehaving();
+
+    let peer_results = status.peer_results.into_iter().map(|(node_id, result)| {
+        let (verification, reason) = match result {
+            PeerResult::Pending => (PeerVerification::Pending, String::new()),
+            PeerResult::Round1Verified => (PeerVerification::Round1Verified, String::new()),
+            PeerResult::Verified => (PeerVerification::Verified, String::new()),
+            PeerResult::Rejected(reason) => (PeerVerification::Rejected, reason),
+        };
+        custodydkg::PeerResult { node_id, verification: verification as i32, reason }
+    }).collect();
 
-        let shard = self.dkg_engine
-            .finalize(&group_id)
-            .map_err(|e| Status::internal(format!("finalize failed: {:?}", e)))?;
+    let complaints = status.complaints.into_iter().map(|c| DkgComplaint {
+        accuser: c.accuser,
+        accused: c.accused,
+        reason: c.reason,
+        confirmed: c.confirmed,
+    }).collect();
 
-        Ok(Response::new(FinalizeDkgResponse {
-            shard_base64: base64::encode(shard),
-        }))
+    GetDkgStatusResponse {
+        phase: phase as i32,
+        round1_received: status.round1_received as u32,
+        round2_received: status.round2_received as u32,
+        expected: status.expected as u32,
+        deadline_unix: status.deadline
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default(),
+        group_public_key: status.group_public_key.unwrap_or_default(),
+        error,
+        peer_results,
+        complaints,
+        misbehaving,
+        kind: status.kind.name().to_string(),
     }
 }
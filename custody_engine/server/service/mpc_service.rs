@@ -4,12 +4,36 @@ use tonic::{Request, Response, Status};
 
 use mpc::custody_mpc_server::{CustodyMpc, CustodyMpcServer};
 use mpc::{SignMessageRequest, SignMessageResponse};
+use mpc::{SignBatchRequest, SignBatchResponse, SignBatchItemResult};
+use mpc::{VerifySignatureRequest, VerifySignatureResponse, verify_signature_request::Payload};
+use mpc::{SignEcdsaRequest, SignEcdsaResponse, GenerateEcdsaPresignaturesRequest, GenerateEcdsaPresignaturesResponse};
+use mpc::{UpdatePolicyRequest, UpdatePolicyResponse, GetPolicyRequest, GetPolicyResponse};
+use mpc::{SubmitForApprovalRequest, SubmitForApprovalResponse};
+use mpc::{ApproveRequest, ApproveResponse};
+use mpc::{RefreshSharesRequest, RefreshSharesResponse};
+use mpc::{ReshareGroupRequest, ReshareGroupResponse, GetReshareStatusRequest, GetReshareStatusResponse, NodeReshareStatus};
+use mpc::{ImportKeyRequest, ImportKeyResponse};
+use mpc::{RepairShareRequest, RepairShareResponse};
 
 use crate::mpc::coordinator::MPCSigningCoordinator;
 use mpc::{ProvisionVaultAndShardsRequest, ProvisionVaultAndShardsResponse};
 use crate::vault::{store_record, VaultRecord};
-use crate::registry::{OperationalDID, RootDID, MPCGroupDescriptor, MPCMemberDescriptor};
+use crate::registry::{OperationalDID, OperationalDIDRegistry, RootDID, MPCGroupDescriptor, DeviceRecord};
+use crate::policy::SigningPolicy;
+use crate::mpc::approval::{Approval, KeyOperation};
+use crate::mpc::intent::{self, SigningIntent};
+use crate::mpc::device;
+use crate::audit::now_rfc3339;
 
+use crate::ciphersuite::{self, SuiteId};
+use crate::verification;
+use crate::mpc::ecdsa::{EcdsaMessageFormat, ECDSA_PROTOCOL, ECDSA_SUITE_NAME};
+use crate::with_ciphersuite;
+use crate::dkg::types::reshare_participants;
+use crate::sim;
+use custodydkg::DkgPhase;
+
+use std::collections::HashSet;
 use uuid::Uuid;
 
 pub mod custody {
@@ -25,6 +49,52 @@ async fn generate_new_vault_id() -> String {
     format!("vault-{}", Uuid::new_v4())
 }
 
+/// Helpers for repairing `lost_node`'s share: the ones asked for, each once, or else the
+/// first `threshold` of the other members. At least the threshold of them are needed.
+fn repair_helpers(group: &MPCGroupDescriptor, lost_node: &str, requested: Vec<String>) -> Result<Vec<String>, Status> {
+    let members = group.members.iter().map(|m| m.node_id.clone()).collect::<Vec<_>>();
+    if !members.iter().any(|m| m == lost_node) {
+        return Err(Status::invalid_argument(format!("{lost_node} holds no share of the group")));
+    }
+    let mut helpers = if requested.is_empty() {
+        members.into_iter()
+            .filter(|m| m != lost_node && !device::is_device_participant(m))
+            .take(group.threshold as usize)
+            .collect()
+    } else {
+        requested
+    };
+    let mut seen = HashSet::new();
+    helpers.retain(|helper| seen.insert(helper.clone()));
+    if helpers.len() < group.threshold as usize {
+        return Err(Status::failed_precondition(format!("at least {} other members must help", group.threshold)));
+    }
+    Ok(helpers)
+}
+
+/// The key operation an approval request is for, with the parameters it will run with;
+/// `None` for a signing request
+fn key_operation(registry: &OperationalDIDRegistry, req: &SubmitForApprovalRequest) -> Result<Option<KeyOperation>, Status> {
+    match mpc::KeyOperation::from_i32(req.operation) {
+        Some(mpc::KeyOperation::Unspecified) => Ok(None),
+        Some(mpc::KeyOperation::RotateShards) => Ok(Some(KeyOperation::Rotate)),
+        Some(mpc::KeyOperation::RefreshShares) => Ok(Some(KeyOperation::Refresh)),
+        Some(mpc::KeyOperation::ReshareGroup) => {
+            if req.members.is_empty() || req.threshold == 0 {
+                return Err(Status::invalid_argument("a reshare is approved for its members and threshold"));
+            }
+            Ok(Some(KeyOperation::Reshare { threshold: req.threshold, members: req.members.clone() }))
+        }
+        Some(mpc::KeyOperation::RepairShare) => {
+            let group = registry.get_mpc_group(&OperationalDID(req.operational_did.clone()))
+                .ok_or(Status::not_found("MPC group not found"))?;
+            let helpers = repair_helpers(&group, &req.lost_node, req.helpers.clone())?;
+            Ok(Some(KeyOperation::Repair { lost_node: req.lost_node.clone(), helpers }))
+        }
+        None => Err(Status::invalid_argument(format!("Unknown key operation {}", req.operation))),
+    }
+}
+
 #[tonic::async_trait]
 impl CustodyMpc for CustodyMpcService {
     async fn sign_message(
@@ -32,9 +102,11 @@ impl CustodyMpc for CustodyMpcService {
         request: Request<SignMessageRequest>,
     ) -> Result<Response<SignMessageResponse>, Status> {
         let req = request.into_inner();
+        let intent = SigningIntent::from_proto(req.intent, &req.justification)
+            .map_err(|e| Status::invalid_argument(e))?;
 
         let sig = self.coordinator
-            .sign(&req.operational_did, req.message)
+            .sign(&req.operational_did, &intent, req.message, Some(req.approval_request_id.as_str()).filter(|id| !id.is_empty()))
             .await
             .map_err(|e| Status::internal(format!("Sign failed: {e}")))?;
 
@@ -43,6 +115,120 @@ impl CustodyMpc for CustodyMpcService {
         }))
     }
 
+    async fn sign_batch(
+        &self,
+        request: Request<SignBatchRequest>,
+    ) -> Result<Response<SignBatchResponse>, Status> {
+        let req = request.into_inner();
+        let intent = SigningIntent::from_proto(req.intent, &req.justification)
+            .map_err(|e| Status::invalid_argument(e))?;
+
+        let results = self.coordinator
+            .sign_batch(&req.operational_did, &intent, req.messages)
+            .await
+            .map_err(|e| Status::internal(format!("Batch sign failed: {e}")))?
+            .into_iter()
+            .enumerate()
+            .map(|(index, result)| match result {
+                Ok(signature) => SignBatchItemResult { index: index as u32, signature, error: String::new() },
+                Err(error) => SignBatchItemResult { index: index as u32, signature: vec![], error },
+            })
+            .collect();
+
+        Ok(Response::new(SignBatchResponse {
+            results,
+        }))
+    }
+
+    async fn verify_signature(
+        &self,
+        request: Request<VerifySignatureRequest>,
+    ) -> Result<Response<VerifySignatureResponse>, Status> {
+        let req = request.into_inner();
+        let registry = &self.coordinator.registry;
+
+        let result = match req.payload {
+            Some(Payload::Raw(raw)) => {
+                // Typed signatures cover the domain-separated bytes, not the payload itself
+                let message = match req.intent {
+                    0 => Ok(raw.message),
+                    code => SigningIntent::from_proto(code, "verify").and_then(|i| intent::signing_bytes(&i, &raw.message)),
+                };
+                message.and_then(|m| verification::verify_raw(registry, &req.operational_did, &m, &raw.signature))
+            }
+            Some(Payload::Jws(jws)) => {
+                let detached = (!req.jws_payload.is_empty()).then_some(req.jws_payload.as_slice());
+                verification::verify_jws(registry, &req.operational_did, &jws, detached)
+            }
+            Some(Payload::VcJson(vc_json)) => verification::verify_vc_proof(registry, &req.operational_did, &vc_json),
+            None => return Err(Status::invalid_argument("Missing signature payload")),
+        };
+
+        // An invalid signature is a normal answer, not an RPC failure
+        let response = match result {
+            Ok(verified) => VerifySignatureResponse {
+                valid: true,
+                key_epoch: verified.key_epoch,
+                group_id: verified.group_id,
+                current: verified.current,
+                error: String::new(),
+            },
+            Err(error) => VerifySignatureResponse {
+                valid: false,
+                error,
+                ..Default::default()
+            },
+        };
+
+        Ok(Response::new(response))
+    }
+
+    async fn sign_ecdsa(
+        &self,
+        request: Request<SignEcdsaRequest>,
+    ) -> Result<Response<SignEcdsaResponse>, Status> {
+        let req = request.into_inner();
+        let format = EcdsaMessageFormat::from_name(&req.format)
+            .map_err(|e| Status::invalid_argument(e))?;
+        let intent = SigningIntent::from_proto(req.intent, &req.justification)
+            .map_err(|e| Status::invalid_argument(e))?;
+
+        let signature = self.coordinator
+            .sign_ecdsa(&req.operational_did, &intent, &req.message, format, Some(req.approval_request_id.as_str()).filter(|id| !id.is_empty()))
+            .await
+            .map_err(|e| Status::internal(format!("ECDSA sign failed: {e}")))?;
+
+        Ok(Response::new(SignEcdsaResponse {
+            signature,
+        }))
+    }
+
+    async fn generate_ecdsa_presignatures(
+        &self,
+        request: Request<GenerateEcdsaPresignaturesRequest>,
+    ) -> Result<Response<GenerateEcdsaPresignaturesResponse>, Status> {
+        let req = request.into_inner();
+
+        let group = self.coordinator.registry.get_mpc_group(&req.operational_did)
+            .ok_or(Status::not_found("MPC group not found"))?;
+        if group.dkg_protocol.as_deref() != Some(ECDSA_PROTOCOL) {
+            return Err(Status::failed_precondition("DID is not backed by a threshold ECDSA group"));
+        }
+        let nodes = group.members.iter().map(|m| m.node_id.clone()).collect::<Vec<_>>();
+
+        // Presignatures are independent, so each one is its own session
+        let mut presignature_ids = Vec::with_capacity(req.count as usize);
+        for _ in 0..req.count {
+            let id = orchestrator::orchestrate_ecdsa_presign(&req.operational_did, nodes.clone())
+                .await.map_err(|e| Status::internal(format!("Presign failed: {e}")))?;
+            presignature_ids.push(id);
+        }
+
+        Ok(Response::new(GenerateEcdsaPresignaturesResponse {
+            presignature_ids,
+        }))
+    }
+
     async fn provision_vault_and_shards(
         &self,
         request: Request<ProvisionVaultAndShardsRequest>,
@@ -51,6 +237,47 @@ impl CustodyMpc for CustodyMpcService {
         let op_did = OperationalDID(req.operational_did.clone());
         let root_did = RootDID(req.root_did.clone());
 
+        // Derived child key: no DKG, every node tweaks the parent group's shares
+        if !req.parent_operational_did.is_empty() {
+            let group_pubkey = self.coordinator
+                .derive_child_did(&req.operational_did, &req.root_did, &req.parent_operational_did, &req.derivation_path)
+                .await
+                .map_err(|e| Status::internal(format!("Key derivation failed: {e}")))?;
+
+            let mpc_group = self.coordinator.registry.get_mpc_group(&op_did)
+                .ok_or(Status::internal("Derived group not recorded"))?;
+            let vault_id = self.coordinator.registry.get_vault_id_for_operational_did(&op_did)
+                .ok_or(Status::internal("Derived vault not recorded"))?;
+
+            return Ok(Response::new(ProvisionVaultAndShardsResponse {
+                vault_id,
+                group_id: mpc_group.group_id,
+                group_public_key: group_pubkey,
+                dkg_protocol: mpc_group.dkg_protocol.unwrap_or_default(),
+            }));
+        }
+
+        // The initial policy picks the threshold and committee. It is the only policy
+        // accepted without a group signature, since the group does not exist yet.
+        let mut policy = if req.policy_json.is_empty() {
+            SigningPolicy::default()
+        } else {
+            serde_json::from_str::<SigningPolicy>(&req.policy_json)
+                .map_err(|e| Status::invalid_argument(format!("Invalid policy: {e}")))?
+        };
+        policy.version = 1;
+        policy.validate().map_err(|e| Status::invalid_argument(e))?;
+
+        let use_ecdsa = req.ciphersuite == ECDSA_SUITE_NAME;
+        if use_ecdsa && !req.devices.is_empty() {
+            return Err(Status::invalid_argument("Devices can only join FROST groups"));
+        }
+        let suite = if use_ecdsa {
+            SuiteId::default()
+        } else {
+            SuiteId::from_name(&req.ciphersuite).map_err(|e| Status::invalid_argument(e))?
+        };
+
         // Step 1: create vault_id
         let vault_id = generate_new_vault_id().await;
 
@@ -66,36 +293,67 @@ impl CustodyMpc for CustodyMpcService {
         store_record(&vault_id, &record)
             .map_err(|e| Status::internal(format!("vault store failed: {e}")))?;
 
-        // Step 3: trigger DKG
-        let peers = discover::discover_peer_nodes("custody-nodes.default.svc.cluster.local")
-            .await.map_err(|e| Status::internal(format!("peer discovery failed: {e}")))?;
-
-        let threshold = 2; // TODO: replace with policy engine call
-        let group_id = orchestrator::orchestrate_dkg(&req.operational_did, threshold, peers.clone())
-            .await.map_err(|e| Status::internal(format!("DKG orchestration failed: {e}")))?;
-
-        // Step 4: assemble MPC group descriptor
-        let mpc_group = MPCGroupDescriptor {
-            group_id: group_id.clone(),
-            members: peers.iter().enumerate().map(|(i, node)| MPCMemberDescriptor {
-                vault_reference: vault_id.clone(),
-                custody_node_id: node.clone(),
-                shard_index: i as u8,
-            }).collect(),
-            threshold,
-            dkg_protocol: Some("frost-dkg-v1".to_string()),
-            session_state: None,
-        };
-
+        // Register first so the local DKG engine can record the group it finalizes
         self.coordinator.registry.register_operational_did(
             op_did.clone(),
             root_did.clone(),
             vault_id.clone(),
             vec![], // DID doc will be added later
         ).map_err(|e| Status::internal(format!("register DID failed: {e:?}")))?;
+        self.coordinator.registry.set_signing_policy(&op_did, policy.clone())
+            .map_err(|e| Status::internal(format!("set policy failed: {e:?}")))?;
 
-        self.coordinator.registry.set_mpc_group(&op_did, mpc_group.clone())
-            .map_err(|e| Status::internal(format!("set MPC group failed: {e:?}")))?;
+        // Step 3: trigger DKG with the policy's committee (or every discovered node)
+        let peers = if policy.participants.is_empty() {
+            discover::discover_peer_nodes("custody-nodes.default.svc.cluster.local")
+                .await.map_err(|e| Status::internal(format!("peer discovery failed: {e}")))?
+        } else {
+            policy.participants.clone()
+        };
+
+        let threshold = policy.threshold as u32;
+
+        // Threshold ECDSA: the local ECDSA engine records the group when keygen finalizes
+        if use_ecdsa {
+            orchestrator::orchestrate_ecdsa_keygen(&req.operational_did, threshold, peers.clone())
+                .await.map_err(|e| Status::internal(format!("ECDSA keygen failed: {e}")))?;
+
+            let mpc_group = self.coordinator.registry.get_mpc_group(&op_did)
+                .ok_or(Status::internal("ECDSA group not recorded"))?;
+            let group_pubkey = aggregate_group_public_key(&mpc_group)
+                .map_err(|e| Status::internal(e))?;
+
+            return Ok(Response::new(ProvisionVaultAndShardsResponse {
+                vault_id,
+                group_id: mpc_group.group_id,
+                group_public_key: group_pubkey,
+                dkg_protocol: ECDSA_PROTOCOL.to_string(),
+            }));
+        }
+
+        // Enrolled devices join the DKG as extra participants
+        let mut participants = peers.clone();
+        for enrollment in &req.devices {
+            let home_node = if enrollment.home_node.is_empty() { self.coordinator.local_node_id.clone() } else { enrollment.home_node.clone() };
+            let participant_id = device::device_participant_id(&enrollment.device_id, &home_node);
+            self.coordinator.registry.register_device(&op_did, DeviceRecord {
+                device_id: enrollment.device_id.clone(),
+                participant_id: participant_id.clone(),
+                identity_public_key: enrollment.identity_public_key.clone(),
+                encryption_public_key: enrollment.encryption_public_key.clone(),
+                required_for_signing: enrollment.required_for_signing,
+                registered_at: now_rfc3339(),
+            }).map_err(|e| Status::internal(format!("register device failed: {e:?}")))?;
+            participants.push(participant_id);
+        }
+
+        let group_id = orchestrator::orchestrate_dkg(&req.operational_did, threshold, participants.clone(), suite)
+            .await.map_err(|e| Status::internal(format!("DKG orchestration failed: {e}")))?;
+
+        // Step 4: the local DKG engine recorded the group, members resolved through membership
+        let mpc_group = self.coordinator.registry.get_mpc_group(&op_did)
+            .filter(|g| g.group_id == group_id)
+            .ok_or(Status::internal("DKG group not recorded"))?;
 
         let group_pubkey = aggregate_group_public_key(&mpc_group)
         .map_err(|e| Status::internal(e))?;
@@ -104,71 +362,68 @@ impl CustodyMpc for CustodyMpcService {
             vault_id,
             group_id,
             group_public_key: group_pubkey,
+            dkg_protocol: suite.dkg_protocol().to_string(),
         }))
     }
 
     fn aggregate_group_public_key(group: &MPCGroupDescriptor) -> Result<Vec<u8>, String> {
-        use frost_ed25519::keys::VerifyingKey;
-        use frost_ed25519::keys::PublicKeyPackage;
-        use frost_ed25519::Identifier;
-        use std::collections::HashMap;
-    
-        let mut pubkeys = HashMap::new();
-    
-        for member in &group.members {
-            let id = Identifier::try_from(member.custody_node_id.as_bytes())
-                .map_err(|_| "Invalid Identifier")?;
-    
-            let pk_b64 = &member.public_share;
-            let pk_bytes = base64::decode(pk_b64).map_err(|_| "Invalid base64 public key")?;
-            let verifying_key = VerifyingKey::from_bytes(&pk_bytes)
-                .map_err(|_| "Invalid verifying key")?;
-    
-            pubkeys.insert(id, verifying_key);
+        // ECDSA groups store the compressed group key directly
+        if group.dkg_protocol.as_deref() == Some(ECDSA_PROTOCOL) {
+            return group.public_key_package.clone().ok_or("Group public key missing".into());
         }
-    
-        let package = PublicKeyPackage::try_from(pubkeys)
-            .map_err(|e| format!("Group public key aggregation failed: {:?}", e))?;
-    
-        Ok(package.group_public().to_bytes().to_vec())
+
+        let suite = SuiteId::from_dkg_protocol(group.dkg_protocol.as_deref())?;
+        let package = group.public_key_package.as_ref()
+            .ok_or("Group public key package missing")?;
+
+        with_ciphersuite!(suite, |C| ciphersuite::group_verifying_key::<C>(package))
     }
 
     async fn rotate_shards(
         &self,
         request: Request<RotateShardsRequest>,
     ) -> Result<Response<RotateShardsResponse>, Status> {
-        let op_did = request.into_inner().operational_did;
+        let req = request.into_inner();
+        let op_did = req.operational_did;
+
+        // Step 0: Rotation of a DID under an approval policy needs an approved request
+        self.coordinator.approvals
+            .authorize_operation(&self.coordinator.registry, &op_did, &req.approval_request_id, &KeyOperation::Rotate)
+            .map_err(|e| Status::permission_denied(e))?;
     
         // Step 1: Get current vault_id
         let vault_id = self.coordinator.registry
             .get_vault_id_for_operational_did(&op_did)
             .ok_or(Status::not_found("Vault ID not found"))?;
     
-        // Step 2: Discover peers again (could also reuse existing group)
-        let peers = discover::discover_peer_nodes("custody-nodes.default.svc.cluster.local")
-            .await.map_err(|e| Status::internal(format!("Discovery failed: {e}")))?;
-    
-        let threshold = 2; // Load from policy engine if needed
+        // Step 2: The DID's policy sets the threshold and committee; otherwise discover peers again
+        let policy = self.coordinator.registry.get_signing_policy(&OperationalDID(op_did.clone())).unwrap_or_default();
+        let peers = if policy.participants.is_empty() {
+            discover::discover_peer_nodes("custody-nodes.default.svc.cluster.local")
+                .await.map_err(|e| Status::internal(format!("Discovery failed: {e}")))?
+        } else {
+            policy.participants.clone()
+        };
+
+        let threshold = policy.threshold as u32;
+
+        // Rotation keeps the ciphersuite the DID was provisioned with
+        let current_group = self.coordinator.registry.get_mpc_group(&op_did)
+            .ok_or(Status::not_found("MPC group not found"))?;
+        let suite = SuiteId::from_dkg_protocol(current_group.dkg_protocol.as_deref())
+            .map_err(|e| Status::internal(e))?;
     
-        // Step 3: Run orchestrator to rotate shards
-        let new_group_id = orchestrator::orchestrate_dkg(&op_did, threshold, peers.clone())
+        // Step 3: Run orchestrator to rotate shards; registered devices get a fresh share too
+        let mut participants = peers.clone();
+        participants.extend(self.coordinator.registry.get_devices(&op_did).into_iter().map(|d| d.participant_id));
+
+        let new_group_id = orchestrator::orchestrate_dkg(&op_did, threshold, participants.clone(), suite)
             .await.map_err(|e| Status::internal(format!("DKG failed: {e}")))?;
     
-        // Step 4: Replace MPC group in registry
-        let new_group = MPCGroupDescriptor {
-            group_id: new_group_id.clone(),
-            members: peers.iter().enumerate().map(|(i, node)| MPCMemberDescriptor {
-                vault_reference: vault_id.clone(),
-                custody_node_id: node.clone(),
-                shard_index: i as u8,
-            }).collect(),
-            threshold,
-            dkg_protocol: Some("frost-dkg-v1".to_string()),
-            session_state: None,
-        };
-    
-        self.coordinator.registry.set_mpc_group(&OperationalDID(op_did.clone()), new_group)
-            .map_err(|e| Status::internal(format!("Failed to update MPC group: {e:?}")))?;
+        // Step 4: The local DKG engine replaced the MPC group when the session finalized
+        let new_group = self.coordinator.registry.get_mpc_group(&op_did)
+            .filter(|g| g.group_id == new_group_id)
+            .ok_or(Status::internal("DKG group not recorded"))?;
 
         // Step 5: Aggregate new public key
         let group_pubkey = aggregate_group_public_key(&new_group)
@@ -199,4 +454,319 @@ impl CustodyMpc for CustodyMpcService {
             new_group_id,
         }))
     }
+
+    async fn refresh_shares(
+        &self,
+        request: Request<RefreshSharesRequest>,
+    ) -> Result<Response<RefreshSharesResponse>, Status> {
+        let req = request.into_inner();
+        let op_did = OperationalDID(req.operational_did.clone());
+
+        // Step 0: A refresh under an approval policy needs an approved request
+        self.coordinator.approvals
+            .authorize_operation(&self.coordinator.registry, &req.operational_did, &req.approval_request_id, &KeyOperation::Refresh)
+            .map_err(|e| Status::permission_denied(e))?;
+
+        // Step 1: Every member of the current group refreshes; nobody joins or leaves
+        let group = self.coordinator.registry.get_mpc_group(&op_did)
+            .ok_or(Status::not_found("MPC group not found"))?;
+        let members = group.members.iter().map(|m| m.node_id.clone()).collect::<Vec<_>>();
+        let group_public_key = with_ciphersuite!(
+            SuiteId::from_dkg_protocol(group.dkg_protocol.as_deref()).map_err(|e| Status::failed_precondition(e))?,
+            |C| ciphersuite::group_verifying_key::<C>(group.public_key_package.as_deref().unwrap_or_default())
+        ).map_err(|e| Status::internal(e))?;
+
+        // Step 2: Run the refresh; the DID document keeps its key, so nothing else changes
+        let group_id = orchestrator::orchestrate_refresh(&req.operational_did, members)
+            .await.map_err(|e| Status::internal(format!("Refresh failed: {e}")))?;
+
+        Ok(Response::new(RefreshSharesResponse { group_id, group_public_key }))
+    }
+
+    async fn reshare_group(
+        &self,
+        request: Request<ReshareGroupRequest>,
+    ) -> Result<Response<ReshareGroupResponse>, Status> {
+        let req = request.into_inner();
+        let op_did = OperationalDID(req.operational_did.clone());
+
+        // Step 0: Changing who holds the key needs approval for exactly this committee and threshold
+        let operation = KeyOperation::Reshare { threshold: req.threshold, members: req.members.clone() };
+        self.coordinator.approvals
+            .authorize_operation(&self.coordinator.registry, &req.operational_did, &req.approval_request_id, &operation)
+            .map_err(|e| Status::permission_denied(e))?;
+
+        // Step 1: Dealers default to the whole current committee; at least its threshold must deal
+        let group = self.coordinator.registry.get_mpc_group(&op_did)
+            .ok_or(Status::not_found("MPC group not found"))?;
+        let suite = SuiteId::from_dkg_protocol(group.dkg_protocol.as_deref())
+            .map_err(|e| Status::failed_precondition(e))?;
+        let current = group.members.iter().map(|m| m.node_id.clone()).collect::<Vec<_>>();
+        let dealers = if req.dealers.is_empty() { current.clone() } else { req.dealers };
+        if let Some(outsider) = dealers.iter().find(|d| !current.contains(d)) {
+            return Err(Status::invalid_argument(format!("{outsider} holds no share of the group")));
+        }
+        if dealers.len() < group.threshold as usize {
+            return Err(Status::invalid_argument(format!("at least {} current members must deal", group.threshold)));
+        }
+        let old_public_key_package = group.public_key_package
+            .ok_or(Status::failed_precondition("group has no public key package"))?;
+
+        // Step 2: Start it everywhere and let it finish in the background; the group key
+        // does not change, so the DID document stays as it is
+        let group_id = orchestrator::start_reshare(&req.operational_did, &dealers, &req.members, req.threshold, suite, &old_public_key_package)
+            .await.map_err(|e| Status::internal(format!("Reshare failed to start: {e}")))?;
+        let participants = reshare_participants(&dealers, &req.members);
+
+        let (waiting_on, watched) = (participants.clone(), group_id.clone());
+        tokio::spawn(sim::network::inherit(async move {
+            if let Err(e) = orchestrator::await_reshare(&watched, &waiting_on).await {
+                println!("❌ Reshare {watched} failed: {e}");
+            }
+        }));
+
+        Ok(Response::new(ReshareGroupResponse { group_id, participants }))
+    }
+
+    async fn get_reshare_status(
+        &self,
+        request: Request<GetReshareStatusRequest>,
+    ) -> Result<Response<GetReshareStatusResponse>, Status> {
+        let req = request.into_inner();
+        if req.participants.is_empty() {
+            return Err(Status::invalid_argument("participants are required"));
+        }
+
+        let mut nodes = Vec::new();
+        let mut group_keys = Vec::new();
+        for (node, status) in orchestrator::reshare_progress(&req.group_id, &req.participants).await {
+            nodes.push(match status {
+                Ok(status) => {
+                    if status.phase == DkgPhase::Finalized as i32 {
+                        group_keys.push(status.group_public_key.clone());
+                    }
+                    NodeReshareStatus {
+                        node,
+                        phase: status.phase,
+                        received: status.round2_received.max(status.round1_received),
+                        expected: status.expected,
+                        error: status.error,
+                    }
+                }
+                Err(error) => NodeReshareStatus { node, phase: DkgPhase::Unspecified as i32, received: 0, expected: 0, error },
+            });
+        }
+
+        let complete = group_keys.len() == nodes.len() && group_keys.windows(2).all(|w| w[0] == w[1]);
+        Ok(Response::new(GetReshareStatusResponse {
+            nodes,
+            complete,
+            group_public_key: if complete { group_keys.pop().unwrap_or_default() } else { Vec::new() },
+        }))
+    }
+
+    async fn import_key(
+        &self,
+        request: Request<ImportKeyRequest>,
+    ) -> Result<Response<ImportKeyResponse>, Status> {
+        let req = request.into_inner();
+        let op_did = OperationalDID(req.operational_did.clone());
+        if self.coordinator.registry.get_mpc_group(&op_did).is_some() {
+            return Err(Status::already_exists("DID already has a group"));
+        }
+        if req.public_key.len() != 32 {
+            return Err(Status::invalid_argument("public_key must be a 32-byte Ed25519 key"));
+        }
+
+        let mut policy = if req.policy_json.is_empty() {
+            SigningPolicy::default()
+        } else {
+            serde_json::from_str::<SigningPolicy>(&req.policy_json)
+                .map_err(|e| Status::invalid_argument(format!("Invalid policy: {e}")))?
+        };
+        policy.version = 1;
+        policy.validate().map_err(|e| Status::invalid_argument(e))?;
+
+        // Step 1: Vault record and DID registration, as for a newly provisioned DID
+        let vault_id = generate_new_vault_id().await;
+        let record = VaultRecord {
+            root_did: req.root_did.clone(),
+            op_dids: vec![req.operational_did.clone()],
+            mpc_shard: None,
+            group_metadata: None,
+            public_keys: vec![],
+            vcs: vec![],
+            bbs_private_key: None,
+            bbs_public_key: None,
+            active_nonce: None,
+            batch_nonces: vec![],
+            ecdsa_share: None,
+            ecdsa_presignatures: Default::default(),
+            shard_epoch: 0,
+            dkg_sessions: Default::default(),
+            consumed_approvals: Default::default(),
+        };
+        store_record(&vault_id, &record)
+            .map_err(|e| Status::internal(format!("vault store failed: {e}")))?;
+        self.coordinator.registry.register_operational_did(
+            op_did.clone(),
+            RootDID(req.root_did.clone()),
+            vault_id.clone(),
+            vec![],
+        ).map_err(|e| Status::internal(format!("register DID failed: {e:?}")))?;
+
+        // Only the committee applies until the proof is signed: the client's intents, time
+        // windows, rate limit or approvers could refuse it and strand the imported key
+        let committee = SigningPolicy {
+            threshold: policy.threshold,
+            participants: policy.participants.clone(),
+            ..SigningPolicy::default()
+        };
+        self.coordinator.registry.set_signing_policy(&op_did, committee)
+            .map_err(|e| Status::internal(format!("set policy failed: {e:?}")))?;
+
+        // Step 2: This node opens the key and deals it over the policy's committee
+        let members = if policy.participants.is_empty() {
+            discover::discover_peer_nodes("custody-nodes.default.svc.cluster.local")
+                .await.map_err(|e| Status::internal(format!("peer discovery failed: {e}")))?
+        } else {
+            policy.participants.clone()
+        };
+        let sealed_key = custodydkg::SealedKey { ephemeral_key: req.ephemeral_key, ciphertext: req.ciphertext };
+        let group_id = orchestrator::orchestrate_import(
+            &req.operational_did,
+            &self.coordinator.local_node_id,
+            &members,
+            policy.threshold as u32,
+            &req.public_key,
+            sealed_key,
+        ).await.map_err(|e| Status::internal(format!("Import failed: {e}")))?;
+
+        // Step 3: The group signs a challenge under the imported key, so the client
+        // knows its own copy of the private key is no longer needed
+        let challenge = format!("custody-import-v1:{group_id}").into_bytes();
+        let proof_signature = self.coordinator
+            .sign(&req.operational_did, &SigningIntent::DidAuth, challenge.clone(), None)
+            .await
+            .map_err(|e| Status::internal(format!("Import proof signing failed: {e}")))?;
+        let message = intent::signing_bytes(&SigningIntent::DidAuth, &challenge)
+            .map_err(|e| Status::internal(e))?;
+        ciphersuite::verify::<frost_ed25519::Ed25519Sha512>(&req.public_key, &message, &proof_signature)
+            .map_err(|e| Status::internal(format!("Import proof does not verify: {e}")))?;
+
+        // Step 4: The client's policy governs every signature from here on
+        self.coordinator.registry.set_signing_policy(&op_did, policy)
+            .map_err(|e| Status::internal(format!("set policy failed: {e:?}")))?;
+
+        println!("📥 Imported key of {} into group {group_id}", req.operational_did);
+        Ok(Response::new(ImportKeyResponse {
+            vault_id,
+            group_id,
+            group_public_key: req.public_key,
+            proof_signature,
+        }))
+    }
+
+    async fn repair_share(
+        &self,
+        request: Request<RepairShareRequest>,
+    ) -> Result<Response<RepairShareResponse>, Status> {
+        let req = request.into_inner();
+        let op_did = OperationalDID(req.operational_did.clone());
+
+        // Step 1: Helpers default to the first `threshold` of the other members
+        let group = self.coordinator.registry.get_mpc_group(&op_did)
+            .ok_or(Status::not_found("MPC group not found"))?;
+        let helpers = repair_helpers(&group, &req.lost_node, req.helpers)?;
+
+        // Step 2: Handing a node a share needs approval for exactly this node and these helpers
+        let operation = KeyOperation::Repair { lost_node: req.lost_node.clone(), helpers: helpers.clone() };
+        self.coordinator.approvals
+            .authorize_operation(&self.coordinator.registry, &req.operational_did, &req.approval_request_id, &operation)
+            .map_err(|e| Status::permission_denied(e))?;
+
+        // Step 3: Run the repair; nothing about the group or the DID document changes
+        let group_id = orchestrator::orchestrate_repair(&req.operational_did, &helpers, &req.lost_node)
+            .await.map_err(|e| Status::internal(format!("Repair failed: {e}")))?;
+
+        Ok(Response::new(RepairShareResponse { group_id, helpers }))
+    }
+
+    async fn update_policy(
+        &self,
+        request: Request<UpdatePolicyRequest>,
+    ) -> Result<Response<UpdatePolicyResponse>, Status> {
+        let req = request.into_inner();
+
+        let policy = serde_json::from_str::<SigningPolicy>(&req.policy_json)
+            .map_err(|e| Status::invalid_argument(format!("Invalid policy: {e}")))?;
+
+        let version = self.coordinator
+            .update_policy(&req.operational_did, policy, Some(req.approval_request_id.as_str()).filter(|id| !id.is_empty()))
+            .await
+            .map_err(|e| Status::internal(format!("Policy update failed: {e}")))?;
+
+        Ok(Response::new(UpdatePolicyResponse { version }))
+    }
+
+    async fn get_policy(
+        &self,
+        request: Request<GetPolicyRequest>,
+    ) -> Result<Response<GetPolicyResponse>, Status> {
+        let op_did = OperationalDID(request.into_inner().operational_did);
+
+        let policy = self.coordinator.registry.get_signing_policy(&op_did).unwrap_or_default();
+        let policy_json = serde_json::to_string(&policy)
+            .map_err(|e| Status::internal(format!("Serialize failed: {e}")))?;
+
+        Ok(Response::new(GetPolicyResponse { policy_json }))
+    }
+
+    async fn submit_for_approval(
+        &self,
+        request: Request<SubmitForApprovalRequest>,
+    ) -> Result<Response<SubmitForApprovalResponse>, Status> {
+        let req = request.into_inner();
+
+        if self.coordinator.registry.get_approval_policy(&OperationalDID(req.operational_did.clone())).is_none() {
+            return Err(Status::failed_precondition("DID has no approval policy"));
+        }
+
+        // A key operation is approved with the parameters it will run with; otherwise
+        // approvers sign off on the exact bytes the vaults will sign for this intent
+        let (kind, message) = if let Some(operation) = key_operation(&self.coordinator.registry, &req)? {
+            (operation.name(), operation.message(&req.operational_did))
+        } else {
+            let intent = SigningIntent::from_proto(req.intent, &req.justification)
+                .map_err(|e| Status::invalid_argument(e))?;
+            (intent.name(), intent::signing_bytes(&intent, &req.message).map_err(|e| Status::invalid_argument(e))?)
+        };
+        let (request_id, digest, expires_at) = self.coordinator.approvals.submit(&req.operational_did, kind, &message);
+
+        Ok(Response::new(SubmitForApprovalResponse {
+            request_id,
+            approval_digest: digest.to_vec(),
+            expires_at,
+        }))
+    }
+
+    async fn approve(
+        &self,
+        request: Request<ApproveRequest>,
+    ) -> Result<Response<ApproveResponse>, Status> {
+        let req = request.into_inner();
+
+        let (approvals, quorum) = self.coordinator.approvals
+            .approve(&self.coordinator.registry, &req.request_id, Approval {
+                approver_id: req.approver_id,
+                signature: req.signature,
+            })
+            .map_err(|e| Status::permission_denied(e))?;
+
+        Ok(Response::new(ApproveResponse {
+            approvals: approvals as u32,
+            quorum: quorum as u32,
+            ready: approvals >= quorum as usize,
+        }))
+    }
 }
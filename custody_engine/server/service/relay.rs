@@ -1,84 +1,806 @@
 
 // File: src/relay.rs
 
-use tonic::{Request, Response, Status};
+use tonic::{Code, Request, Response, Status, Streaming};
 use tonic::transport::Server;
 use crate::dkg::types::*;
-use crate::dkg::engine::DKGEngine;
-use std::sync::Arc;
+use crate::mpc::device::{self, DeviceHub, NodeToDevice};
+use crate::identity::{self, IdentityDirectory, IdentityPublicKeys, NodeIdentity};
+use crate::membership::MEMBERSHIP;
+use crate::tls::{self, PeerIdentity, PeerRole};
+use crate::sim;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use once_cell::sync::Lazy;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
 
 use custodyrelay::custody_relay_server::{CustodyRelay, CustodyRelayServer};
-use custodyrelay::{RelayMessage, Empty};
+use custodyrelay::{RelayMessage, RelayAck, RelayHealth, PeerRelayHealth, Empty};
+pub use custodyrelay::MessageType;
 use crate::custodyrelay::custody_relay_server::{CustodyRelay, CustodyRelayServer};
 
 pub mod custody {
     tonic::include_proto!("custodyrelay");
 }
 
+/// Envelope format this node sends and accepts
+pub const RELAY_VERSION: u32 = 1;
+
+/// Domain separator for relay envelope signatures
+pub const RELAY_ENVELOPE_DOMAIN: &[u8] = b"custody-relay-envelope-v1";
+
+/// Messages a sender streams ahead of the oldest one its peer has not acknowledged;
+/// the receiver buffers as many acknowledgements before it stops reading
+pub const STREAM_WINDOW: usize = 64;
+
+type AckStream = Pin<Box<dyn Stream<Item = Result<RelayAck, Status>> + Send>>;
+
+/// How many message IDs a node remembers to recognise redelivered messages
+const SEEN_MESSAGE_CAPACITY: usize = 65_536;
+
+/// Oldest envelope a node accepts; covers a message's retries and modest clock skew
+pub const MAX_ENVELOPE_AGE: Duration = Duration::from_secs(600);
+
+/// How far ahead of this node's clock an envelope may be dated
+pub const MAX_ENVELOPE_SKEW: Duration = Duration::from_secs(60);
+
+/// Bytes the sending node signs: every field of the envelope and the node it is for,
+/// so an envelope cannot be altered or redirected to another node
+pub fn envelope_signing_input(to_host: &str, msg: &RelayMessage) -> Vec<u8> {
+    let mut input = RELAY_ENVELOPE_DOMAIN.to_vec();
+    let version = msg.version.to_be_bytes();
+    let message_type = msg.message_type.to_be_bytes();
+    let sequence = msg.sequence.to_be_bytes();
+    let sent_at = msg.sent_at_ms.to_be_bytes();
+    let fields: [&[u8]; 11] = [
+        &version,
+        to_host.as_bytes(),
+        msg.sender_node.as_bytes(),
+        &sequence,
+        &sent_at,
+        msg.message_id.as_bytes(),
+        &message_type,
+        msg.group_id.as_bytes(),
+        msg.from_node.as_bytes(),
+        msg.to_node.as_bytes(),
+        &msg.payload,
+    ];
+    for field in fields {
+        input.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        input.extend_from_slice(field);
+    }
+    input
+}
+
+/// Stamps and signs an envelope for `to_host`
+pub fn sign_envelope(identity: &NodeIdentity, to_host: &str, sender_node: &str, sequence: u64, msg: &mut RelayMessage) {
+    msg.version = RELAY_VERSION;
+    msg.sender_node = sender_node.to_string();
+    msg.sequence = sequence;
+    resign_envelope(identity, to_host, msg);
+}
+
+/// Dates and signs an envelope again for another delivery attempt. Sender and sequence
+/// stay, so the peer still recognises a redelivered message by its ID.
+pub fn resign_envelope(identity: &NodeIdentity, to_host: &str, msg: &mut RelayMessage) {
+    msg.sent_at_ms = unix_millis(SystemTime::now());
+    msg.signature = identity.sign(&envelope_signing_input(to_host, msg));
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// What this node has accepted over the relay. A sender whose acknowledgement was
+/// lost sends the message again under the same ID; it is acknowledged, not handled
+/// twice. Every other envelope must carry a higher sequence number than the last one
+/// accepted from its sender, so a captured envelope cannot be played back.
+pub struct RelayInbox {
+    seen: Mutex<(HashSet<String>, VecDeque<String>)>,   // Lookup, and oldest-first for eviction
+    last_sequence: Mutex<HashMap<String, u64>>,          // Sending node → highest sequence accepted
+}
+
+impl RelayInbox {
+    pub fn new() -> Self {
+        RelayInbox {
+            seen: Mutex::new((HashSet::new(), VecDeque::new())),
+            last_sequence: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Checks an envelope addressed to `local_node_id` against its sender's signing key:
+    /// the signature, that the sender may speak for `from_node`, and that it is recent.
+    /// A redelivered message passes; see `is_duplicate` and `is_replay`.
+    pub fn check_envelope(&self, local_node_id: &str, msg: &RelayMessage, signing_key: &[u8]) -> Result<(), String> {
+        identity::verify(signing_key, &envelope_signing_input(local_node_id, msg), &msg.signature)
+            .map_err(|e| format!("bad envelope signature from {}: {e}", msg.sender_node))?;
+
+        // A node speaks for itself and for the devices it is home to
+        let speaks_for_sender = if device::is_device_participant(&msg.from_node) {
+            device::device_home_node(&msg.from_node) == Some(msg.sender_node.as_str())
+        } else {
+            msg.from_node == msg.sender_node
+        };
+        if !speaks_for_sender {
+            return Err(format!("{} cannot send as {}", msg.sender_node, msg.from_node));
+        }
+
+        let now = unix_millis(SystemTime::now());
+        if msg.sent_at_ms + (MAX_ENVELOPE_AGE.as_millis() as u64) < now {
+            return Err(format!("envelope from {} is too old", msg.sender_node));
+        }
+        if msg.sent_at_ms > now + MAX_ENVELOPE_SKEW.as_millis() as u64 {
+            return Err(format!("envelope from {} is dated in the future", msg.sender_node));
+        }
+        Ok(())
+    }
+
+    pub fn is_duplicate(&self, message_id: &str) -> bool {
+        !message_id.is_empty() && self.seen.lock().unwrap().0.contains(message_id)
+    }
+
+    /// A new message whose sequence number is not past the last one accepted from its sender
+    pub fn is_replay(&self, msg: &RelayMessage) -> bool {
+        self.last_sequence.lock().unwrap()
+            .get(&msg.sender_node)
+            .is_some_and(|last| msg.sequence <= *last)
+    }
+
+    /// Remembers a handled message, forgetting the oldest IDs once full
+    pub fn record(&self, msg: &RelayMessage) {
+        let mut last_sequence = self.last_sequence.lock().unwrap();
+        let last = last_sequence.entry(msg.sender_node.clone()).or_insert(0);
+        *last = (*last).max(msg.sequence);
+        drop(last_sequence);
+
+        let mut seen = self.seen.lock().unwrap();
+        let (ids, order) = &mut *seen;
+        if ids.insert(msg.message_id.clone()) {
+            order.push_back(msg.message_id.clone());
+            if order.len() > SEEN_MESSAGE_CAPACITY {
+                if let Some(oldest) = order.pop_front() {
+                    ids.remove(&oldest);
+                }
+            }
+        }
+    }
+}
+
+/// Handles the messages of one channel: group or session ID, sending participant, payload
+pub type RelayHandler = Box<dyn Fn(&str, &str, Vec<u8>) -> Result<(), String> + Send + Sync>;
+
+/// Which component handles each message type. Components register their channels at
+/// startup, so a new protocol plugs in without touching the relay service.
+pub struct RelayHandlers {
+    handlers: RwLock<HashMap<MessageType, RelayHandler>>,
+}
+
+impl RelayHandlers {
+    pub fn new() -> Self {
+        RelayHandlers { handlers: RwLock::new(HashMap::new()) }
+    }
+
+    /// Claims a channel; each has one handler
+    pub fn register(
+        &self,
+        channel: MessageType,
+        handler: impl Fn(&str, &str, Vec<u8>) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Result<(), String> {
+        if channel == MessageType::Unspecified {
+            return Err("cannot register a handler for an unspecified message type".into());
+        }
+        let mut handlers = self.handlers.write().unwrap();
+        if handlers.contains_key(&channel) {
+            return Err(format!("{channel:?} messages already have a handler"));
+        }
+        handlers.insert(channel, Box::new(handler));
+        Ok(())
+    }
+
+    /// Hands a message to its channel's handler
+    pub fn dispatch(&self, channel: MessageType, group_id: &str, from: &str, payload: Vec<u8>) -> Result<(), String> {
+        let handlers = self.handlers.read().unwrap();
+        let handler = handlers.get(&channel).ok_or_else(|| format!("no handler for {channel:?} messages"))?;
+        handler(group_id, from, payload)
+    }
+
+    /// Channels with a handler
+    pub fn channels(&self) -> Vec<MessageType> {
+        let mut channels = self.handlers.read().unwrap().keys().copied().collect::<Vec<_>>();
+        channels.sort();
+        channels
+    }
+}
+
 /// Relay service for custody node-to-node communication
 #[derive(Clone)]
 pub struct RelayService {
-    pub dkg_engine: Arc<DKGEngine>,
+    pub handlers: Arc<RelayHandlers>,
+    pub device_hub: Arc<DeviceHub>,
+    pub identity: Arc<NodeIdentity>,
+    pub directory: Arc<IdentityDirectory>,       // Keys relay envelopes are checked against
     pub local_node_id: String,
+    pub inbox: Arc<RelayInbox>,
+    pub relay: Arc<RelayClient>,                  // Outbound side, for health reports
+}
+
+impl RelayService {
+    /// Checks and handles one envelope. `peer` is the caller's certificate: only the node
+    /// it names may hand over its envelopes. `Authorize` attaches it whenever cluster TLS
+    /// is on, and without it nothing is taken. An error means the message was not taken
+    /// and may be sent again.
+    async fn receive(&self, peer: Option<&PeerIdentity>, msg: RelayMessage) -> Result<RelayAck, Status> {
+        let message_id = msg.message_id.clone();
+        if tls::installed().is_some() {
+            let peer = peer.ok_or_else(|| Status::unauthenticated("relay envelopes need a node certificate"))?;
+            if peer.role != PeerRole::Node || peer.name != msg.sender_node {
+                return Err(Status::permission_denied(format!("{} cannot send envelopes of {}", peer.name, msg.sender_node)));
+            }
+        }
+
+        // An envelope from another version cannot even be checked; resending will not help
+        if msg.version != RELAY_VERSION {
+            let error = format!("unsupported relay version {} (this node speaks {RELAY_VERSION})", msg.version);
+            return Ok(RelayAck { message_id, error, ..Default::default() });
+        }
+
+        // Nothing reaches an engine before its envelope checks out. Senders are only ever
+        // known from the membership file, never from what the sender says about itself.
+        let signing_key = match self.directory.get(&msg.sender_node) {
+            Some(keys) => keys.signing_key,
+            None => {
+                let keys = MEMBERSHIP.get(&msg.sender_node)
+                    .map(|member| member.identity)
+                    .ok_or_else(|| Status::unauthenticated(format!("{} is not a cluster member", msg.sender_node)))?;
+                self.directory.pin(&msg.sender_node, keys.clone()).map_err(Status::unauthenticated)?;
+                keys.signing_key
+            }
+        };
+        if peer.is_some_and(|peer| peer.identity_key != signing_key) {
+            return Err(Status::permission_denied(format!("certificate of {} carries another identity key", msg.sender_node)));
+        }
+        self.inbox.check_envelope(&self.local_node_id, &msg, &signing_key)
+            .map_err(Status::unauthenticated)?;
+
+        if self.inbox.is_duplicate(&message_id) {
+            return Ok(RelayAck { message_id, duplicate: true, ..Default::default() });
+        }
+        if self.inbox.is_replay(&msg) {
+            return Err(Status::permission_denied(format!("replayed envelope from {}", msg.sender_node)));
+        }
+        let envelope = RelayMessage { payload: Vec::new(), ..msg.clone() };
+
+        let channel = msg.message_type();
+
+        // DKG traffic addressed to a device connected to this node goes down its session stream.
+        // An offline device is reported as unavailable, so the sender tries again.
+        if device::is_device_participant(&msg.to_node) {
+            if channel != MessageType::Dkg {
+                let error = format!("devices take no {channel:?} messages");
+                return Ok(RelayAck { message_id, error, ..Default::default() });
+            }
+            self.device_hub
+                .deliver(&msg.to_node, NodeToDevice::Relay {
+                    group_id: msg.group_id,
+                    from: msg.from_node,
+                    payload: msg.payload,
+                })
+                .await
+                .map_err(|e| Status::unavailable(e))?;
+            self.inbox.record(&envelope);
+            return Ok(RelayAck { message_id, ..Default::default() });
+        }
+
+        // Route the raw payload to the handler of its channel. A message the handler
+        // refuses is acknowledged with the reason: resending cannot fix it.
+        let handled = self.handlers.dispatch(channel, &msg.group_id, &msg.from_node, msg.payload);
+        self.inbox.record(&envelope);
+
+        Ok(RelayAck {
+            message_id,
+            error: handled.err().unwrap_or_default(),
+            ..Default::default()
+        })
+    }
 }
 
 #[tonic::async_trait]
 impl CustodyRelay for RelayService {
+    type OpenStreamStream = AckStream;
+
     async fn send_message(
         &self,
         request: Request<RelayMessage>,
-    ) -> Result<Response<Empty>, Status> {
-        let msg = request.into_inner();
+    ) -> Result<Response<RelayAck>, Status> {
+        let peer = request.extensions().get::<PeerIdentity>().cloned();
+        self.receive(peer.as_ref(), request.into_inner()).await.map(Response::new)
+    }
 
-        // Pass raw message payload to the DKG engine handler
-        self.dkg_engine
-            .handle_message(&msg.group_id, &msg.from_node, msg.payload)
-            .map_err(|e| Status::internal(format!("DKG handling failed: {:?}", e)))?;
+    async fn open_stream(
+        &self,
+        request: Request<Streaming<RelayMessage>>,
+    ) -> Result<Response<Self::OpenStreamStream>, Status> {
+        let peer = request.extensions().get::<PeerIdentity>().cloned();
+        let mut inbound = request.into_inner();
+        let (acks, stream) = mpsc::channel::<Result<RelayAck, Status>>(STREAM_WINDOW);
 
-        Ok(Response::new(Empty {}))
+        // Messages are handled one at a time, in the order sent. Once one is not taken,
+        // the rest of the stream is turned away too, so that none overtakes it; the
+        // sender opens a new stream and sends again from the one not taken.
+        let service = self.clone();
+        tokio::spawn(sim::network::inherit(async move {
+            let mut stalled_at: Option<String> = None;
+            while let Some(Ok(msg)) = inbound.next().await {
+                let message_id = msg.message_id.clone();
+                let ack = match &stalled_at {
+                    Some(first) => RelayAck { message_id, retry: format!("sent after {first}, which was not taken"), ..Default::default() },
+                    None => match service.receive(peer.as_ref(), msg).await {
+                        Ok(ack) => ack,
+                        Err(status) => {
+                            stalled_at = Some(message_id.clone());
+                            RelayAck { message_id, retry: status.message().to_string(), ..Default::default() }
+                        }
+                    },
+                };
+                // A sender that stops reading acknowledgements stops being read from
+                if acks.send(Ok(ack)).await.is_err() {
+                    break;
+                }
+            }
+        }));
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(stream)) as Self::OpenStreamStream))
     }
+
+    async fn get_relay_health(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<RelayHealth>, Status> {
+        let peers = self.relay.health().into_iter().map(|peer| PeerRelayHealth {
+            peer: peer.peer,
+            queued: peer.queued as u64,
+            delivered: peer.delivered,
+            rejected: peer.rejected,
+            retries: peer.retries,
+            dropped: peer.dropped,
+            last_error: peer.last_error.unwrap_or_default(),
+            last_delivered_unix: peer.last_delivered
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }).collect();
+
+        Ok(Response::new(RelayHealth { peers }))
+    }
+
+    async fn get_identity(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<custodyrelay::NodeIdentity>, Status> {
+        let keys = self.identity.public_keys();
+
+        Ok(Response::new(custodyrelay::NodeIdentity {
+            signing_key: keys.signing_key,
+            encryption_key: keys.encryption_key,
+        }))
+    }
+}
+
+/// Fetches a peer's identity keys so they can be pinned
+pub async fn fetch_identity(node: &str) -> Result<IdentityPublicKeys, DKGError> {
+    let channel = tls::channel(&format!("{node}:50051"))
+        .await
+        .map_err(|e| DKGError::Unauthenticated(format!("Connect to {node} failed: {e:?}")))?;
+    let mut client = custodyrelay::custody_relay_client::CustodyRelayClient::new(channel);
+
+    let keys = client.get_identity(Request::new(Empty {})).await
+        .map_err(|e| DKGError::Unauthenticated(format!("GetIdentity on {node} failed: {e:?}")))?
+        .into_inner();
+
+    Ok(IdentityPublicKeys {
+        signing_key: keys.signing_key,
+        encryption_key: keys.encryption_key,
+    })
 }
 
-/// RelayClient used to send outbound messages
+/// How hard the relay tries to deliver a message before it gives up on it
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub initial_backoff: Duration,                // Wait after the first failed attempt
+    pub max_backoff: Duration,                    // Backoff doubles up to this
+    pub max_attempts: u32,                        // Attempts per message, the first included
+    pub attempt_timeout: Duration,                // To connect, and to wait for each acknowledgement
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: 12,
+            attempt_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// A message the relay gave up on once its retries ran out
+#[derive(Debug, Clone)]
+pub struct Undelivered {
+    pub channel: MessageType,
+    pub group_id: String,
+    pub to_node: String,                          // Final recipient: a node, or a device behind one
+    pub message_id: String,
+    pub error: String,                            // Why the last attempt failed
+}
+
+/// Delivery record of one peer's outbound queue
+#[derive(Debug, Clone, Default)]
+pub struct PeerHealth {
+    pub peer: String,
+    pub queued: usize,                            // Waiting or being retried
+    pub delivered: u64,                           // Acknowledged by the peer
+    pub rejected: u64,                            // Acknowledged, but refused by the peer's handler
+    pub retries: u64,                             // Attempts that failed and were tried again
+    pub dropped: u64,                             // Given up after `max_attempts`
+    pub last_error: Option<String>,
+    pub last_delivered: Option<SystemTime>,
+}
+
+type UndeliverableHandler = Box<dyn Fn(&Undelivered) + Send + Sync>;
+
+/// One peer's outbound queue; a single worker drains it over one stream at a time
+struct PeerQueue {
+    sender: mpsc::UnboundedSender<RelayMessage>,
+    health: Arc<Mutex<PeerHealth>>,
+    next_sequence: u64,
+}
+
+/// Runs the queue workers of clients made outside a tokio runtime
+static RELAY_RUNTIME: Lazy<Handle> = Lazy::new(|| {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().expect("relay runtime");
+    let handle = runtime.handle().clone();
+    std::thread::Builder::new()
+        .name("relay".into())
+        .spawn(move || runtime.block_on(std::future::pending::<()>()))
+        .expect("relay thread");
+    handle
+});
+
+/// RelayClient used to send outbound messages. Sending only queues a message; each
+/// peer has one queue, drained in order by a worker that keeps a stream open to the
+/// peer, reopens it when it fails and retries with exponential backoff until the peer
+/// acknowledges. A message and its retries share an ID, so the peer handles it once.
+/// Queues live in memory: a restarted node relies on the protocols' own resend requests.
+///
+/// Every envelope is numbered when queued and signed with the node's identity key each
+/// time it is sent, so a message retried for a long time is never too old to accept.
+/// Numbering starts from the clock when a queue is created, so a restarted node's
+/// envelopes still follow the ones its peers accepted before.
 #[derive(Clone)]
 pub struct RelayClient {
     pub local_node_id: String,
+    identity: Arc<NodeIdentity>,
+    policy: RetryPolicy,
+    runtime: Handle,
+    queues: Arc<Mutex<HashMap<String, PeerQueue>>>,   // Host → queue
+    undeliverable: Arc<RwLock<Vec<UndeliverableHandler>>>,
 }
 
 impl RelayClient {
-    pub fn new(local_node_id: &str) -> Self {
+    pub fn new(identity: Arc<NodeIdentity>, local_node_id: &str) -> Self {
+        Self::with_policy(identity, local_node_id, RetryPolicy::default())
+    }
+
+    pub fn with_policy(identity: Arc<NodeIdentity>, local_node_id: &str, policy: RetryPolicy) -> Self {
         RelayClient {
             local_node_id: local_node_id.to_string(),
+            identity,
+            policy,
+            runtime: Handle::try_current().unwrap_or_else(|_| RELAY_RUNTIME.clone()),
+            queues: Arc::new(Mutex::new(HashMap::new())),
+            undeliverable: Arc::new(RwLock::new(Vec::new())),
         }
     }
 
-    /// Send a message to a remote peer
-    pub fn send_message(
+    /// Calls `handler` for every message the relay gives up on
+    pub fn on_undeliverable(&self, handler: impl Fn(&Undelivered) + Send + Sync + 'static) {
+        self.undeliverable.write().unwrap().push(Box::new(handler));
+    }
+
+    /// Delivery record of every peer this node has sent to
+    pub fn health(&self) -> Vec<PeerHealth> {
+        let mut peers = self.queues.lock().unwrap().values()
+            .map(|queue| queue.health.lock().unwrap().clone())
+            .collect::<Vec<_>>();
+        peers.sort_by(|a, b| a.peer.cmp(&b.peer));
+        peers
+    }
+
+    /// Send a message on `channel` to a remote peer
+    pub fn send(
         &self,
+        channel: MessageType,
         group_id: &str,
         to_node: &str,
         payload: Vec<u8>,
     ) -> Result<(), DKGError> {
-        let uri = format!("http://{}:50051", to_node);
-        let channel = std::thread::spawn(move || {
-            tokio::runtime::Runtime::new().unwrap().block_on(async move {
-                let mut client = custodyrelay::custody_relay_client::CustodyRelayClient::connect(uri)
-                    .await
-                    .map_err(|e| DKGError::CryptoFailure(format!("Connect failed: {e:?}")))?;
-
-                let msg = RelayMessage {
-                    group_id: group_id.to_string(),
-                    from_node: self.local_node_id.clone(),
-                    payload,
-                };
+        self.deliver(channel, &self.local_node_id, group_id, to_node, payload)
+    }
+
+    /// Relay a DKG message a device sent up its session stream, keeping the device as the sender
+    pub fn forward_message(
+        &self,
+        from_device: &str,
+        group_id: &str,
+        to_node: &str,
+        payload: Vec<u8>,
+    ) -> Result<(), DKGError> {
+        self.deliver(MessageType::Dkg, from_device, group_id, to_node, payload)
+    }
 
-                client.send_message(Request::new(msg)).await
-                    .map_err(|e| DKGError::CryptoFailure(format!("Send failed: {e:?}")))?;
+    /// Queues the message for its peer, starting the peer's worker on first use
+    fn deliver(
+        &self,
+        channel: MessageType,
+        from_node: &str,
+        group_id: &str,
+        to_node: &str,
+        payload: Vec<u8>,
+    ) -> Result<(), DKGError> {
+        // Devices are reached through their home node
+        let (host, device_recipient) = match device::device_home_node(to_node) {
+            Some(home) => (home.to_string(), to_node.to_string()),
+            None => (to_node.to_string(), String::new()),
+        };
+        let mut msg = RelayMessage {
+            group_id: group_id.to_string(),
+            from_node: from_node.to_string(),
+            payload,
+            to_node: device_recipient,
+            message_id: uuid::Uuid::new_v4().to_string(),
+            message_type: channel as i32,
+            ..Default::default()
+        };
 
-                Ok(())
-            })
-        }).join().unwrap();
+        // Numbered under the lock, so numbers follow queue order; the worker signs it
+        let mut queues = self.queues.lock().unwrap();
+        let queue = queues.entry(host.clone()).or_insert_with(|| self.start_worker(&host));
+        queue.next_sequence += 1;
+        msg.version = RELAY_VERSION;
+        msg.sender_node = self.local_node_id.clone();
+        msg.sequence = queue.next_sequence;
+        queue.health.lock().unwrap().queued += 1;
+        queue.sender.send(msg)
+            .map_err(|_| DKGError::CryptoFailure(format!("Relay queue for {host} is closed")))
+    }
 
-        channel
+    fn start_worker(&self, host: &str) -> PeerQueue {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let health = Arc::new(Mutex::new(PeerHealth { peer: host.to_string(), ..Default::default() }));
+        // The worker's connections leave from this node (only a simulated network cares)
+        let worker = drain_queue(
+            self.identity.clone(),
+            host.to_string(),
+            receiver,
+            self.policy.clone(),
+            health.clone(),
+            self.undeliverable.clone(),
+        );
+        let local_node_id = self.local_node_id.clone();
+        self.runtime.spawn(async move { sim::network::on_behalf_of(&local_node_id, worker).await });
+        PeerQueue { sender, health, next_sequence: unix_millis(SystemTime::now()) * 1000 }
     }
-}
\ No newline at end of file
+}
+
+/// How a stream to a peer ended
+enum StreamEnd {
+    Drained,                  // The queue closed and everything in it was acknowledged
+    Unsupported,              // The peer predates relay streams; it takes one call per message
+    Failed(String),           // The oldest unacknowledged message was not delivered
+}
+
+/// Delivers a peer's messages in the order they were queued, over one stream at a time.
+/// When a stream fails, the worker backs off, opens a new one and sends again from the
+/// oldest unacknowledged message; the peer handles a stream in order and takes nothing
+/// after a message it turned away, which keeps each group's messages in order. Only
+/// that oldest message is charged an attempt, and it is given up on after the last.
+async fn drain_queue(
+    identity: Arc<NodeIdentity>,
+    host: String,
+    mut queue: mpsc::UnboundedReceiver<RelayMessage>,
+    policy: RetryPolicy,
+    health: Arc<Mutex<PeerHealth>>,
+    undeliverable: Arc<RwLock<Vec<UndeliverableHandler>>>,
+) {
+    let mut pending = VecDeque::new();            // Taken off the queue, not yet acknowledged
+    let mut unary_client = None;                  // Set once the peer turns out to have no streams
+    let mut streams = true;
+    let mut backoff = policy.initial_backoff;
+    let mut attempt = 1;
+    loop {
+        if pending.is_empty() {
+            match queue.recv().await {
+                Some(msg) => pending.push_back(msg),
+                None => return,
+            }
+        }
+        let oldest = pending.front().map(|msg: &RelayMessage| msg.message_id.clone());
+
+        let end = if streams {
+            stream_queue(&identity, &host, &mut queue, &mut pending, &policy, &health).await
+        } else {
+            let msg = pending.front().expect("pending is not empty").clone();
+            match tokio::time::timeout(policy.attempt_timeout, attempt_delivery(&mut unary_client, &identity, &host, msg.clone())).await {
+                Ok(Ok(ack)) => {
+                    record_ack(&health, &msg, &ack);
+                    pending.pop_front();
+                    backoff = policy.initial_backoff;
+                    attempt = 1;
+                    continue;
+                }
+                Ok(Err(e)) => StreamEnd::Failed(e),
+                Err(_) => StreamEnd::Failed("attempt timed out".to_string()),
+            }
+        };
+
+        // Anything acknowledged starts the next message afresh
+        if pending.front().map(|msg| &msg.message_id) != oldest.as_ref() {
+            backoff = policy.initial_backoff;
+            attempt = 1;
+        }
+        let error = match end {
+            StreamEnd::Drained => return,
+            StreamEnd::Unsupported => {
+                println!("⚠️ {host} has no relay streams; sending one call per message");
+                streams = false;
+                continue;
+            }
+            StreamEnd::Failed(e) => e,
+        };
+        unary_client = None;
+
+        let mut peer = health.lock().unwrap();
+        peer.last_error = Some(error.clone());
+        if attempt >= policy.max_attempts {
+            let msg = pending.pop_front().expect("a message failed");
+            peer.queued -= 1;
+            peer.dropped += 1;
+            println!("⚠️ Giving up relay message {} to {} after {attempt} attempts: {error}", msg.message_id, peer.peer);
+            drop(peer);
+
+            let to_node = if msg.to_node.is_empty() { host.clone() } else { msg.to_node.clone() };
+            let undelivered = Undelivered {
+                channel: msg.message_type(),
+                group_id: msg.group_id.clone(),
+                to_node,
+                message_id: msg.message_id.clone(),
+                error,
+            };
+            for handler in undeliverable.read().unwrap().iter() {
+                handler(&undelivered);
+            }
+            backoff = policy.initial_backoff;
+            attempt = 1;
+            continue;
+        }
+        peer.retries += 1;
+        drop(peer);
+
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(policy.max_backoff);
+        attempt += 1;
+    }
+}
+
+/// Streams `pending`, then whatever is queued after it, to `host` until the stream
+/// ends. At most `STREAM_WINDOW` messages wait for acknowledgement; the rest stay
+/// queued, and the peer's acknowledgements pace the sending. Acknowledged messages
+/// leave `pending`.
+async fn stream_queue(
+    identity: &NodeIdentity,
+    host: &str,
+    queue: &mut mpsc::UnboundedReceiver<RelayMessage>,
+    pending: &mut VecDeque<RelayMessage>,
+    policy: &RetryPolicy,
+    health: &Mutex<PeerHealth>,
+) -> StreamEnd {
+    let channel = match tokio::time::timeout(policy.attempt_timeout, tls::channel(&format!("{host}:50051"))).await {
+        Ok(Ok(channel)) => channel,
+        Ok(Err(e)) => return StreamEnd::Failed(format!("Connect failed: {e:?}")),
+        Err(_) => return StreamEnd::Failed("connect timed out".to_string()),
+    };
+    let mut client = custodyrelay::custody_relay_client::CustodyRelayClient::new(channel);
+    let (outbound, wire) = mpsc::channel::<RelayMessage>(STREAM_WINDOW);
+    let mut acks = match tokio::time::timeout(policy.attempt_timeout, client.open_stream(ReceiverStream::new(wire))).await {
+        Ok(Ok(response)) => response.into_inner(),
+        Ok(Err(status)) if status.code() == Code::Unimplemented => return StreamEnd::Unsupported,
+        Ok(Err(status)) => return StreamEnd::Failed(format!("Open stream failed: {status:?}")),
+        Err(_) => return StreamEnd::Failed("open stream timed out".to_string()),
+    };
+
+    let mut sent = 0;                             // Front of `pending` already on the stream
+    let mut closed = false;                       // Queue closed; finish what is pending
+    let mut ack_deadline = Instant::now() + policy.attempt_timeout;
+    loop {
+        while sent < pending.len() {
+            let mut msg = pending[sent].clone();
+            resign_envelope(identity, host, &mut msg);
+            if outbound.send(msg).await.is_err() {
+                return StreamEnd::Failed("stream closed".to_string());
+            }
+            if sent == 0 {
+                ack_deadline = Instant::now() + policy.attempt_timeout;
+            }
+            sent += 1;
+        }
+
+        tokio::select! {
+            ack = tokio::time::timeout_at(ack_deadline, acks.message()), if sent > 0 => {
+                let ack = match ack {
+                    Ok(Ok(Some(ack))) => ack,
+                    Ok(Ok(None)) => return StreamEnd::Failed("stream closed by peer".to_string()),
+                    Ok(Err(status)) => return StreamEnd::Failed(format!("Stream failed: {status:?}")),
+                    Err(_) => return StreamEnd::Failed("acknowledgement timed out".to_string()),
+                };
+                let oldest = pending.front().expect("a message is on the stream");
+                if ack.message_id != oldest.message_id {
+                    return StreamEnd::Failed(format!("acknowledgement for {} out of order", ack.message_id));
+                }
+                if !ack.retry.is_empty() {
+                    return StreamEnd::Failed(ack.retry);
+                }
+                let msg = pending.pop_front().expect("checked above");
+                record_ack(health, &msg, &ack);
+                sent -= 1;
+                ack_deadline = Instant::now() + policy.attempt_timeout;
+            }
+            next = queue.recv(), if !closed && pending.len() < STREAM_WINDOW => match next {
+                Some(msg) => pending.push_back(msg),
+                None => closed = true,
+            },
+            else => return StreamEnd::Drained,
+        }
+    }
+}
+
+/// Counts an acknowledged message
+fn record_ack(health: &Mutex<PeerHealth>, msg: &RelayMessage, ack: &RelayAck) {
+    let mut peer = health.lock().unwrap();
+    peer.queued -= 1;
+    peer.last_delivered = Some(SystemTime::now());
+    if ack.error.is_empty() {
+        peer.delivered += 1;
+    } else {
+        // The peer has it and refused it; sending it again changes nothing
+        peer.rejected += 1;
+        println!("⚠️ {} refused relay message {}: {}", peer.peer, msg.message_id, ack.error);
+    }
+}
+
+/// One call for one message, to a peer without relay streams; the connection is kept
+/// for the next message and dropped on failure
+async fn attempt_delivery(
+    client: &mut Option<custodyrelay::custody_relay_client::CustodyRelayClient<tonic::transport::Channel>>,
+    identity: &NodeIdentity,
+    host: &str,
+    mut msg: RelayMessage,
+) -> Result<RelayAck, String> {
+    if client.is_none() {
+        let channel = tls::channel(&format!("{host}:50051"))
+            .await
+            .map_err(|e| format!("Connect failed: {e:?}"))?;
+        *client = Some(custodyrelay::custody_relay_client::CustodyRelayClient::new(channel));
+    }
+    let connected = client.as_mut().expect("connected above");
+
+    resign_envelope(identity, host, &mut msg);
+    connected.send_message(Request::new(msg)).await
+        .map(Response::into_inner)
+        .map_err(|e| format!("Send failed: {e:?}"))
+}
@@ -2,11 +2,22 @@
 use tonic::{Request, Response, Status};
 use crate::vault;
 use crate::registry::OperationalDIDRegistry;
+use crate::mpc::derivation;
+use crate::mpc::approval::{Approval, ApprovalBundle};
+use crate::mpc::intent::SigningIntent;
+use crate::mpc::ecdsa::EcdsaMessageFormat;
+use crate::policy;
 
 use vault::custody_vault_server::{CustodyVault, CustodyVaultServer};
 use vault::{
     GenerateNonceRequest, GenerateNonceResponse,
     PartialSignRequest, PartialSignResponse,
+    GenerateNonceBatchRequest, GenerateNonceBatchResponse,
+    PartialSignBatchRequest, PartialSignBatchResponse, BatchSignResult,
+    ListEcdsaPresignaturesRequest, ListEcdsaPresignaturesResponse,
+    EcdsaPartialSignRequest, EcdsaPartialSignResponse,
+    RegisterDerivedKeyRequest, RegisterDerivedKeyResponse,
+    SetPolicyRequest, SetPolicyResponse,
 };
 
 pub mod custody {
@@ -44,11 +55,152 @@ impl CustodyVault for VaultService {
             .map(|c| (c.peer_id, c.commitment))
             .collect::<Vec<_>>();
 
-        let signature = vault::partial_sign(&self.registry, &req.operational_did, &req.message, &commitments)
-            .map_err(|e| Status::internal(e))?;
+        let approvals = req.approvals.map(|b| ApprovalBundle {
+            request_id: b.request_id,
+            expires_at: b.expires_at,
+            approvals: b.approvals.into_iter()
+                .map(|a| Approval { approver_id: a.approver_id, signature: a.signature })
+                .collect(),
+        });
+
+        let intent = SigningIntent::from_proto(req.intent, &req.justification)
+            .map_err(|e| Status::invalid_argument(e))?;
+
+        let signature = vault::partial_sign(&self.registry, &req.operational_did, &intent, &req.message, &commitments, approvals.as_ref())
+            .map_err(|e| Status::permission_denied(e))?;
 
         Ok(Response::new(PartialSignResponse {
             signature,
         }))
     }
+
+    async fn generate_nonce_batch(
+        &self,
+        request: Request<GenerateNonceBatchRequest>,
+    ) -> Result<Response<GenerateNonceBatchResponse>, Status> {
+        let req = request.into_inner();
+
+        let commitments = vault::generate_nonces_batch(&self.registry, &req.operational_did, req.count as usize)
+            .map_err(|e| Status::internal(e))?;
+
+        Ok(Response::new(GenerateNonceBatchResponse {
+            commitments,
+        }))
+    }
+
+    async fn partial_sign_batch(
+        &self,
+        request: Request<PartialSignBatchRequest>,
+    ) -> Result<Response<PartialSignBatchResponse>, Status> {
+        let req = request.into_inner();
+        let intent = SigningIntent::from_proto(req.intent, &req.justification)
+            .map_err(|e| Status::invalid_argument(e))?;
+
+        let items = req.items.into_iter()
+            .map(|item| {
+                let commitments = item.commitments.into_iter()
+                    .map(|c| (c.peer_id, c.commitment))
+                    .collect::<Vec<_>>();
+                (item.index, item.message, commitments)
+            })
+            .collect::<Vec<_>>();
+
+        let results = vault::partial_sign_batch(&self.registry, &req.operational_did, &intent, &items)
+            .map_err(|e| Status::permission_denied(e))?
+            .into_iter()
+            .map(|(index, result)| match result {
+                Ok(signature) => BatchSignResult { index, signature, error: String::new() },
+                Err(error) => BatchSignResult { index, signature: vec![], error },
+            })
+            .collect();
+
+        Ok(Response::new(PartialSignBatchResponse {
+            results,
+        }))
+    }
+
+    async fn list_ecdsa_presignatures(
+        &self,
+        request: Request<ListEcdsaPresignaturesRequest>,
+    ) -> Result<Response<ListEcdsaPresignaturesResponse>, Status> {
+        let op_did = request.into_inner().operational_did;
+
+        let presignature_ids = vault::list_ecdsa_presignatures(&self.registry, &op_did)
+            .map_err(|e| Status::internal(e))?;
+
+        Ok(Response::new(ListEcdsaPresignaturesResponse {
+            presignature_ids,
+        }))
+    }
+
+    async fn ecdsa_partial_sign(
+        &self,
+        request: Request<EcdsaPartialSignRequest>,
+    ) -> Result<Response<EcdsaPartialSignResponse>, Status> {
+        let req = request.into_inner();
+
+        let approvals = req.approvals.map(|b| ApprovalBundle {
+            request_id: b.request_id,
+            expires_at: b.expires_at,
+            approvals: b.approvals.into_iter()
+                .map(|a| Approval { approver_id: a.approver_id, signature: a.signature })
+                .collect(),
+        });
+
+        let intent = SigningIntent::from_proto(req.intent, &req.justification)
+            .map_err(|e| Status::invalid_argument(e))?;
+        let format = EcdsaMessageFormat::from_name(&req.format)
+            .map_err(|e| Status::invalid_argument(e))?;
+
+        let (share, big_r) = vault::ecdsa_partial_sign(
+            &self.registry,
+            &req.operational_did,
+            &req.presignature_id,
+            &intent,
+            &req.message,
+            format,
+            &req.signers,
+            approvals.as_ref(),
+        ).map_err(|e| Status::permission_denied(e))?;
+
+        Ok(Response::new(EcdsaPartialSignResponse {
+            index: share.index,
+            share: share.share,
+            big_r,
+        }))
+    }
+
+    async fn register_derived_key(
+        &self,
+        request: Request<RegisterDerivedKeyRequest>,
+    ) -> Result<Response<RegisterDerivedKeyResponse>, Status> {
+        let req = request.into_inner();
+
+        let group_public_key = derivation::register_derived_did(
+            &self.registry,
+            &req.operational_did,
+            &req.root_did,
+            &req.parent_operational_did,
+            &req.derivation_path,
+        ).map_err(|e| Status::internal(e))?;
+
+        Ok(Response::new(RegisterDerivedKeyResponse {
+            group_public_key,
+        }))
+    }
+
+    async fn set_policy(
+        &self,
+        request: Request<SetPolicyRequest>,
+    ) -> Result<Response<SetPolicyResponse>, Status> {
+        let req = request.into_inner();
+
+        // The vault checks the group signature itself; an unsigned change is never applied
+        let policy = policy::apply_change(&self.registry, &req.policy_change, &req.signature)
+            .map_err(|e| Status::permission_denied(e))?;
+
+        Ok(Response::new(SetPolicyResponse {
+            version: policy.version,
+        }))
+    }
 }
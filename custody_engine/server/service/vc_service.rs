@@ -64,17 +64,20 @@ impl CustodyVc for CustodyVcService {
             "root" => {
                 println!("[sign_credential] Routing root VC through MPC coordinator");
 
-                // Convert the VC JSON to bytes (message to sign)
+                // The vaults canonicalize and domain-separate the VC themselves (VC_PROOF intent)
                 let message_bytes = vc_json.as_bytes().to_vec();
 
                 // Use the gRPC client to request signature from CustodyMpc
-                let mut mpc_client = custodympc::custody_mpc_client::CustodyMpcClient::connect("http://[::1]:50051")
+                let channel = crate::tls::local_channel("[::1]:50051")
                     .await
                     .map_err(|e| Status::internal(format!("MPC client connect failed: {e}")))?;
+                let mut mpc_client = custodympc::custody_mpc_client::CustodyMpcClient::new(channel);
 
                 let mpc_resp = mpc_client.sign_message(custodympc::SignMessageRequest {
                     operational_did: req.issuer_did.clone(), // Issuer is the op_did in this case
                     message: message_bytes,
+                    intent: custodympc::SigningIntent::VcProof as i32,
+                    ..Default::default()
                 }).await.map_err(|e| Status::internal(format!("MPC sign_message failed: {e}")))?;
 
                 let signature = base64::encode(mpc_resp.into_inner().signature);
@@ -8,6 +8,10 @@ mod dkg;
 mod issuer;
 mod service;
 mod registry;
+mod identity;
+mod membership;
+mod tls;
+mod sim;
 
 use bootstrap::init_bootstrap;
 
@@ -16,6 +20,7 @@ use service::dkg_service::CustodyDkgService;
 use service::mpc_service::CustodyMpcService;
 use service::issuer_service::IssuerService;
 use service::relay_service::RelayService;
+use service::device_service::CustodyDeviceService;
 
 use tonic::transport::Server;
 use vault::custody_vault_server::CustodyVaultServer;
@@ -23,7 +28,9 @@ use mpc::custody_mpc_server::CustodyMpcServer;
 use dkg::custody_dkg_server::CustodyDkgServer;
 use issuer::custody_issuer_server::CustodyIssuerServer;
 use relay::custody_relay_server::CustodyRelayServer;
+use custodydevice::custody_device_server::CustodyDeviceServer;
 
+use std::path::Path;
 use std::sync::Arc;
 
 // Load the generated Rust code from custody.proto (auto-generated by tonic_build)
@@ -72,29 +79,90 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Step 2: Initialize core state
     // I think it left out issuer_registry
     let registry = Arc::new(registry::OperationalDIDRegistry::new());
-    let relay = Arc::new(relay::RelayClient::new(&boot.local_node_id));
+
+    // Long-term node identity; peers' keys are pinned from the operator file or on first contact
+    let identity = Arc::new(identity::NodeIdentity::load_or_generate(Path::new("/var/lib/custody/node_identity.key"))?);
+    let directory = Arc::new(identity::IdentityDirectory::new());
+    let relay = Arc::new(relay::RelayClient::new(identity.clone(), &boot.local_node_id));
+    let pinned = directory.load_pinned(Path::new("/etc/custody/peer_identities.json"))?;
+    println!("🪪 Node identity loaded, {pinned} peer identities pinned");
+
+    // Enrolled custody nodes keep one FROST identifier for good; their keys are pinned up front
+    let enrolled = membership::MEMBERSHIP.load(Path::new("/var/lib/custody/membership.json"))?;
+    membership::MEMBERSHIP.pin_into(&directory)?;
+    match membership::MEMBERSHIP.get(&boot.local_node_id) {
+        Some(me) if me.identity != identity.public_keys() => {
+            return Err(format!("{} is enrolled with a different identity key", boot.local_node_id).into());
+        }
+        Some(me) => println!("👥 {enrolled} custody nodes enrolled, this node is #{}", me.identifier),
+        None => println!("⚠️ {} is not enrolled; its FROST identifier is derived from its node ID", boot.local_node_id),
+    }
+
+    // Mutual TLS with certificates from the cluster CA (`custody ca issue`); ours must carry our identity key
+    let cluster_tls = tls::ClusterTls::load(Path::new("/etc/custody/tls"))?;
+    cluster_tls.check_local(&boot.local_node_id, &identity.public_keys())?;
+    let server_tls = cluster_tls.server_config();
+    let device_tls = cluster_tls.device_server_config();
+    tls::install(cluster_tls)?;
+    println!("🔐 Cluster TLS loaded for {}", boot.local_node_id);
+
     let dkg_engine = Arc::new(dkg::engine::DKGEngine::new(
         registry.clone(),
         relay.clone(),
+        identity.clone(),
+        directory.clone(),
+        boot.local_node_id.clone(),
+    ));
+    let ecdsa_engine = Arc::new(mpc::ecdsa_engine::EcdsaEngine::new(
+        registry.clone(),
+        relay.clone(),
+        identity.clone(),
+        directory.clone(),
         boot.local_node_id.clone(),
     ));
 
+    // Each engine takes the relay channels of its protocols
+    let relay_handlers = Arc::new(relay::RelayHandlers::new());
+    dkg_engine.register_relay_handlers(&relay_handlers)?;
+    ecdsa_engine.register_relay_handlers(&relay_handlers)?;
+    dkg_engine.watch_relay();
+
+    // Resume DKG sessions interrupted by a restart, or abort them and tell their peers
+    let recovered = dkg_engine.recover_sessions();
+    println!("♻️ {} interrupted DKG sessions recovered", recovered.len());
+
+    let device_hub = Arc::new(mpc::device::DeviceHub::new());
+
     // Step 3: Mount all services
     let vault_service = VaultService { registry: registry.clone() };
     let relay_service = RelayService {
-        dkg_engine: dkg_engine.clone(),
+        handlers: relay_handlers.clone(),
+        device_hub: device_hub.clone(),
+        identity: identity.clone(),
+        directory: directory.clone(),
         local_node_id: boot.local_node_id.clone(),
+        inbox: Arc::new(relay::RelayInbox::new()),
+        relay: relay.clone(),
     };
     let dkg_service = CustodyDkgService {
         dkg_engine: dkg_engine.clone(),
+        ecdsa_engine: ecdsa_engine.clone(),
     };
     let mpc_service = CustodyMpcService {
         coordinator: mpc::coordinator::MPCSigningCoordinator {
             registry: registry.clone(),
             relay: relay.clone(),
+            approvals: Arc::new(mpc::approval::ApprovalQueue::new()),
             local_node_id: boot.local_node_id.clone(),
         },
     };
+    let device_service = CustodyDeviceService {
+        registry: registry.clone(),
+        hub: device_hub.clone(),
+        relay: relay.clone(),
+        dkg_engine: dkg_engine.clone(),
+        local_node_id: boot.local_node_id.clone(),
+    };
     let issuer_service = IssuerService {}; // Stateless
 
     println!("🚀 Custody Engine starting on {}", boot.relay_bind);
@@ -113,27 +181,45 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // Start the gRPC server and bind our service handlers (to be implemented)
-    server::builder()
-        .add_service(KeyServiceServer::new(MyKeyService::default()))
-        .add_service(SigningServiceServer::new(MySigningService::default()))
-        .add_service(HealthServiceServer::new(MyHealthService::default()))
-        .add_service(CustodyManagementServiceServer::new(custody_mgmt_service))
-        .add_service(CustodyRelayServer::new(relay_service))
-        .add_service(CustodyDkgServer::new(dkg_service))
-        .add_service(CustodyVaultServer::new(vault_service))
-        .add_service(CustodyMpcServer::new(mpc_service))
-        .add_service(CustodyIssuerServer::new(issuer_service))
-        .add_service(CustodyVcServer::new(vc_service))
+    // Every caller on the node port holds a cluster certificate. Relay and vault take calls
+    // from nodes only; the device service checks its callers itself.
+    let nodes = tls::Authorize::nodes(directory.clone());
+    let nodes_and_operators = tls::Authorize::nodes_and_operators(directory.clone());
+    let node_server = Server::builder()
+        .tls_config(server_tls)?
+        .add_service(KeyServiceServer::with_interceptor(MyKeyService::default(), nodes_and_operators.clone()))
+        .add_service(SigningServiceServer::with_interceptor(MySigningService::default(), nodes_and_operators.clone()))
+        .add_service(HealthServiceServer::with_interceptor(MyHealthService::default(), nodes_and_operators.clone()))
+        .add_service(CustodyManagementServiceServer::with_interceptor(custody_mgmt_service, nodes_and_operators.clone()))
+        .add_service(CustodyRelayServer::with_interceptor(relay_service, nodes.clone()))
+        .add_service(CustodyDkgServer::with_interceptor(dkg_service, nodes_and_operators.clone()))
+        .add_service(CustodyVaultServer::with_interceptor(vault_service, nodes))
+        .add_service(CustodyMpcServer::with_interceptor(mpc_service, nodes_and_operators.clone()))
+        .add_service(CustodyIssuerServer::with_interceptor(issuer_service, nodes_and_operators.clone()))
+        .add_service(CustodyDeviceServer::new(device_service.clone()))
+        .add_service(CustodyVcServer::with_interceptor(vc_service, nodes_and_operators))
        // .serve("[::1]:50051".parse()?)
-        .serve(boot.relay_bind)
-        .await?;
+        .serve(boot.relay_bind);
+
+    // Devices have no certificate, so they get a listener of their own that asks for none.
+    // Only services that admit anonymous callers go here: the device service, which opens
+    // sessions for anyone and refuses everything else without a certificate, and health,
+    // so probes need no credentials.
+    println!("📱 Device listener on {}", boot.device_bind);
+    let device_server = Server::builder()
+        .tls_config(device_tls)?
+        .add_service(CustodyDeviceServer::new(device_service))
+        .add_service(HealthServiceServer::new(MyHealthService::default()))
+        .serve(boot.device_bind);
+
+    tokio::try_join!(node_server, device_server)?;
     Ok(())
 }
 
-// Use TLS/mTLS → add .tls_config(...) to the server builder
-// We can skip TLS for local dev but should include it for production.
 // to run the gRPC server
 // cargo run -p custody_server
 
-// then check health endpoint useing grpcurl
-// grpcurl -plaintext localhost:50051 custody.HealthService/Check
\ No newline at end of file
+// then check health endpoint useing grpcurl with an operator certificate
+// grpcurl -cacert ca.pem -cert operator.pem -key operator.key node-a:50051 custody.HealthService/Check
+// or without one on the device listener
+// grpcurl -cacert ca.pem node-a:50052 custody.HealthService/Check
\ No newline at end of file